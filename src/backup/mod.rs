@@ -0,0 +1,1053 @@
+//! Versioned backup of users with their accounts, categories, tags and transactions.
+//! Backup is written as one JSON document or as NDJSON, one record per line.
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    io::{BufRead, Write},
+};
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    commands::transactions::transactionquery::{TransactionOrder, TransactionQuery},
+    errors::{MoneyCalcError, MoneyCalcResult},
+    models::{
+        account::Account, category::Category, importedtransaction::ImportedTransaction,
+        money::Money, moneytransaction::MoneyTransaction, tag::Tag, user::User,
+    },
+    providers::{CategoryProvider, DataProvider, TagProvider, bases::validate_overdraft},
+};
+
+/// Version of backup format written by this crate.
+pub const BACKUP_VERSION: u32 = 1;
+
+/// Count of transactions read from provider at once.
+const EXPORT_PAGE_SIZE: u32 = 500;
+
+/// Account with its balance before all its transactions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountBackup {
+    pub account: Account,
+    pub initial_balance: Money,
+}
+
+/// Tags of one transaction by their ids.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionTags {
+    pub transaction_id: String,
+    pub tag_ids: Vec<i32>,
+}
+
+/// Users with their accounts, categories, tags and transactions.
+/// Bank ids of imported transactions are kept, so restored accounts don't import statements twice.
+/// Budgets and recurring templates are not part of backup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Backup {
+    pub version: u32,
+    pub created: NaiveDateTime,
+    pub users: Vec<User>,
+    pub accounts: Vec<AccountBackup>,
+    pub categories: Vec<Category>,
+    pub tags: Vec<Tag>,
+    pub transactions: Vec<MoneyTransaction>,
+    pub transaction_tags: Vec<TransactionTags>,
+    pub imported: Vec<ImportedTransaction>,
+}
+
+/// How restore treats ids of backup records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestoreMode {
+    /// Keep ids, fails with Conflict if they are taken.
+    PreserveIds,
+    /// Give records new ids, references between records are rewritten.
+    RemapIds,
+}
+
+/// Line of NDJSON backup, the first line is header.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BackupLine {
+    Header {
+        version: u32,
+        created: NaiveDateTime,
+    },
+    User(User),
+    Account(AccountBackup),
+    Category(Category),
+    Tag(Tag),
+    Transaction(MoneyTransaction),
+    TransactionTags(TransactionTags),
+    Imported(ImportedTransaction),
+}
+
+impl Backup {
+    fn new() -> Self {
+        Self {
+            version: BACKUP_VERSION,
+            created: chrono::Utc::now().naive_utc(),
+            users: vec![],
+            accounts: vec![],
+            categories: vec![],
+            tags: vec![],
+            transactions: vec![],
+            transaction_tags: vec![],
+            imported: vec![],
+        }
+    }
+
+    pub fn to_json(&self) -> MoneyCalcResult<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(text: &str) -> MoneyCalcResult<Self> {
+        let backup: Backup = serde_json::from_str(text).map_err(invalid_backup)?;
+        check_version(backup.version)?;
+        Ok(backup)
+    }
+
+    pub fn write_ndjson<W: Write>(&self, mut writer: W) -> MoneyCalcResult<()> {
+        let header = BackupLine::Header {
+            version: self.version,
+            created: self.created,
+        };
+        let lines = std::iter::once(header)
+            .chain(self.users.iter().cloned().map(BackupLine::User))
+            .chain(self.accounts.iter().cloned().map(BackupLine::Account))
+            .chain(self.categories.iter().cloned().map(BackupLine::Category))
+            .chain(self.tags.iter().cloned().map(BackupLine::Tag))
+            .chain(
+                self.transactions
+                    .iter()
+                    .cloned()
+                    .map(BackupLine::Transaction),
+            )
+            .chain(
+                self.transaction_tags
+                    .iter()
+                    .cloned()
+                    .map(BackupLine::TransactionTags),
+            )
+            .chain(self.imported.iter().cloned().map(BackupLine::Imported));
+        for line in lines {
+            serde_json::to_writer(&mut writer, &line)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    pub fn read_ndjson<R: BufRead>(reader: R) -> MoneyCalcResult<Self> {
+        let mut backup: Option<Backup> = None;
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let line: BackupLine = serde_json::from_str(&line).map_err(invalid_backup)?;
+            match (backup.as_mut(), line) {
+                (None, BackupLine::Header { version, created }) => {
+                    check_version(version)?;
+                    backup = Some(Backup {
+                        version,
+                        created,
+                        ..Backup::new()
+                    });
+                }
+                (None, _) => {
+                    return Err(MoneyCalcError::Validation(
+                        "backup must start with header".to_string(),
+                    ));
+                }
+                (Some(_), BackupLine::Header { .. }) => {
+                    return Err(MoneyCalcError::Validation(
+                        "backup has more than one header".to_string(),
+                    ));
+                }
+                (Some(backup), BackupLine::User(user)) => backup.users.push(user),
+                (Some(backup), BackupLine::Account(account)) => backup.accounts.push(account),
+                (Some(backup), BackupLine::Category(category)) => backup.categories.push(category),
+                (Some(backup), BackupLine::Tag(tag)) => backup.tags.push(tag),
+                (Some(backup), BackupLine::Transaction(transaction)) => {
+                    backup.transactions.push(transaction)
+                }
+                (Some(backup), BackupLine::TransactionTags(tags)) => {
+                    backup.transaction_tags.push(tags)
+                }
+                (Some(backup), BackupLine::Imported(imported)) => backup.imported.push(imported),
+            }
+        }
+        backup.ok_or_else(|| MoneyCalcError::Validation("backup is empty".to_string()))
+    }
+}
+
+fn invalid_backup(error: serde_json::Error) -> MoneyCalcError {
+    MoneyCalcError::Validation(format!("invalid backup: {}", error))
+}
+
+fn check_version(version: u32) -> MoneyCalcResult<()> {
+    if version == 0 || version > BACKUP_VERSION {
+        return Err(MoneyCalcError::Validation(format!(
+            "unsupported backup version {}",
+            version
+        )));
+    }
+    Ok(())
+}
+
+/// Backup of one user.
+pub async fn export_user<T: DataProvider + CategoryProvider + TagProvider + ?Sized>(
+    provider: &T,
+    user: &User,
+) -> MoneyCalcResult<Backup> {
+    let mut backup = Backup::new();
+    export_user_into(provider, user, &mut backup).await?;
+    drop_outside_links(&mut backup);
+    Ok(backup)
+}
+
+/// Backup of all users in storage.
+pub async fn export_all<T: DataProvider + CategoryProvider + TagProvider + ?Sized>(
+    provider: &T,
+) -> MoneyCalcResult<Backup> {
+    let mut backup = Backup::new();
+    for user in provider.get_users().await? {
+        export_user_into(provider, &user, &mut backup).await?;
+    }
+    drop_outside_links(&mut backup);
+    Ok(backup)
+}
+
+/// Transfer legs linked to transactions of users outside backup lose the link,
+/// restored transaction can't refer to record that is not restored with it.
+fn drop_outside_links(backup: &mut Backup) {
+    let ids: HashSet<String> = backup
+        .transactions
+        .iter()
+        .map(|transaction| transaction.id.clone())
+        .collect();
+    for transaction in &mut backup.transactions {
+        if transaction
+            .linked_transaction_id
+            .as_ref()
+            .is_some_and(|id| !ids.contains(id))
+        {
+            transaction.linked_transaction_id = None;
+        }
+    }
+}
+
+async fn export_user_into<T: DataProvider + CategoryProvider + TagProvider + ?Sized>(
+    provider: &T,
+    user: &User,
+    backup: &mut Backup,
+) -> MoneyCalcResult<()> {
+    let mut transactions = vec![];
+    let mut cursor = None;
+    loop {
+        let page = provider
+            .get_transactions(&TransactionQuery {
+                user_id: Some(user.id),
+                order: TransactionOrder::DateAsc,
+                limit: EXPORT_PAGE_SIZE,
+                cursor,
+                ..Default::default()
+            })
+            .await?;
+        transactions.extend(page.transactions);
+        cursor = page.next_cursor;
+        if cursor.is_none() {
+            break;
+        }
+    }
+
+    for account in provider.get_accounts_by_user(user).await? {
+        let mut initial_balance = account.money;
+        for transaction in &transactions {
+            if transaction.account.id == account.id {
+                initial_balance = initial_balance.checked_sub(transaction.signed_amount()?)?;
+            }
+        }
+        backup
+            .imported
+            .extend(provider.get_imported_transactions(&account).await?);
+        backup.accounts.push(AccountBackup {
+            account,
+            initial_balance,
+        });
+    }
+    for transaction in &transactions {
+        let tags = provider.get_transaction_tags(&transaction.id).await?;
+        if !tags.is_empty() {
+            backup.transaction_tags.push(TransactionTags {
+                transaction_id: transaction.id.clone(),
+                tag_ids: tags.iter().map(|tag| tag.id).collect(),
+            });
+        }
+    }
+    backup.users.push(user.clone());
+    backup
+        .categories
+        .extend(provider.get_categories(user).await?);
+    backup.tags.extend(provider.get_tags(user).await?);
+    backup.transactions.extend(transactions);
+    Ok(())
+}
+
+/// Records ordered so that every record comes after the record it refers to.
+/// References to keys missing in records are ignored.
+fn referenced_first<'a, R, K: Eq + Hash>(
+    records: &'a [R],
+    key: impl Fn(&'a R) -> K,
+    reference: impl Fn(&'a R) -> Option<K>,
+    what: &str,
+) -> MoneyCalcResult<Vec<&'a R>> {
+    let keys: HashSet<K> = records.iter().map(&key).collect();
+    let mut placed: HashSet<K> = HashSet::new();
+    let mut ordered = vec![];
+    let mut pending: Vec<&R> = records.iter().collect();
+    while !pending.is_empty() {
+        let (ready, waiting): (Vec<_>, Vec<_>) = pending.into_iter().partition(|record| {
+            reference(record).is_none_or(|id| placed.contains(&id) || !keys.contains(&id))
+        });
+        if ready.is_empty() {
+            return Err(MoneyCalcError::Validation(format!("{} form a cycle", what)));
+        }
+        placed.extend(ready.iter().map(|record| key(record)));
+        ordered.extend(ready);
+        pending = waiting;
+    }
+    Ok(ordered)
+}
+
+/// Records of backup in restore order.
+struct RestorePlan<'a> {
+    categories: Vec<&'a Category>,
+    transactions: Vec<&'a MoneyTransaction>,
+}
+
+/// Check references inside backup and conflicts with stored records without writing anything.
+async fn check_restore<'a, T: DataProvider + CategoryProvider + TagProvider + ?Sized>(
+    provider: &T,
+    backup: &'a Backup,
+    mode: RestoreMode,
+) -> MoneyCalcResult<RestorePlan<'a>> {
+    let invalid = |message: String| Err(MoneyCalcError::Validation(message));
+    let users: HashSet<i32> = backup.users.iter().map(|user| user.id).collect();
+    let accounts: HashMap<i32, &Account> = backup
+        .accounts
+        .iter()
+        .map(|backup| (backup.account.id, &backup.account))
+        .collect();
+    let categories: HashMap<i32, &Category> = backup
+        .categories
+        .iter()
+        .map(|category| (category.id, category))
+        .collect();
+    let tags: HashMap<i32, &Tag> = backup.tags.iter().map(|tag| (tag.id, tag)).collect();
+    let transactions: HashMap<&str, &MoneyTransaction> = backup
+        .transactions
+        .iter()
+        .map(|transaction| (transaction.id.as_str(), transaction))
+        .collect();
+    let numbers: HashSet<&str> = backup
+        .users
+        .iter()
+        .map(|user| user.number.as_str())
+        .collect();
+    let category_names: HashSet<(i32, Option<i32>, &str)> = backup
+        .categories
+        .iter()
+        .map(|category| (category.user_id, category.parent_id, category.name.as_str()))
+        .collect();
+    let tag_names: HashSet<(i32, &str)> = backup
+        .tags
+        .iter()
+        .map(|tag| (tag.user_id, tag.name.as_str()))
+        .collect();
+    let imported: HashSet<(i32, &str)> = backup
+        .imported
+        .iter()
+        .map(|imported| (imported.account_id, imported.external_id.as_str()))
+        .collect();
+    if users.len() != backup.users.len()
+        || numbers.len() != backup.users.len()
+        || accounts.len() != backup.accounts.len()
+        || categories.len() != backup.categories.len()
+        || category_names.len() != backup.categories.len()
+        || tags.len() != backup.tags.len()
+        || tag_names.len() != backup.tags.len()
+        || transactions.len() != backup.transactions.len()
+        || imported.len() != backup.imported.len()
+    {
+        return invalid("backup has repeated records".to_string());
+    }
+
+    for AccountBackup {
+        account,
+        initial_balance,
+    } in &backup.accounts
+    {
+        if !users.contains(&account.user_id) {
+            return invalid(format!(
+                "account {} belongs to user {} missing in backup",
+                account.id, account.user_id
+            ));
+        }
+        validate_overdraft(&account.overdraft, account.money.currency())?;
+        initial_balance.check_currency(&account.money)?;
+    }
+    for category in &backup.categories {
+        if !users.contains(&category.user_id) {
+            return invalid(format!(
+                "category {} belongs to user {} missing in backup",
+                category.id, category.user_id
+            ));
+        }
+        if let Some(parent_id) = category.parent_id
+            && categories
+                .get(&parent_id)
+                .is_none_or(|parent| parent.user_id != category.user_id)
+        {
+            return invalid(format!(
+                "category {} refers to parent {} missing in backup",
+                category.id, parent_id
+            ));
+        }
+    }
+    for tag in &backup.tags {
+        if !users.contains(&tag.user_id) {
+            return invalid(format!(
+                "tag {} belongs to user {} missing in backup",
+                tag.id, tag.user_id
+            ));
+        }
+    }
+    for transaction in &backup.transactions {
+        let missing = |what: &str, id: i32| {
+            invalid(format!(
+                "transaction {} refers to {} {} missing in backup",
+                transaction.id, what, id
+            ))
+        };
+        if !users.contains(&transaction.user.id) {
+            return missing("user", transaction.user.id);
+        }
+        let Some(account) = accounts.get(&transaction.account.id) else {
+            return missing("account", transaction.account.id);
+        };
+        transaction.amount.check_currency(&account.money)?;
+        if let Some(category_id) = transaction.category_id
+            && !categories.contains_key(&category_id)
+        {
+            return missing("category", category_id);
+        }
+        for linked in [
+            &transaction.linked_transaction_id,
+            &transaction.correction_of,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if !transactions.contains_key(linked.as_str()) {
+                return invalid(format!(
+                    "transaction {} refers to transaction {} missing in backup",
+                    transaction.id, linked
+                ));
+            }
+        }
+    }
+    for TransactionTags {
+        transaction_id,
+        tag_ids,
+    } in &backup.transaction_tags
+    {
+        let Some(transaction) = transactions.get(transaction_id.as_str()) else {
+            return invalid(format!(
+                "tags refer to transaction {} missing in backup",
+                transaction_id
+            ));
+        };
+        if let Some(tag_id) = tag_ids.iter().find(|id| {
+            tags.get(id)
+                .is_none_or(|tag| tag.user_id != transaction.account.user_id)
+        }) {
+            return invalid(format!(
+                "transaction {} refers to tag {} missing in backup",
+                transaction_id, tag_id
+            ));
+        }
+    }
+    for imported in &backup.imported {
+        if transactions
+            .get(imported.transaction_id.as_str())
+            .is_none_or(|transaction| transaction.account.id != imported.account_id)
+        {
+            return invalid(format!(
+                "bank id {} refers to transaction {} missing in account {} of backup",
+                imported.external_id, imported.transaction_id, imported.account_id
+            ));
+        }
+    }
+
+    let plan = RestorePlan {
+        categories: referenced_first(
+            &backup.categories,
+            |category| category.id,
+            |category| category.parent_id,
+            "categories",
+        )?,
+        // Corrected transaction is restored before its correction.
+        transactions: referenced_first(
+            &backup.transactions,
+            |transaction| transaction.id.as_str(),
+            |transaction| transaction.correction_of.as_deref(),
+            "transaction corrections",
+        )?,
+    };
+
+    let conflict = |message: String| Err(MoneyCalcError::Conflict(message));
+    let stored_users = provider.get_users().await?;
+    for user in &backup.users {
+        if stored_users
+            .iter()
+            .any(|stored| stored.number == user.number)
+        {
+            return conflict(format!("user with number {} already exists", user.number));
+        }
+    }
+    if mode == RestoreMode::RemapIds {
+        return Ok(plan);
+    }
+    for user in &stored_users {
+        if users.contains(&user.id) {
+            return conflict(format!("user {} already exists", user.id));
+        }
+        if let Some(category) = provider
+            .get_categories(user)
+            .await?
+            .iter()
+            .find(|category| categories.contains_key(&category.id))
+        {
+            return conflict(format!("category {} already exists", category.id));
+        }
+        if let Some(tag) = provider
+            .get_tags(user)
+            .await?
+            .iter()
+            .find(|tag| tags.contains_key(&tag.id))
+        {
+            return conflict(format!("tag {} already exists", tag.id));
+        }
+    }
+    if let Some(account) = provider
+        .get_accounts()
+        .await?
+        .iter()
+        .find(|account| accounts.contains_key(&account.id))
+    {
+        return conflict(format!("account {} already exists", account.id));
+    }
+    for transaction in &backup.transactions {
+        match provider.get_transaction_by_id(&transaction.id).await {
+            Ok(_) => return conflict(format!("transaction {} already exists", transaction.id)),
+            Err(MoneyCalcError::NotFound(_)) => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(plan)
+}
+
+/// Load backup into provider.
+/// Backup is checked for missing references and taken ids before the first record is written,
+/// so invalid or conflicting backup leaves storage unchanged.
+/// Records are written one by one, storage error in the middle keeps records written before it.
+/// Balances are restored as they were stored, transactions do not change them again.
+pub async fn restore<T: DataProvider + CategoryProvider + TagProvider + ?Sized>(
+    provider: &T,
+    backup: &Backup,
+    mode: RestoreMode,
+) -> MoneyCalcResult<()> {
+    check_version(backup.version)?;
+    let plan = check_restore(provider, backup, mode).await?;
+    let remap = mode == RestoreMode::RemapIds;
+    let new_id = |id: i32| if remap { 0 } else { id };
+    // References below were checked by check_restore, so lookups can't fail.
+
+    let mut users = HashMap::new();
+    for user in &backup.users {
+        let restored = User {
+            id: new_id(user.id),
+            ..user.clone()
+        };
+        users.insert(user.id, provider.restore_user(&restored).await?);
+    }
+
+    let mut accounts = HashMap::new();
+    for AccountBackup {
+        account,
+        initial_balance,
+    } in &backup.accounts
+    {
+        let restored = Account {
+            id: new_id(account.id),
+            user_id: users[&account.user_id].id,
+            ..account.clone()
+        };
+        accounts.insert(
+            account.id,
+            provider
+                .restore_account(&restored, *initial_balance)
+                .await?,
+        );
+    }
+
+    let mut categories: HashMap<i32, Category> = HashMap::new();
+    for category in plan.categories {
+        let restored = Category {
+            id: new_id(category.id),
+            user_id: users[&category.user_id].id,
+            parent_id: category.parent_id.map(|id| categories[&id].id),
+            name: category.name.clone(),
+        };
+        let restored = provider.restore_category(&restored).await?;
+        categories.insert(category.id, restored);
+    }
+
+    let mut tags = HashMap::new();
+    for tag in &backup.tags {
+        let restored = Tag {
+            id: new_id(tag.id),
+            user_id: users[&tag.user_id].id,
+            name: tag.name.clone(),
+        };
+        tags.insert(tag.id, provider.restore_tag(&restored).await?);
+    }
+
+    let ids: HashMap<&str, String> = backup
+        .transactions
+        .iter()
+        .map(|transaction| {
+            let id = if remap {
+                Uuid::new_v4().to_string()
+            } else {
+                transaction.id.clone()
+            };
+            (transaction.id.as_str(), id)
+        })
+        .collect();
+    let transaction_id = |id: &String| ids.get(id.as_str()).cloned().unwrap_or_else(|| id.clone());
+    for transaction in plan.transactions {
+        provider
+            .restore_transaction(&MoneyTransaction {
+                id: transaction_id(&transaction.id),
+                user: users[&transaction.user.id].clone(),
+                account: accounts[&transaction.account.id].clone(),
+                linked_transaction_id: transaction
+                    .linked_transaction_id
+                    .as_ref()
+                    .map(transaction_id),
+                correction_of: transaction.correction_of.as_ref().map(transaction_id),
+                category_id: transaction.category_id.map(|id| categories[&id].id),
+                ..transaction.clone()
+            })
+            .await?;
+    }
+
+    for transaction_tags in &backup.transaction_tags {
+        let restored: Vec<Tag> = transaction_tags
+            .tag_ids
+            .iter()
+            .map(|id| tags[id].clone())
+            .collect();
+        provider
+            .set_transaction_tags(&transaction_id(&transaction_tags.transaction_id), &restored)
+            .await?;
+    }
+
+    for imported in &backup.imported {
+        provider
+            .restore_imported_transaction(&ImportedTransaction {
+                account_id: accounts[&imported.account_id].id,
+                external_id: imported.external_id.clone(),
+                transaction_id: transaction_id(&imported.transaction_id),
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        backup::{Backup, RestoreMode, export_all, export_user, restore},
+        commands::{
+            accounts::addaccountcommand::AddAccountCommand,
+            categories::addcategorycommand::AddCategoryCommand,
+            tags::addtagcommand::AddTagCommand,
+            transactions::{
+                reversetransactioncommand::ReverseTransactionCommand,
+                transfercommand::TransferCommand,
+            },
+            users::addusercommand::AddUserCommand,
+        },
+        config::{SqliteConfiguration, StorageConfiguration},
+        errors::MoneyCalcError,
+        models::{
+            account::OverdraftPolicy,
+            money::{Currency, Money},
+            moneytransaction::{MoneyTransaction, PaymentType},
+            user::User,
+        },
+        providers::{
+            AccountProvider, CategoryProvider, ReconciliationProvider, TagProvider,
+            TransactionWorker, UserProvider, bases::sqlite::SqliteProvider,
+        },
+    };
+
+    fn rub(amount: &str) -> Money {
+        Money::parse(amount, Currency::RUB).unwrap()
+    }
+
+    async fn add_user(provider: &SqliteProvider, number: &str) -> User {
+        provider
+            .add_user(&AddUserCommand {
+                user_name: "scam".to_string(),
+                user_number: number.to_string(),
+            })
+            .await
+            .unwrap();
+        provider.get_user_by_number(number).await.unwrap()
+    }
+
+    async fn seeded_provider() -> (SqliteProvider, User) {
+        let provider = SqliteConfiguration::memory_base().configure().unwrap();
+        let user = add_user(&provider, "1").await;
+        for name in ["Checking", "Savings"] {
+            provider
+                .add_account(&AddAccountCommand {
+                    user_id: user.id,
+                    account_name: name.to_string(),
+                    initial_balance: rub("100.00"),
+                    overdraft: OverdraftPolicy::default(),
+                })
+                .await
+                .unwrap();
+        }
+        let accounts = provider.get_accounts_by_user(&user).await.unwrap();
+        provider
+            .add_category(&AddCategoryCommand {
+                user_id: user.id,
+                parent_id: None,
+                name: "Food".to_string(),
+            })
+            .await
+            .unwrap();
+        let food = provider.get_categories(&user).await.unwrap().remove(0);
+        provider
+            .add_category(&AddCategoryCommand {
+                user_id: user.id,
+                parent_id: Some(food.id),
+                name: "Cafe".to_string(),
+            })
+            .await
+            .unwrap();
+        let cafe = provider.get_categories(&user).await.unwrap().remove(1);
+        for name in ["trip", "work"] {
+            provider
+                .add_tag(&AddTagCommand {
+                    user_id: user.id,
+                    name: name.to_string(),
+                })
+                .await
+                .unwrap();
+        }
+        let tags = provider.get_tags(&user).await.unwrap();
+        let create_date = chrono::Utc::now().naive_utc();
+        let coffee = provider
+            .execute_transaction(&MoneyTransaction {
+                id: String::new(),
+                amount: rub("25.50"),
+                description: "Coffee".to_string(),
+                user: user.clone(),
+                account: accounts[0].clone(),
+                payment_type: PaymentType::Outcome,
+                payment_target: "Cafe".to_string(),
+                create_date,
+                linked_transaction_id: None,
+                correction_of: None,
+                category_id: Some(cafe.id),
+            })
+            .await
+            .unwrap();
+        provider
+            .set_transaction_tags(&coffee.id, &tags)
+            .await
+            .unwrap();
+        provider
+            .execute_external_transaction(
+                &MoneyTransaction {
+                    amount: rub("10.00"),
+                    description: "Cashback".to_string(),
+                    payment_type: PaymentType::Income,
+                    category_id: None,
+                    ..coffee.clone()
+                },
+                "FITID-1",
+            )
+            .await
+            .unwrap();
+        provider
+            .transfer(&TransferCommand {
+                from_account_id: accounts[0].id,
+                to_account_id: accounts[1].id,
+                amount: rub("50.00"),
+                exchange_rate: None,
+                description: "Save".to_string(),
+                create_date,
+            })
+            .await
+            .unwrap();
+        provider
+            .reverse_transaction(&ReverseTransactionCommand {
+                transaction_id: coffee.id,
+                description: "Refund".to_string(),
+                create_date,
+            })
+            .await
+            .unwrap();
+        (provider, user)
+    }
+
+    fn records(backup: &Backup) -> serde_json::Value {
+        serde_json::json!([
+            backup.users,
+            backup.accounts,
+            backup.categories,
+            backup.tags,
+            backup.transactions,
+            backup.transaction_tags,
+            backup.imported
+        ])
+    }
+
+    #[tokio::test]
+    async fn backup_round_trip_test() {
+        let (source, user) = seeded_provider().await;
+        let backup = export_user(&source, &user).await.unwrap();
+        assert_eq!(backup.accounts.len(), 2);
+        assert_eq!(backup.categories.len(), 2);
+        assert_eq!(backup.tags.len(), 2);
+        assert_eq!(backup.transactions.len(), 5);
+        assert_eq!(backup.transaction_tags[0].tag_ids.len(), 2);
+        assert_eq!(backup.imported.len(), 1);
+        assert_eq!(backup.accounts[0].initial_balance, rub("100.00"));
+
+        let mut ndjson = vec![];
+        backup.write_ndjson(&mut ndjson).unwrap();
+        let backup = Backup::read_ndjson(ndjson.as_slice()).unwrap();
+        let target = SqliteConfiguration::memory_base().configure().unwrap();
+        restore(&target, &backup, RestoreMode::PreserveIds)
+            .await
+            .unwrap();
+        let restored = export_all(&target).await.unwrap();
+        assert_eq!(records(&restored), records(&backup));
+        assert!(
+            target
+                .reconcile_balances(false)
+                .await
+                .unwrap()
+                .discrepancies
+                .is_empty()
+        );
+        assert!(matches!(
+            restore(&target, &backup, RestoreMode::PreserveIds).await,
+            Err(MoneyCalcError::Conflict(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn backup_remap_test() {
+        let (source, _) = seeded_provider().await;
+        let backup =
+            Backup::from_json(&export_all(&source).await.unwrap().to_json().unwrap()).unwrap();
+
+        let target = SqliteConfiguration::memory_base().configure().unwrap();
+        let existing = add_user(&target, "2").await;
+        target
+            .add_account(&AddAccountCommand {
+                user_id: existing.id,
+                account_name: "Other".to_string(),
+                initial_balance: rub("1.00"),
+                overdraft: OverdraftPolicy::default(),
+            })
+            .await
+            .unwrap();
+        restore(&target, &backup, RestoreMode::RemapIds)
+            .await
+            .unwrap();
+
+        let user = target.get_user_by_number("1").await.unwrap();
+        assert_ne!(user.id, backup.users[0].id);
+        let restored = export_user(&target, &user).await.unwrap();
+        let balances: Vec<_> = restored
+            .accounts
+            .iter()
+            .map(|account| (account.account.name.clone(), account.account.money))
+            .collect();
+        let expected: Vec<_> = backup
+            .accounts
+            .iter()
+            .map(|account| (account.account.name.clone(), account.account.money))
+            .collect();
+        assert_eq!(balances, expected);
+        assert_eq!(restored.transactions.len(), backup.transactions.len());
+        for transaction in &restored.transactions {
+            assert!(
+                backup
+                    .transactions
+                    .iter()
+                    .all(|old| old.id != transaction.id)
+            );
+            for linked in [
+                &transaction.linked_transaction_id,
+                &transaction.correction_of,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                target.get_transaction_by_id(linked).await.unwrap();
+            }
+        }
+        assert!(
+            target
+                .reconcile_balances(false)
+                .await
+                .unwrap()
+                .discrepancies
+                .is_empty()
+        );
+
+        let categories = target.get_categories(&user).await.unwrap();
+        assert_eq!(categories[1].parent_id, Some(categories[0].id));
+        let coffee = restored
+            .transactions
+            .iter()
+            .find(|transaction| transaction.payment_type == PaymentType::Outcome)
+            .unwrap();
+        assert_eq!(coffee.category_id, Some(categories[1].id));
+        assert_eq!(
+            target.get_transaction_tags(&coffee.id).await.unwrap(),
+            target.get_tags(&user).await.unwrap()
+        );
+        let imported = &restored.imported[0];
+        assert!(
+            target
+                .execute_external_transaction(
+                    &target
+                        .get_transaction_by_id(&imported.transaction_id)
+                        .await
+                        .unwrap(),
+                    &imported.external_id
+                )
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn backup_conflict_test() {
+        let (source, _) = seeded_provider().await;
+        add_user(&source, "2").await;
+        let backup = export_all(&source).await.unwrap();
+        let target = SqliteConfiguration::memory_base().configure().unwrap();
+        add_user(&target, "2").await;
+
+        assert!(matches!(
+            restore(&target, &backup, RestoreMode::RemapIds).await,
+            Err(MoneyCalcError::Conflict(_))
+        ));
+        assert_eq!(target.get_users().await.unwrap().len(), 1);
+        assert!(target.get_accounts().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn backup_missing_reference_test() {
+        let (source, user) = seeded_provider().await;
+        let mut backup = export_user(&source, &user).await.unwrap();
+        backup.transaction_tags[0].tag_ids.push(1000);
+        let target = SqliteConfiguration::memory_base().configure().unwrap();
+
+        assert!(matches!(
+            restore(&target, &backup, RestoreMode::PreserveIds).await,
+            Err(MoneyCalcError::Validation(_))
+        ));
+        assert!(target.get_users().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn backup_outside_link_test() {
+        let (source, user) = seeded_provider().await;
+        let other = add_user(&source, "2").await;
+        source
+            .add_account(&AddAccountCommand {
+                user_id: other.id,
+                account_name: "Other".to_string(),
+                initial_balance: rub("1.00"),
+                overdraft: OverdraftPolicy::default(),
+            })
+            .await
+            .unwrap();
+        let from = source.get_accounts_by_user(&user).await.unwrap().remove(0);
+        let to = source.search_account_by_user(&other).await.unwrap();
+        source
+            .transfer(&TransferCommand {
+                from_account_id: from.id,
+                to_account_id: to.id,
+                amount: rub("5.00"),
+                exchange_rate: None,
+                description: "Gift".to_string(),
+                create_date: chrono::Utc::now().naive_utc(),
+            })
+            .await
+            .unwrap();
+
+        let mut backup = export_user(&source, &user).await.unwrap();
+        let gift = backup
+            .transactions
+            .iter()
+            .find(|transaction| transaction.description == "Gift")
+            .unwrap();
+        assert_eq!(gift.linked_transaction_id, None);
+        let all = export_all(&source).await.unwrap();
+        assert!(
+            all.transactions
+                .iter()
+                .filter(|transaction| transaction.description == "Gift")
+                .all(|transaction| transaction.linked_transaction_id.is_some())
+        );
+
+        let target = SqliteConfiguration::memory_base().configure().unwrap();
+        backup.transactions[0].linked_transaction_id = Some("missing".to_string());
+        assert!(matches!(
+            restore(&target, &backup, RestoreMode::RemapIds).await,
+            Err(MoneyCalcError::Validation(_))
+        ));
+        backup.transactions[0].linked_transaction_id = None;
+        restore(&target, &backup, RestoreMode::RemapIds)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn backup_version_test() {
+        let text = r#"{"type":"header","version":99,"created":"2025-01-01T00:00:00"}"#;
+        assert!(matches!(
+            Backup::read_ndjson(text.as_bytes()),
+            Err(MoneyCalcError::Validation(_))
+        ));
+        assert!(Backup::read_ndjson("".as_bytes()).is_err());
+        assert!(Backup::from_json("{}").is_err());
+    }
+}
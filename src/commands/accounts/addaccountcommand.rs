@@ -1,5 +1,9 @@
+use crate::models::{account::OverdraftPolicy, money::Money};
+
+#[derive(Clone, Debug)]
 pub struct AddAccountCommand {
     pub user_id: i32,
     pub account_name: String,
-    pub initial_balance: f32,
+    pub initial_balance: Money,
+    pub overdraft: OverdraftPolicy,
 }
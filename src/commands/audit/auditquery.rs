@@ -0,0 +1,32 @@
+use chrono::NaiveDateTime;
+
+/// Filter for audit log.
+/// Every set field narrows result, unset fields are ignored.
+/// from_date is inclusive, to_date is exclusive.
+/// after_id is id of the last record of previous page.
+#[derive(Clone, Debug)]
+pub struct AuditQuery {
+    pub actor: Option<String>,
+    pub action: Option<String>,
+    pub entity: Option<String>,
+    pub entity_id: Option<String>,
+    pub from_date: Option<NaiveDateTime>,
+    pub to_date: Option<NaiveDateTime>,
+    pub after_id: Option<i64>,
+    pub limit: u32,
+}
+
+impl Default for AuditQuery {
+    fn default() -> Self {
+        Self {
+            actor: None,
+            action: None,
+            entity: None,
+            entity_id: None,
+            from_date: None,
+            to_date: None,
+            after_id: None,
+            limit: 100,
+        }
+    }
+}
@@ -0,0 +1,14 @@
+use crate::models::{
+    budget::{BudgetPeriod, BudgetScope},
+    money::Money,
+};
+
+/// Create budget, limit is planned spending for one period.
+#[derive(Clone, Debug)]
+pub struct AddBudgetCommand {
+    pub user_id: i32,
+    pub name: String,
+    pub scope: BudgetScope,
+    pub period: BudgetPeriod,
+    pub limit: Money,
+}
@@ -0,0 +1,7 @@
+/// Create category, parent_id is None for top level category.
+#[derive(Clone, Debug)]
+pub struct AddCategoryCommand {
+    pub user_id: i32,
+    pub parent_id: Option<i32>,
+    pub name: String,
+}
@@ -1,2 +1,8 @@
 pub mod accounts;
+pub mod audit;
+pub mod budgets;
+pub mod categories;
+pub mod recurring;
+pub mod tags;
+pub mod transactions;
 pub mod users;
@@ -0,0 +1,19 @@
+use chrono::NaiveDate;
+
+use crate::models::{money::Money, moneytransaction::PaymentType, recurring::Schedule};
+
+/// Create recurring transaction template.
+/// Amount is positive and in account currency, payment type is Income or Outcome.
+#[derive(Clone, Debug)]
+pub struct AddRecurringCommand {
+    pub user_id: i32,
+    pub account_id: i32,
+    pub amount: Money,
+    pub payment_type: PaymentType,
+    pub payment_target: String,
+    pub description: String,
+    pub category_id: Option<i32>,
+    pub schedule: Schedule,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+}
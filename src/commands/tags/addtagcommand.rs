@@ -0,0 +1,5 @@
+#[derive(Clone, Debug)]
+pub struct AddTagCommand {
+    pub user_id: i32,
+    pub name: String,
+}
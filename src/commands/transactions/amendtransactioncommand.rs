@@ -0,0 +1,15 @@
+use chrono::NaiveDateTime;
+
+use crate::models::money::Money;
+
+/// Replace transaction by corrected copy, account and payment type are kept.
+/// amount is positive and in account currency.
+#[derive(Clone, Debug)]
+pub struct AmendTransactionCommand {
+    pub transaction_id: String,
+    pub amount: Money,
+    pub description: String,
+    pub payment_target: String,
+    pub category_id: Option<i32>,
+    pub create_date: NaiveDateTime,
+}
@@ -0,0 +1,4 @@
+pub mod amendtransactioncommand;
+pub mod reversetransactioncommand;
+pub mod transactionquery;
+pub mod transfercommand;
@@ -0,0 +1,10 @@
+use chrono::NaiveDateTime;
+
+/// Cancel transaction by compensating entry dated create_date.
+/// Transaction is voided when create_date is its own date.
+#[derive(Clone, Debug)]
+pub struct ReverseTransactionCommand {
+    pub transaction_id: String,
+    pub description: String,
+    pub create_date: NaiveDateTime,
+}
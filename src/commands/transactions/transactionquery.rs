@@ -0,0 +1,56 @@
+use chrono::NaiveDateTime;
+
+use crate::models::{money::Money, moneytransaction::PaymentType};
+
+/// Order of transactions in query result.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TransactionOrder {
+    DateAsc,
+    #[default]
+    DateDesc,
+    AmountAsc,
+    AmountDesc,
+}
+
+/// Filter for transaction history.
+/// Every set field narrows result, unset fields are ignored.
+/// from_date is inclusive, to_date is exclusive.
+/// Amount bounds compare absolute amount in the bound currency.
+/// category_id matches the category and all its subcategories.
+/// cursor is next_cursor of previous page.
+#[derive(Clone, Debug)]
+pub struct TransactionQuery {
+    pub account_id: Option<i32>,
+    pub user_id: Option<i32>,
+    pub from_date: Option<NaiveDateTime>,
+    pub to_date: Option<NaiveDateTime>,
+    pub payment_type: Option<PaymentType>,
+    pub payment_target: Option<String>,
+    pub min_amount: Option<Money>,
+    pub max_amount: Option<Money>,
+    pub description_contains: Option<String>,
+    pub category_id: Option<i32>,
+    pub order: TransactionOrder,
+    pub limit: u32,
+    pub cursor: Option<String>,
+}
+
+impl Default for TransactionQuery {
+    fn default() -> Self {
+        Self {
+            account_id: None,
+            user_id: None,
+            from_date: None,
+            to_date: None,
+            payment_type: None,
+            payment_target: None,
+            min_amount: None,
+            max_amount: None,
+            description_contains: None,
+            category_id: None,
+            order: TransactionOrder::default(),
+            limit: 50,
+            cursor: None,
+        }
+    }
+}
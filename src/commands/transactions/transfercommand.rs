@@ -0,0 +1,16 @@
+use chrono::NaiveDateTime;
+
+use crate::models::{exchangerate::ExchangeRate, money::Money};
+
+/// Move money from one account to another.
+/// amount is in currency of source account.
+/// exchange_rate is required when target account has another currency.
+#[derive(Clone, Debug)]
+pub struct TransferCommand {
+    pub from_account_id: i32,
+    pub to_account_id: i32,
+    pub amount: Money,
+    pub exchange_rate: Option<ExchangeRate>,
+    pub description: String,
+    pub create_date: NaiveDateTime,
+}
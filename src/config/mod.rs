@@ -1,18 +1,114 @@
-use crate::providers::{
-    AccountProvider, TransactionWorker, UserProvider, bases::sqlite::SqliteProvider,
+pub mod settings;
+
+use std::{fmt, str::FromStr, time::Duration};
+
+#[cfg(feature = "memory")]
+use crate::providers::bases::memory::MemoryProvider;
+#[cfg(feature = "postgres")]
+use crate::providers::bases::postgres::PostgresProvider;
+use crate::{
+    errors::{MoneyCalcError, MoneyCalcResult},
+    providers::{
+        AccountProvider, DataProvider, TransactionWorker, UserProvider,
+        bases::{
+            sqlite::SqliteProvider,
+            sqlitepool::{BUSY_TIMEOUT, DEFAULT_POOL_SIZE},
+        },
+    },
 };
 
+/// Sqlite storage settings.
+/// pool_size is count of connections shared by provider and its clones.
+/// journal_mode is set on every connection to file database, in-memory base keeps its own.
+/// busy_timeout is how long a connection waits for a lock held by another one.
+/// auto_migrate makes configure apply migrations.
+/// audit_hash_chain makes every audit record keep hash of the previous one.
 #[derive(Clone, Debug)]
 pub struct SqliteConfiguration {
     pub connection_string: String,
     pub memory_base: bool,
+    pub pool_size: usize,
+    pub journal_mode: JournalMode,
+    pub busy_timeout: Duration,
+    pub auto_migrate: bool,
+    pub audit_hash_chain: bool,
+}
+
+/// Sqlite journal mode.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JournalMode {
+    Delete,
+    Truncate,
+    Persist,
+    Memory,
+    /// Readers don't wait for writer.
+    #[default]
+    Wal,
+    Off,
+}
+
+impl JournalMode {
+    pub const ALL: [JournalMode; 6] = [
+        JournalMode::Delete,
+        JournalMode::Truncate,
+        JournalMode::Persist,
+        JournalMode::Memory,
+        JournalMode::Wal,
+        JournalMode::Off,
+    ];
+
+    /// Value of journal_mode pragma.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JournalMode::Delete => "DELETE",
+            JournalMode::Truncate => "TRUNCATE",
+            JournalMode::Persist => "PERSIST",
+            JournalMode::Memory => "MEMORY",
+            JournalMode::Wal => "WAL",
+            JournalMode::Off => "OFF",
+        }
+    }
+}
+
+impl fmt::Display for JournalMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for JournalMode {
+    type Err = MoneyCalcError;
+
+    /// Parse pragma value in any case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        JournalMode::ALL
+            .into_iter()
+            .find(|mode| mode.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                MoneyCalcError::Validation(format!(
+                    "unknown journal mode {}, expected one of {}",
+                    s,
+                    JournalMode::ALL
+                        .map(|mode| mode.as_str().to_ascii_lowercase())
+                        .join(", ")
+                ))
+            })
+    }
 }
 
 pub trait StorageConfiguration<T>
 where
     T: UserProvider + AccountProvider + TransactionWorker,
 {
-    fn configure(&self) -> Result<T, Box<dyn std::error::Error>>;
+    fn configure(&self) -> MoneyCalcResult<T>;
+
+    /// Provider behind trait object, for code which keeps storage as `Box<dyn DataProvider>`.
+    fn configure_boxed(&self) -> MoneyCalcResult<Box<dyn DataProvider>>
+    where
+        T: 'static,
+    {
+        Ok(Box::new(self.configure()?))
+    }
 }
 
 impl SqliteConfiguration {
@@ -20,6 +116,11 @@ impl SqliteConfiguration {
         Self {
             connection_string: connection_string.to_string(),
             memory_base: false,
+            pool_size: DEFAULT_POOL_SIZE,
+            journal_mode: JournalMode::default(),
+            busy_timeout: BUSY_TIMEOUT,
+            auto_migrate: true,
+            audit_hash_chain: false,
         }
     }
 
@@ -27,19 +128,146 @@ impl SqliteConfiguration {
         Self {
             connection_string: String::new(),
             memory_base: true,
+            pool_size: DEFAULT_POOL_SIZE,
+            journal_mode: JournalMode::default(),
+            busy_timeout: BUSY_TIMEOUT,
+            auto_migrate: true,
+            audit_hash_chain: false,
         }
     }
+
+    pub fn with_pool_size(mut self, pool_size: usize) -> Self {
+        self.pool_size = pool_size;
+        self
+    }
+
+    pub fn with_journal_mode(mut self, journal_mode: JournalMode) -> Self {
+        self.journal_mode = journal_mode;
+        self
+    }
+
+    pub fn with_busy_timeout(mut self, busy_timeout: Duration) -> Self {
+        self.busy_timeout = busy_timeout;
+        self
+    }
+
+    pub fn with_auto_migrate(mut self, auto_migrate: bool) -> Self {
+        self.auto_migrate = auto_migrate;
+        self
+    }
+
+    pub fn with_audit_hash_chain(mut self, audit_hash_chain: bool) -> Self {
+        self.audit_hash_chain = audit_hash_chain;
+        self
+    }
 }
 
 impl StorageConfiguration<SqliteProvider> for SqliteConfiguration {
-    fn configure(&self) -> Result<SqliteProvider, Box<dyn std::error::Error>> {
-        SqliteProvider::new(&self, true)
+    fn configure(&self) -> MoneyCalcResult<SqliteProvider> {
+        SqliteProvider::new(self, self.auto_migrate)
+    }
+}
+
+/// PostgreSQL storage settings.
+/// connection_string is libpq key=value string or postgresql:// url.
+/// schema, when set, is a plain identifier of schema holding all tables, it is created if missing.
+/// pool_size is maximum count of connections shared by provider and its clones.
+/// auto_migrate makes provider apply migrations on first use.
+#[cfg(feature = "postgres")]
+#[derive(Clone, Debug)]
+pub struct PostgresConfiguration {
+    pub connection_string: String,
+    pub schema: Option<String>,
+    pub pool_size: usize,
+    pub auto_migrate: bool,
+    pub audit_hash_chain: bool,
+}
+
+#[cfg(feature = "postgres")]
+impl PostgresConfiguration {
+    pub fn new(connection_string: &str) -> Self {
+        Self {
+            connection_string: connection_string.to_string(),
+            schema: None,
+            pool_size: DEFAULT_POOL_SIZE,
+            auto_migrate: true,
+            audit_hash_chain: false,
+        }
+    }
+
+    pub fn with_schema(mut self, schema: &str) -> Self {
+        self.schema = Some(schema.to_string());
+        self
+    }
+
+    pub fn with_pool_size(mut self, pool_size: usize) -> Self {
+        self.pool_size = pool_size;
+        self
+    }
+
+    pub fn with_auto_migrate(mut self, auto_migrate: bool) -> Self {
+        self.auto_migrate = auto_migrate;
+        self
+    }
+
+    pub fn with_audit_hash_chain(mut self, audit_hash_chain: bool) -> Self {
+        self.audit_hash_chain = audit_hash_chain;
+        self
+    }
+}
+
+/// Connections are opened and migrations applied on first use of provider.
+#[cfg(feature = "postgres")]
+impl StorageConfiguration<PostgresProvider> for PostgresConfiguration {
+    fn configure(&self) -> MoneyCalcResult<PostgresProvider> {
+        PostgresProvider::new(self, self.auto_migrate)
+    }
+}
+
+/// Storage chosen at runtime, like from settings of application.
+#[derive(Clone, Debug)]
+pub enum ProviderConfiguration {
+    Sqlite(SqliteConfiguration),
+    #[cfg(feature = "memory")]
+    Memory,
+    #[cfg(feature = "postgres")]
+    Postgres(PostgresConfiguration),
+}
+
+impl ProviderConfiguration {
+    pub fn configure(&self) -> MoneyCalcResult<Box<dyn DataProvider>> {
+        match self {
+            Self::Sqlite(config) => config.configure_boxed(),
+            #[cfg(feature = "memory")]
+            Self::Memory => Ok(Box::new(MemoryProvider::new())),
+            #[cfg(feature = "postgres")]
+            Self::Postgres(config) => config.configure_boxed(),
+        }
+    }
+}
+
+impl From<SqliteConfiguration> for ProviderConfiguration {
+    fn from(value: SqliteConfiguration) -> Self {
+        Self::Sqlite(value)
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl From<PostgresConfiguration> for ProviderConfiguration {
+    fn from(value: PostgresConfiguration) -> Self {
+        Self::Postgres(value)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::config::{SqliteConfiguration, StorageConfiguration};
+    use std::sync::Arc;
+
+    use crate::{
+        commands::users::addusercommand::AddUserCommand,
+        config::{ProviderConfiguration, SqliteConfiguration, StorageConfiguration},
+        providers::DataProvider,
+    };
 
     #[test]
     fn create_storage_by_config() {
@@ -62,4 +290,40 @@ mod tests {
         let res = std::fs::remove_file("test.db").is_err();
         assert!(res);
     }
+
+    async fn add_user_from_clone(config: ProviderConfiguration) {
+        let provider: Arc<dyn DataProvider> = Arc::from(config.configure().unwrap());
+        let clone = provider.clone();
+        tokio::spawn(async move {
+            clone
+                .add_user(&AddUserCommand {
+                    user_name: "scam".to_string(),
+                    user_number: "1".to_string(),
+                })
+                .await
+                .unwrap();
+        })
+        .await
+        .unwrap();
+        assert_eq!(provider.get_users().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn configure_provider_at_runtime_test() {
+        add_user_from_clone(SqliteConfiguration::memory_base().into()).await;
+    }
+
+    #[cfg(feature = "memory")]
+    #[tokio::test]
+    async fn configure_memory_provider_at_runtime_test() {
+        add_user_from_clone(ProviderConfiguration::Memory).await;
+    }
+
+    #[tokio::test]
+    async fn configure_boxed_provider_test() {
+        let provider = SqliteConfiguration::memory_base()
+            .configure_boxed()
+            .unwrap();
+        assert!(provider.get_users().await.unwrap().is_empty());
+    }
 }
@@ -0,0 +1,475 @@
+//! Storage settings loaded from TOML file and environment variables.
+//!
+//! File keeps settings in `[storage]` table, other tables of the file are left to application:
+//! ```toml
+//! [storage]
+//! provider = "sqlite"
+//! path = "money.db3"
+//! pool_size = 4
+//! journal_mode = "wal"
+//! busy_timeout_ms = 5000
+//! auto_migrate = true
+//! ```
+//! Environment variable with ENV_PREFIX and upper case name of setting,
+//! like MONEYCALC_STORAGE_POOL_SIZE, overrides the file.
+use std::{path::Path, str::FromStr, time::Duration};
+
+use serde::Deserialize;
+
+#[cfg(feature = "postgres")]
+use crate::config::PostgresConfiguration;
+use crate::{
+    config::{JournalMode, ProviderConfiguration, SqliteConfiguration},
+    errors::{MoneyCalcError, MoneyCalcResult},
+};
+
+pub const ENV_PREFIX: &str = "MONEYCALC_STORAGE_";
+
+/// Sqlite path of in-memory base.
+pub const MEMORY_PATH: &str = ":memory:";
+
+/// Settings of `[storage]` table, missing ones take defaults of provider configuration.
+/// Settings which chosen provider doesn't use are rejected, as they are likely a mistake.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StorageSettings {
+    /// sqlite, memory or postgres, sqlite when not set.
+    pub provider: Option<String>,
+    /// Sqlite database file or MEMORY_PATH.
+    pub path: Option<String>,
+    /// Postgres connection string.
+    pub url: Option<String>,
+    /// Postgres schema holding all tables.
+    pub schema: Option<String>,
+    pub pool_size: Option<usize>,
+    pub journal_mode: Option<String>,
+    pub busy_timeout_ms: Option<u64>,
+    pub auto_migrate: Option<bool>,
+    pub audit_hash_chain: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct SettingsFile {
+    #[serde(default)]
+    storage: StorageSettings,
+}
+
+impl StorageSettings {
+    /// Settings of file overridden by environment, validated for chosen provider.
+    pub fn load(path: impl AsRef<Path>) -> MoneyCalcResult<ProviderConfiguration> {
+        Self::from_file(path)?
+            .merge(Self::from_env()?)
+            .configuration()
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> MoneyCalcResult<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            MoneyCalcError::Validation(format!(
+                "can't read settings file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Self::from_toml(&text).map_err(|e| match e {
+            MoneyCalcError::Validation(message) => {
+                MoneyCalcError::Validation(format!("{}: {}", path.display(), message))
+            }
+            e => e,
+        })
+    }
+
+    pub fn from_toml(text: &str) -> MoneyCalcResult<Self> {
+        toml::from_str::<SettingsFile>(text)
+            .map(|file| file.storage)
+            .map_err(|e| {
+                MoneyCalcError::Validation(format!(
+                    "invalid storage settings: {}",
+                    e.to_string().trim_end()
+                ))
+            })
+    }
+
+    /// Settings from environment variables with ENV_PREFIX.
+    pub fn from_env() -> MoneyCalcResult<Self> {
+        Self::from_vars(std::env::vars_os().filter_map(|(name, value)| {
+            Some((name.into_string().ok()?, value.into_string().ok()?))
+        }))
+    }
+
+    /// Settings from pairs of variable name and value, names without ENV_PREFIX are skipped.
+    pub fn from_vars<I, K, V>(vars: I) -> MoneyCalcResult<Self>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut settings = Self::default();
+        for (name, value) in vars {
+            let (name, value) = (name.as_ref(), value.as_ref());
+            let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            match key {
+                "PROVIDER" => settings.provider = Some(value.to_string()),
+                "PATH" => settings.path = Some(value.to_string()),
+                "URL" => settings.url = Some(value.to_string()),
+                "SCHEMA" => settings.schema = Some(value.to_string()),
+                "POOL_SIZE" => settings.pool_size = Some(parse_var(name, value, "a number")?),
+                "JOURNAL_MODE" => settings.journal_mode = Some(value.to_string()),
+                "BUSY_TIMEOUT_MS" => {
+                    settings.busy_timeout_ms = Some(parse_var(name, value, "a number")?)
+                }
+                "AUTO_MIGRATE" => {
+                    settings.auto_migrate = Some(parse_var(name, value, "true or false")?)
+                }
+                "AUDIT_HASH_CHAIN" => {
+                    settings.audit_hash_chain = Some(parse_var(name, value, "true or false")?)
+                }
+                _ => {
+                    return Err(MoneyCalcError::Validation(format!(
+                        "unknown storage setting variable {}",
+                        name
+                    )));
+                }
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Settings set in other replace ones of self.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            provider: other.provider.or(self.provider),
+            path: other.path.or(self.path),
+            url: other.url.or(self.url),
+            schema: other.schema.or(self.schema),
+            pool_size: other.pool_size.or(self.pool_size),
+            journal_mode: other.journal_mode.or(self.journal_mode),
+            busy_timeout_ms: other.busy_timeout_ms.or(self.busy_timeout_ms),
+            auto_migrate: other.auto_migrate.or(self.auto_migrate),
+            audit_hash_chain: other.audit_hash_chain.or(self.audit_hash_chain),
+        }
+    }
+
+    /// Validate settings and build configuration of chosen provider.
+    pub fn configuration(&self) -> MoneyCalcResult<ProviderConfiguration> {
+        if self.pool_size == Some(0) {
+            return Err(MoneyCalcError::Validation(
+                "pool_size must be positive".to_string(),
+            ));
+        }
+        let provider = self.provider.as_deref().unwrap_or("sqlite");
+        match provider {
+            "sqlite" => {
+                self.check_used(
+                    provider,
+                    &[
+                        "path",
+                        "pool_size",
+                        "journal_mode",
+                        "busy_timeout_ms",
+                        "auto_migrate",
+                        "audit_hash_chain",
+                    ],
+                )?;
+                let path = self
+                    .path
+                    .as_deref()
+                    .filter(|path| !path.trim().is_empty())
+                    .ok_or_else(|| {
+                        MoneyCalcError::Validation(format!(
+                            "path is required by sqlite provider, use {} for in-memory base",
+                            MEMORY_PATH
+                        ))
+                    })?;
+                let mut config = if path == MEMORY_PATH {
+                    SqliteConfiguration::memory_base()
+                } else {
+                    SqliteConfiguration::new(path)
+                };
+                if let Some(pool_size) = self.pool_size {
+                    config = config.with_pool_size(pool_size);
+                }
+                if let Some(journal_mode) = &self.journal_mode {
+                    config = config.with_journal_mode(JournalMode::from_str(journal_mode)?);
+                }
+                if let Some(busy_timeout_ms) = self.busy_timeout_ms {
+                    config = config.with_busy_timeout(Duration::from_millis(busy_timeout_ms));
+                }
+                if let Some(auto_migrate) = self.auto_migrate {
+                    config = config.with_auto_migrate(auto_migrate);
+                }
+                if let Some(audit_hash_chain) = self.audit_hash_chain {
+                    config = config.with_audit_hash_chain(audit_hash_chain);
+                }
+                Ok(ProviderConfiguration::Sqlite(config))
+            }
+            #[cfg(feature = "memory")]
+            "memory" => {
+                self.check_used(provider, &[])?;
+                Ok(ProviderConfiguration::Memory)
+            }
+            #[cfg(feature = "postgres")]
+            "postgres" => {
+                self.check_used(
+                    provider,
+                    &[
+                        "url",
+                        "schema",
+                        "pool_size",
+                        "auto_migrate",
+                        "audit_hash_chain",
+                    ],
+                )?;
+                let url = self.url.as_deref().ok_or_else(|| {
+                    MoneyCalcError::Validation("url is required by postgres provider".to_string())
+                })?;
+                let mut config = PostgresConfiguration::new(url);
+                if let Some(schema) = &self.schema {
+                    config = config.with_schema(schema);
+                }
+                if let Some(pool_size) = self.pool_size {
+                    config = config.with_pool_size(pool_size);
+                }
+                if let Some(auto_migrate) = self.auto_migrate {
+                    config = config.with_auto_migrate(auto_migrate);
+                }
+                if let Some(audit_hash_chain) = self.audit_hash_chain {
+                    config = config.with_audit_hash_chain(audit_hash_chain);
+                }
+                Ok(ProviderConfiguration::Postgres(config))
+            }
+            #[cfg(not(feature = "memory"))]
+            "memory" => Err(disabled_provider(provider)),
+            #[cfg(not(feature = "postgres"))]
+            "postgres" => Err(disabled_provider(provider)),
+            _ => Err(MoneyCalcError::Validation(format!(
+                "unknown storage provider {}, expected sqlite, memory or postgres",
+                provider
+            ))),
+        }
+    }
+
+    fn check_used(&self, provider: &str, used: &[&str]) -> MoneyCalcResult<()> {
+        let set = [
+            ("path", self.path.is_some()),
+            ("url", self.url.is_some()),
+            ("schema", self.schema.is_some()),
+            ("pool_size", self.pool_size.is_some()),
+            ("journal_mode", self.journal_mode.is_some()),
+            ("busy_timeout_ms", self.busy_timeout_ms.is_some()),
+            ("auto_migrate", self.auto_migrate.is_some()),
+            ("audit_hash_chain", self.audit_hash_chain.is_some()),
+        ];
+        match set
+            .iter()
+            .find(|(name, is_set)| *is_set && !used.contains(name))
+        {
+            Some((name, _)) => Err(MoneyCalcError::Validation(format!(
+                "{} is not used by {} provider",
+                name, provider
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(not(all(feature = "memory", feature = "postgres")))]
+fn disabled_provider(provider: &str) -> MoneyCalcError {
+    MoneyCalcError::Validation(format!(
+        "{} provider requires crate feature `{}`",
+        provider, provider
+    ))
+}
+
+fn parse_var<T: FromStr>(name: &str, value: &str, expected: &str) -> MoneyCalcResult<T> {
+    value.trim().parse().map_err(|_| {
+        MoneyCalcError::Validation(format!("{} must be {}, got {}", name, expected, value))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        config::{
+            JournalMode, ProviderConfiguration, SqliteConfiguration,
+            settings::{ENV_PREFIX, StorageSettings},
+        },
+        errors::MoneyCalcError,
+    };
+
+    fn validation_message<T: std::fmt::Debug>(result: Result<T, MoneyCalcError>) -> String {
+        match result {
+            Err(MoneyCalcError::Validation(message)) => message,
+            other => panic!("expected validation error, got {:?}", other),
+        }
+    }
+
+    #[allow(irrefutable_let_patterns)]
+    fn sqlite(config: ProviderConfiguration) -> SqliteConfiguration {
+        let ProviderConfiguration::Sqlite(config) = config else {
+            panic!("sqlite is the default provider");
+        };
+        config
+    }
+
+    #[test]
+    fn file_and_env_settings_test() {
+        let file = StorageSettings::from_toml(
+            r#"
+            [application]
+            name = "money"
+
+            [storage]
+            path = "money.db3"
+            pool_size = 2
+            journal_mode = "truncate"
+            busy_timeout_ms = 250
+            "#,
+        )
+        .unwrap();
+        let env = StorageSettings::from_vars([
+            ("HOME", "/root"),
+            ("MONEYCALC_STORAGE_POOL_SIZE", " 8"),
+            ("MONEYCALC_STORAGE_AUTO_MIGRATE", "false"),
+        ])
+        .unwrap();
+
+        let config = sqlite(file.merge(env).configuration().unwrap());
+        assert_eq!(config.connection_string, "money.db3");
+        assert!(!config.memory_base);
+        assert_eq!(config.pool_size, 8);
+        assert_eq!(config.journal_mode, JournalMode::Truncate);
+        assert_eq!(config.busy_timeout, Duration::from_millis(250));
+        assert!(!config.auto_migrate);
+        assert!(!config.audit_hash_chain);
+
+        let memory = StorageSettings::from_toml("[storage]\npath = \":memory:\"").unwrap();
+        let config = sqlite(memory.configuration().unwrap());
+        assert!(config.memory_base);
+        assert!(config.auto_migrate);
+    }
+
+    #[test]
+    fn invalid_settings_test() {
+        assert!(
+            validation_message(StorageSettings::from_toml("[storage]\npool = 2")).contains("pool")
+        );
+        assert!(
+            validation_message(StorageSettings::from_toml("[storage]\npool_size = \"2\""))
+                .contains("pool_size")
+        );
+        assert!(
+            validation_message(StorageSettings::from_vars([(
+                format!("{}POOL_SIZE", ENV_PREFIX),
+                "many".to_string()
+            )]))
+            .contains("MONEYCALC_STORAGE_POOL_SIZE must be a number")
+        );
+        assert!(
+            validation_message(StorageSettings::from_vars([(
+                "MONEYCALC_STORAGE_PATHS",
+                "a"
+            )]))
+            .contains("MONEYCALC_STORAGE_PATHS")
+        );
+
+        for (toml, expected) in [
+            ("", "path is required"),
+            (
+                "path = \"a.db3\"\npool_size = 0",
+                "pool_size must be positive",
+            ),
+            (
+                "path = \"a.db3\"\njournal_mode = \"fast\"",
+                "unknown journal mode fast",
+            ),
+            (
+                "path = \"a.db3\"\nschema = \"money\"",
+                "schema is not used by sqlite",
+            ),
+            ("provider = \"mysql\"", "unknown storage provider mysql"),
+            #[cfg(feature = "memory")]
+            (
+                "provider = \"memory\"\npath = \"a.db3\"",
+                "path is not used by memory",
+            ),
+            #[cfg(not(feature = "memory"))]
+            (
+                "provider = \"memory\"",
+                "memory provider requires crate feature `memory`",
+            ),
+            #[cfg(feature = "postgres")]
+            ("provider = \"postgres\"", "url is required"),
+            #[cfg(feature = "postgres")]
+            (
+                "provider = \"postgres\"\nurl = \"host=localhost\"\njournal_mode = \"wal\"",
+                "journal_mode is not used by postgres",
+            ),
+            #[cfg(not(feature = "postgres"))]
+            (
+                "provider = \"postgres\"",
+                "postgres provider requires crate feature `postgres`",
+            ),
+        ] {
+            let settings = StorageSettings::from_toml(&format!("[storage]\n{}", toml)).unwrap();
+            let message = validation_message(settings.configuration());
+            assert!(message.contains(expected), "{}: {}", toml, message);
+        }
+
+        let missing = std::env::temp_dir().join(format!("{}.toml", uuid::Uuid::new_v4()));
+        assert!(
+            validation_message(StorageSettings::from_file(&missing))
+                .contains("can't read settings file")
+        );
+    }
+
+    #[tokio::test]
+    async fn configure_from_file_test() {
+        let dir = std::env::temp_dir();
+        let base = dir.join(format!("{}.db3", uuid::Uuid::new_v4()));
+        let file = dir.join(format!("{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &file,
+            format!(
+                "[storage]\nprovider = \"sqlite\"\npath = {:?}\njournal_mode = \"delete\"\n",
+                base.to_str().unwrap()
+            ),
+        )
+        .unwrap();
+
+        let provider = StorageSettings::from_file(&file)
+            .unwrap()
+            .configuration()
+            .unwrap()
+            .configure()
+            .unwrap();
+        assert!(provider.get_users().await.unwrap().is_empty());
+        let journal_mode: String = rusqlite::Connection::open(&base)
+            .unwrap()
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal_mode, "delete");
+
+        drop(provider);
+        std::fs::remove_file(&base).unwrap();
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[cfg(feature = "postgres")]
+    #[test]
+    fn configure_postgres_settings_test() {
+        let settings = StorageSettings::from_toml(
+            "[storage]\nprovider = \"postgres\"\nurl = \"host=localhost\"\nschema = \"money\"\npool_size = 3",
+        )
+        .unwrap();
+        let ProviderConfiguration::Postgres(config) = settings.configuration().unwrap() else {
+            panic!("postgres provider is chosen");
+        };
+        assert_eq!(config.schema.as_deref(), Some("money"));
+        assert_eq!(config.pool_size, 3);
+    }
+}
@@ -0,0 +1,104 @@
+use std::fmt;
+
+use crate::models::money::MoneyError;
+
+/// Error of storage operations.
+/// Storage and Migration keep original error of the database driver as source.
+#[derive(Debug)]
+pub enum MoneyCalcError {
+    /// Requested record does not exist.
+    NotFound(String),
+    /// Record conflicts with existing one, like already taken unique number
+    /// or record still referenced by others on delete.
+    Conflict(String),
+    /// Account balance is too low for the operation.
+    InsufficientFunds(String),
+    /// Input data is incorrect, like reference to a missing record.
+    Validation(String),
+    /// Money arithmetic failed, like currency mismatch or overflow.
+    Money(MoneyError),
+    /// Database is locked or busy by another connection.
+    Busy(String),
+    Storage(Box<dyn std::error::Error + Send + Sync>),
+    Migration(Box<dyn std::error::Error + Send + Sync>),
+}
+
+pub type MoneyCalcResult<T> = Result<T, MoneyCalcError>;
+
+impl fmt::Display for MoneyCalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoneyCalcError::NotFound(what) => write!(f, "not found: {}", what),
+            MoneyCalcError::Conflict(what) => write!(f, "conflict: {}", what),
+            MoneyCalcError::InsufficientFunds(what) => write!(f, "insufficient funds: {}", what),
+            MoneyCalcError::Validation(what) => write!(f, "validation failed: {}", what),
+            MoneyCalcError::Money(error) => write!(f, "{}", error),
+            MoneyCalcError::Busy(what) => write!(f, "database is busy: {}", what),
+            MoneyCalcError::Storage(error) => write!(f, "storage error: {}", error),
+            MoneyCalcError::Migration(error) => write!(f, "migration error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for MoneyCalcError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MoneyCalcError::Money(error) => Some(error),
+            MoneyCalcError::Storage(error) | MoneyCalcError::Migration(error) => {
+                Some(error.as_ref())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<MoneyError> for MoneyCalcError {
+    fn from(value: MoneyError) -> Self {
+        MoneyCalcError::Money(value)
+    }
+}
+
+impl From<std::io::Error> for MoneyCalcError {
+    fn from(value: std::io::Error) -> Self {
+        MoneyCalcError::Storage(Box::new(value))
+    }
+}
+
+impl From<serde_json::Error> for MoneyCalcError {
+    fn from(value: serde_json::Error) -> Self {
+        MoneyCalcError::Storage(Box::new(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use crate::{
+        errors::MoneyCalcError,
+        models::money::{Currency, MoneyError},
+    };
+
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+
+    #[test]
+    fn error_is_send_sync_test() {
+        assert_send_sync::<MoneyCalcError>();
+    }
+
+    #[test]
+    fn error_source_test() {
+        let error: MoneyCalcError = MoneyError::CurrencyMismatch {
+            left: Currency::RUB,
+            right: Currency::USD,
+        }
+        .into();
+        assert!(error.source().is_some());
+        assert_eq!(error.to_string(), "currency mismatch: RUB and USD");
+        assert!(
+            MoneyCalcError::NotFound("user 1".to_string())
+                .source()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,404 @@
+use std::io::Read;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+use crate::{
+    errors::{MoneyCalcError, MoneyCalcResult},
+    imports::{ImportReport, RowError},
+    models::{
+        account::Account,
+        money::Money,
+        moneytransaction::{MoneyTransaction, PaymentType},
+        user::User,
+    },
+};
+
+/// Column of csv file, by zero based index or by header name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsvColumn {
+    Index(usize),
+    Header(String),
+}
+
+/// Meaning of sign in single amount column.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SignConvention {
+    /// Negative amount is outcome, like most bank statements.
+    #[default]
+    NegativeIsOutcome,
+    /// Negative amount is income, like credit card statements.
+    NegativeIsIncome,
+}
+
+/// Columns holding amount of row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AmountColumns {
+    /// One column with signed amount.
+    Signed(CsvColumn),
+    /// Separate columns, at most one of them may be non-zero in every row.
+    /// Empty column counts as zero.
+    DebitCredit { debit: CsvColumn, credit: CsvColumn },
+}
+
+/// How csv statement maps to transactions.
+/// date_format is chrono format, formats without time give transactions at midnight.
+/// Amounts are in currency of target account.
+#[derive(Debug, Clone)]
+pub struct CsvMapping {
+    pub date: CsvColumn,
+    pub date_format: String,
+    pub amount: AmountColumns,
+    pub sign: SignConvention,
+    pub decimal_separator: char,
+    pub thousands_separator: Option<char>,
+    pub payment_target: Option<CsvColumn>,
+    pub description: Option<CsvColumn>,
+    pub delimiter: u8,
+    pub has_headers: bool,
+}
+
+impl CsvMapping {
+    pub fn new(date: CsvColumn, amount: AmountColumns) -> Self {
+        Self {
+            date,
+            date_format: "%Y-%m-%d".to_string(),
+            amount,
+            sign: SignConvention::default(),
+            decimal_separator: '.',
+            thousands_separator: None,
+            payment_target: None,
+            description: None,
+            delimiter: b',',
+            has_headers: true,
+        }
+    }
+
+    pub fn with_date_format(mut self, date_format: &str) -> Self {
+        self.date_format = date_format.to_string();
+        self
+    }
+
+    pub fn with_sign(mut self, sign: SignConvention) -> Self {
+        self.sign = sign;
+        self
+    }
+
+    pub fn with_separators(
+        mut self,
+        decimal_separator: char,
+        thousands_separator: Option<char>,
+    ) -> Self {
+        self.decimal_separator = decimal_separator;
+        self.thousands_separator = thousands_separator;
+        self
+    }
+
+    pub fn with_payment_target(mut self, payment_target: CsvColumn) -> Self {
+        self.payment_target = Some(payment_target);
+        self
+    }
+
+    pub fn with_description(mut self, description: CsvColumn) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    pub fn with_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+}
+
+/// Amount columns with header names resolved to indexes.
+#[derive(Clone, Copy)]
+enum AmountIndexes {
+    Signed(usize),
+    DebitCredit { debit: usize, credit: usize },
+}
+
+/// Parse csv statement into transactions of account.
+/// Bad rows are reported in errors and do not stop the import.
+/// Fails only if file can not be read or mapped header is missing.
+pub fn import_csv<R: Read>(
+    reader: R,
+    mapping: &CsvMapping,
+    user: &User,
+    account: &Account,
+) -> MoneyCalcResult<ImportReport> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(mapping.delimiter)
+        .has_headers(mapping.has_headers)
+        .flexible(true)
+        .from_reader(reader);
+    let headers = if mapping.has_headers {
+        Some(reader.headers().map_err(csv_error)?.clone())
+    } else {
+        None
+    };
+    let index = |column: &CsvColumn| -> MoneyCalcResult<usize> {
+        match column {
+            CsvColumn::Index(index) => Ok(*index),
+            CsvColumn::Header(name) => headers
+                .as_ref()
+                .and_then(|headers| headers.iter().position(|header| header.trim() == name))
+                .ok_or_else(|| {
+                    MoneyCalcError::Validation(format!("csv column {} not found", name))
+                }),
+        }
+    };
+    let date = index(&mapping.date)?;
+    let amount = match &mapping.amount {
+        AmountColumns::Signed(column) => AmountIndexes::Signed(index(column)?),
+        AmountColumns::DebitCredit { debit, credit } => AmountIndexes::DebitCredit {
+            debit: index(debit)?,
+            credit: index(credit)?,
+        },
+    };
+    let payment_target = mapping.payment_target.as_ref().map(index).transpose()?;
+    let description = mapping.description.as_ref().map(index).transpose()?;
+
+    let mut report = ImportReport {
+        transactions: vec![],
+        errors: vec![],
+    };
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(error) => {
+                report.errors.push(RowError {
+                    line: error
+                        .position()
+                        .map(|position| position.line())
+                        .unwrap_or(0),
+                    message: error.to_string(),
+                });
+                continue;
+            }
+        };
+        let line = record
+            .position()
+            .map(|position| position.line())
+            .unwrap_or(0);
+        let field = |index: usize| -> Result<&str, String> {
+            record
+                .get(index)
+                .map(str::trim)
+                .ok_or_else(|| format!("row has no column {}", index))
+        };
+        let optional = |index: Option<usize>| -> Result<String, String> {
+            index
+                .map(|index| field(index).map(str::to_string))
+                .unwrap_or_else(|| Ok(String::new()))
+        };
+        let row = || -> Result<MoneyTransaction, String> {
+            let create_date = parse_date(field(date)?, &mapping.date_format)?;
+            let (payment_type, amount) = match amount {
+                AmountIndexes::Signed(index) => {
+                    let amount = parse_amount(field(index)?, mapping, account)?;
+                    let outcome =
+                        amount.is_negative() == (mapping.sign == SignConvention::NegativeIsOutcome);
+                    let payment_type = if outcome {
+                        PaymentType::Outcome
+                    } else {
+                        PaymentType::Income
+                    };
+                    (payment_type, amount)
+                }
+                AmountIndexes::DebitCredit { debit, credit } => {
+                    let non_zero = |index: usize| -> Result<Option<Money>, String> {
+                        let text = field(index)?;
+                        if text.is_empty() {
+                            return Ok(None);
+                        }
+                        let amount = parse_amount(text, mapping, account)?;
+                        Ok((!amount.is_zero()).then_some(amount))
+                    };
+                    match (non_zero(debit)?, non_zero(credit)?) {
+                        (Some(_), Some(_)) => {
+                            return Err("only one of debit and credit may be non-zero".to_string());
+                        }
+                        (Some(amount), None) => (PaymentType::Outcome, amount),
+                        (None, Some(amount)) => (PaymentType::Income, amount),
+                        (None, None) => {
+                            (PaymentType::Income, Money::zero(account.money.currency()))
+                        }
+                    }
+                }
+            };
+            if amount.is_zero() {
+                return Err("amount is zero".to_string());
+            }
+            Ok(MoneyTransaction {
+                id: String::new(),
+                amount: amount.checked_abs().map_err(|error| error.to_string())?,
+                description: optional(description)?,
+                user: user.clone(),
+                account: account.clone(),
+                payment_type,
+                payment_target: optional(payment_target)?,
+                create_date,
+                linked_transaction_id: None,
+                category_id: None,
+                correction_of: None,
+            })
+        };
+        match row() {
+            Ok(transaction) => report.transactions.push(transaction),
+            Err(message) => report.errors.push(RowError { line, message }),
+        }
+    }
+    Ok(report)
+}
+
+fn csv_error(error: csv::Error) -> MoneyCalcError {
+    MoneyCalcError::Validation(format!("csv: {}", error))
+}
+
+fn parse_date(text: &str, format: &str) -> Result<NaiveDateTime, String> {
+    NaiveDateTime::parse_from_str(text, format)
+        .or_else(|_| {
+            NaiveDate::parse_from_str(text, format).map(|date| date.and_time(NaiveTime::MIN))
+        })
+        .map_err(|error| format!("invalid date {}: {}", text, error))
+}
+
+fn parse_amount(text: &str, mapping: &CsvMapping, account: &Account) -> Result<Money, String> {
+    let mut normalized: String = text
+        .chars()
+        .filter(|c| !c.is_whitespace() && Some(*c) != mapping.thousands_separator)
+        .collect();
+    if mapping.decimal_separator != '.' {
+        normalized = normalized.replace(mapping.decimal_separator, ".");
+    }
+    Money::parse(&normalized, account.money.currency()).map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::{
+        imports::csvimport::{AmountColumns, CsvColumn, CsvMapping, SignConvention, import_csv},
+        models::{
+            account::Account,
+            money::{Currency, Money},
+            moneytransaction::PaymentType,
+            user::User,
+        },
+    };
+
+    fn rub(amount: &str) -> Money {
+        Money::parse(amount, Currency::RUB).unwrap()
+    }
+
+    fn target() -> (User, Account) {
+        (
+            User::new(
+                1,
+                "scam".to_string(),
+                "1".to_string(),
+                "2025-01-01".to_string(),
+            ),
+            Account::new(1, "Checking".to_string(), rub("0.00")),
+        )
+    }
+
+    #[test]
+    fn signed_amount_import_test() {
+        let (user, account) = target();
+        let statement = "\
+Date;Payee;Amount;Memo
+31.03.2025;Shop;-1 234,50;Groceries
+01.04.2025;Employer;50 000,00;Salary
+02.04.2025;Cafe;abc;Coffee
+2025-04-03;Cafe;-100,00;Coffee
+04.04.2025;Bank;0,00;Fee
+";
+        let mapping = CsvMapping::new(
+            CsvColumn::Header("Date".to_string()),
+            AmountColumns::Signed(CsvColumn::Header("Amount".to_string())),
+        )
+        .with_delimiter(b';')
+        .with_date_format("%d.%m.%Y")
+        .with_separators(',', Some(' '))
+        .with_payment_target(CsvColumn::Index(1))
+        .with_description(CsvColumn::Header("Memo".to_string()));
+        let report = import_csv(statement.as_bytes(), &mapping, &user, &account).unwrap();
+
+        assert_eq!(report.transactions.len(), 2);
+        let outcome = &report.transactions[0];
+        assert_eq!(outcome.payment_type, PaymentType::Outcome);
+        assert_eq!(outcome.amount, rub("1234.50"));
+        assert_eq!(outcome.payment_target, "Shop");
+        assert_eq!(outcome.description, "Groceries");
+        assert_eq!(
+            outcome.create_date,
+            NaiveDate::from_ymd_opt(2025, 3, 31)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+        );
+        assert_eq!(report.transactions[1].payment_type, PaymentType::Income);
+        assert_eq!(report.transactions[1].amount, rub("50000.00"));
+        let lines: Vec<_> = report.errors.iter().map(|error| error.line).collect();
+        assert_eq!(lines, vec![4, 5, 6]);
+
+        let card = mapping.with_sign(SignConvention::NegativeIsIncome);
+        let report = import_csv(statement.as_bytes(), &card, &user, &account).unwrap();
+        assert_eq!(report.transactions[0].payment_type, PaymentType::Income);
+        assert_eq!(report.transactions[1].payment_type, PaymentType::Outcome);
+    }
+
+    #[test]
+    fn debit_credit_import_test() {
+        let (user, account) = target();
+        let statement = "\
+2025-03-31 10:15:00,Shop,12.30,
+2025-04-01 09:00:00,Employer,,100.00
+2025-04-02 09:00:00,Bank,1.00,2.00
+2025-04-03 09:00:00,Short
+2025-04-04 09:00:00,Cafe,12.50,0.00
+2025-04-05 09:00:00,Refund,0.00,3.00
+2025-04-06 09:00:00,Nothing,0.00,
+";
+        let mapping = CsvMapping::new(
+            CsvColumn::Index(0),
+            AmountColumns::DebitCredit {
+                debit: CsvColumn::Index(2),
+                credit: CsvColumn::Index(3),
+            },
+        )
+        .with_headers(false)
+        .with_date_format("%Y-%m-%d %H:%M:%S")
+        .with_payment_target(CsvColumn::Index(1));
+        let report = import_csv(statement.as_bytes(), &mapping, &user, &account).unwrap();
+
+        let amounts: Vec<(PaymentType, Money)> = report
+            .transactions
+            .iter()
+            .map(|transaction| (transaction.payment_type, transaction.amount))
+            .collect();
+        assert_eq!(
+            amounts,
+            [
+                (PaymentType::Outcome, rub("12.30")),
+                (PaymentType::Income, rub("100.00")),
+                (PaymentType::Outcome, rub("12.50")),
+                (PaymentType::Income, rub("3.00")),
+            ]
+        );
+        let lines: Vec<u64> = report.errors.iter().map(|error| error.line).collect();
+        assert_eq!(lines, [3, 4, 7]);
+
+        let missing = CsvMapping::new(
+            CsvColumn::Header("Date".to_string()),
+            AmountColumns::Signed(CsvColumn::Index(1)),
+        );
+        assert!(import_csv(statement.as_bytes(), &missing, &user, &account).is_err());
+    }
+}
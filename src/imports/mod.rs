@@ -0,0 +1,22 @@
+pub mod csvimport;
+pub mod ofx;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::moneytransaction::MoneyTransaction;
+
+/// Row of imported file that was skipped.
+/// line is line number in file, starting from 1.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RowError {
+    pub line: u64,
+    pub message: String,
+}
+
+/// Transactions parsed from file and errors of skipped rows.
+/// Transactions are not saved, they are ready for execute_transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub transactions: Vec<MoneyTransaction>,
+    pub errors: Vec<RowError>,
+}
@@ -0,0 +1,452 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    errors::{MoneyCalcError, MoneyCalcResult},
+    imports::RowError,
+    models::{
+        account::Account,
+        money::Money,
+        moneytransaction::{MoneyTransaction, PaymentType},
+        user::User,
+    },
+    providers::TransactionWorker,
+};
+
+/// Transaction of statement with bank id.
+/// line is line number of its STMTTRN element.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfxTransaction {
+    pub fitid: String,
+    pub line: u64,
+    pub transaction: MoneyTransaction,
+}
+
+/// Parsed statement, errors are STMTTRN entries that were skipped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfxStatement {
+    pub transactions: Vec<OfxTransaction>,
+    pub errors: Vec<RowError>,
+}
+
+/// Result of statement import.
+/// imported are stored transactions with their ids, duplicates are FITIDs already imported into the account,
+/// errors are entries that were skipped or failed to execute.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfxImportReport {
+    pub imported: Vec<MoneyTransaction>,
+    pub duplicates: Vec<String>,
+    pub errors: Vec<RowError>,
+}
+
+/// Parse OFX 1.x (SGML) or 2.x (XML) statement into transactions of account.
+/// Negative TRNAMT is outcome, NAME is payment target and MEMO is description.
+/// Fails if there is no OFX element or statement currency differs from account one.
+pub fn parse_ofx(text: &str, user: &User, account: &Account) -> MoneyCalcResult<OfxStatement> {
+    let start = text
+        .to_ascii_uppercase()
+        .find("<OFX>")
+        .ok_or_else(|| MoneyCalcError::Validation("OFX element not found".to_string()))?;
+
+    let mut statement = OfxStatement {
+        transactions: vec![],
+        errors: vec![],
+    };
+    let mut current: Option<(u64, HashMap<String, String>)> = None;
+    let mut line = 1 + text[..start].matches('\n').count() as u64;
+    let mut counted = start;
+    let mut position = start;
+    while let Some(open) = text[position..].find('<').map(|index| position + index) {
+        let Some(close) = text[open..].find('>').map(|index| open + index) else {
+            break;
+        };
+        line += text[counted..open].matches('\n').count() as u64;
+        counted = open;
+        let tag = text[open + 1..close].trim().to_ascii_uppercase();
+        let next = text[close + 1..]
+            .find('<')
+            .map(|index| close + 1 + index)
+            .unwrap_or(text.len());
+        let value = decode_entities(text[close + 1..next].trim());
+        position = next;
+
+        if tag == "STMTTRN" {
+            current = Some((line, HashMap::new()));
+        } else if tag == "/STMTTRN" {
+            if let Some((line, fields)) = current.take() {
+                match ofx_transaction(&fields, line, user, account) {
+                    Ok(transaction) => statement.transactions.push(transaction),
+                    Err(message) => statement.errors.push(RowError { line, message }),
+                }
+            }
+        } else if tag == "CURDEF" && !value.eq_ignore_ascii_case(account.money.currency().code()) {
+            return Err(MoneyCalcError::Validation(format!(
+                "statement currency {} differs from account currency {}",
+                value,
+                account.money.currency()
+            )));
+        } else if let Some((_, fields)) = current.as_mut()
+            && !tag.starts_with('/')
+            && !value.is_empty()
+        {
+            fields.insert(tag, value);
+        }
+    }
+    Ok(statement)
+}
+
+/// Parse statement and execute its transactions on account.
+/// Transactions with FITID already imported into account are skipped, so
+/// importing overlapping statements does not double balance changes.
+/// Transaction which fails to execute is reported with its FITID and the rest are still imported.
+pub async fn import_ofx<T: TransactionWorker + ?Sized>(
+    worker: &T,
+    text: &str,
+    user: &User,
+    account: &Account,
+) -> MoneyCalcResult<OfxImportReport> {
+    let statement = parse_ofx(text, user, account)?;
+    let mut report = OfxImportReport {
+        imported: vec![],
+        duplicates: vec![],
+        errors: statement.errors,
+    };
+    for OfxTransaction {
+        fitid,
+        line,
+        transaction,
+    } in statement.transactions
+    {
+        match worker
+            .execute_external_transaction(&transaction, &fitid)
+            .await
+        {
+            Ok(Some(stored)) => report.imported.push(stored),
+            Ok(None) => report.duplicates.push(fitid),
+            Err(error) => report.errors.push(RowError {
+                line,
+                message: format!("transaction {}: {}", fitid, error),
+            }),
+        }
+    }
+    Ok(report)
+}
+
+fn ofx_transaction(
+    fields: &HashMap<String, String>,
+    line: u64,
+    user: &User,
+    account: &Account,
+) -> Result<OfxTransaction, String> {
+    let field = |name: &str| {
+        fields
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("{} is missing", name))
+    };
+    let fitid = field("FITID")?;
+    let create_date = parse_ofx_date(&field("DTPOSTED")?)?;
+    let amount = parse_ofx_amount(&field("TRNAMT")?, account)?;
+    if amount.is_zero() {
+        return Err(format!("transaction {} has zero amount", fitid));
+    }
+    let payment_type = if amount.is_negative() {
+        PaymentType::Outcome
+    } else {
+        PaymentType::Income
+    };
+    Ok(OfxTransaction {
+        transaction: MoneyTransaction {
+            id: String::new(),
+            amount: amount.checked_abs().map_err(|error| error.to_string())?,
+            description: fields.get("MEMO").cloned().unwrap_or_default(),
+            user: user.clone(),
+            account: account.clone(),
+            payment_type,
+            payment_target: fields
+                .get("NAME")
+                .or_else(|| fields.get("PAYEEID"))
+                .cloned()
+                .unwrap_or_default(),
+            create_date,
+            linked_transaction_id: None,
+            category_id: None,
+            correction_of: None,
+        },
+        fitid,
+        line,
+    })
+}
+
+/// OFX date is YYYYMMDD with optional HHMMSS, fraction and timezone, timezone is ignored.
+fn parse_ofx_date(text: &str) -> Result<NaiveDateTime, String> {
+    let digits: String = text.chars().take_while(char::is_ascii_digit).collect();
+    let parsed = if digits.len() >= 14 {
+        NaiveDateTime::parse_from_str(&digits[..14], "%Y%m%d%H%M%S")
+    } else if digits.len() >= 8 {
+        NaiveDate::parse_from_str(&digits[..8], "%Y%m%d").map(|date| date.and_time(NaiveTime::MIN))
+    } else {
+        return Err(format!("invalid date {}", text));
+    };
+    parsed.map_err(|error| format!("invalid date {}: {}", text, error))
+}
+
+/// Amount with dot or comma separator, extra zero fraction digits are dropped.
+fn parse_ofx_amount(text: &str, account: &Account) -> Result<Money, String> {
+    let currency = account.money.currency();
+    let mut normalized = text.replace(',', ".");
+    if let Some((integer, fraction)) = normalized.split_once('.') {
+        let digits = currency.minor_digits() as usize;
+        if fraction.len() > digits && fraction[digits..].chars().all(|c| c == '0') {
+            normalized = format!("{}.{}", integer, &fraction[..digits]);
+        }
+    }
+    Money::parse(normalized.trim_end_matches('.'), currency).map_err(|error| error.to_string())
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::{
+        commands::{
+            accounts::addaccountcommand::AddAccountCommand, users::addusercommand::AddUserCommand,
+        },
+        config::{SqliteConfiguration, StorageConfiguration},
+        errors::MoneyCalcError,
+        imports::ofx::{import_ofx, parse_ofx},
+        models::{
+            account::{Account, OverdraftPolicy},
+            money::{Currency, Money},
+            moneytransaction::PaymentType,
+            user::User,
+        },
+        providers::{AccountProvider, TransactionWorker, UserProvider},
+    };
+
+    const SGML_STATEMENT: &str = "OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+
+<OFX>
+<BANKMSGSRSV1><STMTTRNRS><STMTRS>
+<CURDEF>RUB
+<BANKTRANLIST>
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>20250331101500.000[+3:MSK]
+<TRNAMT>-1234.50
+<FITID>100
+<NAME>Shop &amp; Co
+<MEMO>Groceries
+</STMTTRN>
+<STMTTRN>
+<TRNTYPE>CREDIT
+<DTPOSTED>20250401
+<TRNAMT>5000,00
+<FITID>101
+<NAME>Employer
+</STMTTRN>
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>20250402
+<FITID>102
+</STMTTRN>
+</BANKTRANLIST>
+</STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>
+";
+
+    const XML_STATEMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<?OFX OFXHEADER="200" VERSION="220"?>
+<OFX>
+  <BANKMSGSRSV1><STMTTRNRS><STMTRS>
+    <CURDEF>RUB</CURDEF>
+    <BANKTRANLIST>
+      <STMTTRN>
+        <TRNTYPE>DEBIT</TRNTYPE>
+        <DTPOSTED>20250331101500</DTPOSTED>
+        <TRNAMT>-1234.5000</TRNAMT>
+        <FITID>100</FITID>
+        <NAME>Shop &amp; Co</NAME>
+        <MEMO>Groceries</MEMO>
+      </STMTTRN>
+      <STMTTRN>
+        <TRNTYPE>DEBIT</TRNTYPE>
+        <DTPOSTED>20250403</DTPOSTED>
+        <TRNAMT>-10.00</TRNAMT>
+        <FITID>103</FITID>
+        <NAME>Cafe</NAME>
+      </STMTTRN>
+    </BANKTRANLIST>
+  </STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>
+"#;
+
+    fn rub(amount: &str) -> Money {
+        Money::parse(amount, Currency::RUB).unwrap()
+    }
+
+    fn target() -> (User, Account) {
+        (
+            User::new(
+                1,
+                "scam".to_string(),
+                "1".to_string(),
+                "2025-01-01".to_string(),
+            ),
+            Account::new(1, "Checking".to_string(), rub("0.00")),
+        )
+    }
+
+    #[test]
+    fn parse_sgml_test() {
+        let (user, account) = target();
+        let statement = parse_ofx(SGML_STATEMENT, &user, &account).unwrap();
+        assert_eq!(statement.transactions.len(), 2);
+        let first = &statement.transactions[0];
+        assert_eq!(first.fitid, "100");
+        assert_eq!(first.transaction.payment_type, PaymentType::Outcome);
+        assert_eq!(first.transaction.amount, rub("1234.50"));
+        assert_eq!(first.transaction.payment_target, "Shop & Co");
+        assert_eq!(first.transaction.description, "Groceries");
+        assert_eq!(
+            first.transaction.create_date.to_string(),
+            "2025-03-31 10:15:00"
+        );
+        let second = &statement.transactions[1];
+        assert_eq!(second.transaction.payment_type, PaymentType::Income);
+        assert_eq!(second.transaction.amount, rub("5000.00"));
+        assert_eq!(statement.errors.len(), 1);
+        assert_eq!(statement.errors[0].line, 24);
+
+        let dollars = Account::new(1, "Dollars".to_string(), Money::zero(Currency::USD));
+        assert!(matches!(
+            parse_ofx(SGML_STATEMENT, &user, &dollars),
+            Err(MoneyCalcError::Validation(_))
+        ));
+        assert!(parse_ofx("not a statement", &user, &account).is_err());
+
+        // SGML tags and currency code are case-insensitive.
+        let lowercase = parse_ofx(&SGML_STATEMENT.to_lowercase(), &user, &account).unwrap();
+        assert_eq!(lowercase.transactions.len(), 2);
+        assert_eq!(lowercase.errors.len(), 1);
+    }
+
+    #[test]
+    fn parse_xml_test() {
+        let (user, account) = target();
+        let statement = parse_ofx(XML_STATEMENT, &user, &account).unwrap();
+        assert!(statement.errors.is_empty());
+        let fitids: Vec<_> = statement
+            .transactions
+            .iter()
+            .map(|transaction| transaction.fitid.as_str())
+            .collect();
+        assert_eq!(fitids, vec!["100", "103"]);
+        assert_eq!(statement.transactions[0].transaction.amount, rub("1234.50"));
+        assert_eq!(
+            statement.transactions[0].transaction.payment_target,
+            "Shop & Co"
+        );
+    }
+
+    #[tokio::test]
+    async fn import_ofx_test() {
+        let provider = SqliteConfiguration::memory_base().configure().unwrap();
+        provider
+            .add_user(&AddUserCommand {
+                user_name: String::from_str("scam").unwrap(),
+                user_number: "1".to_string(),
+            })
+            .await
+            .unwrap();
+        let user = provider.get_user_by_number("1").await.unwrap();
+        provider
+            .add_account(&AddAccountCommand {
+                user_id: user.id,
+                account_name: "Checking".to_string(),
+                initial_balance: rub("2000.00"),
+                overdraft: OverdraftPolicy::default(),
+            })
+            .await
+            .unwrap();
+        let account = provider.search_account_by_user(&user).await.unwrap();
+
+        let report = import_ofx(&provider, SGML_STATEMENT, &user, &account)
+            .await
+            .unwrap();
+        assert_eq!(report.imported.len(), 2);
+        assert!(report.duplicates.is_empty());
+        assert_eq!(report.errors.len(), 1);
+        for imported in &report.imported {
+            let stored = provider.get_transaction_by_id(&imported.id).await.unwrap();
+            assert_eq!(stored.amount, imported.amount);
+        }
+        let balance = provider.get_account_by_id(account.id).await.unwrap().money;
+        assert_eq!(balance, rub("5765.50"));
+
+        // Overlapping statement only adds new transactions.
+        let report = import_ofx(&provider, XML_STATEMENT, &user, &account)
+            .await
+            .unwrap();
+        assert_eq!(report.imported.len(), 1);
+        assert_eq!(report.duplicates, vec!["100".to_string()]);
+        let balance = provider.get_account_by_id(account.id).await.unwrap().money;
+        assert_eq!(balance, rub("5755.50"));
+    }
+
+    #[tokio::test]
+    async fn import_ofx_failed_transaction_test() {
+        let provider = SqliteConfiguration::memory_base().configure().unwrap();
+        provider
+            .add_user(&AddUserCommand {
+                user_name: String::from_str("scam").unwrap(),
+                user_number: "1".to_string(),
+            })
+            .await
+            .unwrap();
+        let user = provider.get_user_by_number("1").await.unwrap();
+        provider
+            .add_account(&AddAccountCommand {
+                user_id: user.id,
+                account_name: "Checking".to_string(),
+                initial_balance: rub("1000.00"),
+                overdraft: OverdraftPolicy::Forbid,
+            })
+            .await
+            .unwrap();
+        let account = provider.search_account_by_user(&user).await.unwrap();
+
+        let report = import_ofx(&provider, SGML_STATEMENT, &user, &account)
+            .await
+            .unwrap();
+        assert_eq!(report.imported.len(), 1);
+        assert_eq!(report.imported[0].amount, rub("5000.00"));
+        assert_eq!(report.errors.len(), 2);
+        assert_eq!(report.errors[0].line, 24);
+        assert_eq!(report.errors[1].line, 9);
+        assert!(report.errors[1].message.contains("100"));
+        let balance = provider.get_account_by_id(account.id).await.unwrap().money;
+        assert_eq!(balance, rub("6000.00"));
+
+        // Failed transaction is not marked as imported and is retried.
+        let report = import_ofx(&provider, SGML_STATEMENT, &user, &account)
+            .await
+            .unwrap();
+        assert_eq!(report.imported.len(), 1);
+        assert_eq!(report.duplicates, vec!["101".to_string()]);
+        let balance = provider.get_account_by_id(account.id).await.unwrap().money;
+        assert_eq!(balance, rub("4765.50"));
+    }
+}
@@ -1,4 +1,43 @@
-mod commands;
-mod config;
-mod models;
-mod providers;
+//! Core functionality for money transactions in storage with data providers.
+//!
+//! Storage is created from configuration, then used through provider traits:
+//! ```no_run
+//! use moneycalc::prelude::*;
+//!
+//! # async fn run() -> MoneyCalcResult<()> {
+//! let provider = SqliteConfiguration::memory_base().configure()?;
+//! provider
+//!     .add_user(&AddUserCommand {
+//!         user_name: "Ivan".to_string(),
+//!         user_number: "88005553535".to_string(),
+//!     })
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+pub mod backup;
+pub mod commands;
+pub mod config;
+pub mod errors;
+pub mod imports;
+pub mod models;
+pub mod prelude;
+pub mod providers;
+
+pub use crate::{
+    config::{
+        JournalMode, ProviderConfiguration, SqliteConfiguration, StorageConfiguration,
+        settings::StorageSettings,
+    },
+    errors::{MoneyCalcError, MoneyCalcResult},
+    providers::{
+        AccountProvider, AuditProvider, BalanceHistoryProvider, BudgetProvider, CategoryProvider,
+        DataProvider, ExchangeRateProvider, ReconciliationProvider, RecurringProvider, TagProvider,
+        TransactionWorker, UserProvider, bases::sqlite::SqliteProvider,
+    },
+};
+
+#[cfg(feature = "memory")]
+pub use crate::providers::bases::memory::MemoryProvider;
+#[cfg(feature = "postgres")]
+pub use crate::{config::PostgresConfiguration, providers::bases::postgres::PostgresProvider};
@@ -1,28 +1,62 @@
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
+use crate::models::money::{Money, MoneyError};
+
+/// How far account balance may go below zero.
+/// Limit is positive amount in account currency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OverdraftPolicy {
+    #[default]
+    Forbid,
+    Limit(Money),
+    Unlimited,
+}
+
+impl OverdraftPolicy {
+    /// Check if balance after debit is allowed by policy.
+    pub fn allows(&self, balance: Money) -> Result<bool, MoneyError> {
+        match self {
+            OverdraftPolicy::Forbid => Ok(!balance.is_negative()),
+            OverdraftPolicy::Limit(limit) => {
+                limit.check_currency(&balance)?;
+                Ok(balance.minor_units() >= -limit.minor_units())
+            }
+            OverdraftPolicy::Unlimited => Ok(true),
+        }
+    }
+}
+
 /// Account type.
 /// id for identification in base.
 /// user_id for id of user.
 /// name of account.
-/// money is money count.
+/// money is money count in account currency.
+/// is_primary marks default account of user.
+/// overdraft limits negative balance.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Account {
     pub id: i32,
     pub user_id: i32,
     pub name: String,
-    pub money: f32,
+    pub money: Money,
     pub creation_date: NaiveDate,
+    #[serde(default)]
+    pub is_primary: bool,
+    #[serde(default)]
+    pub overdraft: OverdraftPolicy,
 }
 
 impl Account {
-    pub fn new(user_id: i32, name: String, money: f32) -> Self {
+    pub fn new(user_id: i32, name: String, money: Money) -> Self {
         Self {
             id: 0,
             user_id,
             name: name.to_owned(),
             money,
             creation_date: chrono::Utc::now().naive_utc().date(),
+            is_primary: false,
+            overdraft: OverdraftPolicy::default(),
         }
     }
 
@@ -30,8 +64,10 @@ impl Account {
         id: i32,
         user_id: i32,
         name: String,
-        money: f32,
+        money: Money,
         creation_date: NaiveDate,
+        is_primary: bool,
+        overdraft: OverdraftPolicy,
     ) -> Self {
         Self {
             id,
@@ -39,6 +75,34 @@ impl Account {
             name,
             money,
             creation_date,
+            is_primary,
+            overdraft,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{
+        account::OverdraftPolicy,
+        money::{Currency, Money},
+    };
+
+    #[test]
+    fn overdraft_policy_test() {
+        let rub = |amount: &str| Money::parse(amount, Currency::RUB).unwrap();
+        assert!(OverdraftPolicy::Forbid.allows(rub("0.00")).unwrap());
+        assert!(!OverdraftPolicy::Forbid.allows(rub("-0.01")).unwrap());
+
+        let limit = OverdraftPolicy::Limit(rub("50.00"));
+        assert!(limit.allows(rub("-50.00")).unwrap());
+        assert!(!limit.allows(rub("-50.01")).unwrap());
+        assert!(limit.allows(Money::from_minor(-1, Currency::USD)).is_err());
+
+        assert!(
+            OverdraftPolicy::Unlimited
+                .allows(rub("-1000000.00"))
+                .unwrap()
+        );
+    }
+}
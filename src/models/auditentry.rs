@@ -0,0 +1,20 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Record of one change made through providers.
+/// action is name of provider method, entity and entity_id point to changed record.
+/// before is None for added records and after is None for deleted ones.
+/// hash is set when log is hash chained, it covers the record and hash of previous one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub actor: String,
+    pub action: String,
+    pub entity: String,
+    pub entity_id: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub create_date: NaiveDateTime,
+    pub hash: Option<String>,
+}
@@ -0,0 +1,20 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::models::money::Money;
+
+/// Balance of account at the end of day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailyBalance {
+    pub date: NaiveDate,
+    pub balance: Money,
+}
+
+/// Stored balance of account at moment, used as starting point of balance calculation.
+/// Snapshot is dropped when transaction dated at or before it is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceSnapshot {
+    pub account_id: i32,
+    pub date: NaiveDateTime,
+    pub balance: Money,
+}
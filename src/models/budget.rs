@@ -0,0 +1,115 @@
+use chrono::{Datelike, Days, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::models::money::Money;
+
+/// Repeating budget period.
+/// Monthly starts on the first day of month, weekly starts on Monday.
+/// Custom periods follow each other every `days` days from `start`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BudgetPeriod {
+    Monthly,
+    Weekly,
+    Custom { start: NaiveDate, days: u32 },
+}
+
+impl BudgetPeriod {
+    /// Period containing date, start is inclusive and end is exclusive.
+    /// None for custom period with zero length.
+    pub fn range(&self, date: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+        match self {
+            BudgetPeriod::Monthly => {
+                let start = date.with_day(1)?;
+                Some((start, start.checked_add_months(Months::new(1))?))
+            }
+            BudgetPeriod::Weekly => {
+                let start =
+                    date.checked_sub_days(Days::new(date.weekday().num_days_from_monday() as u64))?;
+                Some((start, start.checked_add_days(Days::new(7))?))
+            }
+            BudgetPeriod::Custom { start, days } => {
+                if *days == 0 {
+                    return None;
+                }
+                let length = *days as i64;
+                let offset = (date - *start).num_days().div_euclid(length) * length;
+                let from = *start + chrono::Duration::days(offset);
+                Some((from, from + chrono::Duration::days(length)))
+            }
+        }
+    }
+}
+
+/// Which outcome transactions are counted by budget.
+/// PaymentTarget pattern matches whole target ignoring case, `*` matches any text.
+/// Category includes its subcategories.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BudgetScope {
+    Account(i32),
+    PaymentTarget(String),
+    Category(i32),
+}
+
+/// Spending limit of user for every period.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Budget {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub scope: BudgetScope,
+    pub period: BudgetPeriod,
+    pub limit: Money,
+}
+
+/// State of budget in one period.
+/// Spent is in budget currency, remaining is negative when budget is exceeded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BudgetProgress {
+    pub budget: Budget,
+    pub from_date: NaiveDate,
+    pub to_date: NaiveDate,
+    pub planned: Money,
+    pub spent: Money,
+    pub remaining: Money,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::models::budget::BudgetPeriod;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn budget_period_range_test() {
+        assert_eq!(
+            BudgetPeriod::Monthly.range(date(2024, 12, 31)),
+            Some((date(2024, 12, 1), date(2025, 1, 1)))
+        );
+        assert_eq!(
+            BudgetPeriod::Weekly.range(date(2025, 3, 16)),
+            Some((date(2025, 3, 10), date(2025, 3, 17)))
+        );
+
+        let custom = BudgetPeriod::Custom {
+            start: date(2025, 1, 10),
+            days: 14,
+        };
+        assert_eq!(
+            custom.range(date(2025, 1, 24)),
+            Some((date(2025, 1, 24), date(2025, 2, 7)))
+        );
+        assert_eq!(
+            custom.range(date(2025, 1, 9)),
+            Some((date(2024, 12, 27), date(2025, 1, 10)))
+        );
+        let empty = BudgetPeriod::Custom {
+            start: date(2025, 1, 10),
+            days: 0,
+        };
+        assert_eq!(empty.range(date(2025, 1, 10)), None);
+    }
+}
@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// Spending category of user.
+/// parent_id links subcategory to its category, like Food > Groceries.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Category {
+    pub id: i32,
+    pub user_id: i32,
+    pub parent_id: Option<i32>,
+    pub name: String,
+}
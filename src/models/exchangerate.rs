@@ -0,0 +1,190 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::money::{Currency, Money, MoneyError};
+
+/// Count of decimal digits kept in exchange rate.
+pub const RATE_SCALE: u32 = 8;
+
+const RATE_FACTOR: i128 = 10_i128.pow(RATE_SCALE);
+
+/// Exchange rate between two currencies.
+/// One major unit of `from` costs `rate` major units of `to`.
+/// Rate is stored as integer count of 10^-8 parts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ExchangeRate {
+    pub from: Currency,
+    pub to: Currency,
+    rate_units: i64,
+}
+
+impl ExchangeRate {
+    /// Create rate from decimal string like "92.5".
+    pub fn new(from: Currency, to: Currency, rate: &str) -> Result<Self, MoneyError> {
+        let invalid = || MoneyError::InvalidAmount(rate.to_owned());
+        let (integer, fraction) = match rate.trim().split_once('.') {
+            Some((integer, fraction)) => (integer, fraction),
+            None => (rate.trim(), ""),
+        };
+        if (integer.is_empty() && fraction.is_empty())
+            || fraction.len() > RATE_SCALE as usize
+            || !integer
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let mut rate_units: i64 = 0;
+        let padded = format!("{:0<width$}", fraction, width = RATE_SCALE as usize);
+        for digit in integer.chars().chain(padded.chars()) {
+            rate_units = rate_units
+                .checked_mul(10)
+                .and_then(|value| value.checked_add(digit as i64 - '0' as i64))
+                .ok_or(MoneyError::Overflow)?;
+        }
+
+        Self::from_units(from, to, rate_units)
+    }
+
+    /// Create rate from count of 10^-8 parts.
+    pub fn from_units(from: Currency, to: Currency, rate_units: i64) -> Result<Self, MoneyError> {
+        if rate_units <= 0 {
+            return Err(MoneyError::InvalidAmount(rate_units.to_string()));
+        }
+        Ok(Self {
+            from,
+            to,
+            rate_units,
+        })
+    }
+
+    /// Rate of currency to itself.
+    pub fn identity(currency: Currency) -> Self {
+        Self {
+            from: currency,
+            to: currency,
+            rate_units: RATE_FACTOR as i64,
+        }
+    }
+
+    pub fn rate_units(&self) -> i64 {
+        self.rate_units
+    }
+
+    /// Rate of opposite direction, rounded half away from zero to 10^-8.
+    pub fn inverse(&self) -> Result<Self, MoneyError> {
+        let numerator = RATE_FACTOR * RATE_FACTOR;
+        let denominator = self.rate_units as i128;
+        let mut result = numerator / denominator;
+        if (numerator % denominator) * 2 >= denominator {
+            result += 1;
+        }
+
+        let rate_units = i64::try_from(result).map_err(|_| MoneyError::Overflow)?;
+        Self::from_units(self.to, self.from, rate_units)
+    }
+
+    /// Convert money in `from` currency to `to` currency.
+    /// Result is rounded half away from zero to minor units of `to`.
+    pub fn convert(&self, money: Money) -> Result<Money, MoneyError> {
+        if money.currency() != self.from {
+            return Err(MoneyError::CurrencyMismatch {
+                left: money.currency(),
+                right: self.from,
+            });
+        }
+
+        let numerator =
+            money.minor_units() as i128 * self.rate_units as i128 * self.to.minor_factor() as i128;
+        let denominator = self.from.minor_factor() as i128 * RATE_FACTOR;
+        let mut result = numerator / denominator;
+        if (numerator % denominator).abs() * 2 >= denominator {
+            result += numerator.signum();
+        }
+
+        let minor_units = i64::try_from(result).map_err(|_| MoneyError::Overflow)?;
+        Ok(Money::from_minor(minor_units, self.to))
+    }
+}
+
+impl fmt::Display for ExchangeRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let factor = RATE_FACTOR as i64;
+        let fraction = format!(
+            "{:0width$}",
+            self.rate_units % factor,
+            width = RATE_SCALE as usize
+        );
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            write!(f, "{}/{} {}", self.from, self.to, self.rate_units / factor)
+        } else {
+            write!(
+                f,
+                "{}/{} {}.{}",
+                self.from,
+                self.to,
+                self.rate_units / factor,
+                fraction
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{
+        exchangerate::ExchangeRate,
+        money::{Currency, Money},
+    };
+
+    #[test]
+    fn exchange_rate_parse_test() {
+        let rate = ExchangeRate::new(Currency::USD, Currency::RUB, "92.5").unwrap();
+        assert_eq!(rate.rate_units(), 9_250_000_000);
+        assert_eq!(rate.to_string(), "USD/RUB 92.5");
+        assert!(ExchangeRate::new(Currency::USD, Currency::RUB, "0").is_err());
+        assert!(ExchangeRate::new(Currency::USD, Currency::RUB, "-1").is_err());
+        assert!(ExchangeRate::new(Currency::USD, Currency::RUB, "1.000000001").is_err());
+    }
+
+    #[test]
+    fn exchange_rate_convert_test() {
+        let rate = ExchangeRate::new(Currency::USD, Currency::RUB, "92.5").unwrap();
+        let money = Money::parse("10.01", Currency::USD).unwrap();
+        assert_eq!(
+            rate.convert(money).unwrap(),
+            Money::parse("925.93", Currency::RUB).unwrap()
+        );
+
+        let rate = ExchangeRate::new(Currency::USD, Currency::JPY, "150.456").unwrap();
+        assert_eq!(
+            rate.convert(Money::parse("-1.00", Currency::USD).unwrap())
+                .unwrap(),
+            Money::from_minor(-150, Currency::JPY)
+        );
+        assert!(rate.convert(Money::from_minor(1, Currency::RUB)).is_err());
+    }
+
+    #[test]
+    fn exchange_rate_inverse_test() {
+        let rate = ExchangeRate::new(Currency::USD, Currency::RUB, "80").unwrap();
+        let inverse = rate.inverse().unwrap();
+        assert_eq!(inverse.from, Currency::RUB);
+        assert_eq!(inverse.to, Currency::USD);
+        assert_eq!(inverse.to_string(), "RUB/USD 0.0125");
+
+        let rate = ExchangeRate::new(Currency::USD, Currency::RUB, "3").unwrap();
+        assert_eq!(rate.inverse().unwrap().rate_units(), 33_333_333);
+        let money = Money::parse("12.34", Currency::EUR).unwrap();
+        assert_eq!(
+            ExchangeRate::identity(Currency::EUR)
+                .convert(money)
+                .unwrap(),
+            money
+        );
+    }
+}
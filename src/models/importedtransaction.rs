@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+/// Bank id of transaction imported from statement into account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportedTransaction {
+    pub account_id: i32,
+    pub external_id: String,
+    pub transaction_id: String,
+}
@@ -1,3 +1,14 @@
 pub mod account;
+pub mod auditentry;
+pub mod balance;
+pub mod budget;
+pub mod category;
+pub mod exchangerate;
+pub mod importedtransaction;
+pub mod money;
 pub mod moneytransaction;
+pub mod reconciliation;
+pub mod recurring;
+pub mod tag;
+pub mod transactionpage;
 pub mod user;
@@ -0,0 +1,344 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Errors of money arithmetic and parsing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoneyError {
+    CurrencyMismatch { left: Currency, right: Currency },
+    Overflow,
+    InvalidAmount(String),
+    InvalidCurrency(String),
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoneyError::CurrencyMismatch { left, right } => {
+                write!(f, "currency mismatch: {} and {}", left, right)
+            }
+            MoneyError::Overflow => write!(f, "money amount overflow"),
+            MoneyError::InvalidAmount(amount) => write!(f, "invalid money amount: {}", amount),
+            MoneyError::InvalidCurrency(code) => write!(f, "invalid currency code: {}", code),
+        }
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+/// Currency, three letter uppercase code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Currency {
+    code: [u8; 3],
+}
+
+/// Currencies with a number of minor digits other than two.
+const MINOR_DIGITS_EXCEPTIONS: &[(&str, u32)] = &[
+    ("BHD", 3),
+    ("BIF", 0),
+    ("CLP", 0),
+    ("DJF", 0),
+    ("GNF", 0),
+    ("IQD", 3),
+    ("ISK", 0),
+    ("JOD", 3),
+    ("JPY", 0),
+    ("KMF", 0),
+    ("KRW", 0),
+    ("KWD", 3),
+    ("LYD", 3),
+    ("OMR", 3),
+    ("PYG", 0),
+    ("RWF", 0),
+    ("TND", 3),
+    ("UGX", 0),
+    ("VND", 0),
+    ("VUV", 0),
+    ("XAF", 0),
+    ("XOF", 0),
+    ("XPF", 0),
+];
+
+impl Currency {
+    pub const RUB: Currency = Currency { code: *b"RUB" };
+    pub const USD: Currency = Currency { code: *b"USD" };
+    pub const EUR: Currency = Currency { code: *b"EUR" };
+    pub const GBP: Currency = Currency { code: *b"GBP" };
+    pub const CNY: Currency = Currency { code: *b"CNY" };
+    pub const JPY: Currency = Currency { code: *b"JPY" };
+
+    pub fn new(code: &str) -> Result<Self, MoneyError> {
+        let bytes = code.trim().as_bytes();
+        if bytes.len() != 3 || !bytes.iter().all(|b| b.is_ascii_alphabetic()) {
+            return Err(MoneyError::InvalidCurrency(code.to_owned()));
+        }
+
+        let mut result = [0u8; 3];
+        for (index, byte) in bytes.iter().enumerate() {
+            result[index] = byte.to_ascii_uppercase();
+        }
+        Ok(Self { code: result })
+    }
+
+    pub fn code(&self) -> &str {
+        // Constructors only accept ascii letters.
+        std::str::from_utf8(&self.code).unwrap_or("XXX")
+    }
+
+    /// Count of digits after decimal point, 2 for most currencies.
+    pub fn minor_digits(&self) -> u32 {
+        MINOR_DIGITS_EXCEPTIONS
+            .iter()
+            .find(|(code, _)| *code == self.code())
+            .map(|(_, digits)| *digits)
+            .unwrap_or(2)
+    }
+
+    /// Count of minor units in one major unit.
+    pub fn minor_factor(&self) -> i64 {
+        10_i64.pow(self.minor_digits())
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl TryFrom<String> for Currency {
+    type Error = MoneyError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Currency::new(&value)
+    }
+}
+
+impl From<Currency> for String {
+    fn from(value: Currency) -> Self {
+        value.code().to_owned()
+    }
+}
+
+impl std::str::FromStr for Currency {
+    type Err = MoneyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Currency::new(s)
+    }
+}
+
+/// Money amount.
+/// Stored as integer count of minor units (cents, kopecks) of the currency,
+/// so arithmetic is exact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Money {
+    minor_units: i64,
+    currency: Currency,
+}
+
+impl Money {
+    pub fn from_minor(minor_units: i64, currency: Currency) -> Self {
+        Self {
+            minor_units,
+            currency,
+        }
+    }
+
+    pub fn from_major(major_units: i64, currency: Currency) -> Result<Self, MoneyError> {
+        let minor_units = major_units
+            .checked_mul(currency.minor_factor())
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Self::from_minor(minor_units, currency))
+    }
+
+    pub fn zero(currency: Currency) -> Self {
+        Self::from_minor(0, currency)
+    }
+
+    /// Parse decimal amount like "-1234.5" in given currency.
+    /// Fails if amount has more fraction digits than currency allows.
+    pub fn parse(amount: &str, currency: Currency) -> Result<Self, MoneyError> {
+        let invalid = || MoneyError::InvalidAmount(amount.to_owned());
+        let text = amount.trim();
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (integer, fraction) = match text.split_once('.') {
+            Some((integer, fraction)) => (integer, fraction),
+            None => (text, ""),
+        };
+
+        let digits = currency.minor_digits() as usize;
+        if (integer.is_empty() && fraction.is_empty())
+            || fraction.len() > digits
+            || !integer
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let mut minor_units: i64 = 0;
+        let padded = format!("{:0<width$}", fraction, width = digits);
+        for digit in integer.chars().chain(padded.chars()) {
+            minor_units = minor_units
+                .checked_mul(10)
+                .and_then(|value| value.checked_add(digit as i64 - '0' as i64))
+                .ok_or(MoneyError::Overflow)?;
+        }
+
+        if negative {
+            minor_units = -minor_units;
+        }
+        Ok(Self::from_minor(minor_units, currency))
+    }
+
+    pub fn minor_units(&self) -> i64 {
+        self.minor_units
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.minor_units == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.minor_units < 0
+    }
+
+    pub fn checked_add(&self, other: Money) -> Result<Money, MoneyError> {
+        self.check_currency(&other)?;
+        let minor_units = self
+            .minor_units
+            .checked_add(other.minor_units)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Self::from_minor(minor_units, self.currency))
+    }
+
+    pub fn checked_sub(&self, other: Money) -> Result<Money, MoneyError> {
+        self.check_currency(&other)?;
+        let minor_units = self
+            .minor_units
+            .checked_sub(other.minor_units)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Self::from_minor(minor_units, self.currency))
+    }
+
+    pub fn checked_neg(&self) -> Result<Money, MoneyError> {
+        let minor_units = self.minor_units.checked_neg().ok_or(MoneyError::Overflow)?;
+        Ok(Self::from_minor(minor_units, self.currency))
+    }
+
+    pub fn checked_abs(&self) -> Result<Money, MoneyError> {
+        let minor_units = self.minor_units.checked_abs().ok_or(MoneyError::Overflow)?;
+        Ok(Self::from_minor(minor_units, self.currency))
+    }
+
+    pub fn check_currency(&self, other: &Money) -> Result<(), MoneyError> {
+        if self.currency != other.currency {
+            return Err(MoneyError::CurrencyMismatch {
+                left: self.currency,
+                right: other.currency,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.minor_units < 0 { "-" } else { "" };
+        let digits = self.currency.minor_digits();
+        let absolute = self.minor_units.unsigned_abs();
+        let factor = 10_u64.pow(digits);
+        if digits == 0 {
+            write!(f, "{}{} {}", sign, absolute, self.currency)
+        } else {
+            write!(
+                f,
+                "{}{}.{:0width$} {}",
+                sign,
+                absolute / factor,
+                absolute % factor,
+                self.currency,
+                width = digits as usize
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::money::{Currency, Money, MoneyError};
+
+    #[test]
+    fn currency_parse_test() {
+        assert_eq!(Currency::new("usd").unwrap(), Currency::USD);
+        assert!(Currency::new("US").is_err());
+        assert!(Currency::new("U5D").is_err());
+        assert_eq!(Currency::JPY.minor_digits(), 0);
+        assert_eq!(Currency::RUB.minor_digits(), 2);
+    }
+
+    #[test]
+    fn money_parse_test() {
+        let money = Money::parse("16777217.01", Currency::RUB).unwrap();
+        assert_eq!(money.minor_units(), 1_677_721_701);
+        assert_eq!(
+            Money::parse("-0.5", Currency::RUB).unwrap().minor_units(),
+            -50
+        );
+        assert_eq!(Money::parse("12", Currency::JPY).unwrap().minor_units(), 12);
+        assert!(Money::parse("1.234", Currency::RUB).is_err());
+        assert!(Money::parse("1.2", Currency::JPY).is_err());
+        assert!(Money::parse("abc", Currency::RUB).is_err());
+        assert!(Money::parse("", Currency::RUB).is_err());
+        assert_eq!(
+            Money::parse("99999999999999999999", Currency::RUB),
+            Err(MoneyError::Overflow)
+        );
+    }
+
+    #[test]
+    fn money_display_test() {
+        assert_eq!(
+            Money::from_minor(-1_677_721_701, Currency::RUB).to_string(),
+            "-16777217.01 RUB"
+        );
+        assert_eq!(Money::from_minor(5, Currency::USD).to_string(), "0.05 USD");
+        assert_eq!(Money::from_minor(500, Currency::JPY).to_string(), "500 JPY");
+    }
+
+    #[test]
+    fn money_arithmetic_test() {
+        let mut money = Money::parse("16777216.00", Currency::RUB).unwrap();
+        let cent = Money::from_minor(1, Currency::RUB);
+        for _ in 0..1000 {
+            money = money.checked_add(cent).unwrap();
+        }
+        assert_eq!(money, Money::parse("16777226.00", Currency::RUB).unwrap());
+        assert_eq!(
+            money.checked_sub(money).unwrap(),
+            Money::zero(Currency::RUB)
+        );
+        assert!(
+            Money::from_minor(i64::MAX, Currency::RUB)
+                .checked_add(cent)
+                .is_err()
+        );
+        assert_eq!(
+            money.checked_add(Money::from_minor(1, Currency::USD)),
+            Err(MoneyError::CurrencyMismatch {
+                left: Currency::RUB,
+                right: Currency::USD
+            })
+        );
+    }
+}
@@ -2,15 +2,23 @@ use chrono::NaiveDateTime;
 
 use serde::{Deserialize, Serialize};
 
-use crate::models::{account::Account, user::User};
+use crate::models::{
+    account::Account,
+    money::{Money, MoneyError},
+    user::User,
+};
 /*
 Type for payment.
  */
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PaymentType {
     None = 0,
     Income = 1,
     Outcome = 2,
+    /// Transfer between accounts, amount is negative for the debited account.
+    Transfer = 3,
+    /// Compensating entry of reversed transaction, amount is signed as it changes balance.
+    Reversal = 4,
 }
 
 /*
@@ -20,11 +28,30 @@ Contains information about user, payment type and payment count.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MoneyTransaction {
     pub id: String,
-    pub amount: f32,
+    pub amount: Money,
     pub description: String,
     pub user: User,
     pub account: Account,
     pub payment_type: PaymentType,
     pub payment_target: String,
     pub create_date: NaiveDateTime,
+    /// Id of paired record, set for transfers.
+    #[serde(default)]
+    pub linked_transaction_id: Option<String>,
+    #[serde(default)]
+    pub category_id: Option<i32>,
+    /// Id of corrected transaction, set for reversals and amended copies.
+    #[serde(default)]
+    pub correction_of: Option<String>,
+}
+
+impl MoneyTransaction {
+    /// Amount as it changes account balance.
+    pub fn signed_amount(&self) -> Result<Money, MoneyError> {
+        match self.payment_type {
+            PaymentType::Income | PaymentType::Transfer | PaymentType::Reversal => Ok(self.amount),
+            PaymentType::Outcome => self.amount.checked_neg(),
+            PaymentType::None => Ok(Money::zero(self.amount.currency())),
+        }
+    }
 }
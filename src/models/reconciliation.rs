@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::money::Money;
+
+/// Account whose stored balance differs from initial balance plus ledger.
+/// difference is stored minus expected.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceDiscrepancy {
+    pub account_id: i32,
+    pub stored: Money,
+    pub expected: Money,
+    pub difference: Money,
+}
+
+/// Result of balance reconciliation.
+/// repaired is true when stored balances of discrepancies were replaced by expected ones.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReconciliationReport {
+    pub checked_accounts: usize,
+    pub discrepancies: Vec<BalanceDiscrepancy>,
+    pub repaired: bool,
+}
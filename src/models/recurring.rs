@@ -0,0 +1,217 @@
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::models::{money::Money, moneytransaction::PaymentType};
+
+/// When recurring transaction happens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Schedule {
+    /// Every month on day, months shorter than day use their last day.
+    MonthlyOnDay(u32),
+    /// Every `weeks` weeks from start date of template.
+    EveryWeeks(u32),
+    /// Last Monday to Friday of every month.
+    LastBusinessDay,
+}
+
+impl Schedule {
+    /// Day must be in 1..=31, weeks must be positive.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Schedule::MonthlyOnDay(day) => (1..=31).contains(day),
+            Schedule::EveryWeeks(weeks) => *weeks > 0,
+            Schedule::LastBusinessDay => true,
+        }
+    }
+
+    /// First occurrence on or after date for schedule starting at start.
+    /// None for invalid schedule or dates out of range.
+    pub fn next_on_or_after(&self, start: NaiveDate, date: NaiveDate) -> Option<NaiveDate> {
+        if !self.is_valid() {
+            return None;
+        }
+        let date = date.max(start);
+        match self {
+            Schedule::MonthlyOnDay(_) | Schedule::LastBusinessDay => {
+                let current = self.in_month(date)?;
+                if current >= date {
+                    return Some(current);
+                }
+                self.in_month(date.with_day(1)?.checked_add_months(Months::new(1))?)
+            }
+            Schedule::EveryWeeks(weeks) => {
+                let length = *weeks as i64 * 7;
+                let offset = (date - start).num_days();
+                let periods = (offset + length - 1) / length;
+                start.checked_add_days(Days::new((periods * length) as u64))
+            }
+        }
+    }
+
+    /// Occurrence in month of date for monthly schedules.
+    fn in_month(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let first = date.with_day(1)?;
+        let last = first.checked_add_months(Months::new(1))?.pred_opt()?;
+        match self {
+            Schedule::MonthlyOnDay(day) => last.with_day((*day).min(last.day())),
+            Schedule::LastBusinessDay => {
+                let back = match last.weekday() {
+                    Weekday::Sat => 1,
+                    Weekday::Sun => 2,
+                    _ => 0,
+                };
+                last.checked_sub_days(Days::new(back))
+            }
+            Schedule::EveryWeeks(_) => None,
+        }
+    }
+}
+
+/// Template of transaction repeated by schedule.
+/// Occurrences are generated from start date up to end date inclusive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecurringTransaction {
+    pub id: i32,
+    pub user_id: i32,
+    pub account_id: i32,
+    pub amount: Money,
+    pub payment_type: PaymentType,
+    pub payment_target: String,
+    pub description: String,
+    pub category_id: Option<i32>,
+    pub schedule: Schedule,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+}
+
+impl RecurringTransaction {
+    /// Occurrences after last generated one, up to today inclusive.
+    pub fn due_occurrences(&self, last: Option<NaiveDate>, today: NaiveDate) -> Vec<NaiveDate> {
+        let until = match self.end_date {
+            Some(end_date) => end_date.min(today),
+            None => today,
+        };
+        let mut dates = vec![];
+        let mut from = match last {
+            Some(last) => last.succ_opt(),
+            None => Some(self.start_date),
+        };
+        while let Some(date) =
+            from.and_then(|from| self.schedule.next_on_or_after(self.start_date, from))
+        {
+            if date > until {
+                break;
+            }
+            dates.push(date);
+            from = date.succ_opt();
+        }
+        dates
+    }
+}
+
+/// Transaction generated for one occurrence of recurring transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecurringOccurrence {
+    pub recurring_id: i32,
+    pub date: NaiveDate,
+    pub transaction_id: String,
+}
+
+/// Template whose due occurrences were not generated, next run retries them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecurringFailure {
+    pub recurring_id: i32,
+    pub message: String,
+}
+
+/// Occurrences generated by one run and templates which failed in it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenerationReport {
+    pub occurrences: Vec<RecurringOccurrence>,
+    pub failures: Vec<RecurringFailure>,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::models::{
+        money::{Currency, Money},
+        moneytransaction::PaymentType,
+        recurring::{RecurringTransaction, Schedule},
+    };
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn schedule_next_test() {
+        let start = date(2025, 1, 1);
+        let monthly = Schedule::MonthlyOnDay(31);
+        assert_eq!(
+            monthly.next_on_or_after(start, date(2025, 2, 1)),
+            Some(date(2025, 2, 28))
+        );
+        assert_eq!(
+            monthly.next_on_or_after(start, date(2025, 3, 1)),
+            Some(date(2025, 3, 31))
+        );
+
+        let biweekly = Schedule::EveryWeeks(2);
+        assert_eq!(biweekly.next_on_or_after(start, start), Some(start));
+        assert_eq!(
+            biweekly.next_on_or_after(start, date(2025, 1, 2)),
+            Some(date(2025, 1, 15))
+        );
+        assert_eq!(
+            biweekly.next_on_or_after(start, date(2024, 6, 1)),
+            Some(start)
+        );
+
+        // May 31 2025 is Saturday.
+        assert_eq!(
+            Schedule::LastBusinessDay.next_on_or_after(start, date(2025, 5, 1)),
+            Some(date(2025, 5, 30))
+        );
+        assert_eq!(
+            Schedule::LastBusinessDay.next_on_or_after(start, date(2025, 5, 31)),
+            Some(date(2025, 6, 30))
+        );
+        assert_eq!(Schedule::EveryWeeks(0).next_on_or_after(start, start), None);
+        assert_eq!(
+            Schedule::MonthlyOnDay(32).next_on_or_after(start, start),
+            None
+        );
+    }
+
+    #[test]
+    fn due_occurrences_test() {
+        let recurring = RecurringTransaction {
+            id: 1,
+            user_id: 1,
+            account_id: 1,
+            amount: Money::from_minor(100, Currency::RUB),
+            payment_type: PaymentType::Outcome,
+            payment_target: "Rent".to_string(),
+            description: "Rent".to_string(),
+            category_id: None,
+            schedule: Schedule::MonthlyOnDay(5),
+            start_date: date(2025, 1, 10),
+            end_date: Some(date(2025, 5, 5)),
+        };
+        assert_eq!(
+            recurring.due_occurrences(None, date(2025, 4, 4)),
+            vec![date(2025, 2, 5), date(2025, 3, 5)]
+        );
+        assert_eq!(
+            recurring.due_occurrences(Some(date(2025, 3, 5)), date(2025, 12, 1)),
+            vec![date(2025, 4, 5), date(2025, 5, 5)]
+        );
+        assert!(
+            recurring
+                .due_occurrences(Some(date(2025, 5, 5)), date(2025, 12, 1))
+                .is_empty()
+        );
+    }
+}
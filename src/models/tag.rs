@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+/// Free label of user, transaction may have many tags.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+}
@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::moneytransaction::MoneyTransaction;
+
+/// Page of transaction history.
+/// next_cursor is None on the last page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionPage {
+    pub transactions: Vec<MoneyTransaction>,
+    pub next_cursor: Option<String>,
+}
@@ -17,6 +17,7 @@ pub struct User {
 }
 
 impl User {
+    #[allow(clippy::redundant_field_names)]
     pub fn new(id: i32, name: String, number: String, create_date: String) -> Self {
         let date = match NaiveDate::from_str(create_date.as_str()) {
             Ok(res) => res,
@@ -0,0 +1,62 @@
+//! Types needed for everyday work with storage.
+//! `use moneycalc::prelude::*;` brings provider traits in scope,
+//! so their methods can be called on providers.
+pub use crate::{
+    backup::{
+        AccountBackup, BACKUP_VERSION, Backup, RestoreMode, TransactionTags, export_all,
+        export_user, restore,
+    },
+    commands::{
+        accounts::addaccountcommand::AddAccountCommand,
+        audit::auditquery::AuditQuery,
+        budgets::addbudgetcommand::AddBudgetCommand,
+        categories::addcategorycommand::AddCategoryCommand,
+        recurring::addrecurringcommand::AddRecurringCommand,
+        tags::addtagcommand::AddTagCommand,
+        transactions::{
+            amendtransactioncommand::AmendTransactionCommand,
+            reversetransactioncommand::ReverseTransactionCommand,
+            transactionquery::{TransactionOrder, TransactionQuery},
+            transfercommand::TransferCommand,
+        },
+        users::addusercommand::AddUserCommand,
+    },
+    config::{
+        JournalMode, ProviderConfiguration, SqliteConfiguration, StorageConfiguration,
+        settings::StorageSettings,
+    },
+    errors::{MoneyCalcError, MoneyCalcResult},
+    imports::{
+        ImportReport, RowError,
+        csvimport::{AmountColumns, CsvColumn, CsvMapping, SignConvention, import_csv},
+        ofx::{OfxImportReport, OfxStatement, OfxTransaction, import_ofx, parse_ofx},
+    },
+    models::{
+        account::{Account, OverdraftPolicy},
+        auditentry::AuditEntry,
+        balance::{BalanceSnapshot, DailyBalance},
+        budget::{Budget, BudgetPeriod, BudgetProgress, BudgetScope},
+        category::Category,
+        exchangerate::ExchangeRate,
+        importedtransaction::ImportedTransaction,
+        money::{Currency, Money, MoneyError},
+        moneytransaction::{MoneyTransaction, PaymentType},
+        reconciliation::{BalanceDiscrepancy, ReconciliationReport},
+        recurring::{
+            GenerationReport, RecurringFailure, RecurringOccurrence, RecurringTransaction, Schedule,
+        },
+        tag::Tag,
+        transactionpage::TransactionPage,
+        user::User,
+    },
+    providers::{
+        AccountProvider, AuditProvider, BalanceHistoryProvider, BudgetProvider, CategoryProvider,
+        DataProvider, ExchangeRateProvider, ReconciliationProvider, RecurringProvider, TagProvider,
+        TransactionWorker, UserProvider, bases::sqlite::SqliteProvider,
+    },
+};
+
+#[cfg(feature = "memory")]
+pub use crate::providers::bases::memory::MemoryProvider;
+#[cfg(feature = "postgres")]
+pub use crate::{config::PostgresConfiguration, providers::bases::postgres::PostgresProvider};
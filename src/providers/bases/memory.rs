@@ -0,0 +1,2342 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{
+    commands::{
+        accounts::addaccountcommand::AddAccountCommand,
+        audit::auditquery::AuditQuery,
+        budgets::addbudgetcommand::AddBudgetCommand,
+        categories::addcategorycommand::AddCategoryCommand,
+        recurring::addrecurringcommand::AddRecurringCommand,
+        tags::addtagcommand::AddTagCommand,
+        transactions::{
+            amendtransactioncommand::AmendTransactionCommand,
+            reversetransactioncommand::ReverseTransactionCommand,
+            transactionquery::{TransactionOrder, TransactionQuery},
+            transfercommand::TransferCommand,
+        },
+        users::addusercommand::AddUserCommand,
+    },
+    errors::{MoneyCalcError, MoneyCalcResult},
+    models::{
+        account::{Account, OverdraftPolicy},
+        auditentry::AuditEntry,
+        balance::{BalanceSnapshot, DailyBalance},
+        budget::{Budget, BudgetPeriod, BudgetProgress, BudgetScope},
+        category::Category,
+        exchangerate::ExchangeRate,
+        importedtransaction::ImportedTransaction,
+        money::{Currency, Money},
+        moneytransaction::{MoneyTransaction, PaymentType},
+        reconciliation::{BalanceDiscrepancy, ReconciliationReport},
+        recurring::{
+            GenerationReport, RecurringFailure, RecurringOccurrence, RecurringTransaction,
+        },
+        tag::Tag,
+        transactionpage::TransactionPage,
+        user::User,
+    },
+    providers::{
+        AccountProvider, AuditProvider, BalanceHistoryProvider, BudgetProvider, CategoryProvider,
+        ExchangeRateProvider, ReconciliationProvider, RecurringProvider, TagProvider,
+        TransactionWorker, UserProvider,
+        bases::{
+            AUDIT_DATE_FORMAT, DEFAULT_ACTOR, audit_hash, check_executable, end_of_day,
+            transaction_cursor, validate_overdraft,
+        },
+    },
+};
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Provider keeping all data in process memory.
+/// Behaves like SqliteProvider, including ids, ordering, errors and audit log,
+/// so code using providers can be tested without database.
+/// Clones share the same data.
+#[derive(Clone)]
+pub struct MemoryProvider {
+    state: Arc<Mutex<MemoryState>>,
+    auditor: Auditor,
+}
+
+impl Default for MemoryProvider {
+    fn default() -> Self {
+        Self {
+            state: Arc::default(),
+            auditor: Auditor {
+                actor: Arc::from(DEFAULT_ACTOR),
+                hash_chain: false,
+            },
+        }
+    }
+}
+
+impl MemoryProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clone of provider which logs changes in audit log as made by actor.
+    pub fn with_actor(&self, actor: &str) -> Self {
+        let mut provider = self.clone();
+        provider.auditor.actor = Arc::from(actor);
+        provider
+    }
+
+    /// Make every audit record keep hash of the previous one, like audit_hash_chain of sqlite.
+    pub fn with_audit_hash_chain(mut self, audit_hash_chain: bool) -> Self {
+        self.auditor.hash_chain = audit_hash_chain;
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemoryState> {
+        // Every operation checks everything before changing state, so state of panicked one is consistent.
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Stored transaction, user and account are read by ids like sqlite join does.
+#[derive(Clone)]
+struct TransactionRow {
+    id: String,
+    amount: Money,
+    description: String,
+    user_id: i32,
+    account_id: i32,
+    payment_type: PaymentType,
+    payment_target: String,
+    create_date: NaiveDateTime,
+    linked_transaction_id: Option<String>,
+    category_id: Option<i32>,
+    correction_of: Option<String>,
+}
+
+impl TransactionRow {
+    /// Signed amount as it changed account balance, like LEDGER_AMOUNT.
+    fn ledger_amount(&self) -> MoneyCalcResult<Money> {
+        match self.payment_type {
+            PaymentType::Outcome => Ok(self.amount.checked_neg()?),
+            PaymentType::None => Ok(Money::zero(self.amount.currency())),
+            _ => Ok(self.amount),
+        }
+    }
+}
+
+/// Stored audit record, before and after are kept as hashed json text.
+struct AuditRow {
+    id: i64,
+    actor: Arc<str>,
+    action: &'static str,
+    entity: &'static str,
+    entity_id: String,
+    before: Option<String>,
+    after: Option<String>,
+    create_date: NaiveDateTime,
+    hash: Option<String>,
+}
+
+impl AuditRow {
+    fn hash_fields<'a>(
+        &'a self,
+        previous: Option<&'a str>,
+        create_date: &'a str,
+    ) -> [Option<&'a str>; 8] {
+        [
+            previous,
+            Some(&self.actor),
+            Some(self.action),
+            Some(self.entity),
+            Some(&self.entity_id),
+            self.before.as_deref(),
+            self.after.as_deref(),
+            Some(create_date),
+        ]
+    }
+
+    fn read(&self) -> MoneyCalcResult<AuditEntry> {
+        let json = |text: &Option<String>| text.as_deref().map(serde_json::from_str).transpose();
+        Ok(AuditEntry {
+            id: self.id,
+            actor: self.actor.to_string(),
+            action: self.action.to_string(),
+            entity: self.entity.to_string(),
+            entity_id: self.entity_id.clone(),
+            before: json(&self.before)?,
+            after: json(&self.after)?,
+            create_date: self.create_date,
+            hash: self.hash.clone(),
+        })
+    }
+}
+
+/// Writes audit records of changes made through provider.
+#[derive(Clone, Debug)]
+struct Auditor {
+    actor: Arc<str>,
+    hash_chain: bool,
+}
+
+impl Auditor {
+    /// Record of change, it gets id and hash when appended to log.
+    fn entry<T: Serialize>(
+        &self,
+        action: &'static str,
+        entity: &'static str,
+        entity_id: impl ToString,
+        before: Option<&T>,
+        after: Option<&T>,
+    ) -> MoneyCalcResult<AuditRow> {
+        Ok(AuditRow {
+            id: 0,
+            actor: self.actor.clone(),
+            action,
+            entity,
+            entity_id: entity_id.to_string(),
+            before: before.map(serde_json::to_string).transpose()?,
+            after: after.map(serde_json::to_string).transpose()?,
+            create_date: chrono::Utc::now().naive_utc(),
+            hash: None,
+        })
+    }
+
+    fn append(&self, state: &mut MemoryState, mut row: AuditRow) {
+        row.id = state.audit.len() as i64 + 1;
+        if self.hash_chain {
+            let previous = state.audit.iter().rev().find_map(|row| row.hash.clone());
+            let create_date = row.create_date.format(AUDIT_DATE_FORMAT).to_string();
+            row.hash = Some(audit_hash(
+                row.hash_fields(previous.as_deref(), &create_date),
+            ));
+        }
+        state.audit.push(row);
+    }
+
+    fn record<T: Serialize>(
+        &self,
+        state: &mut MemoryState,
+        action: &'static str,
+        entity: &'static str,
+        entity_id: impl ToString,
+        before: Option<&T>,
+        after: Option<&T>,
+    ) -> MoneyCalcResult<()> {
+        let row = self.entry(action, entity, entity_id, before, after)?;
+        self.append(state, row);
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct MemoryState {
+    users: BTreeMap<i32, User>,
+    accounts: BTreeMap<i32, Account>,
+    /// Initial balances of accounts, start of their balance history.
+    initial_balances: BTreeMap<i32, Money>,
+    transactions: HashMap<String, TransactionRow>,
+    /// Transaction ids by account and bank id.
+    imported: BTreeMap<(i32, String), String>,
+    exchange_rates: BTreeMap<(Currency, Currency, NaiveDate), ExchangeRate>,
+    categories: BTreeMap<i32, Category>,
+    tags: BTreeMap<i32, Tag>,
+    /// Pairs of transaction id and tag id.
+    transaction_tags: BTreeSet<(String, i32)>,
+    budgets: BTreeMap<i32, Budget>,
+    recurring: BTreeMap<i32, RecurringTransaction>,
+    /// Generated transaction ids by template and occurrence date.
+    occurrences: BTreeMap<(i32, NaiveDate), String>,
+    snapshots: BTreeMap<(i32, NaiveDateTime), Money>,
+    audit: Vec<AuditRow>,
+}
+
+/// Next id like sqlite rowid, one more than the largest one.
+fn next_id<T>(records: &BTreeMap<i32, T>) -> i32 {
+    records.last_key_value().map(|(id, _)| id + 1).unwrap_or(1)
+}
+
+fn missing_reference(what: String) -> MoneyCalcError {
+    MoneyCalcError::Validation(format!("{} does not exist", what))
+}
+
+/// Record can't be deleted while others refer to it.
+fn still_referenced(what: String) -> MoneyCalcError {
+    MoneyCalcError::Conflict(format!("{} is still referenced", what))
+}
+
+/// Payment target matches budget pattern like sqlite like does:
+/// `*` matches any text, ascii letters are compared case insensitively.
+fn matches_pattern(pattern: &str, target: &str) -> bool {
+    let pattern = pattern.to_ascii_lowercase();
+    let target = target.to_ascii_lowercase();
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let [first, middle @ .., last] = parts.as_slice() else {
+        return pattern == target;
+    };
+    let Some(mut rest) = target.strip_prefix(first) else {
+        return false;
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+impl MemoryState {
+    fn user(&self, id: i32) -> MoneyCalcResult<&User> {
+        self.users
+            .get(&id)
+            .ok_or_else(|| MoneyCalcError::NotFound(format!("user {}", id)))
+    }
+
+    fn account(&self, id: i32) -> MoneyCalcResult<&Account> {
+        self.accounts
+            .get(&id)
+            .ok_or_else(|| MoneyCalcError::NotFound(format!("account {}", id)))
+    }
+
+    fn read(&self, row: &TransactionRow) -> MoneyCalcResult<MoneyTransaction> {
+        Ok(MoneyTransaction {
+            id: row.id.clone(),
+            amount: row.amount,
+            description: row.description.clone(),
+            user: self.user(row.user_id)?.clone(),
+            account: self.account(row.account_id)?.clone(),
+            payment_type: row.payment_type,
+            payment_target: row.payment_target.clone(),
+            create_date: row.create_date,
+            linked_transaction_id: row.linked_transaction_id.clone(),
+            category_id: row.category_id,
+            correction_of: row.correction_of.clone(),
+        })
+    }
+
+    fn transaction_row(&self, id: &str) -> MoneyCalcResult<&TransactionRow> {
+        self.transactions
+            .get(id)
+            .ok_or_else(|| MoneyCalcError::NotFound(format!("transaction {}", id)))
+    }
+
+    fn transaction(&self, id: &str) -> MoneyCalcResult<MoneyTransaction> {
+        self.read(self.transaction_row(id)?)
+    }
+
+    /// Owner of transaction is the owner of its account.
+    fn transaction_owner(&self, id: &str) -> MoneyCalcResult<i32> {
+        let row = self.transaction_row(id)?;
+        Ok(self.account(row.account_id)?.user_id)
+    }
+
+    fn add_user(&mut self, user: User) -> MoneyCalcResult<User> {
+        if self.users.contains_key(&user.id) {
+            return Err(MoneyCalcError::Conflict(format!(
+                "user {} already exists",
+                user.id
+            )));
+        }
+        if self.users.values().any(|other| other.number == user.number) {
+            return Err(MoneyCalcError::Conflict(format!(
+                "user with number {} already exists",
+                user.number
+            )));
+        }
+        self.users.insert(user.id, user.clone());
+        Ok(user)
+    }
+
+    fn add_account(
+        &mut self,
+        account: Account,
+        initial_balance: Money,
+    ) -> MoneyCalcResult<Account> {
+        if !self.users.contains_key(&account.user_id) {
+            return Err(missing_reference(format!("user {}", account.user_id)));
+        }
+        if self.accounts.contains_key(&account.id) {
+            return Err(MoneyCalcError::Conflict(format!(
+                "account {} already exists",
+                account.id
+            )));
+        }
+        self.accounts.insert(account.id, account.clone());
+        self.initial_balances.insert(account.id, initial_balance);
+        Ok(account)
+    }
+
+    /// Latest rate of pair on or before date, taken from either direction.
+    /// Rate of opposite pair is inverted, direct pair wins on the same date.
+    fn exchange_rate(
+        &self,
+        from: Currency,
+        to: Currency,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<ExchangeRate> {
+        if from == to {
+            return Ok(ExchangeRate::identity(from));
+        }
+
+        let latest = self
+            .exchange_rates
+            .iter()
+            .filter(|((rate_from, rate_to, rate_date), _)| {
+                *rate_date <= date
+                    && ((*rate_from, *rate_to) == (from, to)
+                        || (*rate_from, *rate_to) == (to, from))
+            })
+            .max_by_key(|((rate_from, _, rate_date), _)| (*rate_date, *rate_from == from));
+        match latest {
+            Some(((rate_from, _, _), rate)) if *rate_from == from => Ok(*rate),
+            Some((_, rate)) => Ok(rate.inverse()?),
+            None => Err(MoneyCalcError::NotFound(format!(
+                "exchange rate {}/{} on {}",
+                from, to, date
+            ))),
+        }
+    }
+
+    fn category(&self, id: i32) -> MoneyCalcResult<&Category> {
+        self.categories
+            .get(&id)
+            .ok_or_else(|| MoneyCalcError::NotFound(format!("category {}", id)))
+    }
+
+    /// Categories and tags can only be used by their owner.
+    fn check_category_owner(&self, category_id: i32, user_id: i32) -> MoneyCalcResult<&Category> {
+        let category = self.category(category_id)?;
+        if category.user_id != user_id {
+            return Err(MoneyCalcError::Validation(format!(
+                "category {} belongs to another user",
+                category_id
+            )));
+        }
+        Ok(category)
+    }
+
+    /// Name of category is unique among categories of user with the same parent.
+    fn check_category_name(&self, category: &Category) -> MoneyCalcResult<()> {
+        let taken = self.categories.values().any(|other| {
+            other.id != category.id
+                && other.user_id == category.user_id
+                && other.parent_id == category.parent_id
+                && other.name == category.name
+        });
+        if taken {
+            return Err(MoneyCalcError::Conflict(format!(
+                "category {} already exists",
+                category.name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Insert category with checked parent, like restore_category does.
+    fn insert_category(&mut self, mut category: Category) -> MoneyCalcResult<Category> {
+        if let Some(parent_id) = category.parent_id {
+            self.check_category_owner(parent_id, category.user_id)?;
+        }
+        if category.id == 0 {
+            category.id = next_id(&self.categories);
+        }
+        if self.categories.contains_key(&category.id) {
+            return Err(MoneyCalcError::Conflict(format!(
+                "category {} already exists",
+                category.id
+            )));
+        }
+        if !self.users.contains_key(&category.user_id) {
+            return Err(missing_reference(format!("user {}", category.user_id)));
+        }
+        self.check_category_name(&category)?;
+        self.categories.insert(category.id, category.clone());
+        Ok(category)
+    }
+
+    /// Category is root or one of its subcategories.
+    fn in_subcategories(&self, category_id: Option<i32>, root: i32) -> bool {
+        let mut current = category_id;
+        while let Some(id) = current {
+            if id == root {
+                return true;
+            }
+            current = self
+                .categories
+                .get(&id)
+                .and_then(|category| category.parent_id);
+        }
+        false
+    }
+
+    fn tag(&self, id: i32) -> MoneyCalcResult<&Tag> {
+        self.tags
+            .get(&id)
+            .ok_or_else(|| MoneyCalcError::NotFound(format!("tag {}", id)))
+    }
+
+    /// Name of tag is unique among tags of user.
+    fn check_tag_name(&self, tag: &Tag) -> MoneyCalcResult<()> {
+        let taken = self.tags.values().any(|other| {
+            other.id != tag.id && other.user_id == tag.user_id && other.name == tag.name
+        });
+        if taken {
+            return Err(MoneyCalcError::Conflict(format!(
+                "tag {} already exists",
+                tag.name
+            )));
+        }
+        Ok(())
+    }
+
+    fn insert_tag(&mut self, mut tag: Tag) -> MoneyCalcResult<Tag> {
+        if tag.id == 0 {
+            tag.id = next_id(&self.tags);
+        }
+        if self.tags.contains_key(&tag.id) {
+            return Err(MoneyCalcError::Conflict(format!(
+                "tag {} already exists",
+                tag.id
+            )));
+        }
+        if !self.users.contains_key(&tag.user_id) {
+            return Err(missing_reference(format!("user {}", tag.user_id)));
+        }
+        self.check_tag_name(&tag)?;
+        self.tags.insert(tag.id, tag.clone());
+        Ok(tag)
+    }
+
+    /// Tags of transaction ordered by name.
+    fn transaction_tags(&self, transaction_id: &str) -> MoneyCalcResult<Vec<Tag>> {
+        let mut tags = self
+            .transaction_tags
+            .iter()
+            .filter(|(id, _)| id == transaction_id)
+            .map(|(_, tag_id)| self.tag(*tag_id).cloned())
+            .collect::<MoneyCalcResult<Vec<_>>>()?;
+        tags.sort_by(|left, right| left.name.cmp(&right.name));
+        Ok(tags)
+    }
+
+    fn budget(&self, id: i32) -> MoneyCalcResult<&Budget> {
+        self.budgets
+            .get(&id)
+            .ok_or_else(|| MoneyCalcError::NotFound(format!("budget {}", id)))
+    }
+
+    /// Budget must have positive limit and period, its scope must belong to the user.
+    fn validate_budget(
+        &self,
+        user_id: i32,
+        scope: &BudgetScope,
+        period: &BudgetPeriod,
+        limit: Money,
+    ) -> MoneyCalcResult<()> {
+        if limit.is_negative() || limit.is_zero() {
+            return Err(MoneyCalcError::Validation(format!(
+                "budget limit {} must be positive",
+                limit
+            )));
+        }
+        if let BudgetPeriod::Custom { days: 0, .. } = period {
+            return Err(MoneyCalcError::Validation(
+                "custom budget period must be at least one day".to_string(),
+            ));
+        }
+        match scope {
+            BudgetScope::Account(account_id) => {
+                if self.account(*account_id)?.user_id != user_id {
+                    return Err(MoneyCalcError::Validation(format!(
+                        "account {} belongs to another user",
+                        account_id
+                    )));
+                }
+            }
+            BudgetScope::PaymentTarget(pattern) => {
+                if pattern.trim().is_empty() {
+                    return Err(MoneyCalcError::Validation(
+                        "budget payment target pattern is empty".to_string(),
+                    ));
+                }
+            }
+            BudgetScope::Category(category_id) => {
+                self.check_category_owner(*category_id, user_id)?;
+            }
+        }
+        if !self.users.contains_key(&user_id) {
+            return Err(missing_reference(format!("user {}", user_id)));
+        }
+        Ok(())
+    }
+
+    /// Sum outcome transactions in scope of budget for the period containing date.
+    /// Reversed transactions are skipped, amended ones are counted by their corrected copy.
+    fn budget_progress(&self, budget: &Budget, date: NaiveDate) -> MoneyCalcResult<BudgetProgress> {
+        let (from_date, to_date) = budget.period.range(date).ok_or_else(|| {
+            MoneyCalcError::Validation(format!("budget {} has invalid period", budget.id))
+        })?;
+        let (from, to) = (
+            from_date.and_time(NaiveTime::MIN),
+            to_date.and_time(NaiveTime::MIN),
+        );
+        let reversed: HashSet<&str> = self
+            .transactions
+            .values()
+            .filter(|row| row.payment_type == PaymentType::Reversal)
+            .filter_map(|row| row.correction_of.as_deref())
+            .collect();
+
+        let currency = budget.limit.currency();
+        let mut spent = Money::zero(currency);
+        for row in self.transactions.values() {
+            let in_scope = match &budget.scope {
+                BudgetScope::Account(account_id) => row.account_id == *account_id,
+                BudgetScope::PaymentTarget(pattern) => {
+                    matches_pattern(pattern, &row.payment_target)
+                }
+                BudgetScope::Category(category_id) => {
+                    self.in_subcategories(row.category_id, *category_id)
+                }
+            };
+            if !in_scope
+                || row.payment_type != PaymentType::Outcome
+                || row.create_date < from
+                || row.create_date >= to
+                || reversed.contains(row.id.as_str())
+                || self.account(row.account_id)?.user_id != budget.user_id
+            {
+                continue;
+            }
+            let amount = row.amount.checked_abs()?;
+            let rate = self.exchange_rate(amount.currency(), currency, row.create_date.date())?;
+            spent = spent.checked_add(rate.convert(amount)?)?;
+        }
+        Ok(BudgetProgress {
+            budget: budget.clone(),
+            from_date,
+            to_date,
+            planned: budget.limit,
+            spent,
+            remaining: budget.limit.checked_sub(spent)?,
+        })
+    }
+
+    fn recurring(&self, id: i32) -> MoneyCalcResult<&RecurringTransaction> {
+        self.recurring
+            .get(&id)
+            .ok_or_else(|| MoneyCalcError::NotFound(format!("recurring transaction {}", id)))
+    }
+
+    fn validate_recurring(&self, command: &AddRecurringCommand) -> MoneyCalcResult<()> {
+        if !matches!(
+            command.payment_type,
+            PaymentType::Income | PaymentType::Outcome
+        ) {
+            return Err(MoneyCalcError::Validation(
+                "recurring transaction must be income or outcome".to_string(),
+            ));
+        }
+        if command.amount.is_negative() || command.amount.is_zero() {
+            return Err(MoneyCalcError::Validation(format!(
+                "recurring amount {} must be positive",
+                command.amount
+            )));
+        }
+        if !command.schedule.is_valid() {
+            return Err(MoneyCalcError::Validation(format!(
+                "invalid schedule {:?}",
+                command.schedule
+            )));
+        }
+        if command
+            .end_date
+            .is_some_and(|end_date| end_date < command.start_date)
+        {
+            return Err(MoneyCalcError::Validation(
+                "recurring transaction ends before start".to_string(),
+            ));
+        }
+        let account = self.account(command.account_id)?;
+        if account.user_id != command.user_id {
+            return Err(MoneyCalcError::Validation(format!(
+                "account {} belongs to another user",
+                account.id
+            )));
+        }
+        account.money.check_currency(&command.amount)?;
+        if let Some(category_id) = command.category_id {
+            self.check_category_owner(category_id, command.user_id)?;
+        }
+        Ok(())
+    }
+
+    /// Balance of account after all transactions dated at or before moment.
+    /// Starts from latest snapshot before moment, or from initial balance without one.
+    fn balance_at(&self, account_id: i32, at: NaiveDateTime) -> MoneyCalcResult<Money> {
+        let account = self.account(account_id)?;
+        let snapshot = self
+            .snapshots
+            .range((account_id, NaiveDateTime::MIN)..=(account_id, at))
+            .next_back();
+        let (since, mut balance) = match snapshot {
+            Some(((_, date), balance)) => (Some(*date), *balance),
+            None => (
+                None,
+                self.initial_balances
+                    .get(&account_id)
+                    .copied()
+                    .unwrap_or(Money::zero(account.money.currency())),
+            ),
+        };
+        for row in self.transactions.values() {
+            if row.account_id == account_id
+                && row.create_date <= at
+                && since.is_none_or(|since| row.create_date > since)
+            {
+                balance = balance.checked_add(row.ledger_amount()?)?;
+            }
+        }
+        Ok(balance)
+    }
+}
+
+/// Changes of one operation, written to state only after all of them are checked.
+#[derive(Default)]
+struct Batch {
+    balances: BTreeMap<i32, Money>,
+    rows: Vec<TransactionRow>,
+}
+
+impl Batch {
+    /// Balance of account with changes of batch.
+    fn balance(&self, state: &MemoryState, account_id: i32) -> MoneyCalcResult<Money> {
+        match self.balances.get(&account_id) {
+            Some(balance) => Ok(*balance),
+            None => Ok(state.account(account_id)?.money),
+        }
+    }
+
+    /// Add signed amount to balance, debit is checked by overdraft policy.
+    fn change_money(
+        &mut self,
+        state: &MemoryState,
+        account_id: i32,
+        amount: Money,
+    ) -> MoneyCalcResult<()> {
+        let account = state.account(account_id)?;
+        let balance = self.balance(state, account_id)?;
+        let new_balance = balance.checked_add(amount)?;
+        if amount.is_negative() && !account.overdraft.allows(new_balance)? {
+            return Err(MoneyCalcError::InsufficientFunds(format!(
+                "account {} has {}, debit of {} is not allowed",
+                account_id,
+                balance,
+                amount.checked_abs()?
+            )));
+        }
+        self.balances.insert(account_id, new_balance);
+        Ok(())
+    }
+
+    /// Add transaction row, user of row is the owner of transaction account.
+    fn insert(
+        &mut self,
+        state: &MemoryState,
+        id: &str,
+        transaction: &MoneyTransaction,
+    ) -> MoneyCalcResult<()> {
+        if let Some(category_id) = transaction.category_id {
+            state.check_category_owner(category_id, transaction.account.user_id)?;
+        }
+        let taken = |id: &str| {
+            state.transactions.contains_key(id) || self.rows.iter().any(|row| row.id == id)
+        };
+        if taken(id) {
+            return Err(MoneyCalcError::Conflict(format!(
+                "transaction {} already exists",
+                id
+            )));
+        }
+        if !state.users.contains_key(&transaction.account.user_id) {
+            return Err(missing_reference(format!(
+                "user {}",
+                transaction.account.user_id
+            )));
+        }
+        if !state.accounts.contains_key(&transaction.account.id) {
+            return Err(missing_reference(format!(
+                "account {}",
+                transaction.account.id
+            )));
+        }
+        if let Some(correction_of) = &transaction.correction_of
+            && !taken(correction_of)
+        {
+            return Err(missing_reference(format!("transaction {}", correction_of)));
+        }
+        self.rows.push(TransactionRow {
+            id: id.to_string(),
+            amount: transaction.amount,
+            description: transaction.description.clone(),
+            user_id: transaction.account.user_id,
+            account_id: transaction.account.id,
+            payment_type: transaction.payment_type,
+            payment_target: transaction.payment_target.clone(),
+            create_date: transaction.create_date,
+            linked_transaction_id: transaction.linked_transaction_id.clone(),
+            category_id: transaction.category_id,
+            correction_of: transaction.correction_of.clone(),
+        });
+        Ok(())
+    }
+
+    /// Write changes, snapshots at or after new transactions no longer hold their balance.
+    fn commit(self, state: &mut MemoryState) {
+        for (account_id, balance) in self.balances {
+            if let Some(account) = state.accounts.get_mut(&account_id) {
+                account.money = balance;
+            }
+        }
+        for row in self.rows {
+            state.snapshots.retain(|(account_id, date), _| {
+                *account_id != row.account_id || *date < row.create_date
+            });
+            state.transactions.insert(row.id.clone(), row);
+        }
+    }
+}
+
+/// Add Reversal entries for transaction and its transfer pair, returns reversal of transaction.
+fn reverse_transaction(
+    state: &MemoryState,
+    batch: &mut Batch,
+    original: &MoneyTransaction,
+    description: &str,
+    create_date: NaiveDateTime,
+) -> MoneyCalcResult<MoneyTransaction> {
+    if let PaymentType::Reversal = original.payment_type {
+        return Err(MoneyCalcError::Validation(format!(
+            "transaction {} is a reversal",
+            original.id
+        )));
+    }
+    let reversed = state.transactions.values().any(|row| {
+        row.payment_type == PaymentType::Reversal
+            && row.correction_of.as_deref() == Some(original.id.as_str())
+    });
+    if reversed {
+        return Err(MoneyCalcError::Conflict(format!(
+            "transaction {} is already reversed",
+            original.id
+        )));
+    }
+
+    let mut legs = vec![original.clone()];
+    if let (PaymentType::Transfer, Some(linked_id)) =
+        (original.payment_type, &original.linked_transaction_id)
+    {
+        legs.push(state.transaction(linked_id)?);
+    }
+    let ids: Vec<String> = legs.iter().map(|_| Uuid::new_v4().to_string()).collect();
+    let mut reversals = vec![];
+    for (index, leg) in legs.iter().enumerate() {
+        let reversal = MoneyTransaction {
+            id: ids[index].clone(),
+            amount: leg.signed_amount()?.checked_neg()?,
+            description: description.to_string(),
+            user: leg.user.clone(),
+            account: leg.account.clone(),
+            payment_type: PaymentType::Reversal,
+            payment_target: leg.payment_target.clone(),
+            create_date,
+            linked_transaction_id: ids.get(1 - index).cloned(),
+            category_id: leg.category_id,
+            correction_of: Some(leg.id.clone()),
+        };
+        batch.change_money(state, leg.account.id, reversal.amount)?;
+        batch.insert(state, &reversal.id, &reversal)?;
+        reversals.push(reversal);
+    }
+    Ok(reversals.swap_remove(0))
+}
+
+/// Sort key of transaction, compared like sqlite compares stored values.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum SortKey {
+    Date(String),
+    Amount(u64),
+}
+
+fn sort_key(order: TransactionOrder, row: &TransactionRow) -> SortKey {
+    match order {
+        TransactionOrder::DateAsc | TransactionOrder::DateDesc => {
+            SortKey::Date(row.create_date.format("%F %T%.f").to_string())
+        }
+        TransactionOrder::AmountAsc | TransactionOrder::AmountDesc => {
+            SortKey::Amount(row.amount.minor_units().unsigned_abs())
+        }
+    }
+}
+
+fn matches_query(state: &MemoryState, query: &TransactionQuery, row: &TransactionRow) -> bool {
+    let amount = row.amount.minor_units().unsigned_abs();
+    let in_bound = |bound: &Option<Money>, ordering: Ordering| {
+        bound.is_none_or(|bound| {
+            row.amount.currency() == bound.currency()
+                && amount.cmp(&bound.minor_units().unsigned_abs()) != ordering
+        })
+    };
+    query.account_id.is_none_or(|id| row.account_id == id)
+        && query.user_id.is_none_or(|id| row.user_id == id)
+        && query.from_date.is_none_or(|date| row.create_date >= date)
+        && query.to_date.is_none_or(|date| row.create_date < date)
+        && query
+            .payment_type
+            .is_none_or(|payment_type| row.payment_type == payment_type)
+        && query
+            .payment_target
+            .as_ref()
+            .is_none_or(|target| &row.payment_target == target)
+        && in_bound(&query.min_amount, Ordering::Less)
+        && in_bound(&query.max_amount, Ordering::Greater)
+        && query
+            .category_id
+            .is_none_or(|id| state.in_subcategories(row.category_id, id))
+        && query.description_contains.as_ref().is_none_or(|text| {
+            // Like sqlite LIKE, only ascii letters are compared case insensitively.
+            row.description
+                .to_ascii_lowercase()
+                .contains(&text.to_ascii_lowercase())
+        })
+}
+
+/// Generate transactions for occurrences of template after the last generated one.
+/// Nothing is written when one of them fails.
+fn generate_occurrences(
+    state: &mut MemoryState,
+    auditor: &Auditor,
+    recurring_id: i32,
+    today: NaiveDate,
+) -> MoneyCalcResult<Vec<RecurringOccurrence>> {
+    let recurring = state.recurring(recurring_id)?.clone();
+    let last = state
+        .occurrences
+        .range((recurring_id, NaiveDate::MIN)..=(recurring_id, NaiveDate::MAX))
+        .next_back()
+        .map(|((_, date), _)| *date);
+    let dates = recurring.due_occurrences(last, today);
+    if dates.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let account = state.account(recurring.account_id)?.clone();
+    let user = state.user(recurring.user_id)?.clone();
+    let mut batch = Batch::default();
+    let mut records = vec![];
+    let mut occurrences = vec![];
+    for date in dates {
+        let transaction = MoneyTransaction {
+            id: Uuid::new_v4().to_string(),
+            amount: recurring.amount,
+            description: recurring.description.clone(),
+            user: user.clone(),
+            account: account.clone(),
+            payment_type: recurring.payment_type,
+            payment_target: recurring.payment_target.clone(),
+            create_date: date.and_time(NaiveTime::MIN),
+            linked_transaction_id: None,
+            correction_of: None,
+            category_id: recurring.category_id,
+        };
+        batch.change_money(state, account.id, transaction.signed_amount()?)?;
+        batch.insert(state, &transaction.id, &transaction)?;
+        // Logged as it is read right after its insert, with balance of account at that moment.
+        let stored = MoneyTransaction {
+            account: Account {
+                money: batch.balance(state, account.id)?,
+                ..account.clone()
+            },
+            ..transaction.clone()
+        };
+        records.push(auditor.entry(
+            "generate_due",
+            "transaction",
+            &transaction.id,
+            None,
+            Some(&stored),
+        )?);
+        occurrences.push(RecurringOccurrence {
+            recurring_id,
+            date,
+            transaction_id: transaction.id,
+        });
+    }
+    batch.commit(state);
+    for record in records {
+        auditor.append(state, record);
+    }
+    for occurrence in &occurrences {
+        state.occurrences.insert(
+            (recurring_id, occurrence.date),
+            occurrence.transaction_id.clone(),
+        );
+    }
+    Ok(occurrences)
+}
+
+#[async_trait]
+impl UserProvider for MemoryProvider {
+    async fn add_user(&self, add_user_command: &AddUserCommand) -> MoneyCalcResult<()> {
+        let mut state = self.lock();
+        let user = User {
+            id: next_id(&state.users),
+            name: add_user_command.user_name.clone(),
+            number: add_user_command.user_number.clone(),
+            creation_date: chrono::Utc::now().naive_utc().date(),
+        };
+        let user = state.add_user(user)?;
+        self.auditor
+            .record(&mut state, "add_user", "user", user.id, None, Some(&user))
+    }
+
+    async fn get_users(&self) -> MoneyCalcResult<Vec<User>> {
+        Ok(self.lock().users.values().cloned().collect())
+    }
+
+    async fn get_user_by_number(&self, number: &str) -> MoneyCalcResult<User> {
+        self.lock()
+            .users
+            .values()
+            .find(|user| user.number == number)
+            .cloned()
+            .ok_or_else(|| MoneyCalcError::NotFound(format!("user with number {}", number)))
+    }
+
+    async fn delete_user_by_id(&self, id: i32) -> MoneyCalcResult<()> {
+        let mut state = self.lock();
+        let user = state.user(id)?.clone();
+        let referenced = state.accounts.values().any(|account| account.user_id == id)
+            || state
+                .categories
+                .values()
+                .any(|category| category.user_id == id)
+            || state.tags.values().any(|tag| tag.user_id == id)
+            || state.budgets.values().any(|budget| budget.user_id == id)
+            || state
+                .recurring
+                .values()
+                .any(|recurring| recurring.user_id == id);
+        if referenced {
+            return Err(still_referenced(format!("user {}", id)));
+        }
+        state.users.remove(&id);
+        self.auditor.record(
+            &mut state,
+            "delete_user_by_id",
+            "user",
+            id,
+            Some(&user),
+            None,
+        )
+    }
+
+    async fn restore_user(&self, user: &User) -> MoneyCalcResult<User> {
+        let mut state = self.lock();
+        let mut user = user.clone();
+        if user.id == 0 {
+            user.id = next_id(&state.users);
+        }
+        let restored = state.add_user(user)?;
+        self.auditor.record(
+            &mut state,
+            "restore_user",
+            "user",
+            restored.id,
+            None,
+            Some(&restored),
+        )?;
+        Ok(restored)
+    }
+}
+
+#[async_trait]
+impl AccountProvider for MemoryProvider {
+    async fn search_account_by_user(&self, user: &User) -> MoneyCalcResult<Account> {
+        let state = self.lock();
+        let accounts = state
+            .accounts
+            .values()
+            .filter(|account| account.user_id == user.id);
+        // Oldest primary account, or the oldest one when there is no primary.
+        accounts
+            .min_by_key(|account| (!account.is_primary, account.id))
+            .cloned()
+            .ok_or_else(|| MoneyCalcError::NotFound(format!("account of user {}", user.id)))
+    }
+
+    async fn get_accounts_by_user(&self, user: &User) -> MoneyCalcResult<Vec<Account>> {
+        Ok(self
+            .lock()
+            .accounts
+            .values()
+            .filter(|account| account.user_id == user.id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_account_by_id(&self, id: i32) -> MoneyCalcResult<Account> {
+        self.lock().account(id).cloned()
+    }
+
+    async fn get_account_by_name(&self, user: &User, name: &str) -> MoneyCalcResult<Account> {
+        self.lock()
+            .accounts
+            .values()
+            .find(|account| account.user_id == user.id && account.name == name)
+            .cloned()
+            .ok_or_else(|| {
+                MoneyCalcError::NotFound(format!("account {} of user {}", name, user.id))
+            })
+    }
+
+    async fn set_primary_account(&self, account: &Account) -> MoneyCalcResult<()> {
+        let mut state = self.lock();
+        let account = state.account(account.id)?.clone();
+        for other in state.accounts.values_mut() {
+            if other.user_id == account.user_id {
+                other.is_primary = other.id == account.id;
+            }
+        }
+        let after = state.account(account.id)?.clone();
+        self.auditor.record(
+            &mut state,
+            "set_primary_account",
+            "account",
+            account.id,
+            Some(&account),
+            Some(&after),
+        )
+    }
+
+    async fn add_account(&self, add_account_command: &AddAccountCommand) -> MoneyCalcResult<()> {
+        validate_overdraft(
+            &add_account_command.overdraft,
+            add_account_command.initial_balance.currency(),
+        )?;
+        let mut state = self.lock();
+        let is_primary = !state
+            .accounts
+            .values()
+            .any(|account| account.user_id == add_account_command.user_id && account.is_primary);
+        let account = Account {
+            id: next_id(&state.accounts),
+            user_id: add_account_command.user_id,
+            name: add_account_command.account_name.clone(),
+            money: add_account_command.initial_balance,
+            creation_date: chrono::Utc::now().naive_utc().date(),
+            is_primary,
+            overdraft: add_account_command.overdraft,
+        };
+        let account = state.add_account(account, add_account_command.initial_balance)?;
+        self.auditor.record(
+            &mut state,
+            "add_account",
+            "account",
+            account.id,
+            None,
+            Some(&account),
+        )
+    }
+
+    async fn delete_account(&self, account: &Account) -> MoneyCalcResult<()> {
+        let mut state = self.lock();
+        let account = state.account(account.id)?.clone();
+        let referenced = state
+            .transactions
+            .values()
+            .any(|row| row.account_id == account.id)
+            || state
+                .budgets
+                .values()
+                .any(|budget| budget.scope == BudgetScope::Account(account.id))
+            || state
+                .recurring
+                .values()
+                .any(|recurring| recurring.account_id == account.id);
+        if referenced {
+            return Err(still_referenced(format!("account {}", account.id)));
+        }
+        state
+            .snapshots
+            .retain(|(account_id, _), _| *account_id != account.id);
+        state.accounts.remove(&account.id);
+        state.initial_balances.remove(&account.id);
+        let mut accounts: Vec<&mut Account> = state
+            .accounts
+            .values_mut()
+            .filter(|other| other.user_id == account.user_id)
+            .collect();
+        if !accounts.iter().any(|other| other.is_primary)
+            && let Some(oldest) = accounts.first_mut()
+        {
+            oldest.is_primary = true;
+        }
+        self.auditor.record(
+            &mut state,
+            "delete_account",
+            "account",
+            account.id,
+            Some(&account),
+            None,
+        )
+    }
+
+    async fn change_money(&self, account: &Account, payment_count: Money) -> MoneyCalcResult<()> {
+        let mut state = self.lock();
+        let before = state.account(account.id)?.clone();
+        let mut batch = Batch::default();
+        batch.change_money(&state, account.id, payment_count)?;
+        batch.commit(&mut state);
+        let after = state.account(account.id)?.clone();
+        self.auditor.record(
+            &mut state,
+            "change_money",
+            "account",
+            account.id,
+            Some(&before),
+            Some(&after),
+        )
+    }
+
+    async fn set_overdraft_policy(
+        &self,
+        account: &Account,
+        overdraft: OverdraftPolicy,
+    ) -> MoneyCalcResult<()> {
+        let mut state = self.lock();
+        let before = state.account(account.id)?.clone();
+        validate_overdraft(&overdraft, before.money.currency())?;
+        let after = Account {
+            overdraft,
+            ..before.clone()
+        };
+        state.accounts.insert(after.id, after.clone());
+        self.auditor.record(
+            &mut state,
+            "set_overdraft_policy",
+            "account",
+            after.id,
+            Some(&before),
+            Some(&after),
+        )
+    }
+
+    async fn get_accounts(&self) -> MoneyCalcResult<Vec<Account>> {
+        Ok(self.lock().accounts.values().cloned().collect())
+    }
+
+    async fn restore_account(
+        &self,
+        account: &Account,
+        initial_balance: Money,
+    ) -> MoneyCalcResult<Account> {
+        validate_overdraft(&account.overdraft, account.money.currency())?;
+        initial_balance.check_currency(&account.money)?;
+        let mut state = self.lock();
+        let mut account = account.clone();
+        if account.id == 0 {
+            account.id = next_id(&state.accounts);
+        }
+        let restored = state.add_account(account, initial_balance)?;
+        self.auditor.record(
+            &mut state,
+            "restore_account",
+            "account",
+            restored.id,
+            None,
+            Some(&restored),
+        )?;
+        Ok(restored)
+    }
+}
+
+#[async_trait]
+impl TransactionWorker for MemoryProvider {
+    async fn execute_transaction(
+        &self,
+        transaction: &MoneyTransaction,
+    ) -> MoneyCalcResult<MoneyTransaction> {
+        check_executable(transaction)?;
+        let amount = transaction.signed_amount()?;
+        let mut state = self.lock();
+        let mut batch = Batch::default();
+        batch.change_money(&state, transaction.account.id, amount)?;
+        let id = Uuid::new_v4().to_string();
+        batch.insert(&state, &id, transaction)?;
+        batch.commit(&mut state);
+        let stored = state.transaction(&id)?;
+        self.auditor.record(
+            &mut state,
+            "execute_transaction",
+            "transaction",
+            &id,
+            None,
+            Some(&stored),
+        )?;
+        Ok(stored)
+    }
+
+    async fn execute_external_transaction(
+        &self,
+        transaction: &MoneyTransaction,
+        external_id: &str,
+    ) -> MoneyCalcResult<Option<MoneyTransaction>> {
+        check_executable(transaction)?;
+        let amount = transaction.signed_amount()?;
+        let mut state = self.lock();
+        let key = (transaction.account.id, external_id.to_string());
+        if state.imported.contains_key(&key) {
+            return Ok(None);
+        }
+        let mut batch = Batch::default();
+        batch.change_money(&state, transaction.account.id, amount)?;
+        let id = Uuid::new_v4().to_string();
+        batch.insert(&state, &id, transaction)?;
+        batch.commit(&mut state);
+        state.imported.insert(key, id.clone());
+        let stored = state.transaction(&id)?;
+        self.auditor.record(
+            &mut state,
+            "execute_external_transaction",
+            "transaction",
+            &id,
+            None,
+            Some(&stored),
+        )?;
+        Ok(Some(stored))
+    }
+
+    async fn get_imported_transactions(
+        &self,
+        account: &Account,
+    ) -> MoneyCalcResult<Vec<ImportedTransaction>> {
+        Ok(self
+            .lock()
+            .imported
+            .iter()
+            .filter(|((account_id, _), _)| *account_id == account.id)
+            .map(
+                |((account_id, external_id), transaction_id)| ImportedTransaction {
+                    account_id: *account_id,
+                    external_id: external_id.clone(),
+                    transaction_id: transaction_id.clone(),
+                },
+            )
+            .collect())
+    }
+
+    async fn restore_imported_transaction(
+        &self,
+        imported: &ImportedTransaction,
+    ) -> MoneyCalcResult<()> {
+        let mut state = self.lock();
+        let transaction = state.transaction_row(&imported.transaction_id)?;
+        if transaction.account_id != imported.account_id {
+            return Err(MoneyCalcError::Validation(format!(
+                "transaction {} does not belong to account {}",
+                imported.transaction_id, imported.account_id
+            )));
+        }
+        let key = (imported.account_id, imported.external_id.clone());
+        if state.imported.contains_key(&key) {
+            return Err(MoneyCalcError::Conflict(format!(
+                "bank id {} is already imported into account {}",
+                imported.external_id, imported.account_id
+            )));
+        }
+        state.imported.insert(key, imported.transaction_id.clone());
+        self.auditor.record(
+            &mut state,
+            "restore_imported_transaction",
+            "transaction",
+            &imported.transaction_id,
+            None,
+            Some(imported),
+        )
+    }
+
+    async fn transfer(&self, transfer_command: &TransferCommand) -> MoneyCalcResult<()> {
+        if transfer_command.from_account_id == transfer_command.to_account_id {
+            return Err(MoneyCalcError::Validation(
+                "transfer source and target accounts must differ".to_string(),
+            ));
+        }
+        if transfer_command.amount.minor_units() <= 0 {
+            return Err(MoneyCalcError::Validation(
+                "transfer amount must be positive".to_string(),
+            ));
+        }
+
+        let mut state = self.lock();
+        let from_account = state.account(transfer_command.from_account_id)?.clone();
+        let to_account = state.account(transfer_command.to_account_id)?.clone();
+        transfer_command
+            .amount
+            .check_currency(&from_account.money)?;
+        let credit = match &transfer_command.exchange_rate {
+            Some(rate) => {
+                if rate.from != from_account.money.currency()
+                    || rate.to != to_account.money.currency()
+                {
+                    return Err(MoneyCalcError::Validation(format!(
+                        "exchange rate {} does not match accounts currencies",
+                        rate
+                    )));
+                }
+                rate.convert(transfer_command.amount)?
+            }
+            None => {
+                from_account.money.check_currency(&to_account.money)?;
+                transfer_command.amount
+            }
+        };
+        let debit = transfer_command.amount.checked_neg()?;
+
+        let mut batch = Batch::default();
+        batch.change_money(&state, from_account.id, debit)?;
+        batch.change_money(&state, to_account.id, credit)?;
+        let debit_id = Uuid::new_v4().to_string();
+        let credit_id = Uuid::new_v4().to_string();
+        let debit_transaction = MoneyTransaction {
+            id: debit_id.clone(),
+            amount: debit,
+            description: transfer_command.description.clone(),
+            user: state.user(from_account.user_id)?.clone(),
+            payment_type: PaymentType::Transfer,
+            payment_target: to_account.name.clone(),
+            create_date: transfer_command.create_date,
+            linked_transaction_id: Some(credit_id.clone()),
+            correction_of: None,
+            category_id: None,
+            account: from_account.clone(),
+        };
+        let credit_transaction = MoneyTransaction {
+            id: credit_id.clone(),
+            amount: credit,
+            description: transfer_command.description.clone(),
+            user: state.user(to_account.user_id)?.clone(),
+            payment_type: PaymentType::Transfer,
+            payment_target: from_account.name.clone(),
+            create_date: transfer_command.create_date,
+            linked_transaction_id: Some(debit_id.clone()),
+            correction_of: None,
+            category_id: None,
+            account: to_account,
+        };
+        batch.insert(&state, &debit_id, &debit_transaction)?;
+        batch.insert(&state, &credit_id, &credit_transaction)?;
+        batch.commit(&mut state);
+        for id in [debit_id, credit_id] {
+            let stored = state.transaction(&id)?;
+            self.auditor.record(
+                &mut state,
+                "transfer",
+                "transaction",
+                &id,
+                None,
+                Some(&stored),
+            )?;
+        }
+        Ok(())
+    }
+
+    async fn reverse_transaction(
+        &self,
+        reverse_command: &ReverseTransactionCommand,
+    ) -> MoneyCalcResult<MoneyTransaction> {
+        let mut state = self.lock();
+        let original = state.transaction(&reverse_command.transaction_id)?;
+        let mut batch = Batch::default();
+        let reversal = reverse_transaction(
+            &state,
+            &mut batch,
+            &original,
+            &reverse_command.description,
+            reverse_command.create_date,
+        )?;
+        batch.commit(&mut state);
+        let stored = state.transaction(&reversal.id)?;
+        self.auditor.record(
+            &mut state,
+            "reverse_transaction",
+            "transaction",
+            &original.id,
+            Some(&original),
+            Some(&stored),
+        )?;
+        Ok(reversal)
+    }
+
+    async fn amend_transaction(
+        &self,
+        amend_command: &AmendTransactionCommand,
+    ) -> MoneyCalcResult<MoneyTransaction> {
+        if amend_command.amount.is_negative() || amend_command.amount.is_zero() {
+            return Err(MoneyCalcError::Validation(format!(
+                "amended amount {} must be positive",
+                amend_command.amount
+            )));
+        }
+        let mut state = self.lock();
+        let original = state.transaction(&amend_command.transaction_id)?;
+        if let PaymentType::Transfer = original.payment_type {
+            return Err(MoneyCalcError::Validation(
+                "transfers can't be amended".to_string(),
+            ));
+        }
+        let mut batch = Batch::default();
+        reverse_transaction(
+            &state,
+            &mut batch,
+            &original,
+            &original.description,
+            amend_command.create_date,
+        )?;
+        let amended = MoneyTransaction {
+            id: Uuid::new_v4().to_string(),
+            amount: amend_command.amount,
+            description: amend_command.description.clone(),
+            payment_target: amend_command.payment_target.clone(),
+            create_date: amend_command.create_date,
+            linked_transaction_id: None,
+            category_id: amend_command.category_id,
+            correction_of: Some(original.id.clone()),
+            ..original.clone()
+        };
+        batch.change_money(&state, amended.account.id, amended.signed_amount()?)?;
+        batch.insert(&state, &amended.id, &amended)?;
+        batch.commit(&mut state);
+        let stored = state.transaction(&amended.id)?;
+        self.auditor.record(
+            &mut state,
+            "amend_transaction",
+            "transaction",
+            &original.id,
+            Some(&original),
+            Some(&stored),
+        )?;
+        Ok(amended)
+    }
+
+    async fn get_transaction_by_id(&self, id: &str) -> MoneyCalcResult<MoneyTransaction> {
+        self.lock().transaction(id)
+    }
+
+    async fn restore_transaction(
+        &self,
+        transaction: &MoneyTransaction,
+    ) -> MoneyCalcResult<MoneyTransaction> {
+        let mut state = self.lock();
+        let id = if transaction.id.is_empty() {
+            Uuid::new_v4().to_string()
+        } else {
+            transaction.id.clone()
+        };
+        let account = state.account(transaction.account.id)?.clone();
+        transaction.amount.check_currency(&account.money)?;
+        let mut batch = Batch::default();
+        batch.insert(
+            &state,
+            &id,
+            &MoneyTransaction {
+                account,
+                ..transaction.clone()
+            },
+        )?;
+        batch.commit(&mut state);
+        let restored = state.transaction(&id)?;
+        self.auditor.record(
+            &mut state,
+            "restore_transaction",
+            "transaction",
+            &id,
+            None,
+            Some(&restored),
+        )?;
+        Ok(restored)
+    }
+
+    async fn get_transactions(&self, query: &TransactionQuery) -> MoneyCalcResult<TransactionPage> {
+        if query.limit == 0 {
+            return Err(MoneyCalcError::Validation(
+                "transactions query limit must be positive".to_string(),
+            ));
+        }
+        let descending = matches!(
+            query.order,
+            TransactionOrder::DateDesc | TransactionOrder::AmountDesc
+        );
+        let after = match &query.cursor {
+            Some(cursor) => {
+                let invalid = || MoneyCalcError::Validation(format!("invalid cursor: {}", cursor));
+                let (key, id) = cursor.rsplit_once('|').ok_or_else(invalid)?;
+                let key = match query.order {
+                    TransactionOrder::DateAsc | TransactionOrder::DateDesc => {
+                        SortKey::Date(key.to_string())
+                    }
+                    TransactionOrder::AmountAsc | TransactionOrder::AmountDesc => {
+                        SortKey::Amount(key.parse().map_err(|_| invalid())?)
+                    }
+                };
+                Some((key, id.to_string()))
+            }
+            None => None,
+        };
+
+        let state = self.lock();
+        let mut rows: Vec<(SortKey, &TransactionRow)> = state
+            .transactions
+            .values()
+            .filter(|row| matches_query(&state, query, row))
+            .map(|row| (sort_key(query.order, row), row))
+            .filter(|(key, row)| {
+                after.as_ref().is_none_or(|(last_key, last_id)| {
+                    let ordering = (key, &row.id).cmp(&(last_key, last_id));
+                    if descending {
+                        ordering == Ordering::Less
+                    } else {
+                        ordering == Ordering::Greater
+                    }
+                })
+            })
+            .collect();
+        rows.sort_by(|(left_key, left), (right_key, right)| {
+            let ordering = (left_key, &left.id).cmp(&(right_key, &right.id));
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+
+        let mut transactions = rows
+            .iter()
+            .take(query.limit as usize + 1)
+            .map(|(_, row)| state.read(row))
+            .collect::<MoneyCalcResult<Vec<_>>>()?;
+        let mut next_cursor = None;
+        if transactions.len() > query.limit as usize {
+            transactions.truncate(query.limit as usize);
+            next_cursor = transactions
+                .last()
+                .map(|transaction| transaction_cursor(query, transaction));
+        }
+        Ok(TransactionPage {
+            transactions,
+            next_cursor,
+        })
+    }
+}
+
+#[async_trait]
+impl ExchangeRateProvider for MemoryProvider {
+    async fn add_exchange_rate(&self, rate: &ExchangeRate, date: NaiveDate) -> MoneyCalcResult<()> {
+        if rate.from == rate.to {
+            return Err(MoneyCalcError::Validation(format!(
+                "exchange rate {} must be between different currencies",
+                rate
+            )));
+        }
+        let mut state = self.lock();
+        let before = state
+            .exchange_rates
+            .insert((rate.from, rate.to, date), *rate);
+        self.auditor.record(
+            &mut state,
+            "add_exchange_rate",
+            "exchange_rate",
+            format!("{}/{} {}", rate.from, rate.to, date),
+            before.as_ref(),
+            Some(rate),
+        )
+    }
+
+    async fn get_exchange_rate(
+        &self,
+        from: Currency,
+        to: Currency,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<ExchangeRate> {
+        self.lock().exchange_rate(from, to, date)
+    }
+
+    async fn convert_money(
+        &self,
+        money: Money,
+        currency: Currency,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<Money> {
+        let rate = self
+            .get_exchange_rate(money.currency(), currency, date)
+            .await?;
+        Ok(rate.convert(money)?)
+    }
+
+    async fn get_total_balance(
+        &self,
+        user: &User,
+        currency: Currency,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<Money> {
+        let state = self.lock();
+        let mut total = Money::zero(currency);
+        for account in state
+            .accounts
+            .values()
+            .filter(|account| account.user_id == user.id)
+        {
+            let rate = state.exchange_rate(account.money.currency(), currency, date)?;
+            total = total.checked_add(rate.convert(account.money)?)?;
+        }
+        Ok(total)
+    }
+
+    async fn convert_transactions(
+        &self,
+        transactions: &[MoneyTransaction],
+        currency: Currency,
+    ) -> MoneyCalcResult<Vec<MoneyTransaction>> {
+        let state = self.lock();
+        let mut transactions = transactions.to_vec();
+        for transaction in transactions.iter_mut() {
+            let rate = state.exchange_rate(
+                transaction.amount.currency(),
+                currency,
+                transaction.create_date.date(),
+            )?;
+            transaction.amount = rate.convert(transaction.amount)?;
+        }
+        Ok(transactions)
+    }
+}
+
+#[async_trait]
+impl CategoryProvider for MemoryProvider {
+    async fn add_category(&self, add_category_command: &AddCategoryCommand) -> MoneyCalcResult<()> {
+        let mut state = self.lock();
+        let category = state.insert_category(Category {
+            id: 0,
+            user_id: add_category_command.user_id,
+            parent_id: add_category_command.parent_id,
+            name: add_category_command.name.clone(),
+        })?;
+        self.auditor.record(
+            &mut state,
+            "add_category",
+            "category",
+            category.id,
+            None,
+            Some(&category),
+        )
+    }
+
+    async fn get_categories(&self, user: &User) -> MoneyCalcResult<Vec<Category>> {
+        Ok(self
+            .lock()
+            .categories
+            .values()
+            .filter(|category| category.user_id == user.id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_category_by_id(&self, id: i32) -> MoneyCalcResult<Category> {
+        self.lock().category(id).cloned()
+    }
+
+    async fn update_category(&self, category: &Category) -> MoneyCalcResult<()> {
+        let mut state = self.lock();
+        let stored = state.category(category.id)?.clone();
+        let mut parent_id = category.parent_id;
+        while let Some(id) = parent_id {
+            if id == category.id {
+                return Err(MoneyCalcError::Validation(format!(
+                    "category {} can't be moved under itself",
+                    category.id
+                )));
+            }
+            parent_id = state.check_category_owner(id, stored.user_id)?.parent_id;
+        }
+        let updated = Category {
+            parent_id: category.parent_id,
+            name: category.name.clone(),
+            ..stored.clone()
+        };
+        state.check_category_name(&updated)?;
+        state.categories.insert(updated.id, updated.clone());
+        self.auditor.record(
+            &mut state,
+            "update_category",
+            "category",
+            updated.id,
+            Some(&stored),
+            Some(&updated),
+        )
+    }
+
+    async fn delete_category(&self, category: &Category) -> MoneyCalcResult<()> {
+        let mut state = self.lock();
+        let category = state.category(category.id)?.clone();
+        let referenced = state
+            .budgets
+            .values()
+            .any(|budget| budget.scope == BudgetScope::Category(category.id))
+            || state
+                .recurring
+                .values()
+                .any(|recurring| recurring.category_id == Some(category.id));
+        if referenced {
+            return Err(still_referenced(format!("category {}", category.id)));
+        }
+        for child in state.categories.values_mut() {
+            if child.parent_id == Some(category.id) {
+                child.parent_id = category.parent_id;
+            }
+        }
+        for row in state.transactions.values_mut() {
+            if row.category_id == Some(category.id) {
+                row.category_id = None;
+            }
+        }
+        state.categories.remove(&category.id);
+        self.auditor.record(
+            &mut state,
+            "delete_category",
+            "category",
+            category.id,
+            Some(&category),
+            None,
+        )
+    }
+
+    async fn set_transaction_category(
+        &self,
+        transaction_id: &str,
+        category: Option<&Category>,
+    ) -> MoneyCalcResult<()> {
+        let mut state = self.lock();
+        let before = state.transaction(transaction_id)?;
+        let category_id = category.map(|category| category.id);
+        if let Some(category_id) = category_id {
+            state.check_category_owner(category_id, before.account.user_id)?;
+        }
+        if let Some(row) = state.transactions.get_mut(transaction_id) {
+            row.category_id = category_id;
+        }
+        let after = state.transaction(transaction_id)?;
+        self.auditor.record(
+            &mut state,
+            "set_transaction_category",
+            "transaction",
+            transaction_id,
+            Some(&before),
+            Some(&after),
+        )
+    }
+
+    async fn restore_category(&self, category: &Category) -> MoneyCalcResult<Category> {
+        let mut state = self.lock();
+        let restored = state.insert_category(category.clone())?;
+        self.auditor.record(
+            &mut state,
+            "restore_category",
+            "category",
+            restored.id,
+            None,
+            Some(&restored),
+        )?;
+        Ok(restored)
+    }
+}
+
+#[async_trait]
+impl TagProvider for MemoryProvider {
+    async fn add_tag(&self, add_tag_command: &AddTagCommand) -> MoneyCalcResult<()> {
+        let mut state = self.lock();
+        let tag = state.insert_tag(Tag {
+            id: 0,
+            user_id: add_tag_command.user_id,
+            name: add_tag_command.name.clone(),
+        })?;
+        self.auditor
+            .record(&mut state, "add_tag", "tag", tag.id, None, Some(&tag))
+    }
+
+    async fn get_tags(&self, user: &User) -> MoneyCalcResult<Vec<Tag>> {
+        let mut tags: Vec<Tag> = self
+            .lock()
+            .tags
+            .values()
+            .filter(|tag| tag.user_id == user.id)
+            .cloned()
+            .collect();
+        tags.sort_by(|left, right| left.name.cmp(&right.name));
+        Ok(tags)
+    }
+
+    async fn rename_tag(&self, tag: &Tag, name: &str) -> MoneyCalcResult<()> {
+        let mut state = self.lock();
+        let before = state.tag(tag.id)?.clone();
+        let renamed = Tag {
+            name: name.to_string(),
+            ..before.clone()
+        };
+        state.check_tag_name(&renamed)?;
+        state.tags.insert(renamed.id, renamed.clone());
+        self.auditor.record(
+            &mut state,
+            "rename_tag",
+            "tag",
+            renamed.id,
+            Some(&before),
+            Some(&renamed),
+        )
+    }
+
+    async fn delete_tag(&self, tag: &Tag) -> MoneyCalcResult<()> {
+        let mut state = self.lock();
+        let tag = state.tag(tag.id)?.clone();
+        state
+            .transaction_tags
+            .retain(|(_, tag_id)| *tag_id != tag.id);
+        state.tags.remove(&tag.id);
+        self.auditor
+            .record(&mut state, "delete_tag", "tag", tag.id, Some(&tag), None)
+    }
+
+    async fn restore_tag(&self, tag: &Tag) -> MoneyCalcResult<Tag> {
+        let mut state = self.lock();
+        let restored = state.insert_tag(tag.clone())?;
+        self.auditor.record(
+            &mut state,
+            "restore_tag",
+            "tag",
+            restored.id,
+            None,
+            Some(&restored),
+        )?;
+        Ok(restored)
+    }
+
+    async fn set_transaction_tags(
+        &self,
+        transaction_id: &str,
+        tags: &[Tag],
+    ) -> MoneyCalcResult<()> {
+        let mut state = self.lock();
+        let user_id = state.transaction_owner(transaction_id)?;
+        let before = state.transaction_tags(transaction_id)?;
+        for tag in tags {
+            if state.tag(tag.id)?.user_id != user_id {
+                return Err(MoneyCalcError::Validation(format!(
+                    "tag {} belongs to another user",
+                    tag.id
+                )));
+            }
+        }
+        state
+            .transaction_tags
+            .retain(|(id, _)| id != transaction_id);
+        for tag in tags {
+            state
+                .transaction_tags
+                .insert((transaction_id.to_string(), tag.id));
+        }
+        let after = state.transaction_tags(transaction_id)?;
+        self.auditor.record(
+            &mut state,
+            "set_transaction_tags",
+            "transaction",
+            transaction_id,
+            Some(&before),
+            Some(&after),
+        )
+    }
+
+    async fn get_transaction_tags(&self, transaction_id: &str) -> MoneyCalcResult<Vec<Tag>> {
+        let state = self.lock();
+        state.transaction_owner(transaction_id)?;
+        state.transaction_tags(transaction_id)
+    }
+}
+
+#[async_trait]
+impl BudgetProvider for MemoryProvider {
+    async fn add_budget(&self, add_budget_command: &AddBudgetCommand) -> MoneyCalcResult<()> {
+        let mut state = self.lock();
+        state.validate_budget(
+            add_budget_command.user_id,
+            &add_budget_command.scope,
+            &add_budget_command.period,
+            add_budget_command.limit,
+        )?;
+        let budget = Budget {
+            id: next_id(&state.budgets),
+            user_id: add_budget_command.user_id,
+            name: add_budget_command.name.clone(),
+            scope: add_budget_command.scope.clone(),
+            period: add_budget_command.period,
+            limit: add_budget_command.limit,
+        };
+        state.budgets.insert(budget.id, budget.clone());
+        self.auditor.record(
+            &mut state,
+            "add_budget",
+            "budget",
+            budget.id,
+            None,
+            Some(&budget),
+        )
+    }
+
+    async fn get_budgets(&self, user: &User) -> MoneyCalcResult<Vec<Budget>> {
+        Ok(self
+            .lock()
+            .budgets
+            .values()
+            .filter(|budget| budget.user_id == user.id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_budget_by_id(&self, id: i32) -> MoneyCalcResult<Budget> {
+        self.lock().budget(id).cloned()
+    }
+
+    async fn update_budget(&self, budget: &Budget) -> MoneyCalcResult<()> {
+        let mut state = self.lock();
+        let stored = state.budget(budget.id)?.clone();
+        state.validate_budget(stored.user_id, &budget.scope, &budget.period, budget.limit)?;
+        let updated = Budget {
+            user_id: stored.user_id,
+            ..budget.clone()
+        };
+        state.budgets.insert(updated.id, updated.clone());
+        self.auditor.record(
+            &mut state,
+            "update_budget",
+            "budget",
+            updated.id,
+            Some(&stored),
+            Some(&updated),
+        )
+    }
+
+    async fn delete_budget(&self, budget: &Budget) -> MoneyCalcResult<()> {
+        let mut state = self.lock();
+        let budget = state.budget(budget.id)?.clone();
+        state.budgets.remove(&budget.id);
+        self.auditor.record(
+            &mut state,
+            "delete_budget",
+            "budget",
+            budget.id,
+            Some(&budget),
+            None,
+        )
+    }
+
+    async fn get_budget_progress(
+        &self,
+        budget: &Budget,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<BudgetProgress> {
+        let state = self.lock();
+        state.budget_progress(state.budget(budget.id)?, date)
+    }
+
+    async fn get_budgets_progress(
+        &self,
+        user: &User,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<Vec<BudgetProgress>> {
+        let state = self.lock();
+        state
+            .budgets
+            .values()
+            .filter(|budget| budget.user_id == user.id)
+            .map(|budget| state.budget_progress(budget, date))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl RecurringProvider for MemoryProvider {
+    async fn add_recurring(
+        &self,
+        add_recurring_command: &AddRecurringCommand,
+    ) -> MoneyCalcResult<()> {
+        let command = add_recurring_command;
+        let mut state = self.lock();
+        state.validate_recurring(command)?;
+        if !state.users.contains_key(&command.user_id) {
+            return Err(missing_reference(format!("user {}", command.user_id)));
+        }
+        let recurring = RecurringTransaction {
+            id: next_id(&state.recurring),
+            user_id: command.user_id,
+            account_id: command.account_id,
+            amount: command.amount,
+            payment_type: command.payment_type,
+            payment_target: command.payment_target.clone(),
+            description: command.description.clone(),
+            category_id: command.category_id,
+            schedule: command.schedule,
+            start_date: command.start_date,
+            end_date: command.end_date,
+        };
+        state.recurring.insert(recurring.id, recurring.clone());
+        self.auditor.record(
+            &mut state,
+            "add_recurring",
+            "recurring",
+            recurring.id,
+            None,
+            Some(&recurring),
+        )
+    }
+
+    async fn get_recurring(&self, user: &User) -> MoneyCalcResult<Vec<RecurringTransaction>> {
+        Ok(self
+            .lock()
+            .recurring
+            .values()
+            .filter(|recurring| recurring.user_id == user.id)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_recurring_by_id(&self, id: i32) -> MoneyCalcResult<RecurringTransaction> {
+        self.lock().recurring(id).cloned()
+    }
+
+    async fn delete_recurring(&self, recurring: &RecurringTransaction) -> MoneyCalcResult<()> {
+        let mut state = self.lock();
+        let recurring = state.recurring(recurring.id)?.clone();
+        state
+            .occurrences
+            .retain(|(recurring_id, _), _| *recurring_id != recurring.id);
+        state.recurring.remove(&recurring.id);
+        self.auditor.record(
+            &mut state,
+            "delete_recurring",
+            "recurring",
+            recurring.id,
+            Some(&recurring),
+            None,
+        )
+    }
+
+    async fn get_occurrences(
+        &self,
+        recurring: &RecurringTransaction,
+    ) -> MoneyCalcResult<Vec<RecurringOccurrence>> {
+        Ok(self
+            .lock()
+            .occurrences
+            .range((recurring.id, NaiveDate::MIN)..=(recurring.id, NaiveDate::MAX))
+            .map(
+                |((recurring_id, date), transaction_id)| RecurringOccurrence {
+                    recurring_id: *recurring_id,
+                    date: *date,
+                    transaction_id: transaction_id.clone(),
+                },
+            )
+            .collect())
+    }
+
+    async fn generate_due(&self, now: NaiveDateTime) -> MoneyCalcResult<GenerationReport> {
+        let ids: Vec<i32> = self.lock().recurring.keys().copied().collect();
+
+        let mut report = GenerationReport::default();
+        for id in ids {
+            let generated = generate_occurrences(&mut self.lock(), &self.auditor, id, now.date());
+            match generated {
+                Ok(generated) => report.occurrences.extend(generated),
+                Err(error) => report.failures.push(RecurringFailure {
+                    recurring_id: id,
+                    message: error.to_string(),
+                }),
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[async_trait]
+impl ReconciliationProvider for MemoryProvider {
+    async fn reconcile_balances(&self, repair: bool) -> MoneyCalcResult<ReconciliationReport> {
+        let mut state = self.lock();
+        let mut expected = state.initial_balances.clone();
+        for row in state.transactions.values() {
+            if let Some(balance) = expected.get_mut(&row.account_id) {
+                *balance = balance.checked_add(row.ledger_amount()?)?;
+            }
+        }
+
+        let mut discrepancies = vec![];
+        for (account_id, expected) in &expected {
+            let before = state.account(*account_id)?.clone();
+            if before.money == *expected {
+                continue;
+            }
+            discrepancies.push(BalanceDiscrepancy {
+                account_id: *account_id,
+                stored: before.money,
+                expected: *expected,
+                difference: before.money.checked_sub(*expected)?,
+            });
+            if repair {
+                let after = Account {
+                    money: *expected,
+                    ..before.clone()
+                };
+                state.accounts.insert(after.id, after.clone());
+                self.auditor.record(
+                    &mut state,
+                    "reconcile_balances",
+                    "account",
+                    account_id,
+                    Some(&before),
+                    Some(&after),
+                )?;
+            }
+        }
+        Ok(ReconciliationReport {
+            checked_accounts: expected.len(),
+            discrepancies,
+            repaired: repair,
+        })
+    }
+}
+
+#[async_trait]
+impl BalanceHistoryProvider for MemoryProvider {
+    async fn get_balance_at(&self, account: &Account, at: NaiveDateTime) -> MoneyCalcResult<Money> {
+        self.lock().balance_at(account.id, at)
+    }
+
+    async fn get_daily_balances(
+        &self,
+        account: &Account,
+        from_date: NaiveDate,
+        to_date: NaiveDate,
+    ) -> MoneyCalcResult<Vec<DailyBalance>> {
+        if from_date > to_date {
+            return Err(MoneyCalcError::Validation(format!(
+                "balance history from {} is after {}",
+                from_date, to_date
+            )));
+        }
+        let state = self.lock();
+        let opening = match from_date.pred_opt() {
+            Some(previous) => state.balance_at(account.id, end_of_day(previous))?,
+            None => state.balance_at(account.id, NaiveDateTime::MIN)?,
+        };
+        let (from, to) = (from_date.and_time(NaiveTime::MIN), end_of_day(to_date));
+        let mut changes: BTreeMap<NaiveDate, Money> = BTreeMap::new();
+        for row in state.transactions.values() {
+            if row.account_id == account.id && row.create_date >= from && row.create_date <= to {
+                let change = changes
+                    .entry(row.create_date.date())
+                    .or_insert(Money::zero(opening.currency()));
+                *change = change.checked_add(row.ledger_amount()?)?;
+            }
+        }
+
+        let mut balance = opening;
+        let mut history = vec![];
+        for date in from_date.iter_days().take_while(|date| *date <= to_date) {
+            if let Some(change) = changes.get(&date) {
+                balance = balance.checked_add(*change)?;
+            }
+            history.push(DailyBalance { date, balance });
+        }
+        Ok(history)
+    }
+
+    async fn add_balance_snapshot(
+        &self,
+        account: &Account,
+        at: NaiveDateTime,
+    ) -> MoneyCalcResult<BalanceSnapshot> {
+        let mut state = self.lock();
+        let snapshot = BalanceSnapshot {
+            account_id: account.id,
+            date: at,
+            balance: state.balance_at(account.id, at)?,
+        };
+        state.snapshots.insert((account.id, at), snapshot.balance);
+        self.auditor.record(
+            &mut state,
+            "add_balance_snapshot",
+            "balance_snapshot",
+            account.id,
+            None,
+            Some(&snapshot),
+        )?;
+        Ok(snapshot)
+    }
+}
+
+#[async_trait]
+impl AuditProvider for MemoryProvider {
+    async fn get_audit_log(&self, query: &AuditQuery) -> MoneyCalcResult<Vec<AuditEntry>> {
+        if query.limit == 0 {
+            return Err(MoneyCalcError::Validation(
+                "audit query limit must be positive".to_string(),
+            ));
+        }
+        let matches = |filter: &Option<String>, value: &str| {
+            filter.as_deref().is_none_or(|filter| filter == value)
+        };
+        self.lock()
+            .audit
+            .iter()
+            .filter(|row| {
+                matches(&query.actor, &row.actor)
+                    && matches(&query.action, row.action)
+                    && matches(&query.entity, row.entity)
+                    && matches(&query.entity_id, &row.entity_id)
+                    && query.from_date.is_none_or(|date| row.create_date >= date)
+                    && query.to_date.is_none_or(|date| row.create_date < date)
+                    && query.after_id.is_none_or(|id| row.id > id)
+            })
+            .take(query.limit as usize)
+            .map(AuditRow::read)
+            .collect()
+    }
+
+    async fn verify_audit_log(&self) -> MoneyCalcResult<Option<i64>> {
+        let state = self.lock();
+        let mut previous: Option<&str> = None;
+        for row in &state.audit {
+            let Some(hash) = &row.hash else {
+                if previous.is_some() {
+                    return Ok(Some(row.id));
+                }
+                continue;
+            };
+            let create_date = row.create_date.format(AUDIT_DATE_FORMAT).to_string();
+            if *hash != audit_hash(row.hash_fields(previous, &create_date)) {
+                return Ok(Some(row.id));
+            }
+            previous = Some(hash);
+        }
+        Ok(None)
+    }
+}
@@ -1,2 +1,3 @@
+#[cfg(feature = "postgres")]
+pub mod postgresmigrations;
 pub mod sqlitemigrations;
-
@@ -0,0 +1,85 @@
+use deadpool_postgres::Client;
+
+use crate::errors::{MoneyCalcError, MoneyCalcResult};
+
+/// Schema of sqlitemigrations.rs for PostgreSQL.
+/// Money columns are NUMERIC in major units with scale of the currency.
+/// Every step runs once, count of applied steps is kept in SchemaVersion.
+pub(crate) const MIGRATIONS_COLLECTION: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS Users (Id INTEGER GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY, Name TEXT NOT NULL, Number TEXT UNIQUE, CreationDate DATE NOT NULL);",
+    "CREATE INDEX IF NOT EXISTS user_name on Users (Name);",
+    "CREATE TABLE IF NOT EXISTS Accounts (Id INTEGER GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY, Name TEXT NOT NULL, UserId INTEGER REFERENCES Users(Id), MoneyCount NUMERIC NOT NULL DEFAULT 0, CreationDate DATE NOT NULL, Currency TEXT NOT NULL DEFAULT 'RUB', IsPrimary BOOLEAN NOT NULL DEFAULT FALSE, OverdraftLimit NUMERIC, InitialBalance NUMERIC NOT NULL DEFAULT 0);",
+    "CREATE TABLE IF NOT EXISTS ExchangeRates (FromCurrency TEXT NOT NULL, ToCurrency TEXT NOT NULL, RateDate DATE NOT NULL, Rate NUMERIC NOT NULL, PRIMARY KEY (FromCurrency, ToCurrency, RateDate));",
+    "CREATE TABLE IF NOT EXISTS Categories (Id INTEGER GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY, UserId INTEGER NOT NULL REFERENCES Users(Id), ParentId INTEGER REFERENCES Categories(Id), Name TEXT NOT NULL);",
+    "CREATE UNIQUE INDEX IF NOT EXISTS category_name on Categories (UserId, COALESCE(ParentId, 0), Name);",
+    "CREATE TABLE IF NOT EXISTS Transactions (Id TEXT COLLATE \"C\" PRIMARY KEY, Amount NUMERIC NOT NULL, Currency TEXT NOT NULL DEFAULT 'RUB', Description TEXT, UserId INTEGER REFERENCES Users(Id), AccountId INTEGER REFERENCES Accounts(Id), PaymentType INTEGER NOT NULL, CreationDate TIMESTAMP NOT NULL, PaymentTarget TEXT, LinkedTransactionId TEXT, CategoryId INTEGER REFERENCES Categories(Id), CorrectionOf TEXT REFERENCES Transactions(Id));",
+    "CREATE INDEX IF NOT EXISTS transaction_account on Transactions (AccountId, CreationDate);",
+    "CREATE INDEX IF NOT EXISTS transaction_correction on Transactions (CorrectionOf);",
+    "CREATE TABLE IF NOT EXISTS Tags (Id INTEGER GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY, UserId INTEGER NOT NULL REFERENCES Users(Id), Name TEXT NOT NULL, UNIQUE(UserId, Name));",
+    "CREATE TABLE IF NOT EXISTS TransactionTags (TransactionId TEXT NOT NULL REFERENCES Transactions(Id), TagId INTEGER NOT NULL REFERENCES Tags(Id), PRIMARY KEY(TransactionId, TagId));",
+    "CREATE TABLE IF NOT EXISTS Budgets (Id INTEGER GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY, UserId INTEGER NOT NULL REFERENCES Users(Id), Name TEXT NOT NULL, AccountId INTEGER REFERENCES Accounts(Id), PaymentTargetPattern TEXT, CategoryId INTEGER REFERENCES Categories(Id), PeriodKind BIGINT NOT NULL, PeriodStart DATE, PeriodDays BIGINT, LimitAmount NUMERIC NOT NULL, Currency TEXT NOT NULL);",
+    "CREATE TABLE IF NOT EXISTS RecurringTransactions (Id INTEGER GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY, UserId INTEGER NOT NULL REFERENCES Users(Id), AccountId INTEGER NOT NULL REFERENCES Accounts(Id), Amount NUMERIC NOT NULL, Currency TEXT NOT NULL, PaymentType INTEGER NOT NULL, PaymentTarget TEXT NOT NULL, Description TEXT NOT NULL, CategoryId INTEGER REFERENCES Categories(Id), ScheduleKind BIGINT NOT NULL, ScheduleValue BIGINT, StartDate DATE NOT NULL, EndDate DATE);",
+    "CREATE TABLE IF NOT EXISTS RecurringOccurrences (RecurringId INTEGER NOT NULL REFERENCES RecurringTransactions(Id), OccurrenceDate DATE NOT NULL, TransactionId TEXT NOT NULL REFERENCES Transactions(Id), PRIMARY KEY(RecurringId, OccurrenceDate));",
+    "CREATE TABLE IF NOT EXISTS AuditLog (Id BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY, Actor TEXT NOT NULL, Action TEXT NOT NULL, Entity TEXT NOT NULL, EntityId TEXT NOT NULL, Before TEXT, After TEXT, CreationDate TIMESTAMP NOT NULL, Hash TEXT);",
+    "CREATE OR REPLACE FUNCTION audit_append_only() RETURNS trigger LANGUAGE plpgsql AS $$ BEGIN RAISE EXCEPTION 'audit log is append-only'; END; $$;",
+    "CREATE TRIGGER audit_no_update BEFORE UPDATE ON AuditLog FOR EACH ROW EXECUTE FUNCTION audit_append_only();",
+    "CREATE TRIGGER audit_no_delete BEFORE DELETE ON AuditLog FOR EACH ROW EXECUTE FUNCTION audit_append_only();",
+    "CREATE TABLE IF NOT EXISTS BalanceSnapshots (AccountId INTEGER NOT NULL REFERENCES Accounts(Id), SnapshotDate TIMESTAMP NOT NULL, Balance NUMERIC NOT NULL, PRIMARY KEY(AccountId, SnapshotDate));",
+    "CREATE TABLE IF NOT EXISTS ImportedTransactions (AccountId INTEGER NOT NULL REFERENCES Accounts(Id), ExternalId TEXT NOT NULL, TransactionId TEXT NOT NULL REFERENCES Transactions(Id), PRIMARY KEY(AccountId, ExternalId));",
+];
+
+/// Any number shared by processes migrating the same database, they wait for each other.
+const MIGRATION_LOCK: i64 = 0x6d6f6e6579;
+
+/// Apply steps which are not applied yet, all of them in one database transaction.
+/// Schema, when given, must be a plain identifier, it is created first and holds all tables.
+pub async fn to_latest(client: &mut Client, schema: Option<&str>) -> MoneyCalcResult<()> {
+    let transaction = client.transaction().await.map_err(migration_error)?;
+    transaction
+        .execute("Select pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK])
+        .await
+        .map_err(migration_error)?;
+    if let Some(schema) = schema {
+        transaction
+            .batch_execute(&format!("CREATE SCHEMA IF NOT EXISTS {};", schema))
+            .await
+            .map_err(migration_error)?;
+    }
+    transaction
+        .batch_execute("CREATE TABLE IF NOT EXISTS SchemaVersion (Version INTEGER NOT NULL);")
+        .await
+        .map_err(migration_error)?;
+    let version: i32 = match transaction
+        .query_opt("Select Version from SchemaVersion", &[])
+        .await
+        .map_err(migration_error)?
+    {
+        Some(row) => row.try_get(0).map_err(migration_error)?,
+        None => {
+            transaction
+                .execute("Insert into SchemaVersion(Version) Values (0)", &[])
+                .await
+                .map_err(migration_error)?;
+            0
+        }
+    };
+
+    for step in MIGRATIONS_COLLECTION.iter().skip(version as usize) {
+        transaction
+            .batch_execute(step)
+            .await
+            .map_err(migration_error)?;
+    }
+    transaction
+        .execute(
+            "Update SchemaVersion set Version = $1",
+            &[&(MIGRATIONS_COLLECTION.len() as i32)],
+        )
+        .await
+        .map_err(migration_error)?;
+    transaction.commit().await.map_err(migration_error)
+}
+
+fn migration_error(error: tokio_postgres::Error) -> MoneyCalcError {
+    MoneyCalcError::Migration(Box::new(error))
+}
@@ -24,15 +24,87 @@ const MIGRATIONS_COLLECTION: &[M<'_>] = &[
     M::up("Alter table Transactions add column PaymentTarget TEXT;"),
     M::up("Create INDEX IF NOT EXISTS user_name on Users (Name)")
         .down("DROP INDEX user_name on Users (Name)"),
+    M::up("ALTER TABLE Accounts ADD COLUMN Currency TEXT NOT NULL DEFAULT 'RUB';"),
+    M::up(
+        "UPDATE Accounts SET MoneyCount = CAST(ROUND(COALESCE(MoneyCount, 0) * 100) AS INTEGER);",
+    ),
+    M::up("ALTER TABLE Transactions ADD COLUMN Currency TEXT NOT NULL DEFAULT 'RUB';"),
+    M::up("UPDATE Transactions SET Amount = CAST(ROUND(COALESCE(Amount, 0) * 100) AS INTEGER);"),
+    M::up("ALTER TABLE Transactions ADD COLUMN LinkedTransactionId TEXT;"),
+    M::up("ALTER TABLE Accounts ADD COLUMN IsPrimary INTEGER NOT NULL DEFAULT 0;"),
+    M::up(
+        "UPDATE Accounts SET IsPrimary = 1 WHERE Id IN (SELECT MIN(Id) FROM Accounts GROUP BY UserId);",
+    ),
+    // Existing accounts get NULL limit, they keep unlimited overdraft they had before.
+    // New accounts always store limit of their policy.
+    M::up("ALTER TABLE Accounts ADD COLUMN OverdraftLimit INTEGER;"),
+    M::up(
+        "CREATE TABLE IF NOT EXISTS ExchangeRates (FromCurrency TEXT NOT NULL, ToCurrency TEXT NOT NULL, RateDate DATE NOT NULL, Rate INTEGER NOT NULL, PRIMARY KEY (FromCurrency, ToCurrency, RateDate));",
+    ),
+    M::up(
+        "CREATE TABLE IF NOT EXISTS Categories (Id INTEGER PRIMARY KEY, UserId INTEGER NOT NULL, ParentId INTEGER, Name TEXT NOT NULL, FOREIGN KEY(UserId) REFERENCES Users(Id), FOREIGN KEY(ParentId) REFERENCES Categories(Id));",
+    ),
+    M::up(
+        "CREATE UNIQUE INDEX IF NOT EXISTS category_name on Categories (UserId, IFNULL(ParentId, 0), Name);",
+    ),
+    M::up("ALTER TABLE Transactions ADD COLUMN CategoryId INTEGER REFERENCES Categories(Id);"),
+    M::up(
+        "CREATE TABLE IF NOT EXISTS Tags (Id INTEGER PRIMARY KEY, UserId INTEGER NOT NULL, Name TEXT NOT NULL, UNIQUE(UserId, Name), FOREIGN KEY(UserId) REFERENCES Users(Id));",
+    ),
+    M::up(
+        "CREATE TABLE IF NOT EXISTS TransactionTags (TransactionId TEXT NOT NULL, TagId INTEGER NOT NULL, PRIMARY KEY(TransactionId, TagId), FOREIGN KEY(TransactionId) REFERENCES Transactions(Id), FOREIGN KEY(TagId) REFERENCES Tags(Id));",
+    ),
+    M::up(
+        "CREATE TABLE IF NOT EXISTS Budgets (Id INTEGER PRIMARY KEY, UserId INTEGER NOT NULL, Name TEXT NOT NULL, AccountId INTEGER, PaymentTargetPattern TEXT, CategoryId INTEGER, PeriodKind INTEGER NOT NULL, PeriodStart DATE, PeriodDays INTEGER, LimitAmount INTEGER NOT NULL, Currency TEXT NOT NULL, FOREIGN KEY(UserId) REFERENCES Users(Id), FOREIGN KEY(AccountId) REFERENCES Accounts(Id), FOREIGN KEY(CategoryId) REFERENCES Categories(Id));",
+    ),
+    M::up(
+        "CREATE TABLE IF NOT EXISTS RecurringTransactions (Id INTEGER PRIMARY KEY, UserId INTEGER NOT NULL, AccountId INTEGER NOT NULL, Amount INTEGER NOT NULL, Currency TEXT NOT NULL, PaymentType INTEGER NOT NULL, PaymentTarget TEXT NOT NULL, Description TEXT NOT NULL, CategoryId INTEGER, ScheduleKind INTEGER NOT NULL, ScheduleValue INTEGER, StartDate DATE NOT NULL, EndDate DATE, FOREIGN KEY(UserId) REFERENCES Users(Id), FOREIGN KEY(AccountId) REFERENCES Accounts(Id), FOREIGN KEY(CategoryId) REFERENCES Categories(Id));",
+    ),
+    M::up(
+        "CREATE TABLE IF NOT EXISTS RecurringOccurrences (RecurringId INTEGER NOT NULL, OccurrenceDate DATE NOT NULL, TransactionId TEXT NOT NULL, PRIMARY KEY(RecurringId, OccurrenceDate), FOREIGN KEY(RecurringId) REFERENCES RecurringTransactions(Id), FOREIGN KEY(TransactionId) REFERENCES Transactions(Id));",
+    ),
+    M::up("ALTER TABLE Transactions ADD COLUMN CorrectionOf TEXT REFERENCES Transactions(Id);"),
+    M::up("CREATE INDEX IF NOT EXISTS transaction_correction on Transactions (CorrectionOf);"),
+    M::up(
+        "CREATE TABLE IF NOT EXISTS AuditLog (Id INTEGER PRIMARY KEY AUTOINCREMENT, Actor TEXT NOT NULL, Action TEXT NOT NULL, Entity TEXT NOT NULL, EntityId TEXT NOT NULL, Before TEXT, After TEXT, CreationDate TEXT NOT NULL, Hash TEXT);",
+    ),
+    M::up(
+        "CREATE TRIGGER IF NOT EXISTS audit_no_update BEFORE UPDATE ON AuditLog BEGIN SELECT RAISE(ABORT, 'audit log is append-only'); END;",
+    ),
+    M::up(
+        "CREATE TRIGGER IF NOT EXISTS audit_no_delete BEFORE DELETE ON AuditLog BEGIN SELECT RAISE(ABORT, 'audit log is append-only'); END;",
+    ),
+    M::up("ALTER TABLE Accounts ADD COLUMN InitialBalance INTEGER NOT NULL DEFAULT 0;"),
+    M::up(
+        "UPDATE Accounts SET InitialBalance = MoneyCount - IFNULL((SELECT SUM(CASE t.PaymentType WHEN 2 THEN -t.Amount WHEN 0 THEN 0 ELSE t.Amount END) FROM Transactions t WHERE t.AccountId = Accounts.Id), 0);",
+    ),
+    M::up(
+        "CREATE TABLE IF NOT EXISTS BalanceSnapshots (AccountId INTEGER NOT NULL, SnapshotDate TEXT NOT NULL, Balance INTEGER NOT NULL, PRIMARY KEY(AccountId, SnapshotDate), FOREIGN KEY(AccountId) REFERENCES Accounts(Id));",
+    ),
+    M::up(
+        "CREATE TABLE IF NOT EXISTS ImportedTransactions (AccountId INTEGER NOT NULL, ExternalId TEXT NOT NULL, TransactionId TEXT NOT NULL, PRIMARY KEY(AccountId, ExternalId), FOREIGN KEY(AccountId) REFERENCES Accounts(Id), FOREIGN KEY(TransactionId) REFERENCES Transactions(Id));",
+    ),
+    // Transactions were inserted by position before, payment target landed in CreationDate
+    // and date in PaymentTarget. Rows whose CreationDate is not a timestamp get them swapped back.
+    M::up(
+        "UPDATE Transactions SET CreationDate = PaymentTarget, PaymentTarget = CreationDate WHERE NOT (IFNULL(CreationDate, '') GLOB '[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9]*' AND datetime(CreationDate) IS NOT NULL) AND PaymentTarget GLOB '[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9]*' AND datetime(PaymentTarget) IS NOT NULL;",
+    ),
 ];
 
 pub const MIGRATIONS: Migrations = Migrations::from_slice(MIGRATIONS_COLLECTION);
 
 #[cfg(test)]
 mod tests {
-    use crate::providers::bases::migrations::sqlitemigrations::MIGRATIONS;
+    use chrono::NaiveDateTime;
+    use rusqlite::{Connection, params};
+    use rusqlite_migration::Migrations;
+
+    use crate::providers::bases::migrations::sqlitemigrations::{
+        MIGRATIONS, MIGRATIONS_COLLECTION,
+    };
 
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     pub fn migrations_validate_test() {
         let validation = MIGRATIONS.validate();
         match validation {
@@ -43,4 +115,107 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    pub fn migrations_money_to_minor_units_test() {
+        let mut connection = Connection::open_in_memory().unwrap();
+        Migrations::from_slice(&MIGRATIONS_COLLECTION[..12])
+            .to_latest(&mut connection)
+            .unwrap();
+        connection
+            .execute(
+                "Insert into Accounts(Name, UserId, MoneyCount, CreationDate) Values ('old', NULL, 16777217.01, '2024-01-01')",
+                [],
+            )
+            .unwrap();
+        connection
+            .execute(
+                "INSERT INTO Transactions(Id, Amount, PaymentType) Values ('old', 0.1, 1)",
+                [],
+            )
+            .unwrap();
+
+        MIGRATIONS.to_latest(&mut connection).unwrap();
+
+        let (money, currency): (i64, String) = connection
+            .query_one("Select MoneyCount, Currency from Accounts", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(money, 1_677_721_701);
+        assert_eq!(currency, "RUB");
+        let amount: i64 = connection
+            .query_one("Select Amount from Transactions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(amount, 10);
+    }
+
+    #[test]
+    pub fn migrations_overdraft_of_existing_accounts_test() {
+        let mut connection = Connection::open_in_memory().unwrap();
+        Migrations::from_slice(&MIGRATIONS_COLLECTION[..19])
+            .to_latest(&mut connection)
+            .unwrap();
+        connection
+            .execute(
+                "Insert into Accounts(Name, UserId, MoneyCount, CreationDate) Values ('old', NULL, -500, '2024-01-01')",
+                [],
+            )
+            .unwrap();
+
+        MIGRATIONS.to_latest(&mut connection).unwrap();
+
+        let limit: Option<i64> = connection
+            .query_one("Select OverdraftLimit from Accounts", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(limit, None);
+    }
+
+    #[test]
+    pub fn migrations_swapped_payment_target_test() {
+        let mut connection = Connection::open_in_memory().unwrap();
+        Migrations::from_slice(&MIGRATIONS_COLLECTION[..12])
+            .to_latest(&mut connection)
+            .unwrap();
+        connection
+            .execute(
+                "Insert into Users(Id, Name, Number, CreationDate) Values (1, 'old', '1', '2024-01-01')",
+                [],
+            )
+            .unwrap();
+        connection
+            .execute(
+                "Insert into Accounts(Id, Name, UserId, MoneyCount, CreationDate) Values (1, 'old', 1, 100, '2024-01-01')",
+                [],
+            )
+            .unwrap();
+        let date = NaiveDateTime::parse_from_str("2024-02-03 10:20:30.5", "%F %T%.f").unwrap();
+        connection
+            .execute(
+                "INSERT INTO Transactions VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params!["old", 10.5, "Lunch", 1, 1, 2, "Shop", date],
+            )
+            .unwrap();
+        connection
+            .execute(
+                "INSERT INTO Transactions(Id, Amount, Description, UserId, AccountId, PaymentType, CreationDate, PaymentTarget) VALUES ('new', 1, 'Bus', 1, 1, 2, ?1, 'Transport')",
+                params![date],
+            )
+            .unwrap();
+
+        MIGRATIONS.to_latest(&mut connection).unwrap();
+
+        let mut statement = connection
+            .prepare("Select CreationDate, PaymentTarget from Transactions order by Id desc")
+            .unwrap();
+        let rows: Vec<(NaiveDateTime, String)> = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            rows,
+            [(date, "Shop".to_string()), (date, "Transport".to_string())]
+        );
+    }
 }
@@ -1,2 +1,156 @@
-pub mod migrations;
+#[cfg(feature = "memory")]
+pub mod memory;
+pub(crate) mod migrations;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "postgres")]
+pub(crate) mod postgrespool;
+#[cfg(feature = "postgres")]
+pub(crate) mod postgresrows;
 pub mod sqlite;
+pub(crate) mod sqlitepool;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    commands::transactions::transactionquery::{TransactionOrder, TransactionQuery},
+    errors::{MoneyCalcError, MoneyCalcResult},
+    models::{
+        account::OverdraftPolicy,
+        budget::{BudgetPeriod, BudgetScope},
+        money::{Currency, Money},
+        moneytransaction::{MoneyTransaction, PaymentType},
+        recurring::Schedule,
+    },
+};
+
+pub(crate) fn validate_overdraft(
+    policy: &OverdraftPolicy,
+    currency: Currency,
+) -> MoneyCalcResult<()> {
+    if let OverdraftPolicy::Limit(limit) = policy {
+        limit.check_currency(&Money::zero(currency))?;
+        if limit.is_negative() {
+            return Err(MoneyCalcError::Validation(format!(
+                "overdraft limit {} must not be negative",
+                limit
+            )));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn transaction_cursor(
+    query: &TransactionQuery,
+    transaction: &MoneyTransaction,
+) -> String {
+    match query.order {
+        TransactionOrder::DateAsc | TransactionOrder::DateDesc => format!(
+            "{}|{}",
+            transaction.create_date.format("%F %T%.f"),
+            transaction.id
+        ),
+        TransactionOrder::AmountAsc | TransactionOrder::AmountDesc => format!(
+            "{}|{}",
+            transaction.amount.minor_units().unsigned_abs(),
+            transaction.id
+        ),
+    }
+}
+
+/// Transfers and reversals have their own operations and can't be executed directly.
+/// Executed amount must be positive, payment type gives its direction.
+pub(crate) fn check_executable(transaction: &MoneyTransaction) -> MoneyCalcResult<()> {
+    match transaction.payment_type {
+        PaymentType::Transfer => Err(MoneyCalcError::Validation(
+            "transfers must be executed by transfer".to_string(),
+        )),
+        PaymentType::Reversal => Err(MoneyCalcError::Validation(
+            "reversals must be executed by reverse_transaction".to_string(),
+        )),
+        _ if transaction.amount.is_negative() || transaction.amount.is_zero() => {
+            Err(MoneyCalcError::Validation(format!(
+                "transaction amount {} must be positive",
+                transaction.amount
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Condition matching category given by parameter and all its subcategories.
+pub(crate) fn in_subcategories(column: &str, parameter: &str) -> String {
+    format!(
+        "{} in (with recursive Subcategories(Id) as (Select {} union all Select c.Id from Categories c join Subcategories s on c.ParentId = s.Id) Select Id from Subcategories)",
+        column, parameter
+    )
+}
+
+/// Escape text for like expression with escape '\\'.
+pub(crate) fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Actor of changes made by provider without with_actor.
+pub const DEFAULT_ACTOR: &str = "system";
+
+/// CreationDate of audit record is hashed as text in this format.
+pub(crate) const AUDIT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+/// Hex sha256 of record fields, every field is prefixed by its length so they can't shift.
+pub(crate) fn audit_hash(fields: [Option<&str>; 8]) -> String {
+    let mut hasher = Sha256::new();
+    for field in fields {
+        match field {
+            Some(field) => hasher.update(format!("{}:{}", field.len(), field)),
+            None => hasher.update("-"),
+        }
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Signed amount of transaction row `t` as it changed account balance.
+pub(crate) const LEDGER_AMOUNT: &str =
+    "CASE t.PaymentType WHEN 2 THEN -t.Amount WHEN 0 THEN 0 ELSE t.Amount END";
+
+/// Last moment of day, transactions of the whole day are at or before it.
+pub(crate) fn end_of_day(date: NaiveDate) -> NaiveDateTime {
+    date.and_time(NaiveTime::from_hms_nano_opt(23, 59, 59, 999_999_999).unwrap())
+}
+
+/// Scope and period columns of budget:
+/// AccountId, PaymentTargetPattern, CategoryId, PeriodKind, PeriodStart, PeriodDays.
+pub(crate) type BudgetColumns = (
+    Option<i32>,
+    Option<String>,
+    Option<i32>,
+    i64,
+    Option<NaiveDate>,
+    Option<u32>,
+);
+
+pub(crate) fn budget_to_sql(scope: &BudgetScope, period: &BudgetPeriod) -> BudgetColumns {
+    let (account_id, pattern, category_id) = match scope {
+        BudgetScope::Account(account_id) => (Some(*account_id), None, None),
+        BudgetScope::PaymentTarget(pattern) => (None, Some(pattern.clone()), None),
+        BudgetScope::Category(category_id) => (None, None, Some(*category_id)),
+    };
+    let (kind, start, days) = match period {
+        BudgetPeriod::Monthly => (1, None, None),
+        BudgetPeriod::Weekly => (2, None, None),
+        BudgetPeriod::Custom { start, days } => (3, Some(*start), Some(*days)),
+    };
+    (account_id, pattern, category_id, kind, start, days)
+}
+
+/// ScheduleKind and ScheduleValue columns of recurring transaction.
+pub(crate) fn schedule_to_sql(schedule: &Schedule) -> (i64, Option<u32>) {
+    match schedule {
+        Schedule::MonthlyOnDay(day) => (1, Some(*day)),
+        Schedule::EveryWeeks(weeks) => (2, Some(*weeks)),
+        Schedule::LastBusinessDay => (3, None),
+    }
+}
@@ -0,0 +1,2975 @@
+use std::sync::Arc;
+
+use crate::{
+    commands::{
+        accounts::addaccountcommand::AddAccountCommand,
+        audit::auditquery::AuditQuery,
+        budgets::addbudgetcommand::AddBudgetCommand,
+        categories::addcategorycommand::AddCategoryCommand,
+        recurring::addrecurringcommand::AddRecurringCommand,
+        tags::addtagcommand::AddTagCommand,
+        transactions::{
+            amendtransactioncommand::AmendTransactionCommand,
+            reversetransactioncommand::ReverseTransactionCommand,
+            transactionquery::TransactionQuery, transfercommand::TransferCommand,
+        },
+        users::addusercommand::AddUserCommand,
+    },
+    config::PostgresConfiguration,
+    errors::{MoneyCalcError, MoneyCalcResult},
+    models::{
+        account::{Account, OverdraftPolicy},
+        auditentry::AuditEntry,
+        balance::{BalanceSnapshot, DailyBalance},
+        budget::{Budget, BudgetPeriod, BudgetProgress, BudgetScope},
+        category::Category,
+        exchangerate::{ExchangeRate, RATE_SCALE},
+        importedtransaction::ImportedTransaction,
+        money::{Currency, Money},
+        moneytransaction::{MoneyTransaction, PaymentType},
+        reconciliation::{BalanceDiscrepancy, ReconciliationReport},
+        recurring::{
+            GenerationReport, RecurringFailure, RecurringOccurrence, RecurringTransaction,
+        },
+        tag::Tag,
+        transactionpage::TransactionPage,
+        user::User,
+    },
+    providers::{
+        AccountProvider, AuditProvider, BalanceHistoryProvider, BudgetProvider, CategoryProvider,
+        ExchangeRateProvider, ReconciliationProvider, RecurringProvider, TagProvider,
+        TransactionWorker, UserProvider,
+        bases::{
+            AUDIT_DATE_FORMAT, DEFAULT_ACTOR, LEDGER_AMOUNT, audit_hash, budget_to_sql,
+            check_executable, end_of_day, escape_like, in_subcategories,
+            postgrespool::ConnectionPool,
+            postgresrows::{
+                ACCOUNT_COLUMNS, AUDIT_COLUMNS, BUDGET_COLUMNS, CATEGORY_COLUMNS,
+                RECURRING_COLUMNS, SqlParam, TAG_COLUMNS, TRANSACTION_SELECT, USER_COLUMNS,
+                account_from_row, audit_entry_from_row, budget_from_row, build_transaction_query,
+                category_from_row, currency_from_row, from_decimal, money_from_row,
+                overdraft_from_sql, overdraft_to_sql, param_refs, payment_type_to_sql,
+                rate_from_sql, recurring_from_row, tag_from_row, to_decimal, transaction_from_row,
+                user_from_row,
+            },
+            schedule_to_sql, transaction_cursor, validate_overdraft,
+        },
+    },
+};
+use async_trait::async_trait;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, SubsecRound};
+use deadpool_postgres::{Client, GenericClient};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use tokio_postgres::error::SqlState;
+use uuid::Uuid;
+
+impl From<tokio_postgres::Error> for MoneyCalcError {
+    fn from(value: tokio_postgres::Error) -> Self {
+        let Some(error) = value.as_db_error() else {
+            return MoneyCalcError::Storage(Box::new(value));
+        };
+        let message = error.message().to_string();
+        let code = error.code();
+        if *code == SqlState::UNIQUE_VIOLATION {
+            MoneyCalcError::Conflict(message)
+        } else if [
+            SqlState::NOT_NULL_VIOLATION,
+            SqlState::CHECK_VIOLATION,
+            SqlState::FOREIGN_KEY_VIOLATION,
+        ]
+        .contains(code)
+        {
+            MoneyCalcError::Validation(message)
+        } else if [
+            SqlState::T_R_SERIALIZATION_FAILURE,
+            SqlState::T_R_DEADLOCK_DETECTED,
+            SqlState::LOCK_NOT_AVAILABLE,
+        ]
+        .contains(code)
+        {
+            MoneyCalcError::Busy(message)
+        } else {
+            MoneyCalcError::Storage(Box::new(value))
+        }
+    }
+}
+
+/// Map foreign key failure of delete to Conflict, the record is still referenced by others.
+fn still_referenced(
+    what: impl Into<String>,
+) -> impl FnOnce(tokio_postgres::Error) -> MoneyCalcError {
+    move |error| {
+        if error.code() == Some(&SqlState::FOREIGN_KEY_VIOLATION) {
+            MoneyCalcError::Conflict(format!("{} is still referenced", what.into()))
+        } else {
+            error.into()
+        }
+    }
+}
+
+/// PostgreSQL storage.
+/// Connections are opened on demand up to pool size, without TLS.
+/// Migrations are applied when the first connection is taken,
+/// so provider is configured without runtime and connection errors come from the first call.
+/// Clones share connection pool.
+#[derive(Clone)]
+pub struct PostgresProvider {
+    pool: ConnectionPool,
+    config: PostgresConfiguration,
+    auditor: Auditor,
+}
+
+impl std::fmt::Debug for PostgresProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PostgresProvider")
+            .field("config", &self.config)
+            .field("auditor", &self.auditor)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PostgresProvider {
+    pub fn new(config: &PostgresConfiguration, apply_migrations: bool) -> MoneyCalcResult<Self> {
+        Ok(Self {
+            pool: ConnectionPool::open(config, apply_migrations)?,
+            config: config.clone(),
+            auditor: Auditor {
+                actor: Arc::from(DEFAULT_ACTOR),
+                hash_chain: config.audit_hash_chain,
+            },
+        })
+    }
+
+    pub fn config(&self) -> &PostgresConfiguration {
+        &self.config
+    }
+
+    /// Clone of provider which logs changes in audit log as made by actor.
+    pub fn with_actor(&self, actor: &str) -> Self {
+        let mut provider = self.clone();
+        provider.auditor.actor = Arc::from(actor);
+        provider
+    }
+
+    /// Take connection from pool, migrations are applied on the first one.
+    async fn client(&self) -> MoneyCalcResult<Client> {
+        self.pool.get().await
+    }
+}
+
+/// Any number, taken by writers of hash chain so they append one by one.
+const AUDIT_LOCK: i64 = 0x61756469;
+
+/// Writes audit records in the database transaction of the change.
+#[derive(Clone, Debug)]
+struct Auditor {
+    actor: Arc<str>,
+    hash_chain: bool,
+}
+
+impl Auditor {
+    async fn record<T: Serialize + Sync>(
+        &self,
+        client: &impl GenericClient,
+        action: &str,
+        entity: &str,
+        entity_id: impl ToString,
+        before: Option<&T>,
+        after: Option<&T>,
+    ) -> MoneyCalcResult<()> {
+        let entity_id = entity_id.to_string();
+        let before = before.map(serde_json::to_string).transpose()?;
+        let after = after.map(serde_json::to_string).transpose()?;
+        // Timestamp keeps microseconds, record is hashed as it is read back.
+        let create_date = chrono::Utc::now().naive_utc().trunc_subsecs(6);
+        let hash = if self.hash_chain {
+            client
+                .execute("Select pg_advisory_xact_lock($1)", &[&AUDIT_LOCK])
+                .await?;
+            let previous: Option<String> = client
+                .query_opt(
+                    "Select Hash from AuditLog where Hash is not null order by Id desc limit 1",
+                    &[],
+                )
+                .await?
+                .map(|row| row.try_get(0))
+                .transpose()?;
+            Some(audit_hash([
+                previous.as_deref(),
+                Some(&self.actor),
+                Some(action),
+                Some(entity),
+                Some(&entity_id),
+                before.as_deref(),
+                after.as_deref(),
+                Some(&create_date.format(AUDIT_DATE_FORMAT).to_string()),
+            ]))
+        } else {
+            None
+        };
+        client
+            .execute(
+                "Insert into AuditLog(Actor, Action, Entity, EntityId, Before, After, CreationDate, Hash) Values ($1, $2, $3, $4, $5, $6, $7, $8)",
+                &[
+                    &self.actor.as_ref(),
+                    &action,
+                    &entity,
+                    &entity_id,
+                    &before,
+                    &after,
+                    &create_date,
+                    &hash,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// Add signed amount to account balance.
+/// Account row stays locked till the end of database transaction,
+/// so concurrent debits are checked against the balance one after another.
+/// Debit fails if resulting balance is not allowed by overdraft policy of account.
+async fn apply_money_change(
+    client: &impl GenericClient,
+    account_id: i32,
+    amount: Money,
+) -> MoneyCalcResult<()> {
+    let row = client
+        .query_opt(
+            "Select MoneyCount, Currency, OverdraftLimit from Accounts where Id = $1 for update",
+            &[&account_id],
+        )
+        .await?
+        .ok_or_else(|| MoneyCalcError::NotFound(format!("account {}", account_id)))?;
+    let currency = currency_from_row(&row, 1)?;
+    let balance = from_decimal(row.try_get(0)?, currency)?;
+    let overdraft = overdraft_from_sql(row.try_get(2)?, currency)?;
+    let new_balance = balance.checked_add(amount)?;
+    if amount.is_negative() && !overdraft.allows(new_balance)? {
+        return Err(MoneyCalcError::InsufficientFunds(format!(
+            "account {} has {}, debit of {} is not allowed",
+            account_id,
+            balance,
+            amount.checked_abs()?
+        )));
+    }
+
+    client
+        .execute(
+            "Update Accounts set MoneyCount = MoneyCount + $2 where Id = $1",
+            &[&account_id, &to_decimal(amount)],
+        )
+        .await?;
+    Ok(())
+}
+
+async fn insert_transaction(
+    client: &impl GenericClient,
+    id: &str,
+    transaction: &MoneyTransaction,
+) -> MoneyCalcResult<()> {
+    if let Some(category_id) = transaction.category_id {
+        check_category_owner(client, category_id, transaction.account.user_id).await?;
+    }
+    client
+        .execute(
+            "INSERT INTO Transactions(Id, Amount, Description, UserId, AccountId, PaymentType, CreationDate, PaymentTarget, Currency, LinkedTransactionId, CategoryId, CorrectionOf) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
+            &[
+                &id,
+                &to_decimal(transaction.amount),
+                &transaction.description,
+                &transaction.account.user_id,
+                &transaction.account.id,
+                &payment_type_to_sql(transaction.payment_type),
+                &transaction.create_date,
+                &transaction.payment_target,
+                &transaction.amount.currency().code(),
+                &transaction.linked_transaction_id,
+                &transaction.category_id,
+                &transaction.correction_of,
+            ],
+        )
+        .await?;
+    client
+        .execute(
+            "Delete from BalanceSnapshots where AccountId = $1 and SnapshotDate >= $2",
+            &[&transaction.account.id, &transaction.create_date],
+        )
+        .await?;
+    Ok(())
+}
+
+/// Balance of account after all transactions dated at or before moment.
+/// Starts from latest snapshot before moment, or from initial balance without one.
+async fn balance_at(
+    client: &impl GenericClient,
+    account_id: i32,
+    at: NaiveDateTime,
+) -> MoneyCalcResult<Money> {
+    let row = client
+        .query_opt(
+            "Select InitialBalance, Currency from Accounts where Id = $1",
+            &[&account_id],
+        )
+        .await?
+        .ok_or_else(|| MoneyCalcError::NotFound(format!("account {}", account_id)))?;
+    let currency = currency_from_row(&row, 1)?;
+    let initial = from_decimal(row.try_get(0)?, currency)?;
+    let snapshot = client
+        .query_opt(
+            "Select SnapshotDate, Balance from BalanceSnapshots where AccountId = $1 and SnapshotDate <= $2 order by SnapshotDate desc limit 1",
+            &[&account_id, &at],
+        )
+        .await?;
+    let (since, start) = match snapshot {
+        Some(row) => (
+            Some(row.try_get::<_, NaiveDateTime>(0)?),
+            from_decimal(row.try_get(1)?, currency)?,
+        ),
+        None => (None, initial),
+    };
+    let change: Decimal = client
+        .query_one(
+            &format!(
+                "Select coalesce(sum({LEDGER_AMOUNT}), 0) from Transactions t where t.AccountId = $1 and t.CreationDate <= $2 and ($3::timestamp is null or t.CreationDate > $3)"
+            ),
+            &[&account_id, &at, &since],
+        )
+        .await?
+        .try_get(0)?;
+    Ok(start.checked_add(from_decimal(change, currency)?)?)
+}
+
+async fn get_transaction(
+    client: &impl GenericClient,
+    id: &str,
+) -> MoneyCalcResult<MoneyTransaction> {
+    let row = client
+        .query_opt(&format!("{} where t.Id = $1", TRANSACTION_SELECT), &[&id])
+        .await?
+        .ok_or_else(|| MoneyCalcError::NotFound(format!("transaction {}", id)))?;
+    transaction_from_row(&row)
+}
+
+/// Write Reversal entries for transaction and its transfer pair, returns reversal of transaction.
+/// Reversals of transfer legs are linked to each other like the legs.
+async fn reverse_transaction(
+    client: &impl GenericClient,
+    original: &MoneyTransaction,
+    description: &str,
+    create_date: NaiveDateTime,
+) -> MoneyCalcResult<MoneyTransaction> {
+    if let PaymentType::Reversal = original.payment_type {
+        return Err(MoneyCalcError::Validation(format!(
+            "transaction {} is a reversal",
+            original.id
+        )));
+    }
+    let reversed: bool = client
+        .query_one(
+            "Select exists(Select 1 from Transactions where CorrectionOf = $1 and PaymentType = $2)",
+            &[&original.id, &payment_type_to_sql(PaymentType::Reversal)],
+        )
+        .await?
+        .try_get(0)?;
+    if reversed {
+        return Err(MoneyCalcError::Conflict(format!(
+            "transaction {} is already reversed",
+            original.id
+        )));
+    }
+
+    let mut legs = vec![original.clone()];
+    if let (PaymentType::Transfer, Some(linked_id)) =
+        (original.payment_type, &original.linked_transaction_id)
+    {
+        legs.push(get_transaction(client, linked_id).await?);
+    }
+    let ids: Vec<String> = legs.iter().map(|_| Uuid::new_v4().to_string()).collect();
+    let mut reversals = vec![];
+    for (index, leg) in legs.iter().enumerate() {
+        let reversal = MoneyTransaction {
+            id: ids[index].clone(),
+            amount: leg.signed_amount()?.checked_neg()?,
+            description: description.to_string(),
+            user: leg.user.clone(),
+            account: leg.account.clone(),
+            payment_type: PaymentType::Reversal,
+            payment_target: leg.payment_target.clone(),
+            create_date,
+            linked_transaction_id: ids.get(1 - index).cloned(),
+            category_id: leg.category_id,
+            correction_of: Some(leg.id.clone()),
+        };
+        apply_money_change(client, leg.account.id, reversal.amount).await?;
+        insert_transaction(client, &reversal.id, &reversal).await?;
+        reversals.push(reversal);
+    }
+    Ok(reversals.swap_remove(0))
+}
+
+/// Move identity of table past explicitly inserted ids.
+async fn sync_identity(client: &impl GenericClient, table: &str) -> MoneyCalcResult<()> {
+    client
+        .execute(
+            &format!(
+                "Select setval(pg_get_serial_sequence('{table}', 'id'), (Select max(Id) from {table}))"
+            ),
+            &[],
+        )
+        .await?;
+    Ok(())
+}
+
+async fn get_category_by_id(client: &impl GenericClient, id: i32) -> MoneyCalcResult<Category> {
+    let row = client
+        .query_opt(
+            &format!("Select {} from Categories where Id = $1", CATEGORY_COLUMNS),
+            &[&id],
+        )
+        .await?
+        .ok_or_else(|| MoneyCalcError::NotFound(format!("category {}", id)))?;
+    category_from_row(&row)
+}
+
+/// Categories and tags can only be used by their owner.
+async fn check_category_owner(
+    client: &impl GenericClient,
+    category_id: i32,
+    user_id: i32,
+) -> MoneyCalcResult<Category> {
+    let category = get_category_by_id(client, category_id).await?;
+    if category.user_id != user_id {
+        return Err(MoneyCalcError::Validation(format!(
+            "category {} belongs to another user",
+            category_id
+        )));
+    }
+    Ok(category)
+}
+
+async fn get_transaction_owner(
+    client: &impl GenericClient,
+    transaction_id: &str,
+) -> MoneyCalcResult<i32> {
+    let row = client
+        .query_opt(
+            "Select a.UserId from Transactions t join Accounts a on a.Id = t.AccountId where t.Id = $1",
+            &[&transaction_id],
+        )
+        .await?
+        .ok_or_else(|| MoneyCalcError::NotFound(format!("transaction {}", transaction_id)))?;
+    Ok(row.try_get(0)?)
+}
+
+async fn get_transaction_tags(
+    client: &impl GenericClient,
+    transaction_id: &str,
+) -> MoneyCalcResult<Vec<Tag>> {
+    client
+        .query(
+            "Select t.Id, t.UserId, t.Name from Tags t join TransactionTags tt on tt.TagId = t.Id where tt.TransactionId = $1 order by t.Name",
+            &[&transaction_id],
+        )
+        .await?
+        .iter()
+        .map(tag_from_row)
+        .collect()
+}
+
+async fn get_tag_by_id(client: &impl GenericClient, id: i32) -> MoneyCalcResult<Tag> {
+    let row = client
+        .query_opt(
+            &format!("Select {} from Tags where Id = $1", TAG_COLUMNS),
+            &[&id],
+        )
+        .await?
+        .ok_or_else(|| MoneyCalcError::NotFound(format!("tag {}", id)))?;
+    tag_from_row(&row)
+}
+
+/// Budget must have positive limit and period, its scope must belong to the user.
+async fn validate_budget(
+    client: &impl GenericClient,
+    user_id: i32,
+    scope: &BudgetScope,
+    period: &BudgetPeriod,
+    limit: Money,
+) -> MoneyCalcResult<()> {
+    if limit.is_negative() || limit.is_zero() {
+        return Err(MoneyCalcError::Validation(format!(
+            "budget limit {} must be positive",
+            limit
+        )));
+    }
+    if let BudgetPeriod::Custom { days: 0, .. } = period {
+        return Err(MoneyCalcError::Validation(
+            "custom budget period must be at least one day".to_string(),
+        ));
+    }
+    match scope {
+        BudgetScope::Account(account_id) => {
+            if get_account_by_id(client, *account_id).await?.user_id != user_id {
+                return Err(MoneyCalcError::Validation(format!(
+                    "account {} belongs to another user",
+                    account_id
+                )));
+            }
+        }
+        BudgetScope::PaymentTarget(pattern) => {
+            if pattern.trim().is_empty() {
+                return Err(MoneyCalcError::Validation(
+                    "budget payment target pattern is empty".to_string(),
+                ));
+            }
+        }
+        BudgetScope::Category(category_id) => {
+            check_category_owner(client, *category_id, user_id).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn get_budget_by_id(client: &impl GenericClient, id: i32) -> MoneyCalcResult<Budget> {
+    let row = client
+        .query_opt(
+            &format!("Select {} from Budgets where Id = $1", BUDGET_COLUMNS),
+            &[&id],
+        )
+        .await?
+        .ok_or_else(|| MoneyCalcError::NotFound(format!("budget {}", id)))?;
+    budget_from_row(&row)
+}
+
+async fn get_budgets(client: &impl GenericClient, user_id: i32) -> MoneyCalcResult<Vec<Budget>> {
+    client
+        .query(
+            &format!(
+                "Select {} from Budgets where UserId = $1 order by Id",
+                BUDGET_COLUMNS
+            ),
+            &[&user_id],
+        )
+        .await?
+        .iter()
+        .map(budget_from_row)
+        .collect()
+}
+
+/// Sum outcome transactions in scope of budget for the period containing date.
+/// Reversed transactions are skipped, amended ones are counted by their corrected copy.
+async fn budget_progress(
+    client: &impl GenericClient,
+    budget: &Budget,
+    date: NaiveDate,
+) -> MoneyCalcResult<BudgetProgress> {
+    let (from_date, to_date) = budget.period.range(date).ok_or_else(|| {
+        MoneyCalcError::Validation(format!("budget {} has invalid period", budget.id))
+    })?;
+    let (scope_condition, scope_value): (String, SqlParam) = match &budget.scope {
+        BudgetScope::Account(account_id) => ("t.AccountId = $4".to_string(), Box::new(*account_id)),
+        BudgetScope::PaymentTarget(pattern) => (
+            "t.PaymentTarget ilike $4 escape '\\'".to_string(),
+            Box::new(escape_like(pattern).replace('*', "%")),
+        ),
+        BudgetScope::Category(category_id) => (
+            in_subcategories("t.CategoryId", "$4::integer"),
+            Box::new(*category_id),
+        ),
+    };
+
+    let sql = format!(
+        "Select abs(t.Amount), t.Currency, t.CreationDate from Transactions t join Accounts a on a.Id = t.AccountId where a.UserId = $1 and t.PaymentType = $5 and t.CreationDate >= $2 and t.CreationDate < $3 and not exists (Select 1 from Transactions r where r.CorrectionOf = t.Id and r.PaymentType = $6) and {}",
+        scope_condition
+    );
+    let rows = client
+        .query(
+            &sql,
+            &[
+                &budget.user_id,
+                &from_date.and_time(NaiveTime::MIN),
+                &to_date.and_time(NaiveTime::MIN),
+                scope_value.as_ref(),
+                &payment_type_to_sql(PaymentType::Outcome),
+                &payment_type_to_sql(PaymentType::Reversal),
+            ],
+        )
+        .await?;
+
+    let currency = budget.limit.currency();
+    let mut spent = Money::zero(currency);
+    for row in rows {
+        let amount = money_from_row(&row, 0, 1)?;
+        let create_date: NaiveDateTime = row.try_get(2)?;
+        let rate =
+            find_exchange_rate(client, amount.currency(), currency, create_date.date()).await?;
+        spent = spent.checked_add(rate.convert(amount)?)?;
+    }
+    Ok(BudgetProgress {
+        budget: budget.clone(),
+        from_date,
+        to_date,
+        planned: budget.limit,
+        spent,
+        remaining: budget.limit.checked_sub(spent)?,
+    })
+}
+
+async fn validate_recurring(
+    client: &impl GenericClient,
+    command: &AddRecurringCommand,
+) -> MoneyCalcResult<()> {
+    if !matches!(
+        command.payment_type,
+        PaymentType::Income | PaymentType::Outcome
+    ) {
+        return Err(MoneyCalcError::Validation(
+            "recurring transaction must be income or outcome".to_string(),
+        ));
+    }
+    if command.amount.is_negative() || command.amount.is_zero() {
+        return Err(MoneyCalcError::Validation(format!(
+            "recurring amount {} must be positive",
+            command.amount
+        )));
+    }
+    if !command.schedule.is_valid() {
+        return Err(MoneyCalcError::Validation(format!(
+            "invalid schedule {:?}",
+            command.schedule
+        )));
+    }
+    if command
+        .end_date
+        .is_some_and(|end_date| end_date < command.start_date)
+    {
+        return Err(MoneyCalcError::Validation(
+            "recurring transaction ends before start".to_string(),
+        ));
+    }
+    let account = get_account_by_id(client, command.account_id).await?;
+    if account.user_id != command.user_id {
+        return Err(MoneyCalcError::Validation(format!(
+            "account {} belongs to another user",
+            account.id
+        )));
+    }
+    account.money.check_currency(&command.amount)?;
+    if let Some(category_id) = command.category_id {
+        check_category_owner(client, category_id, command.user_id).await?;
+    }
+    Ok(())
+}
+
+async fn get_recurring_by_id(
+    client: &impl GenericClient,
+    id: i32,
+) -> MoneyCalcResult<RecurringTransaction> {
+    let row = client
+        .query_opt(
+            &format!(
+                "Select {} from RecurringTransactions where Id = $1",
+                RECURRING_COLUMNS
+            ),
+            &[&id],
+        )
+        .await?
+        .ok_or_else(|| MoneyCalcError::NotFound(format!("recurring transaction {}", id)))?;
+    recurring_from_row(&row)
+}
+
+/// Generate transactions for occurrences of template after the last generated one.
+/// Template row is locked, so concurrent calls generate every occurrence once.
+async fn generate_occurrences(
+    client: &impl GenericClient,
+    auditor: &Auditor,
+    recurring_id: i32,
+    today: NaiveDate,
+) -> MoneyCalcResult<Vec<RecurringOccurrence>> {
+    client
+        .execute(
+            "Select 1 from RecurringTransactions where Id = $1 for update",
+            &[&recurring_id],
+        )
+        .await?;
+    let recurring = get_recurring_by_id(client, recurring_id).await?;
+    let last: Option<NaiveDate> = client
+        .query_one(
+            "Select max(OccurrenceDate) from RecurringOccurrences where RecurringId = $1",
+            &[&recurring_id],
+        )
+        .await?
+        .try_get(0)?;
+    let dates = recurring.due_occurrences(last, today);
+    if dates.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let account = get_account_by_id(client, recurring.account_id).await?;
+    let user = get_user_by_id(client, recurring.user_id).await?;
+    let mut occurrences = vec![];
+    for date in dates {
+        let transaction = MoneyTransaction {
+            id: Uuid::new_v4().to_string(),
+            amount: recurring.amount,
+            description: recurring.description.clone(),
+            user: user.clone(),
+            account: account.clone(),
+            payment_type: recurring.payment_type,
+            payment_target: recurring.payment_target.clone(),
+            create_date: date.and_time(NaiveTime::MIN),
+            linked_transaction_id: None,
+            correction_of: None,
+            category_id: recurring.category_id,
+        };
+        apply_money_change(client, account.id, transaction.signed_amount()?).await?;
+        insert_transaction(client, &transaction.id, &transaction).await?;
+        auditor
+            .record(
+                client,
+                "generate_due",
+                "transaction",
+                &transaction.id,
+                None,
+                Some(&get_transaction(client, &transaction.id).await?),
+            )
+            .await?;
+        client
+            .execute(
+                "Insert into RecurringOccurrences(RecurringId, OccurrenceDate, TransactionId) Values ($1, $2, $3)",
+                &[&recurring_id, &date, &transaction.id],
+            )
+            .await?;
+        occurrences.push(RecurringOccurrence {
+            recurring_id,
+            date,
+            transaction_id: transaction.id,
+        });
+    }
+    Ok(occurrences)
+}
+
+async fn get_account_by_id(client: &impl GenericClient, id: i32) -> MoneyCalcResult<Account> {
+    let row = client
+        .query_opt(
+            &format!("Select {} from Accounts where Id = $1", ACCOUNT_COLUMNS),
+            &[&id],
+        )
+        .await?
+        .ok_or_else(|| MoneyCalcError::NotFound(format!("account {}", id)))?;
+    account_from_row(&row, 0)
+}
+
+async fn get_accounts_by_user(
+    client: &impl GenericClient,
+    user_id: i32,
+) -> MoneyCalcResult<Vec<Account>> {
+    client
+        .query(
+            &format!(
+                "Select {} from Accounts where UserId = $1 order by Id",
+                ACCOUNT_COLUMNS
+            ),
+            &[&user_id],
+        )
+        .await?
+        .iter()
+        .map(|row| account_from_row(row, 0))
+        .collect()
+}
+
+/// Latest rate of pair on or before date, taken from either direction.
+/// Rate of opposite pair is inverted, direct pair wins on the same date.
+async fn find_exchange_rate(
+    client: &impl GenericClient,
+    from: Currency,
+    to: Currency,
+    date: NaiveDate,
+) -> MoneyCalcResult<ExchangeRate> {
+    if from == to {
+        return Ok(ExchangeRate::identity(from));
+    }
+
+    let sql = "Select FromCurrency = $1, Rate from ExchangeRates where ((FromCurrency = $1 and ToCurrency = $2) or (FromCurrency = $2 and ToCurrency = $1)) and RateDate <= $3 order by RateDate desc, FromCurrency = $1 desc limit 1";
+    let latest = client
+        .query_opt(sql, &[&from.code(), &to.code(), &date])
+        .await?;
+    match latest {
+        Some(row) if row.try_get(0)? => rate_from_sql(from, to, row.try_get(1)?),
+        Some(row) => Ok(rate_from_sql(to, from, row.try_get(1)?)?.inverse()?),
+        None => Err(MoneyCalcError::NotFound(format!(
+            "exchange rate {}/{} on {}",
+            from, to, date
+        ))),
+    }
+}
+
+async fn get_user_by_id(client: &impl GenericClient, id: i32) -> MoneyCalcResult<User> {
+    let row = client
+        .query_opt(
+            &format!("Select {} from Users where Id = $1", USER_COLUMNS),
+            &[&id],
+        )
+        .await?
+        .ok_or_else(|| MoneyCalcError::NotFound(format!("user {}", id)))?;
+    user_from_row(&row, 0)
+}
+
+#[async_trait]
+impl TransactionWorker for PostgresProvider {
+    async fn execute_transaction(
+        &self,
+        transaction: &MoneyTransaction,
+    ) -> MoneyCalcResult<MoneyTransaction> {
+        check_executable(transaction)?;
+        let amount = transaction.signed_amount()?;
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        apply_money_change(&connection, transaction.account.id, amount).await?;
+        let id = Uuid::new_v4().to_string();
+        insert_transaction(&connection, &id, transaction).await?;
+        let stored = get_transaction(&connection, &id).await?;
+        self.auditor
+            .record(
+                &connection,
+                "execute_transaction",
+                "transaction",
+                &id,
+                None,
+                Some(&stored),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(stored)
+    }
+
+    async fn execute_external_transaction(
+        &self,
+        transaction: &MoneyTransaction,
+        external_id: &str,
+    ) -> MoneyCalcResult<Option<MoneyTransaction>> {
+        check_executable(transaction)?;
+        let amount = transaction.signed_amount()?;
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        // Account row lock makes concurrent imports of the same statement wait for each other.
+        connection
+            .execute(
+                "Select 1 from Accounts where Id = $1 for update",
+                &[&transaction.account.id],
+            )
+            .await?;
+        let imported = connection
+            .query_opt(
+                "Select 1 from ImportedTransactions where AccountId = $1 and ExternalId = $2",
+                &[&transaction.account.id, &external_id],
+            )
+            .await?;
+        if imported.is_some() {
+            return Ok(None);
+        }
+        apply_money_change(&connection, transaction.account.id, amount).await?;
+        let id = Uuid::new_v4().to_string();
+        insert_transaction(&connection, &id, transaction).await?;
+        connection
+            .execute(
+                "Insert into ImportedTransactions(AccountId, ExternalId, TransactionId) Values ($1, $2, $3)",
+                &[&transaction.account.id, &external_id, &id],
+            )
+            .await?;
+        let stored = get_transaction(&connection, &id).await?;
+        self.auditor
+            .record(
+                &connection,
+                "execute_external_transaction",
+                "transaction",
+                &id,
+                None,
+                Some(&stored),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(Some(stored))
+    }
+
+    async fn get_imported_transactions(
+        &self,
+        account: &Account,
+    ) -> MoneyCalcResult<Vec<ImportedTransaction>> {
+        let client = self.client().await?;
+        client
+            .query(
+                "Select AccountId, ExternalId, TransactionId from ImportedTransactions where AccountId = $1 order by ExternalId collate \"C\"",
+                &[&account.id],
+            )
+            .await?
+            .iter()
+            .map(|row| {
+                Ok(ImportedTransaction {
+                    account_id: row.try_get(0)?,
+                    external_id: row.try_get(1)?,
+                    transaction_id: row.try_get(2)?,
+                })
+            })
+            .collect()
+    }
+
+    async fn restore_imported_transaction(
+        &self,
+        imported: &ImportedTransaction,
+    ) -> MoneyCalcResult<()> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let transaction = get_transaction(&connection, &imported.transaction_id).await?;
+        if transaction.account.id != imported.account_id {
+            return Err(MoneyCalcError::Validation(format!(
+                "transaction {} does not belong to account {}",
+                imported.transaction_id, imported.account_id
+            )));
+        }
+        connection
+            .execute(
+                "Insert into ImportedTransactions(AccountId, ExternalId, TransactionId) Values ($1, $2, $3)",
+                &[
+                    &imported.account_id,
+                    &imported.external_id,
+                    &imported.transaction_id,
+                ],
+            )
+            .await?;
+        self.auditor
+            .record(
+                &connection,
+                "restore_imported_transaction",
+                "transaction",
+                &imported.transaction_id,
+                None,
+                Some(imported),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn transfer(&self, transfer_command: &TransferCommand) -> MoneyCalcResult<()> {
+        if transfer_command.from_account_id == transfer_command.to_account_id {
+            return Err(MoneyCalcError::Validation(
+                "transfer source and target accounts must differ".to_string(),
+            ));
+        }
+        if transfer_command.amount.minor_units() <= 0 {
+            return Err(MoneyCalcError::Validation(
+                "transfer amount must be positive".to_string(),
+            ));
+        }
+
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let from_account = get_account_by_id(&connection, transfer_command.from_account_id).await?;
+        let to_account = get_account_by_id(&connection, transfer_command.to_account_id).await?;
+        transfer_command
+            .amount
+            .check_currency(&from_account.money)?;
+
+        let credit = match &transfer_command.exchange_rate {
+            Some(rate) => {
+                if rate.from != from_account.money.currency()
+                    || rate.to != to_account.money.currency()
+                {
+                    return Err(MoneyCalcError::Validation(format!(
+                        "exchange rate {} does not match accounts currencies",
+                        rate
+                    )));
+                }
+                rate.convert(transfer_command.amount)?
+            }
+            None => {
+                from_account.money.check_currency(&to_account.money)?;
+                transfer_command.amount
+            }
+        };
+        let debit = transfer_command.amount.checked_neg()?;
+
+        apply_money_change(&connection, from_account.id, debit).await?;
+        apply_money_change(&connection, to_account.id, credit).await?;
+
+        let debit_id = Uuid::new_v4().to_string();
+        let credit_id = Uuid::new_v4().to_string();
+        let debit_transaction = MoneyTransaction {
+            id: debit_id.clone(),
+            amount: debit,
+            description: transfer_command.description.clone(),
+            user: get_user_by_id(&connection, from_account.user_id).await?,
+            payment_type: PaymentType::Transfer,
+            payment_target: to_account.name.clone(),
+            create_date: transfer_command.create_date,
+            linked_transaction_id: Some(credit_id.clone()),
+            correction_of: None,
+            category_id: None,
+            account: from_account.clone(),
+        };
+        let credit_transaction = MoneyTransaction {
+            id: credit_id.clone(),
+            amount: credit,
+            description: transfer_command.description.clone(),
+            user: get_user_by_id(&connection, to_account.user_id).await?,
+            payment_type: PaymentType::Transfer,
+            payment_target: from_account.name.clone(),
+            create_date: transfer_command.create_date,
+            linked_transaction_id: Some(debit_id.clone()),
+            correction_of: None,
+            category_id: None,
+            account: to_account,
+        };
+        insert_transaction(&connection, &debit_id, &debit_transaction).await?;
+        insert_transaction(&connection, &credit_id, &credit_transaction).await?;
+        for id in [debit_id, credit_id] {
+            self.auditor
+                .record(
+                    &connection,
+                    "transfer",
+                    "transaction",
+                    &id,
+                    None,
+                    Some(&get_transaction(&connection, &id).await?),
+                )
+                .await?;
+        }
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn reverse_transaction(
+        &self,
+        reverse_command: &ReverseTransactionCommand,
+    ) -> MoneyCalcResult<MoneyTransaction> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let original = get_transaction(&connection, &reverse_command.transaction_id).await?;
+        let reversal = reverse_transaction(
+            &connection,
+            &original,
+            &reverse_command.description,
+            reverse_command.create_date,
+        )
+        .await?;
+        self.auditor
+            .record(
+                &connection,
+                "reverse_transaction",
+                "transaction",
+                &original.id,
+                Some(&original),
+                Some(&get_transaction(&connection, &reversal.id).await?),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(reversal)
+    }
+
+    async fn amend_transaction(
+        &self,
+        amend_command: &AmendTransactionCommand,
+    ) -> MoneyCalcResult<MoneyTransaction> {
+        if amend_command.amount.is_negative() || amend_command.amount.is_zero() {
+            return Err(MoneyCalcError::Validation(format!(
+                "amended amount {} must be positive",
+                amend_command.amount
+            )));
+        }
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let original = get_transaction(&connection, &amend_command.transaction_id).await?;
+        if let PaymentType::Transfer = original.payment_type {
+            return Err(MoneyCalcError::Validation(
+                "transfers can't be amended".to_string(),
+            ));
+        }
+        reverse_transaction(
+            &connection,
+            &original,
+            &original.description,
+            amend_command.create_date,
+        )
+        .await?;
+        let amended = MoneyTransaction {
+            id: Uuid::new_v4().to_string(),
+            amount: amend_command.amount,
+            description: amend_command.description.clone(),
+            payment_target: amend_command.payment_target.clone(),
+            create_date: amend_command.create_date,
+            linked_transaction_id: None,
+            category_id: amend_command.category_id,
+            correction_of: Some(original.id.clone()),
+            ..original.clone()
+        };
+        apply_money_change(&connection, amended.account.id, amended.signed_amount()?).await?;
+        insert_transaction(&connection, &amended.id, &amended).await?;
+        self.auditor
+            .record(
+                &connection,
+                "amend_transaction",
+                "transaction",
+                &original.id,
+                Some(&original),
+                Some(&get_transaction(&connection, &amended.id).await?),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(amended)
+    }
+
+    async fn get_transaction_by_id(&self, id: &str) -> MoneyCalcResult<MoneyTransaction> {
+        let client = self.client().await?;
+        get_transaction(&client, id).await
+    }
+
+    async fn restore_transaction(
+        &self,
+        transaction: &MoneyTransaction,
+    ) -> MoneyCalcResult<MoneyTransaction> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let id = if transaction.id.is_empty() {
+            Uuid::new_v4().to_string()
+        } else {
+            transaction.id.clone()
+        };
+        let account = get_account_by_id(&connection, transaction.account.id).await?;
+        transaction.amount.check_currency(&account.money)?;
+        insert_transaction(
+            &connection,
+            &id,
+            &MoneyTransaction {
+                account,
+                ..transaction.clone()
+            },
+        )
+        .await?;
+        let restored = get_transaction(&connection, &id).await?;
+        self.auditor
+            .record(
+                &connection,
+                "restore_transaction",
+                "transaction",
+                &id,
+                None,
+                Some(&restored),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(restored)
+    }
+
+    async fn get_transactions(&self, query: &TransactionQuery) -> MoneyCalcResult<TransactionPage> {
+        if query.limit == 0 {
+            return Err(MoneyCalcError::Validation(
+                "transactions query limit must be positive".to_string(),
+            ));
+        }
+        let (sql, params) = build_transaction_query(query)?;
+        let client = self.client().await?;
+        let mut transactions = client
+            .query(&sql, &param_refs(&params))
+            .await?
+            .iter()
+            .map(transaction_from_row)
+            .collect::<MoneyCalcResult<Vec<_>>>()?;
+
+        let mut next_cursor = None;
+        if transactions.len() > query.limit as usize {
+            transactions.truncate(query.limit as usize);
+            next_cursor = transactions
+                .last()
+                .map(|transaction| transaction_cursor(query, transaction));
+        }
+        Ok(TransactionPage {
+            transactions,
+            next_cursor,
+        })
+    }
+}
+
+#[async_trait]
+impl UserProvider for PostgresProvider {
+    async fn add_user(&self, add_user_command: &AddUserCommand) -> MoneyCalcResult<()> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let id: i32 = connection
+            .query_one(
+                "insert into Users(Name, Number, CreationDate) values ($1, $2, $3) returning Id",
+                &[
+                    &add_user_command.user_name,
+                    &add_user_command.user_number,
+                    &chrono::Utc::now().naive_utc().date(),
+                ],
+            )
+            .await?
+            .try_get(0)?;
+        let user = get_user_by_id(&connection, id).await?;
+        self.auditor
+            .record(&connection, "add_user", "user", user.id, None, Some(&user))
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn restore_user(&self, user: &User) -> MoneyCalcResult<User> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let id: i32 = connection
+            .query_one(
+                "insert into Users(Id, Name, Number, CreationDate) values (coalesce(nullif($1, 0), nextval(pg_get_serial_sequence('users', 'id'))), $2, $3, $4) returning Id",
+                &[&user.id, &user.name, &user.number, &user.creation_date],
+            )
+            .await?
+            .try_get(0)?;
+        sync_identity(&connection, "users").await?;
+        let restored = get_user_by_id(&connection, id).await?;
+        self.auditor
+            .record(
+                &connection,
+                "restore_user",
+                "user",
+                restored.id,
+                None,
+                Some(&restored),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(restored)
+    }
+
+    async fn get_users(&self) -> MoneyCalcResult<Vec<User>> {
+        let client = self.client().await?;
+        client
+            .query(
+                &format!("select {} from Users order by Id", USER_COLUMNS),
+                &[],
+            )
+            .await?
+            .iter()
+            .map(|row| user_from_row(row, 0))
+            .collect()
+    }
+
+    async fn get_user_by_number(&self, number: &str) -> MoneyCalcResult<User> {
+        let client = self.client().await?;
+        let row = client
+            .query_opt(
+                &format!("Select {} from Users where Number = $1", USER_COLUMNS),
+                &[&number],
+            )
+            .await?
+            .ok_or_else(|| MoneyCalcError::NotFound(format!("user with number {}", number)))?;
+        user_from_row(&row, 0)
+    }
+
+    async fn delete_user_by_id(&self, id: i32) -> MoneyCalcResult<()> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let user = get_user_by_id(&connection, id).await?;
+        connection
+            .execute("Delete from Users where Id = $1", &[&id])
+            .await
+            .map_err(still_referenced(format!("user {}", id)))?;
+        self.auditor
+            .record(
+                &connection,
+                "delete_user_by_id",
+                "user",
+                id,
+                Some(&user),
+                None,
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AccountProvider for PostgresProvider {
+    async fn add_account(&self, add_command: &AddAccountCommand) -> MoneyCalcResult<()> {
+        validate_overdraft(
+            &add_command.overdraft,
+            add_command.initial_balance.currency(),
+        )?;
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        // User row lock keeps one primary account when accounts are added concurrently.
+        connection
+            .execute(
+                "Select 1 from Users where Id = $1 for update",
+                &[&add_command.user_id],
+            )
+            .await?;
+        let id: i32 = connection
+            .query_one(
+                "Insert into Accounts(Name, UserId, MoneyCount, Currency, CreationDate, IsPrimary, OverdraftLimit, InitialBalance) Values ($1, $2, $3, $4, $5, not exists (Select 1 from Accounts where UserId = $2 and IsPrimary), $6, $3) returning Id",
+                &[
+                    &add_command.account_name,
+                    &add_command.user_id,
+                    &to_decimal(add_command.initial_balance),
+                    &add_command.initial_balance.currency().code(),
+                    &chrono::Utc::now().naive_utc().date(),
+                    &overdraft_to_sql(&add_command.overdraft),
+                ],
+            )
+            .await?
+            .try_get(0)?;
+        let account = get_account_by_id(&connection, id).await?;
+        self.auditor
+            .record(
+                &connection,
+                "add_account",
+                "account",
+                account.id,
+                None,
+                Some(&account),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn restore_account(
+        &self,
+        account: &Account,
+        initial_balance: Money,
+    ) -> MoneyCalcResult<Account> {
+        validate_overdraft(&account.overdraft, account.money.currency())?;
+        initial_balance.check_currency(&account.money)?;
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let id: i32 = connection
+            .query_one(
+                "Insert into Accounts(Id, Name, UserId, MoneyCount, Currency, CreationDate, IsPrimary, OverdraftLimit, InitialBalance) Values (coalesce(nullif($1, 0), nextval(pg_get_serial_sequence('accounts', 'id'))), $2, $3, $4, $5, $6, $7, $8, $9) returning Id",
+                &[
+                    &account.id,
+                    &account.name,
+                    &account.user_id,
+                    &to_decimal(account.money),
+                    &account.money.currency().code(),
+                    &account.creation_date,
+                    &account.is_primary,
+                    &overdraft_to_sql(&account.overdraft),
+                    &to_decimal(initial_balance),
+                ],
+            )
+            .await?
+            .try_get(0)?;
+        sync_identity(&connection, "accounts").await?;
+        let restored = get_account_by_id(&connection, id).await?;
+        self.auditor
+            .record(
+                &connection,
+                "restore_account",
+                "account",
+                restored.id,
+                None,
+                Some(&restored),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(restored)
+    }
+
+    async fn delete_account(&self, account: &Account) -> MoneyCalcResult<()> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let account = get_account_by_id(&connection, account.id).await?;
+        connection
+            .execute(
+                "Delete from BalanceSnapshots where AccountId = $1",
+                &[&account.id],
+            )
+            .await?;
+        connection
+            .execute("Delete from Accounts where Id = $1", &[&account.id])
+            .await
+            .map_err(still_referenced(format!("account {}", account.id)))?;
+        connection
+            .execute(
+                "Update Accounts set IsPrimary = true where Id = (Select min(Id) from Accounts where UserId = $1) and not exists (Select 1 from Accounts where UserId = $1 and IsPrimary)",
+                &[&account.user_id],
+            )
+            .await?;
+        self.auditor
+            .record(
+                &connection,
+                "delete_account",
+                "account",
+                account.id,
+                Some(&account),
+                None,
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn change_money(&self, account: &Account, payment_count: Money) -> MoneyCalcResult<()> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let before = get_account_by_id(&connection, account.id).await?;
+        apply_money_change(&connection, account.id, payment_count).await?;
+        self.auditor
+            .record(
+                &connection,
+                "change_money",
+                "account",
+                account.id,
+                Some(&before),
+                Some(&get_account_by_id(&connection, account.id).await?),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn set_overdraft_policy(
+        &self,
+        account: &Account,
+        overdraft: OverdraftPolicy,
+    ) -> MoneyCalcResult<()> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let account = get_account_by_id(&connection, account.id).await?;
+        validate_overdraft(&overdraft, account.money.currency())?;
+        connection
+            .execute(
+                "Update Accounts set OverdraftLimit = $2 where Id = $1",
+                &[&account.id, &overdraft_to_sql(&overdraft)],
+            )
+            .await?;
+        self.auditor
+            .record(
+                &connection,
+                "set_overdraft_policy",
+                "account",
+                account.id,
+                Some(&account),
+                Some(&get_account_by_id(&connection, account.id).await?),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn get_accounts(&self) -> MoneyCalcResult<Vec<Account>> {
+        let client = self.client().await?;
+        client
+            .query(
+                &format!("select {} from Accounts order by Id", ACCOUNT_COLUMNS),
+                &[],
+            )
+            .await?
+            .iter()
+            .map(|row| account_from_row(row, 0))
+            .collect()
+    }
+
+    async fn search_account_by_user(&self, user: &User) -> MoneyCalcResult<Account> {
+        let client = self.client().await?;
+        let row = client
+            .query_opt(
+                &format!(
+                    "Select {} from Accounts where UserId = $1 order by IsPrimary desc, Id limit 1",
+                    ACCOUNT_COLUMNS
+                ),
+                &[&user.id],
+            )
+            .await?
+            .ok_or_else(|| MoneyCalcError::NotFound(format!("account of user {}", user.id)))?;
+        account_from_row(&row, 0)
+    }
+
+    async fn get_accounts_by_user(&self, user: &User) -> MoneyCalcResult<Vec<Account>> {
+        let client = self.client().await?;
+        get_accounts_by_user(&client, user.id).await
+    }
+
+    async fn get_account_by_id(&self, id: i32) -> MoneyCalcResult<Account> {
+        let client = self.client().await?;
+        get_account_by_id(&client, id).await
+    }
+
+    async fn get_account_by_name(&self, user: &User, name: &str) -> MoneyCalcResult<Account> {
+        let client = self.client().await?;
+        let row = client
+            .query_opt(
+                &format!(
+                    "Select {} from Accounts where UserId = $1 and Name = $2 order by Id limit 1",
+                    ACCOUNT_COLUMNS
+                ),
+                &[&user.id, &name],
+            )
+            .await?
+            .ok_or_else(|| {
+                MoneyCalcError::NotFound(format!("account {} of user {}", name, user.id))
+            })?;
+        account_from_row(&row, 0)
+    }
+
+    async fn set_primary_account(&self, account: &Account) -> MoneyCalcResult<()> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let account = get_account_by_id(&connection, account.id).await?;
+        connection
+            .execute(
+                "Update Accounts set IsPrimary = (Id = $1) where UserId = $2",
+                &[&account.id, &account.user_id],
+            )
+            .await?;
+        self.auditor
+            .record(
+                &connection,
+                "set_primary_account",
+                "account",
+                account.id,
+                Some(&account),
+                Some(&get_account_by_id(&connection, account.id).await?),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ExchangeRateProvider for PostgresProvider {
+    async fn add_exchange_rate(&self, rate: &ExchangeRate, date: NaiveDate) -> MoneyCalcResult<()> {
+        if rate.from == rate.to {
+            return Err(MoneyCalcError::Validation(format!(
+                "exchange rate {} must be between different currencies",
+                rate
+            )));
+        }
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let before = match connection
+            .query_opt(
+                "Select Rate from ExchangeRates where FromCurrency = $1 and ToCurrency = $2 and RateDate = $3",
+                &[&rate.from.code(), &rate.to.code(), &date],
+            )
+            .await?
+        {
+            Some(row) => Some(rate_from_sql(rate.from, rate.to, row.try_get(0)?)?),
+            None => None,
+        };
+        connection
+            .execute(
+                "Insert into ExchangeRates(FromCurrency, ToCurrency, RateDate, Rate) Values ($1, $2, $3, $4) on conflict (FromCurrency, ToCurrency, RateDate) do update set Rate = excluded.Rate",
+                &[
+                    &rate.from.code(),
+                    &rate.to.code(),
+                    &date,
+                    &Decimal::new(rate.rate_units(), RATE_SCALE),
+                ],
+            )
+            .await?;
+        self.auditor
+            .record(
+                &connection,
+                "add_exchange_rate",
+                "exchange_rate",
+                format!("{}/{} {}", rate.from, rate.to, date),
+                before.as_ref(),
+                Some(rate),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn get_exchange_rate(
+        &self,
+        from: Currency,
+        to: Currency,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<ExchangeRate> {
+        let client = self.client().await?;
+        find_exchange_rate(&client, from, to, date).await
+    }
+
+    async fn convert_money(
+        &self,
+        money: Money,
+        currency: Currency,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<Money> {
+        let rate = self
+            .get_exchange_rate(money.currency(), currency, date)
+            .await?;
+        Ok(rate.convert(money)?)
+    }
+
+    async fn get_total_balance(
+        &self,
+        user: &User,
+        currency: Currency,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<Money> {
+        let client = self.client().await?;
+        let mut total = Money::zero(currency);
+        for account in get_accounts_by_user(&client, user.id).await? {
+            let rate =
+                find_exchange_rate(&client, account.money.currency(), currency, date).await?;
+            total = total.checked_add(rate.convert(account.money)?)?;
+        }
+        Ok(total)
+    }
+
+    async fn convert_transactions(
+        &self,
+        transactions: &[MoneyTransaction],
+        currency: Currency,
+    ) -> MoneyCalcResult<Vec<MoneyTransaction>> {
+        let client = self.client().await?;
+        let mut transactions = transactions.to_vec();
+        for transaction in transactions.iter_mut() {
+            let rate = find_exchange_rate(
+                &client,
+                transaction.amount.currency(),
+                currency,
+                transaction.create_date.date(),
+            )
+            .await?;
+            transaction.amount = rate.convert(transaction.amount)?;
+        }
+        Ok(transactions)
+    }
+}
+
+#[async_trait]
+impl CategoryProvider for PostgresProvider {
+    async fn add_category(&self, add_category_command: &AddCategoryCommand) -> MoneyCalcResult<()> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        if let Some(parent_id) = add_category_command.parent_id {
+            check_category_owner(&connection, parent_id, add_category_command.user_id).await?;
+        }
+        let id: i32 = connection
+            .query_one(
+                "Insert into Categories(UserId, ParentId, Name) Values ($1, $2, $3) returning Id",
+                &[
+                    &add_category_command.user_id,
+                    &add_category_command.parent_id,
+                    &add_category_command.name,
+                ],
+            )
+            .await?
+            .try_get(0)?;
+        let category = get_category_by_id(&connection, id).await?;
+        self.auditor
+            .record(
+                &connection,
+                "add_category",
+                "category",
+                category.id,
+                None,
+                Some(&category),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn get_categories(&self, user: &User) -> MoneyCalcResult<Vec<Category>> {
+        let client = self.client().await?;
+        client
+            .query(
+                &format!(
+                    "Select {} from Categories where UserId = $1 order by Id",
+                    CATEGORY_COLUMNS
+                ),
+                &[&user.id],
+            )
+            .await?
+            .iter()
+            .map(category_from_row)
+            .collect()
+    }
+
+    async fn get_category_by_id(&self, id: i32) -> MoneyCalcResult<Category> {
+        let client = self.client().await?;
+        get_category_by_id(&client, id).await
+    }
+
+    async fn update_category(&self, category: &Category) -> MoneyCalcResult<()> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let stored = get_category_by_id(&connection, category.id).await?;
+        let mut parent_id = category.parent_id;
+        while let Some(id) = parent_id {
+            if id == category.id {
+                return Err(MoneyCalcError::Validation(format!(
+                    "category {} can't be moved under itself",
+                    category.id
+                )));
+            }
+            parent_id = check_category_owner(&connection, id, stored.user_id)
+                .await?
+                .parent_id;
+        }
+        connection
+            .execute(
+                "Update Categories set ParentId = $2, Name = $3 where Id = $1",
+                &[&category.id, &category.parent_id, &category.name],
+            )
+            .await?;
+        self.auditor
+            .record(
+                &connection,
+                "update_category",
+                "category",
+                category.id,
+                Some(&stored),
+                Some(&get_category_by_id(&connection, category.id).await?),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn delete_category(&self, category: &Category) -> MoneyCalcResult<()> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let category = get_category_by_id(&connection, category.id).await?;
+        connection
+            .execute(
+                "Update Categories set ParentId = $2 where ParentId = $1",
+                &[&category.id, &category.parent_id],
+            )
+            .await?;
+        connection
+            .execute(
+                "Update Transactions set CategoryId = NULL where CategoryId = $1",
+                &[&category.id],
+            )
+            .await?;
+        connection
+            .execute("Delete from Categories where Id = $1", &[&category.id])
+            .await
+            .map_err(still_referenced(format!("category {}", category.id)))?;
+        self.auditor
+            .record(
+                &connection,
+                "delete_category",
+                "category",
+                category.id,
+                Some(&category),
+                None,
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn set_transaction_category(
+        &self,
+        transaction_id: &str,
+        category: Option<&Category>,
+    ) -> MoneyCalcResult<()> {
+        let category_id = category.map(|category| category.id);
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let before = get_transaction(&connection, transaction_id).await?;
+        if let Some(category_id) = category_id {
+            check_category_owner(&connection, category_id, before.account.user_id).await?;
+        }
+        connection
+            .execute(
+                "Update Transactions set CategoryId = $2 where Id = $1",
+                &[&transaction_id, &category_id],
+            )
+            .await?;
+        self.auditor
+            .record(
+                &connection,
+                "set_transaction_category",
+                "transaction",
+                transaction_id,
+                Some(&before),
+                Some(&get_transaction(&connection, transaction_id).await?),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn restore_category(&self, category: &Category) -> MoneyCalcResult<Category> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        if let Some(parent_id) = category.parent_id {
+            check_category_owner(&connection, parent_id, category.user_id).await?;
+        }
+        let id: i32 = connection
+            .query_one(
+                "Insert into Categories(Id, UserId, ParentId, Name) Values (coalesce(nullif($1, 0), nextval(pg_get_serial_sequence('categories', 'id'))), $2, $3, $4) returning Id",
+                &[
+                    &category.id,
+                    &category.user_id,
+                    &category.parent_id,
+                    &category.name,
+                ],
+            )
+            .await?
+            .try_get(0)?;
+        sync_identity(&connection, "categories").await?;
+        let restored = get_category_by_id(&connection, id).await?;
+        self.auditor
+            .record(
+                &connection,
+                "restore_category",
+                "category",
+                restored.id,
+                None,
+                Some(&restored),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(restored)
+    }
+}
+
+#[async_trait]
+impl TagProvider for PostgresProvider {
+    async fn add_tag(&self, add_tag_command: &AddTagCommand) -> MoneyCalcResult<()> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let id: i32 = connection
+            .query_one(
+                "Insert into Tags(UserId, Name) Values ($1, $2) returning Id",
+                &[&add_tag_command.user_id, &add_tag_command.name],
+            )
+            .await?
+            .try_get(0)?;
+        let tag = get_tag_by_id(&connection, id).await?;
+        self.auditor
+            .record(&connection, "add_tag", "tag", tag.id, None, Some(&tag))
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn get_tags(&self, user: &User) -> MoneyCalcResult<Vec<Tag>> {
+        let client = self.client().await?;
+        client
+            .query(
+                &format!(
+                    "Select {} from Tags where UserId = $1 order by Name",
+                    TAG_COLUMNS
+                ),
+                &[&user.id],
+            )
+            .await?
+            .iter()
+            .map(tag_from_row)
+            .collect()
+    }
+
+    async fn rename_tag(&self, tag: &Tag, name: &str) -> MoneyCalcResult<()> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let before = get_tag_by_id(&connection, tag.id).await?;
+        connection
+            .execute("Update Tags set Name = $2 where Id = $1", &[&tag.id, &name])
+            .await?;
+        self.auditor
+            .record(
+                &connection,
+                "rename_tag",
+                "tag",
+                tag.id,
+                Some(&before),
+                Some(&get_tag_by_id(&connection, tag.id).await?),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn delete_tag(&self, tag: &Tag) -> MoneyCalcResult<()> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let tag = get_tag_by_id(&connection, tag.id).await?;
+        connection
+            .execute("Delete from TransactionTags where TagId = $1", &[&tag.id])
+            .await?;
+        connection
+            .execute("Delete from Tags where Id = $1", &[&tag.id])
+            .await?;
+        self.auditor
+            .record(&connection, "delete_tag", "tag", tag.id, Some(&tag), None)
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn restore_tag(&self, tag: &Tag) -> MoneyCalcResult<Tag> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let id: i32 = connection
+            .query_one(
+                "Insert into Tags(Id, UserId, Name) Values (coalesce(nullif($1, 0), nextval(pg_get_serial_sequence('tags', 'id'))), $2, $3) returning Id",
+                &[&tag.id, &tag.user_id, &tag.name],
+            )
+            .await?
+            .try_get(0)?;
+        sync_identity(&connection, "tags").await?;
+        let restored = get_tag_by_id(&connection, id).await?;
+        self.auditor
+            .record(
+                &connection,
+                "restore_tag",
+                "tag",
+                restored.id,
+                None,
+                Some(&restored),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(restored)
+    }
+
+    async fn set_transaction_tags(
+        &self,
+        transaction_id: &str,
+        tags: &[Tag],
+    ) -> MoneyCalcResult<()> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let user_id = get_transaction_owner(&connection, transaction_id).await?;
+        let before = get_transaction_tags(&connection, transaction_id).await?;
+        connection
+            .execute(
+                "Delete from TransactionTags where TransactionId = $1",
+                &[&transaction_id],
+            )
+            .await?;
+        for tag in tags {
+            let owner: i32 = connection
+                .query_opt("Select UserId from Tags where Id = $1", &[&tag.id])
+                .await?
+                .ok_or_else(|| MoneyCalcError::NotFound(format!("tag {}", tag.id)))?
+                .try_get(0)?;
+            if owner != user_id {
+                return Err(MoneyCalcError::Validation(format!(
+                    "tag {} belongs to another user",
+                    tag.id
+                )));
+            }
+            connection
+                .execute(
+                    "Insert into TransactionTags(TransactionId, TagId) Values ($1, $2) on conflict do nothing",
+                    &[&transaction_id, &tag.id],
+                )
+                .await?;
+        }
+        self.auditor
+            .record(
+                &connection,
+                "set_transaction_tags",
+                "transaction",
+                transaction_id,
+                Some(&before),
+                Some(&get_transaction_tags(&connection, transaction_id).await?),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn get_transaction_tags(&self, transaction_id: &str) -> MoneyCalcResult<Vec<Tag>> {
+        let client = self.client().await?;
+        get_transaction_owner(&client, transaction_id).await?;
+        get_transaction_tags(&client, transaction_id).await
+    }
+}
+
+#[async_trait]
+impl BudgetProvider for PostgresProvider {
+    async fn add_budget(&self, add_budget_command: &AddBudgetCommand) -> MoneyCalcResult<()> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        validate_budget(
+            &connection,
+            add_budget_command.user_id,
+            &add_budget_command.scope,
+            &add_budget_command.period,
+            add_budget_command.limit,
+        )
+        .await?;
+        let (account_id, pattern, category_id, kind, start, days) =
+            budget_to_sql(&add_budget_command.scope, &add_budget_command.period);
+        let id: i32 = connection
+            .query_one(
+                "Insert into Budgets(UserId, Name, AccountId, PaymentTargetPattern, CategoryId, PeriodKind, PeriodStart, PeriodDays, LimitAmount, Currency) Values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) returning Id",
+                &[
+                    &add_budget_command.user_id,
+                    &add_budget_command.name,
+                    &account_id,
+                    &pattern,
+                    &category_id,
+                    &kind,
+                    &start,
+                    &days.map(i64::from),
+                    &to_decimal(add_budget_command.limit),
+                    &add_budget_command.limit.currency().code(),
+                ],
+            )
+            .await?
+            .try_get(0)?;
+        let budget = get_budget_by_id(&connection, id).await?;
+        self.auditor
+            .record(
+                &connection,
+                "add_budget",
+                "budget",
+                budget.id,
+                None,
+                Some(&budget),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn get_budgets(&self, user: &User) -> MoneyCalcResult<Vec<Budget>> {
+        let client = self.client().await?;
+        get_budgets(&client, user.id).await
+    }
+
+    async fn get_budget_by_id(&self, id: i32) -> MoneyCalcResult<Budget> {
+        let client = self.client().await?;
+        get_budget_by_id(&client, id).await
+    }
+
+    async fn update_budget(&self, budget: &Budget) -> MoneyCalcResult<()> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let stored = get_budget_by_id(&connection, budget.id).await?;
+        validate_budget(
+            &connection,
+            stored.user_id,
+            &budget.scope,
+            &budget.period,
+            budget.limit,
+        )
+        .await?;
+        let (account_id, pattern, category_id, kind, start, days) =
+            budget_to_sql(&budget.scope, &budget.period);
+        connection
+            .execute(
+                "Update Budgets set Name = $2, AccountId = $3, PaymentTargetPattern = $4, CategoryId = $5, PeriodKind = $6, PeriodStart = $7, PeriodDays = $8, LimitAmount = $9, Currency = $10 where Id = $1",
+                &[
+                    &budget.id,
+                    &budget.name,
+                    &account_id,
+                    &pattern,
+                    &category_id,
+                    &kind,
+                    &start,
+                    &days.map(i64::from),
+                    &to_decimal(budget.limit),
+                    &budget.limit.currency().code(),
+                ],
+            )
+            .await?;
+        self.auditor
+            .record(
+                &connection,
+                "update_budget",
+                "budget",
+                budget.id,
+                Some(&stored),
+                Some(&get_budget_by_id(&connection, budget.id).await?),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn delete_budget(&self, budget: &Budget) -> MoneyCalcResult<()> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let budget = get_budget_by_id(&connection, budget.id).await?;
+        connection
+            .execute("Delete from Budgets where Id = $1", &[&budget.id])
+            .await?;
+        self.auditor
+            .record(
+                &connection,
+                "delete_budget",
+                "budget",
+                budget.id,
+                Some(&budget),
+                None,
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn get_budget_progress(
+        &self,
+        budget: &Budget,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<BudgetProgress> {
+        let client = self.client().await?;
+        let budget = get_budget_by_id(&client, budget.id).await?;
+        budget_progress(&client, &budget, date).await
+    }
+
+    async fn get_budgets_progress(
+        &self,
+        user: &User,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<Vec<BudgetProgress>> {
+        let client = self.client().await?;
+        let mut progress = vec![];
+        for budget in get_budgets(&client, user.id).await? {
+            progress.push(budget_progress(&client, &budget, date).await?);
+        }
+        Ok(progress)
+    }
+}
+
+#[async_trait]
+impl RecurringProvider for PostgresProvider {
+    async fn add_recurring(
+        &self,
+        add_recurring_command: &AddRecurringCommand,
+    ) -> MoneyCalcResult<()> {
+        let command = add_recurring_command;
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        validate_recurring(&connection, command).await?;
+        let (kind, value) = schedule_to_sql(&command.schedule);
+        let id: i32 = connection
+            .query_one(
+                "Insert into RecurringTransactions(UserId, AccountId, Amount, Currency, PaymentType, PaymentTarget, Description, CategoryId, ScheduleKind, ScheduleValue, StartDate, EndDate) Values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12) returning Id",
+                &[
+                    &command.user_id,
+                    &command.account_id,
+                    &to_decimal(command.amount),
+                    &command.amount.currency().code(),
+                    &payment_type_to_sql(command.payment_type),
+                    &command.payment_target,
+                    &command.description,
+                    &command.category_id,
+                    &kind,
+                    &value.map(i64::from),
+                    &command.start_date,
+                    &command.end_date,
+                ],
+            )
+            .await?
+            .try_get(0)?;
+        let recurring = get_recurring_by_id(&connection, id).await?;
+        self.auditor
+            .record(
+                &connection,
+                "add_recurring",
+                "recurring",
+                recurring.id,
+                None,
+                Some(&recurring),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn get_recurring(&self, user: &User) -> MoneyCalcResult<Vec<RecurringTransaction>> {
+        let client = self.client().await?;
+        client
+            .query(
+                &format!(
+                    "Select {} from RecurringTransactions where UserId = $1 order by Id",
+                    RECURRING_COLUMNS
+                ),
+                &[&user.id],
+            )
+            .await?
+            .iter()
+            .map(recurring_from_row)
+            .collect()
+    }
+
+    async fn get_recurring_by_id(&self, id: i32) -> MoneyCalcResult<RecurringTransaction> {
+        let client = self.client().await?;
+        get_recurring_by_id(&client, id).await
+    }
+
+    async fn delete_recurring(&self, recurring: &RecurringTransaction) -> MoneyCalcResult<()> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let recurring = get_recurring_by_id(&connection, recurring.id).await?;
+        connection
+            .execute(
+                "Delete from RecurringOccurrences where RecurringId = $1",
+                &[&recurring.id],
+            )
+            .await?;
+        connection
+            .execute(
+                "Delete from RecurringTransactions where Id = $1",
+                &[&recurring.id],
+            )
+            .await?;
+        self.auditor
+            .record(
+                &connection,
+                "delete_recurring",
+                "recurring",
+                recurring.id,
+                Some(&recurring),
+                None,
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(())
+    }
+
+    async fn get_occurrences(
+        &self,
+        recurring: &RecurringTransaction,
+    ) -> MoneyCalcResult<Vec<RecurringOccurrence>> {
+        let client = self.client().await?;
+        client
+            .query(
+                "Select RecurringId, OccurrenceDate, TransactionId from RecurringOccurrences where RecurringId = $1 order by OccurrenceDate",
+                &[&recurring.id],
+            )
+            .await?
+            .iter()
+            .map(|row| {
+                Ok(RecurringOccurrence {
+                    recurring_id: row.try_get(0)?,
+                    date: row.try_get(1)?,
+                    transaction_id: row.try_get(2)?,
+                })
+            })
+            .collect()
+    }
+
+    async fn generate_due(&self, now: NaiveDateTime) -> MoneyCalcResult<GenerationReport> {
+        let mut client = self.client().await?;
+        let ids = client
+            .query("Select Id from RecurringTransactions order by Id", &[])
+            .await?
+            .iter()
+            .map(|row| row.try_get::<_, i32>(0))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut report = GenerationReport::default();
+        for id in ids {
+            let connection = client.transaction().await?;
+            // Dropped transaction of failed template is rolled back.
+            match generate_occurrences(&connection, &self.auditor, id, now.date()).await {
+                Ok(generated) => {
+                    connection.commit().await?;
+                    report.occurrences.extend(generated);
+                }
+                Err(error) => report.failures.push(RecurringFailure {
+                    recurring_id: id,
+                    message: error.to_string(),
+                }),
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[async_trait]
+impl ReconciliationProvider for PostgresProvider {
+    async fn reconcile_balances(&self, repair: bool) -> MoneyCalcResult<ReconciliationReport> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let lock = if repair { " for update" } else { "" };
+        let rows = connection
+            .query(
+                &format!(
+                    "Select a.Id, a.MoneyCount, a.Currency, a.InitialBalance + coalesce((Select sum({LEDGER_AMOUNT}) from Transactions t where t.AccountId = a.Id), 0) from Accounts a order by a.Id{lock}"
+                ),
+                &[],
+            )
+            .await?;
+        let balances = rows
+            .iter()
+            .map(|row| {
+                Ok((
+                    row.try_get::<_, i32>(0)?,
+                    money_from_row(row, 1, 2)?,
+                    money_from_row(row, 3, 2)?,
+                ))
+            })
+            .collect::<MoneyCalcResult<Vec<_>>>()?;
+
+        let mut discrepancies = vec![];
+        for &(account_id, stored, expected) in &balances {
+            if stored == expected {
+                continue;
+            }
+            discrepancies.push(BalanceDiscrepancy {
+                account_id,
+                stored,
+                expected,
+                difference: stored.checked_sub(expected)?,
+            });
+            if repair {
+                let before = get_account_by_id(&connection, account_id).await?;
+                connection
+                    .execute(
+                        "Update Accounts set MoneyCount = $2 where Id = $1",
+                        &[&account_id, &to_decimal(expected)],
+                    )
+                    .await?;
+                self.auditor
+                    .record(
+                        &connection,
+                        "reconcile_balances",
+                        "account",
+                        account_id,
+                        Some(&before),
+                        Some(&get_account_by_id(&connection, account_id).await?),
+                    )
+                    .await?;
+            }
+        }
+        connection.commit().await?;
+        Ok(ReconciliationReport {
+            checked_accounts: balances.len(),
+            discrepancies,
+            repaired: repair,
+        })
+    }
+}
+
+#[async_trait]
+impl BalanceHistoryProvider for PostgresProvider {
+    async fn get_balance_at(&self, account: &Account, at: NaiveDateTime) -> MoneyCalcResult<Money> {
+        let client = self.client().await?;
+        balance_at(&client, account.id, at).await
+    }
+
+    async fn get_daily_balances(
+        &self,
+        account: &Account,
+        from_date: NaiveDate,
+        to_date: NaiveDate,
+    ) -> MoneyCalcResult<Vec<DailyBalance>> {
+        if from_date > to_date {
+            return Err(MoneyCalcError::Validation(format!(
+                "balance history from {} is after {}",
+                from_date, to_date
+            )));
+        }
+        let client = self.client().await?;
+        let opening = match from_date.pred_opt() {
+            Some(previous) => balance_at(&client, account.id, end_of_day(previous)).await?,
+            None => balance_at(&client, account.id, NaiveDateTime::MIN).await?,
+        };
+        let changes = client
+            .query(
+                &format!(
+                    "Select t.CreationDate::date, sum({LEDGER_AMOUNT}) from Transactions t where t.AccountId = $1 and t.CreationDate >= $2 and t.CreationDate <= $3 group by 1 order by 1"
+                ),
+                &[
+                    &account.id,
+                    &from_date.and_time(NaiveTime::MIN),
+                    &end_of_day(to_date),
+                ],
+            )
+            .await?
+            .iter()
+            .map(|row| {
+                Ok((
+                    row.try_get::<_, NaiveDate>(0)?,
+                    from_decimal(row.try_get(1)?, opening.currency())?,
+                ))
+            })
+            .collect::<MoneyCalcResult<Vec<_>>>()?;
+
+        let mut changes = changes.into_iter().peekable();
+        let mut balance = opening;
+        let mut history = vec![];
+        for date in from_date.iter_days().take_while(|date| *date <= to_date) {
+            if let Some((_, change)) = changes.next_if(|(day, _)| *day == date) {
+                balance = balance.checked_add(change)?;
+            }
+            history.push(DailyBalance { date, balance });
+        }
+        Ok(history)
+    }
+
+    async fn add_balance_snapshot(
+        &self,
+        account: &Account,
+        at: NaiveDateTime,
+    ) -> MoneyCalcResult<BalanceSnapshot> {
+        let mut client = self.client().await?;
+        let connection = client.transaction().await?;
+        let snapshot = BalanceSnapshot {
+            account_id: account.id,
+            date: at,
+            balance: balance_at(&connection, account.id, at).await?,
+        };
+        connection
+            .execute(
+                "Insert into BalanceSnapshots(AccountId, SnapshotDate, Balance) Values ($1, $2, $3) on conflict (AccountId, SnapshotDate) do update set Balance = excluded.Balance",
+                &[&account.id, &at, &to_decimal(snapshot.balance)],
+            )
+            .await?;
+        self.auditor
+            .record(
+                &connection,
+                "add_balance_snapshot",
+                "balance_snapshot",
+                account.id,
+                None,
+                Some(&snapshot),
+            )
+            .await?;
+        connection.commit().await?;
+        Ok(snapshot)
+    }
+}
+
+#[async_trait]
+impl AuditProvider for PostgresProvider {
+    async fn get_audit_log(&self, query: &AuditQuery) -> MoneyCalcResult<Vec<AuditEntry>> {
+        if query.limit == 0 {
+            return Err(MoneyCalcError::Validation(
+                "audit query limit must be positive".to_string(),
+            ));
+        }
+        let mut conditions: Vec<String> = vec![];
+        let mut params: Vec<SqlParam> = vec![];
+        let mut push = |condition: &str, value: SqlParam| {
+            params.push(value);
+            conditions.push(condition.replace('?', &format!("${}", params.len())));
+        };
+        let text_filters = [
+            ("Actor = ?", &query.actor),
+            ("Action = ?", &query.action),
+            ("Entity = ?", &query.entity),
+            ("EntityId = ?", &query.entity_id),
+        ];
+        for (condition, value) in text_filters {
+            if let Some(value) = value {
+                push(condition, Box::new(value.clone()));
+            }
+        }
+        if let Some(from_date) = query.from_date {
+            push("CreationDate >= ?", Box::new(from_date));
+        }
+        if let Some(to_date) = query.to_date {
+            push("CreationDate < ?", Box::new(to_date));
+        }
+        if let Some(after_id) = query.after_id {
+            push("Id > ?", Box::new(after_id));
+        }
+        let mut sql = format!("Select {} from AuditLog", AUDIT_COLUMNS);
+        if !conditions.is_empty() {
+            sql.push_str(" where ");
+            sql.push_str(&conditions.join(" and "));
+        }
+        sql.push_str(&format!(" order by Id limit {}", query.limit));
+
+        let client = self.client().await?;
+        client
+            .query(&sql, &param_refs(&params))
+            .await?
+            .iter()
+            .map(audit_entry_from_row)
+            .collect()
+    }
+
+    async fn verify_audit_log(&self) -> MoneyCalcResult<Option<i64>> {
+        let client = self.client().await?;
+        let rows = client
+            .query(
+                "Select Id, Actor, Action, Entity, EntityId, Before, After, CreationDate, Hash from AuditLog order by Id",
+                &[],
+            )
+            .await?;
+        let mut previous: Option<String> = None;
+        for row in rows {
+            let Some(hash) = row.try_get::<_, Option<String>>(8)? else {
+                if previous.is_some() {
+                    return Ok(Some(row.try_get(0)?));
+                }
+                continue;
+            };
+            let fields: Vec<Option<String>> = (1..7)
+                .map(|index| row.try_get(index))
+                .collect::<Result<_, _>>()?;
+            let create_date = row
+                .try_get::<_, NaiveDateTime>(7)?
+                .format(AUDIT_DATE_FORMAT)
+                .to_string();
+            let expected = audit_hash([
+                previous.as_deref(),
+                fields[0].as_deref(),
+                fields[1].as_deref(),
+                fields[2].as_deref(),
+                fields[3].as_deref(),
+                fields[4].as_deref(),
+                fields[5].as_deref(),
+                Some(&create_date),
+            ]);
+            if hash != expected {
+                return Ok(Some(row.try_get(0)?));
+            }
+            previous = Some(hash);
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+
+    use crate::{
+        commands::{
+            accounts::addaccountcommand::AddAccountCommand, audit::auditquery::AuditQuery,
+            budgets::addbudgetcommand::AddBudgetCommand,
+            categories::addcategorycommand::AddCategoryCommand,
+            recurring::addrecurringcommand::AddRecurringCommand,
+            users::addusercommand::AddUserCommand,
+        },
+        config::PostgresConfiguration,
+        errors::MoneyCalcError,
+        models::{
+            account::{Account, OverdraftPolicy},
+            budget::{BudgetPeriod, BudgetScope},
+            exchangerate::ExchangeRate,
+            money::{Currency, Money},
+            moneytransaction::{MoneyTransaction, PaymentType},
+            recurring::{GenerationReport, Schedule},
+            user::User,
+        },
+        providers::{
+            AccountProvider, AuditProvider, BalanceHistoryProvider, BudgetProvider,
+            CategoryProvider, ExchangeRateProvider, ReconciliationProvider, RecurringProvider,
+            TransactionWorker, UserProvider,
+            bases::{
+                migrations::postgresmigrations::MIGRATIONS_COLLECTION, postgres::PostgresProvider,
+            },
+        },
+    };
+
+    /// Settings for a new schema in database from MONEYCALC_POSTGRES_URL.
+    /// Tests pass without checks when it is not set.
+    fn test_config() -> Option<PostgresConfiguration> {
+        let url = std::env::var("MONEYCALC_POSTGRES_URL").ok()?;
+        Some(
+            PostgresConfiguration::new(&url)
+                .with_schema(&format!("test_{}", uuid::Uuid::new_v4().simple())),
+        )
+    }
+
+    async fn provider_with_account(
+        config: &PostgresConfiguration,
+        initial_balance: Money,
+    ) -> (PostgresProvider, User, Account) {
+        let provider = PostgresProvider::new(config, true).unwrap();
+        let number = uuid::Uuid::new_v4().to_string();
+        provider
+            .add_user(&AddUserCommand {
+                user_name: String::from_str("scam").unwrap(),
+                user_number: number.clone(),
+            })
+            .await
+            .unwrap();
+        let user = provider.get_user_by_number(&number).await.unwrap();
+        provider
+            .add_account(&create_add_account_command(user.id, initial_balance))
+            .await
+            .unwrap();
+        let account = provider.search_account_by_user(&user).await.unwrap();
+        (provider, user, account)
+    }
+
+    #[test]
+    fn invalid_config_test() {
+        let config = PostgresConfiguration::new("host=localhost user=postgres");
+        assert!(PostgresProvider::new(&config, true).is_ok());
+        assert!(matches!(
+            PostgresProvider::new(&config.clone().with_pool_size(0), true),
+            Err(MoneyCalcError::Validation(_))
+        ));
+        assert!(matches!(
+            PostgresProvider::new(&config.clone().with_schema("public; drop"), true),
+            Err(MoneyCalcError::Validation(_))
+        ));
+        assert!(matches!(
+            PostgresProvider::new(&PostgresConfiguration::new("port=port"), true),
+            Err(MoneyCalcError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn numeric_money_columns_test() {
+        let Some(config) = test_config() else {
+            return;
+        };
+        let (provider, user, account) = provider_with_account(&config, rub("100.00")).await;
+        provider
+            .execute_transaction(&outcome(&user, &account, "25.55"))
+            .await
+            .unwrap();
+
+        let client = provider.client().await.unwrap();
+        let row = client
+            .query_one(
+                "Select MoneyCount, InitialBalance from Accounts where Id = $1",
+                &[&account.id],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            row.get::<_, Decimal>(0),
+            Decimal::from_str("74.45").unwrap()
+        );
+        assert_eq!(
+            row.get::<_, Decimal>(1),
+            Decimal::from_str("100.00").unwrap()
+        );
+        let column_type: String = client
+            .query_one(
+                "Select data_type from information_schema.columns where table_schema = current_schema() and table_name = 'transactions' and column_name = 'amount'",
+                &[],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(column_type, "numeric");
+        assert_eq!(
+            provider.get_account_by_id(account.id).await.unwrap().money,
+            rub("74.45")
+        );
+    }
+
+    #[tokio::test]
+    async fn migrations_idempotent_test() {
+        let Some(config) = test_config() else {
+            return;
+        };
+        let (provider, user, _) = provider_with_account(&config, rub("10.00")).await;
+        drop(provider);
+
+        // Schema is already at the latest version, data stays.
+        let provider = PostgresProvider::new(&config, true).unwrap();
+        let users = provider.get_users().await.unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].number, user.number);
+        let client = provider.client().await.unwrap();
+        let version: i32 = client
+            .query_one("Select Version from SchemaVersion", &[])
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(version as usize, MIGRATIONS_COLLECTION.len());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_debits_test() {
+        let Some(config) = test_config() else {
+            return;
+        };
+        let config = config.with_pool_size(4);
+        let (provider, user, account) = provider_with_account(&config, rub("100.00")).await;
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let provider = provider.clone();
+                let transaction = outcome(&user, &account, "30.00");
+                tokio::spawn(async move { provider.execute_transaction(&transaction).await })
+            })
+            .collect();
+        let mut executed = 0;
+        for handle in handles {
+            match handle.await.unwrap() {
+                Ok(_) => executed += 1,
+                Err(error) => assert!(matches!(error, MoneyCalcError::InsufficientFunds(_))),
+            }
+        }
+        assert_eq!(executed, 3);
+        assert_eq!(
+            provider.get_account_by_id(account.id).await.unwrap().money,
+            rub("10.00")
+        );
+        let report = provider.reconcile_balances(false).await.unwrap();
+        assert!(report.discrepancies.is_empty());
+    }
+
+    #[tokio::test]
+    async fn audit_log_test() {
+        let Some(config) = test_config() else {
+            return;
+        };
+        let config = config.with_audit_hash_chain(true);
+        let (provider, _, account) = provider_with_account(&config, rub("100.00")).await;
+        provider
+            .with_actor("bob")
+            .change_money(&account, rub("-30.00"))
+            .await
+            .unwrap();
+
+        let entries = provider
+            .get_audit_log(&AuditQuery {
+                entity: Some("account".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let actions: Vec<(&str, &str)> = entries
+            .iter()
+            .map(|entry| (entry.actor.as_str(), entry.action.as_str()))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![("system", "add_account"), ("bob", "change_money")]
+        );
+        assert_eq!(provider.verify_audit_log().await.unwrap(), None);
+
+        let client = provider.client().await.unwrap();
+        assert!(client.execute("Delete from AuditLog", &[]).await.is_err());
+        client
+            .batch_execute("Drop trigger audit_no_update on AuditLog")
+            .await
+            .unwrap();
+        client
+            .execute(
+                "Update AuditLog set Hash = null where Id = $1",
+                &[&entries[1].id],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            provider.verify_audit_log().await.unwrap(),
+            Some(entries[1].id)
+        );
+        client
+            .execute(
+                "Update AuditLog set Actor = 'alice' where Id = $1",
+                &[&entries[1].id],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            provider.verify_audit_log().await.unwrap(),
+            Some(entries[1].id)
+        );
+    }
+
+    #[tokio::test]
+    async fn exchange_rate_direction_test() {
+        let Some(config) = test_config() else {
+            return;
+        };
+        let provider = PostgresProvider::new(&config, true).unwrap();
+        let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        for (from, to, rate, day) in [
+            (Currency::USD, Currency::EUR, "0.9", date(2020, 1, 1)),
+            (Currency::EUR, Currency::USD, "1.25", date(2025, 3, 1)),
+            (Currency::USD, Currency::EUR, "0.75", date(2025, 3, 10)),
+        ] {
+            provider
+                .add_exchange_rate(&ExchangeRate::new(from, to, rate).unwrap(), day)
+                .await
+                .unwrap();
+        }
+        let rate = async |day| {
+            provider
+                .get_exchange_rate(Currency::USD, Currency::EUR, day)
+                .await
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(rate(date(2024, 12, 31)).await, "USD/EUR 0.9");
+        assert_eq!(rate(date(2025, 3, 2)).await, "USD/EUR 0.8");
+        assert_eq!(rate(date(2025, 3, 10)).await, "USD/EUR 0.75");
+    }
+
+    #[tokio::test]
+    async fn budgets_rates_and_history_test() {
+        let Some(config) = test_config() else {
+            return;
+        };
+        let (provider, user, account) = provider_with_account(&config, rub("1000.00")).await;
+        provider
+            .add_category(&AddCategoryCommand {
+                user_id: user.id,
+                name: "Food".to_string(),
+                parent_id: None,
+            })
+            .await
+            .unwrap();
+        let food = provider.get_categories(&user).await.unwrap().remove(0);
+        provider
+            .add_category(&AddCategoryCommand {
+                user_id: user.id,
+                name: "Snacks".to_string(),
+                parent_id: Some(food.id),
+            })
+            .await
+            .unwrap();
+        let snacks = provider.get_categories(&user).await.unwrap().remove(1);
+
+        let day = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+        for (date, amount, target, category_id) in [
+            (day(3, 2), "100.00", "Grocery 24", Some(snacks.id)),
+            (day(3, 15), "50.00", "GROCERY market", None),
+            (day(4, 1), "70.00", "Grocery 24", Some(food.id)),
+        ] {
+            provider
+                .execute_transaction(&MoneyTransaction {
+                    create_date: date.and_hms_opt(12, 0, 0).unwrap(),
+                    payment_target: target.to_string(),
+                    category_id,
+                    ..outcome(&user, &account, amount)
+                })
+                .await
+                .unwrap();
+        }
+        for (name, scope) in [
+            ("Food", BudgetScope::Category(food.id)),
+            (
+                "Groceries",
+                BudgetScope::PaymentTarget("grocery*".to_string()),
+            ),
+        ] {
+            provider
+                .add_budget(&AddBudgetCommand {
+                    user_id: user.id,
+                    name: name.to_string(),
+                    scope,
+                    period: BudgetPeriod::Monthly,
+                    limit: Money::parse("2.00", Currency::USD).unwrap(),
+                })
+                .await
+                .unwrap();
+        }
+        let rate = ExchangeRate::new(Currency::USD, Currency::RUB, "100").unwrap();
+        provider.add_exchange_rate(&rate, day(1, 1)).await.unwrap();
+        assert_eq!(
+            provider
+                .get_exchange_rate(Currency::RUB, Currency::USD, day(3, 1))
+                .await
+                .unwrap(),
+            rate.inverse().unwrap()
+        );
+        let progress = provider
+            .get_budgets_progress(&user, day(3, 10))
+            .await
+            .unwrap();
+        let spent: Vec<Money> = progress.iter().map(|p| p.spent).collect();
+        assert_eq!(
+            spent,
+            vec![
+                Money::parse("1.00", Currency::USD).unwrap(),
+                Money::parse("1.50", Currency::USD).unwrap()
+            ]
+        );
+
+        let history = provider
+            .get_daily_balances(&account, day(3, 1), day(3, 3))
+            .await
+            .unwrap();
+        let balances: Vec<Money> = history.iter().map(|daily| daily.balance).collect();
+        assert_eq!(balances, vec![rub("1000.00"), rub("900.00"), rub("900.00")]);
+        let snapshot = provider
+            .add_balance_snapshot(&account, day(3, 20).and_hms_opt(0, 0, 0).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(snapshot.balance, rub("850.00"));
+        assert_eq!(
+            provider
+                .get_balance_at(&account, day(4, 2).and_hms_opt(0, 0, 0).unwrap())
+                .await
+                .unwrap(),
+            rub("780.00")
+        );
+    }
+
+    #[tokio::test]
+    async fn recurring_test() {
+        let Some(config) = test_config() else {
+            return;
+        };
+        let (provider, user, account) = provider_with_account(&config, rub("1000.00")).await;
+        let day = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+        provider
+            .add_recurring(&AddRecurringCommand {
+                user_id: user.id,
+                account_id: account.id,
+                amount: rub("300.00"),
+                payment_type: PaymentType::Outcome,
+                payment_target: "Landlord".to_string(),
+                description: "Rent".to_string(),
+                category_id: None,
+                schedule: Schedule::MonthlyOnDay(5),
+                start_date: day(1, 1),
+                end_date: None,
+            })
+            .await
+            .unwrap();
+
+        let now = day(3, 10).and_hms_opt(12, 0, 0).unwrap();
+        let report = provider.generate_due(now).await.unwrap();
+        assert!(report.failures.is_empty());
+        let dates: Vec<NaiveDate> = report.occurrences.iter().map(|o| o.date).collect();
+        assert_eq!(dates, vec![day(1, 5), day(2, 5), day(3, 5)]);
+        assert_eq!(
+            provider.generate_due(now).await.unwrap(),
+            GenerationReport::default()
+        );
+        assert_eq!(
+            provider.get_account_by_id(account.id).await.unwrap().money,
+            rub("100.00")
+        );
+        let recurring = provider.get_recurring(&user).await.unwrap().remove(0);
+        assert_eq!(recurring.schedule, Schedule::MonthlyOnDay(5));
+        assert_eq!(provider.get_occurrences(&recurring).await.unwrap().len(), 3);
+    }
+
+    fn outcome(user: &User, account: &Account, amount: &str) -> MoneyTransaction {
+        MoneyTransaction {
+            description: "Purchase".to_string(),
+            amount: rub(amount),
+            user: user.clone(),
+            account: account.clone(),
+            payment_type: PaymentType::Outcome,
+            payment_target: "Shop".to_string(),
+            id: "".to_string(),
+            create_date: chrono::Utc::now().naive_utc(),
+            linked_transaction_id: None,
+            correction_of: None,
+            category_id: None,
+        }
+    }
+
+    fn rub(amount: &str) -> Money {
+        Money::parse(amount, Currency::RUB).unwrap()
+    }
+
+    fn create_add_account_command(user_id: i32, initial_balance: Money) -> AddAccountCommand {
+        AddAccountCommand {
+            user_id,
+            account_name: String::from_str("TEST ACCOUNT").unwrap(),
+            initial_balance,
+            overdraft: OverdraftPolicy::default(),
+        }
+    }
+}
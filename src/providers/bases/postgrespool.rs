@@ -0,0 +1,89 @@
+use std::{str::FromStr, sync::Arc};
+
+use deadpool_postgres::{Client, Manager, Pool, PoolError};
+use tokio::sync::OnceCell;
+use tokio_postgres::NoTls;
+
+use crate::{
+    config::PostgresConfiguration,
+    errors::{MoneyCalcError, MoneyCalcResult},
+    providers::bases::migrations::postgresmigrations,
+};
+
+impl From<PoolError> for MoneyCalcError {
+    fn from(value: PoolError) -> Self {
+        match value {
+            PoolError::Backend(error) => error.into(),
+            PoolError::Timeout(_) => MoneyCalcError::Busy(value.to_string()),
+            error => MoneyCalcError::Storage(Box::new(error)),
+        }
+    }
+}
+
+/// PostgreSQL connections opened on demand up to pool size, without TLS.
+/// Migrations are applied when the first connection is taken,
+/// so pool is opened without runtime and connection errors come from the first call.
+/// Clones share connections.
+#[derive(Clone)]
+pub struct ConnectionPool {
+    pool: Pool,
+    schema: Option<String>,
+    migrated: Arc<OnceCell<()>>,
+    apply_migrations: bool,
+}
+
+impl ConnectionPool {
+    /// Check configuration and prepare pool, no connection is opened yet.
+    /// Connections of pool use schema of configuration as search path.
+    pub fn open(config: &PostgresConfiguration, apply_migrations: bool) -> MoneyCalcResult<Self> {
+        if config.pool_size == 0 {
+            return Err(MoneyCalcError::Validation(
+                "connection pool size must be positive".to_string(),
+            ));
+        }
+        let mut pg_config = tokio_postgres::Config::from_str(&config.connection_string)
+            .map_err(|e| MoneyCalcError::Validation(format!("invalid connection string: {}", e)))?;
+        if let Some(schema) = &config.schema {
+            let plain = schema.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && schema
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !plain {
+                return Err(MoneyCalcError::Validation(format!(
+                    "schema {} must be a plain identifier",
+                    schema
+                )));
+            }
+            let options = match pg_config.get_options() {
+                Some(options) => format!("{} -c search_path={}", options, schema),
+                None => format!("-c search_path={}", schema),
+            };
+            pg_config.options(&options);
+        }
+        let pool = Pool::builder(Manager::new(pg_config, NoTls))
+            .max_size(config.pool_size)
+            .build()
+            .map_err(|e| MoneyCalcError::Storage(Box::new(e)))?;
+
+        Ok(Self {
+            pool,
+            schema: config.schema.clone(),
+            migrated: Arc::new(OnceCell::new()),
+            apply_migrations,
+        })
+    }
+
+    /// Take connection, waits while all connections are taken.
+    /// Migrations are applied on the first one.
+    pub async fn get(&self) -> MoneyCalcResult<Client> {
+        let mut client = self.pool.get().await?;
+        if self.apply_migrations {
+            self.migrated
+                .get_or_try_init(|| {
+                    postgresmigrations::to_latest(&mut client, self.schema.as_deref())
+                })
+                .await?;
+        }
+        Ok(client)
+    }
+}
@@ -0,0 +1,350 @@
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use tokio_postgres::{Row, types::ToSql};
+
+use crate::{
+    commands::transactions::transactionquery::{TransactionOrder, TransactionQuery},
+    errors::{MoneyCalcError, MoneyCalcResult},
+    models::{
+        account::{Account, OverdraftPolicy},
+        auditentry::AuditEntry,
+        budget::{Budget, BudgetPeriod, BudgetScope},
+        category::Category,
+        exchangerate::{ExchangeRate, RATE_SCALE},
+        money::{Currency, Money},
+        moneytransaction::{MoneyTransaction, PaymentType},
+        recurring::{RecurringTransaction, Schedule},
+        tag::Tag,
+        user::User,
+    },
+    providers::bases::{escape_like, in_subcategories},
+};
+
+pub fn payment_type_to_sql(payment_type: PaymentType) -> i32 {
+    match payment_type {
+        PaymentType::Income => 1,
+        PaymentType::Outcome => 2,
+        PaymentType::Transfer => 3,
+        PaymentType::Reversal => 4,
+        _ => 0,
+    }
+}
+
+fn payment_type_from_sql(value: i32) -> PaymentType {
+    match value {
+        1 => PaymentType::Income,
+        2 => PaymentType::Outcome,
+        3 => PaymentType::Transfer,
+        4 => PaymentType::Reversal,
+        _ => PaymentType::None,
+    }
+}
+
+/// Money is kept as NUMERIC in major units with scale of its currency, like 25.50 RUB.
+pub fn to_decimal(money: Money) -> Decimal {
+    Decimal::new(money.minor_units(), money.currency().minor_digits())
+}
+
+pub fn from_decimal(value: Decimal, currency: Currency) -> MoneyCalcResult<Money> {
+    let mut value = value;
+    value.rescale(currency.minor_digits());
+    let minor_units = i64::try_from(value.mantissa())
+        .map_err(|_| MoneyCalcError::Storage(format!("amount {} is out of range", value).into()))?;
+    Ok(Money::from_minor(minor_units, currency))
+}
+
+pub fn currency_from_row(row: &Row, index: usize) -> MoneyCalcResult<Currency> {
+    Ok(Currency::new(row.try_get(index)?)?)
+}
+
+pub fn money_from_row(row: &Row, amount: usize, currency: usize) -> MoneyCalcResult<Money> {
+    from_decimal(row.try_get(amount)?, currency_from_row(row, currency)?)
+}
+
+/// Overdraft is kept as limit in account currency.
+/// NULL means unlimited overdraft, 0 forbids it.
+pub fn overdraft_to_sql(policy: &OverdraftPolicy) -> Option<Decimal> {
+    match policy {
+        OverdraftPolicy::Forbid => Some(Decimal::ZERO),
+        OverdraftPolicy::Limit(limit) => Some(to_decimal(*limit)),
+        OverdraftPolicy::Unlimited => None,
+    }
+}
+
+pub fn overdraft_from_sql(
+    limit: Option<Decimal>,
+    currency: Currency,
+) -> MoneyCalcResult<OverdraftPolicy> {
+    Ok(match limit {
+        None => OverdraftPolicy::Unlimited,
+        Some(limit) if limit.is_zero() => OverdraftPolicy::Forbid,
+        Some(limit) => OverdraftPolicy::Limit(from_decimal(limit, currency)?),
+    })
+}
+
+/// Exchange rate is kept as NUMERIC with RATE_SCALE digits.
+pub fn rate_from_sql(from: Currency, to: Currency, rate: Decimal) -> MoneyCalcResult<ExchangeRate> {
+    let mut rate = rate;
+    rate.rescale(RATE_SCALE);
+    let rate_units = i64::try_from(rate.mantissa())
+        .map_err(|_| MoneyCalcError::Storage(format!("rate {} is out of range", rate).into()))?;
+    Ok(ExchangeRate::from_units(from, to, rate_units)?)
+}
+
+fn u32_from_sql(value: i64) -> MoneyCalcResult<u32> {
+    u32::try_from(value)
+        .map_err(|_| MoneyCalcError::Storage(format!("value {} is out of range", value).into()))
+}
+
+pub const USER_COLUMNS: &str = "Id, Name, Number, CreationDate";
+
+/// User from USER_COLUMNS starting at column first.
+pub fn user_from_row(row: &Row, first: usize) -> MoneyCalcResult<User> {
+    Ok(User {
+        id: row.try_get(first)?,
+        name: row.try_get(first + 1)?,
+        number: row.try_get(first + 2)?,
+        creation_date: row.try_get(first + 3)?,
+    })
+}
+
+pub const ACCOUNT_COLUMNS: &str =
+    "Id, UserId, Name, MoneyCount, Currency, CreationDate, IsPrimary, OverdraftLimit";
+
+/// Account from ACCOUNT_COLUMNS starting at column first.
+pub fn account_from_row(row: &Row, first: usize) -> MoneyCalcResult<Account> {
+    let currency = currency_from_row(row, first + 4)?;
+    Ok(Account::from_exist(
+        row.try_get(first)?,
+        row.try_get(first + 1)?,
+        row.try_get(first + 2)?,
+        from_decimal(row.try_get(first + 3)?, currency)?,
+        row.try_get(first + 5)?,
+        row.try_get(first + 6)?,
+        overdraft_from_sql(row.try_get(first + 7)?, currency)?,
+    ))
+}
+
+pub const TRANSACTION_SELECT: &str = "Select t.Id, t.Amount, t.Currency, t.Description, t.PaymentType, t.PaymentTarget, t.CreationDate, t.LinkedTransactionId, t.CategoryId, t.CorrectionOf, u.Id, u.Name, u.Number, u.CreationDate, a.Id, a.UserId, a.Name, a.MoneyCount, a.Currency, a.CreationDate, a.IsPrimary, a.OverdraftLimit from Transactions t join Users u on u.Id = t.UserId join Accounts a on a.Id = t.AccountId";
+
+pub fn transaction_from_row(row: &Row) -> MoneyCalcResult<MoneyTransaction> {
+    Ok(MoneyTransaction {
+        id: row.try_get(0)?,
+        amount: money_from_row(row, 1, 2)?,
+        description: row.try_get::<_, Option<String>>(3)?.unwrap_or_default(),
+        payment_type: payment_type_from_sql(row.try_get(4)?),
+        payment_target: row.try_get::<_, Option<String>>(5)?.unwrap_or_default(),
+        create_date: row.try_get(6)?,
+        linked_transaction_id: row.try_get(7)?,
+        category_id: row.try_get(8)?,
+        correction_of: row.try_get(9)?,
+        user: user_from_row(row, 10)?,
+        account: account_from_row(row, 14)?,
+    })
+}
+
+/// Absolute amount of transaction row `t` in minor units, key of amount order.
+/// Amounts are stored with scale of their currency, so scale gives count of minor digits.
+const MINOR_AMOUNT: &str = "abs(t.Amount) * power(10::numeric, scale(t.Amount))";
+
+pub type SqlParam = Box<dyn ToSql + Sync + Send>;
+
+pub fn param_refs(params: &[SqlParam]) -> Vec<&(dyn ToSql + Sync)> {
+    params
+        .iter()
+        .map(|param| param.as_ref() as &(dyn ToSql + Sync))
+        .collect()
+}
+
+/// Build where clause, order and parameters for transactions query.
+/// Pages are read by keyset: cursor keeps sort key and id of last returned row.
+pub fn build_transaction_query(
+    query: &TransactionQuery,
+) -> MoneyCalcResult<(String, Vec<SqlParam>)> {
+    let mut conditions: Vec<String> = vec![];
+    let mut params: Vec<SqlParam> = vec![];
+    let mut push = |condition: &str, value: SqlParam| {
+        params.push(value);
+        conditions.push(condition.replace('?', &format!("${}", params.len())));
+    };
+
+    if let Some(account_id) = query.account_id {
+        push("t.AccountId = ?", Box::new(account_id));
+    }
+    if let Some(user_id) = query.user_id {
+        push("t.UserId = ?", Box::new(user_id));
+    }
+    if let Some(from_date) = query.from_date {
+        push("t.CreationDate >= ?", Box::new(from_date));
+    }
+    if let Some(to_date) = query.to_date {
+        push("t.CreationDate < ?", Box::new(to_date));
+    }
+    if let Some(payment_type) = query.payment_type {
+        push(
+            "t.PaymentType = ?",
+            Box::new(payment_type_to_sql(payment_type)),
+        );
+    }
+    if let Some(payment_target) = &query.payment_target {
+        push("t.PaymentTarget = ?", Box::new(payment_target.clone()));
+    }
+    for (bound, condition) in [
+        (&query.min_amount, "abs(t.Amount) >= ?"),
+        (&query.max_amount, "abs(t.Amount) <= ?"),
+    ] {
+        if let Some(bound) = bound {
+            push(condition, Box::new(to_decimal(*bound)));
+            push(
+                "t.Currency = ?",
+                Box::new(bound.currency().code().to_string()),
+            );
+        }
+    }
+    if let Some(category_id) = query.category_id {
+        push(
+            &in_subcategories("t.CategoryId", "?::integer"),
+            Box::new(category_id),
+        );
+    }
+    if let Some(text) = &query.description_contains {
+        push(
+            "t.Description ilike ? escape '\\'",
+            Box::new(format!("%{}%", escape_like(text))),
+        );
+    }
+
+    let (key, direction) = match query.order {
+        TransactionOrder::DateAsc => ("t.CreationDate", "asc"),
+        TransactionOrder::DateDesc => ("t.CreationDate", "desc"),
+        TransactionOrder::AmountAsc => (MINOR_AMOUNT, "asc"),
+        TransactionOrder::AmountDesc => (MINOR_AMOUNT, "desc"),
+    };
+    if let Some(cursor) = &query.cursor {
+        let invalid = || MoneyCalcError::Validation(format!("invalid cursor: {}", cursor));
+        let (last_key, last_id) = cursor.rsplit_once('|').ok_or_else(invalid)?;
+        let last_key: SqlParam = match query.order {
+            TransactionOrder::DateAsc | TransactionOrder::DateDesc => Box::new(
+                NaiveDateTime::parse_from_str(last_key, "%F %T%.f").map_err(|_| invalid())?,
+            ),
+            TransactionOrder::AmountAsc | TransactionOrder::AmountDesc => Box::new(Decimal::from(
+                last_key.parse::<i64>().map_err(|_| invalid())?,
+            )),
+        };
+        let compare = if direction == "asc" { ">" } else { "<" };
+        params.push(last_key);
+        params.push(Box::new(last_id.to_string()));
+        conditions.push(format!(
+            "({key} {compare} ${k} or ({key} = ${k} and t.Id {compare} ${i}))",
+            key = key,
+            compare = compare,
+            k = params.len() - 1,
+            i = params.len(),
+        ));
+    }
+
+    let mut sql = TRANSACTION_SELECT.to_string();
+    if !conditions.is_empty() {
+        sql.push_str(" where ");
+        sql.push_str(&conditions.join(" and "));
+    }
+    sql.push_str(&format!(
+        " order by {key} {direction}, t.Id {direction} limit {}",
+        query.limit as i64 + 1
+    ));
+    Ok((sql, params))
+}
+
+pub const AUDIT_COLUMNS: &str =
+    "Id, Actor, Action, Entity, EntityId, Before, After, CreationDate, Hash";
+
+pub fn audit_entry_from_row(row: &Row) -> MoneyCalcResult<AuditEntry> {
+    let json = |index: usize| -> MoneyCalcResult<Option<serde_json::Value>> {
+        Ok(row
+            .try_get::<_, Option<&str>>(index)?
+            .map(serde_json::from_str)
+            .transpose()?)
+    };
+    Ok(AuditEntry {
+        id: row.try_get(0)?,
+        actor: row.try_get(1)?,
+        action: row.try_get(2)?,
+        entity: row.try_get(3)?,
+        entity_id: row.try_get(4)?,
+        before: json(5)?,
+        after: json(6)?,
+        create_date: row.try_get(7)?,
+        hash: row.try_get(8)?,
+    })
+}
+
+pub const CATEGORY_COLUMNS: &str = "Id, UserId, ParentId, Name";
+
+pub fn category_from_row(row: &Row) -> MoneyCalcResult<Category> {
+    Ok(Category {
+        id: row.try_get(0)?,
+        user_id: row.try_get(1)?,
+        parent_id: row.try_get(2)?,
+        name: row.try_get(3)?,
+    })
+}
+
+pub const TAG_COLUMNS: &str = "Id, UserId, Name";
+
+pub fn tag_from_row(row: &Row) -> MoneyCalcResult<Tag> {
+    Ok(Tag {
+        id: row.try_get(0)?,
+        user_id: row.try_get(1)?,
+        name: row.try_get(2)?,
+    })
+}
+
+pub const BUDGET_COLUMNS: &str = "Id, UserId, Name, AccountId, PaymentTargetPattern, CategoryId, PeriodKind, PeriodStart, PeriodDays, LimitAmount, Currency";
+
+pub fn budget_from_row(row: &Row) -> MoneyCalcResult<Budget> {
+    let scope = match (row.try_get(3)?, row.try_get(5)?) {
+        (Some(account_id), _) => BudgetScope::Account(account_id),
+        (None, Some(category_id)) => BudgetScope::Category(category_id),
+        (None, None) => BudgetScope::PaymentTarget(row.try_get(4)?),
+    };
+    let period = match row.try_get::<_, i64>(6)? {
+        2 => BudgetPeriod::Weekly,
+        3 => BudgetPeriod::Custom {
+            start: row.try_get(7)?,
+            days: u32_from_sql(row.try_get(8)?)?,
+        },
+        _ => BudgetPeriod::Monthly,
+    };
+    Ok(Budget {
+        id: row.try_get(0)?,
+        user_id: row.try_get(1)?,
+        name: row.try_get(2)?,
+        scope,
+        period,
+        limit: money_from_row(row, 9, 10)?,
+    })
+}
+
+pub const RECURRING_COLUMNS: &str = "Id, UserId, AccountId, Amount, Currency, PaymentType, PaymentTarget, Description, CategoryId, ScheduleKind, ScheduleValue, StartDate, EndDate";
+
+pub fn recurring_from_row(row: &Row) -> MoneyCalcResult<RecurringTransaction> {
+    let value = || -> MoneyCalcResult<u32> { u32_from_sql(row.try_get(10)?) };
+    let schedule = match row.try_get::<_, i64>(9)? {
+        2 => Schedule::EveryWeeks(value()?),
+        3 => Schedule::LastBusinessDay,
+        _ => Schedule::MonthlyOnDay(value()?),
+    };
+    Ok(RecurringTransaction {
+        id: row.try_get(0)?,
+        user_id: row.try_get(1)?,
+        account_id: row.try_get(2)?,
+        amount: money_from_row(row, 3, 4)?,
+        payment_type: payment_type_from_sql(row.try_get(5)?),
+        payment_target: row.try_get(6)?,
+        description: row.try_get(7)?,
+        category_id: row.try_get(8)?,
+        schedule,
+        start_date: row.try_get(11)?,
+        end_date: row.try_get(12)?,
+    })
+}
@@ -1,23 +1,60 @@
-use core::error;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 use crate::{
     commands::{
-        accounts::addaccountcommand::AddAccountCommand, users::addusercommand::AddUserCommand,
+        accounts::addaccountcommand::AddAccountCommand,
+        audit::auditquery::AuditQuery,
+        budgets::addbudgetcommand::AddBudgetCommand,
+        categories::addcategorycommand::AddCategoryCommand,
+        recurring::addrecurringcommand::AddRecurringCommand,
+        tags::addtagcommand::AddTagCommand,
+        transactions::{
+            amendtransactioncommand::AmendTransactionCommand,
+            reversetransactioncommand::ReverseTransactionCommand,
+            transactionquery::{TransactionOrder, TransactionQuery},
+            transfercommand::TransferCommand,
+        },
+        users::addusercommand::AddUserCommand,
     },
     config::SqliteConfiguration,
+    errors::{MoneyCalcError, MoneyCalcResult},
     models::{
-        account::Account,
+        account::{Account, OverdraftPolicy},
+        auditentry::AuditEntry,
+        balance::{BalanceSnapshot, DailyBalance},
+        budget::{Budget, BudgetPeriod, BudgetProgress, BudgetScope},
+        category::Category,
+        exchangerate::ExchangeRate,
+        importedtransaction::ImportedTransaction,
+        money::{Currency, Money},
         moneytransaction::{MoneyTransaction, PaymentType},
+        reconciliation::{BalanceDiscrepancy, ReconciliationReport},
+        recurring::{
+            GenerationReport, RecurringFailure, RecurringOccurrence, RecurringTransaction, Schedule,
+        },
+        tag::Tag,
+        transactionpage::TransactionPage,
         user::User,
     },
     providers::{
-        AccountProvider, TransactionWorker, UserProvider,
-        bases::migrations::sqlitemigrations::MIGRATIONS,
+        AccountProvider, AuditProvider, BalanceHistoryProvider, BudgetProvider, CategoryProvider,
+        ExchangeRateProvider, ReconciliationProvider, RecurringProvider, TagProvider,
+        TransactionWorker, UserProvider,
+        bases::{
+            AUDIT_DATE_FORMAT, LEDGER_AMOUNT, audit_hash, budget_to_sql, check_executable,
+            end_of_day, escape_like, in_subcategories, migrations::sqlitemigrations::MIGRATIONS,
+            schedule_to_sql, sqlitepool::ConnectionPool, transaction_cursor, validate_overdraft,
+        },
     },
 };
 use async_trait::async_trait;
-use rusqlite::{Connection, Error, ToSql, params, types::ToSqlOutput};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use rusqlite::{
+    Connection, OptionalExtension, Row, ToSql, Transaction, TransactionBehavior, params,
+    params_from_iter,
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
+};
+use serde::Serialize;
 use uuid::Uuid;
 
 impl ToSql for PaymentType {
@@ -25,293 +62,4252 @@ impl ToSql for PaymentType {
         let val = match self {
             PaymentType::Income => 1,
             PaymentType::Outcome => 2,
+            PaymentType::Transfer => 3,
+            PaymentType::Reversal => 4,
             _ => 0,
         };
         Ok(ToSqlOutput::from(val))
     }
 }
-#[derive(Debug)]
-pub struct SqliteProvider {
-    connection: Arc<Mutex<Connection>>,
-    config: SqliteConfiguration,
+
+impl FromSql for PaymentType {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        Ok(match value.as_i64()? {
+            1 => PaymentType::Income,
+            2 => PaymentType::Outcome,
+            3 => PaymentType::Transfer,
+            4 => PaymentType::Reversal,
+            _ => PaymentType::None,
+        })
+    }
 }
 
-impl Clone for SqliteProvider {
-    fn clone(&self) -> Self {
-        let connect: Connection;
-        if self.config.memory_base {
-            connect = Connection::open_in_memory().unwrap();
-        } else {
-            connect = Connection::open(self.config.connection_string.as_str()).unwrap();
+impl ToSql for Currency {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.code()))
+    }
+}
+
+impl FromSql for Currency {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        Currency::new(value.as_str()?).map_err(|e| FromSqlError::Other(Box::new(e)))
+    }
+}
+
+impl From<rusqlite::Error> for MoneyCalcError {
+    fn from(value: rusqlite::Error) -> Self {
+        use rusqlite::{Error, ErrorCode, ffi};
+
+        match &value {
+            Error::QueryReturnedNoRows => MoneyCalcError::NotFound("record".to_string()),
+            Error::SqliteFailure(failure, message) => {
+                let message = message.clone().unwrap_or_else(|| failure.to_string());
+                match failure.code {
+                    ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked => {
+                        MoneyCalcError::Busy(message)
+                    }
+                    ErrorCode::ConstraintViolation => match failure.extended_code {
+                        ffi::SQLITE_CONSTRAINT_NOTNULL
+                        | ffi::SQLITE_CONSTRAINT_CHECK
+                        | ffi::SQLITE_CONSTRAINT_FOREIGNKEY => MoneyCalcError::Validation(message),
+                        _ => MoneyCalcError::Conflict(message),
+                    },
+                    _ => MoneyCalcError::Storage(Box::new(value)),
+                }
+            }
+            _ => MoneyCalcError::Storage(Box::new(value)),
         }
+    }
+}
+
+impl From<rusqlite_migration::Error> for MoneyCalcError {
+    fn from(value: rusqlite_migration::Error) -> Self {
+        MoneyCalcError::Migration(Box::new(value))
+    }
+}
 
-        Self {
-            connection: Arc::new(Mutex::new(connect)),
-            config: self.config.clone(),
+/// Map missing row to NotFound with description of the requested record.
+fn not_found(what: impl Into<String>) -> impl FnOnce(rusqlite::Error) -> MoneyCalcError {
+    move |error| match error {
+        rusqlite::Error::QueryReturnedNoRows => MoneyCalcError::NotFound(what.into()),
+        error => error.into(),
+    }
+}
+
+/// Map foreign key failure of delete to Conflict, the record is still referenced by others.
+fn still_referenced(what: impl Into<String>) -> impl FnOnce(rusqlite::Error) -> MoneyCalcError {
+    move |error| match &error {
+        rusqlite::Error::SqliteFailure(failure, _)
+            if failure.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_FOREIGNKEY =>
+        {
+            MoneyCalcError::Conflict(format!("{} is still referenced", what.into()))
         }
+        _ => error.into(),
     }
 }
 
-impl SqliteProvider {
-    pub fn new(
-        config: &SqliteConfiguration,
-        apply_migrations: bool,
-    ) -> Result<Self, Box<dyn std::error::Error>> {
-        let connect: Connection;
-        if config.memory_base {
-            connect = Connection::open_in_memory()?;
-        } else {
-            connect = Connection::open(config.connection_string.as_str())?;
+const USER_COLUMNS: &str = "Id, Name, Number, CreationDate";
+
+fn user_from_row(row: &Row<'_>) -> rusqlite::Result<User> {
+    Ok(User::new(
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get(3)?,
+    ))
+}
+
+/// Overdraft is kept as limit in minor units of account currency.
+/// NULL means unlimited overdraft, 0 forbids it.
+fn overdraft_to_sql(policy: &OverdraftPolicy) -> Option<i64> {
+    match policy {
+        OverdraftPolicy::Forbid => Some(0),
+        OverdraftPolicy::Limit(limit) => Some(limit.minor_units()),
+        OverdraftPolicy::Unlimited => None,
+    }
+}
+
+fn overdraft_from_sql(limit: Option<i64>, currency: Currency) -> OverdraftPolicy {
+    match limit {
+        None => OverdraftPolicy::Unlimited,
+        Some(0) => OverdraftPolicy::Forbid,
+        Some(limit) => OverdraftPolicy::Limit(Money::from_minor(limit, currency)),
+    }
+}
+
+const ACCOUNT_COLUMNS: &str =
+    "Id, UserId, Name, MoneyCount, Currency, CreationDate, IsPrimary, OverdraftLimit";
+
+fn account_from_row(row: &Row<'_>) -> rusqlite::Result<Account> {
+    Ok(Account::from_exist(
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        Money::from_minor(row.get(3)?, row.get(4)?),
+        row.get(5)?,
+        row.get(6)?,
+        overdraft_from_sql(row.get(7)?, row.get(4)?),
+    ))
+}
+
+const TRANSACTION_SELECT: &str = "Select t.Id, t.Amount, t.Currency, t.Description, t.PaymentType, t.PaymentTarget, t.CreationDate, t.LinkedTransactionId, u.Id, u.Name, u.Number, u.CreationDate, a.Id, a.UserId, a.Name, a.MoneyCount, a.Currency, a.CreationDate, a.IsPrimary, a.OverdraftLimit, t.CategoryId, t.CorrectionOf from Transactions t join Users u on u.Id = t.UserId join Accounts a on a.Id = t.AccountId";
+
+fn transaction_from_row(row: &Row<'_>) -> rusqlite::Result<MoneyTransaction> {
+    Ok(MoneyTransaction {
+        id: row.get(0)?,
+        amount: Money::from_minor(row.get(1)?, row.get(2)?),
+        description: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+        payment_type: row.get(4)?,
+        payment_target: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+        create_date: row.get(6)?,
+        linked_transaction_id: row.get(7)?,
+        category_id: row.get(20)?,
+        correction_of: row.get(21)?,
+        user: User::new(row.get(8)?, row.get(9)?, row.get(10)?, row.get(11)?),
+        account: Account::from_exist(
+            row.get(12)?,
+            row.get(13)?,
+            row.get(14)?,
+            Money::from_minor(row.get(15)?, row.get(16)?),
+            row.get(17)?,
+            row.get(18)?,
+            overdraft_from_sql(row.get(19)?, row.get(16)?),
+        ),
+    })
+}
+
+/// Build where clause, order and parameters for transactions query.
+/// Pages are read by keyset: cursor keeps sort key and id of last returned row.
+fn build_transaction_query(
+    query: &TransactionQuery,
+) -> MoneyCalcResult<(String, Vec<Box<dyn ToSql>>)> {
+    let mut conditions: Vec<String> = vec![];
+    let mut params: Vec<Box<dyn ToSql>> = vec![];
+    let mut push = |condition: &str, value: Box<dyn ToSql>| {
+        params.push(value);
+        conditions.push(condition.replace('?', &format!("?{}", params.len())));
+    };
+
+    if let Some(account_id) = query.account_id {
+        push("t.AccountId = ?", Box::new(account_id));
+    }
+    if let Some(user_id) = query.user_id {
+        push("t.UserId = ?", Box::new(user_id));
+    }
+    if let Some(from_date) = query.from_date {
+        push("t.CreationDate >= ?", Box::new(from_date));
+    }
+    if let Some(to_date) = query.to_date {
+        push("t.CreationDate < ?", Box::new(to_date));
+    }
+    if let Some(payment_type) = query.payment_type {
+        push("t.PaymentType = ?", Box::new(payment_type));
+    }
+    if let Some(payment_target) = &query.payment_target {
+        push("t.PaymentTarget = ?", Box::new(payment_target.clone()));
+    }
+    for (bound, condition) in [
+        (&query.min_amount, "abs(t.Amount) >= ?"),
+        (&query.max_amount, "abs(t.Amount) <= ?"),
+    ] {
+        if let Some(bound) = bound {
+            push(condition, Box::new(bound.minor_units()));
+            push("t.Currency = ?", Box::new(bound.currency()));
         }
+    }
+    if let Some(category_id) = query.category_id {
+        push(
+            &in_subcategories("t.CategoryId", "?"),
+            Box::new(category_id),
+        );
+    }
+    if let Some(text) = &query.description_contains {
+        push(
+            "t.Description like ? escape '\\'",
+            Box::new(format!("%{}%", escape_like(text))),
+        );
+    }
 
-        if apply_migrations {
-            let mut mutcon = connect;
+    let (key, direction) = match query.order {
+        TransactionOrder::DateAsc => ("t.CreationDate", "asc"),
+        TransactionOrder::DateDesc => ("t.CreationDate", "desc"),
+        TransactionOrder::AmountAsc => ("abs(t.Amount)", "asc"),
+        TransactionOrder::AmountDesc => ("abs(t.Amount)", "desc"),
+    };
+    if let Some(cursor) = &query.cursor {
+        let (last_key, last_id) = cursor
+            .rsplit_once('|')
+            .ok_or_else(|| MoneyCalcError::Validation(format!("invalid cursor: {}", cursor)))?;
+        let last_key: Box<dyn ToSql> = match query.order {
+            TransactionOrder::DateAsc | TransactionOrder::DateDesc => {
+                Box::new(last_key.to_string())
+            }
+            TransactionOrder::AmountAsc | TransactionOrder::AmountDesc => {
+                Box::new(last_key.parse::<i64>().map_err(|_| {
+                    MoneyCalcError::Validation(format!("invalid cursor: {}", cursor))
+                })?)
+            }
+        };
+        let compare = if direction == "asc" { ">" } else { "<" };
+        params.push(last_key);
+        params.push(Box::new(last_id.to_string()));
+        conditions.push(format!(
+            "({key} {compare} ?{k} or ({key} = ?{k} and t.Id {compare} ?{i}))",
+            key = key,
+            compare = compare,
+            k = params.len() - 1,
+            i = params.len(),
+        ));
+    }
 
-            MIGRATIONS.to_latest(&mut mutcon).unwrap();
+    let mut sql = TRANSACTION_SELECT.to_string();
+    if !conditions.is_empty() {
+        sql.push_str(" where ");
+        sql.push_str(&conditions.join(" and "));
+    }
+    sql.push_str(&format!(
+        " order by {key} {direction}, t.Id {direction} limit {}",
+        query.limit as i64 + 1
+    ));
+    Ok((sql, params))
+}
 
-            Ok(Self {
-                connection: Arc::new(Mutex::new(mutcon)),
-                config: config.clone(),
-            })
-        } else {
-            Ok(Self {
-                connection: Arc::new(Mutex::new(connect)),
-                config: config.clone(),
-            })
+/// Sqlite storage.
+/// Database work runs on blocking threads of tokio runtime,
+/// so waiting for sqlite doesn't stall other tasks.
+/// Clones share connection pool, so they see the same data, in-memory bases included.
+#[derive(Clone, Debug)]
+pub struct SqliteProvider {
+    pool: Arc<ConnectionPool>,
+    config: SqliteConfiguration,
+    auditor: Auditor,
+}
+
+impl SqliteProvider {
+    pub fn new(config: &SqliteConfiguration, apply_migrations: bool) -> MoneyCalcResult<Self> {
+        let pool = ConnectionPool::open(config)?;
+
+        if apply_migrations {
+            let mut connection = pool.get()?;
+            MIGRATIONS.to_latest(&mut connection)?;
         }
+        Ok(Self {
+            pool: Arc::new(pool),
+            config: config.clone(),
+            auditor: Auditor {
+                actor: Arc::from(DEFAULT_ACTOR),
+                hash_chain: config.audit_hash_chain,
+            },
+        })
+    }
+
+    pub fn config(&self) -> &SqliteConfiguration {
+        &self.config
+    }
+
+    /// Clone of provider which logs changes in audit log as made by actor.
+    pub fn with_actor(&self, actor: &str) -> Self {
+        let mut provider = self.clone();
+        provider.auditor.actor = Arc::from(actor);
+        provider
+    }
+
+    async fn execute_query<F, T>(&self, query: F) -> MoneyCalcResult<T>
+    where
+        F: FnOnce(&Connection) -> MoneyCalcResult<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let pool = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let connection = pool.get()?;
+
+            query(&connection)
+        })
+        .await
+        .map_err(|e| MoneyCalcError::Storage(Box::new(e)))?
     }
 
-    fn execute_query<F, T>(&self, query: F) -> Result<T, Box<dyn std::error::Error>>
+    /// Run queries inside one database transaction.
+    /// Commits when closure succeeds, otherwise everything is rolled back.
+    /// Write lock is taken at start, so concurrent transactions wait instead of failing.
+    async fn execute_in_transaction<F, T>(&self, query: F) -> MoneyCalcResult<T>
     where
-        F: FnOnce(&Connection) -> Result<T, Box<dyn std::error::Error>>,
+        F: FnOnce(&Transaction<'_>) -> MoneyCalcResult<T> + Send + 'static,
+        T: Send + 'static,
     {
-        let connection = self.connection.lock().map_err(|e| e.to_string())?;
+        let pool = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut connection = pool.get()?;
+            let transaction =
+                connection.transaction_with_behavior(TransactionBehavior::Immediate)?;
 
-        query(&connection)
+            let result = query(&transaction)?;
+            transaction.commit()?;
+            Ok(result)
+        })
+        .await
+        .map_err(|e| MoneyCalcError::Storage(Box::new(e)))?
     }
 }
 
-#[async_trait]
-impl TransactionWorker for SqliteProvider {
-    async fn execute_transaction(
+pub use crate::providers::bases::DEFAULT_ACTOR;
+
+/// Writes audit records in the database transaction of the change.
+#[derive(Clone, Debug)]
+struct Auditor {
+    actor: Arc<str>,
+    hash_chain: bool,
+}
+
+impl Auditor {
+    fn record<T: Serialize>(
         &self,
-        transaction: &MoneyTransaction,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let amount = match transaction.payment_type {
-            PaymentType::Income => transaction.amount,
-            PaymentType::Outcome => -transaction.amount,
-            _ => 0.0,
+        connection: &Connection,
+        action: &str,
+        entity: &str,
+        entity_id: impl ToString,
+        before: Option<&T>,
+        after: Option<&T>,
+    ) -> MoneyCalcResult<()> {
+        let entity_id = entity_id.to_string();
+        let before = before.map(serde_json::to_string).transpose()?;
+        let after = after.map(serde_json::to_string).transpose()?;
+        let create_date = chrono::Utc::now()
+            .naive_utc()
+            .format(AUDIT_DATE_FORMAT)
+            .to_string();
+        let hash = if self.hash_chain {
+            let previous: Option<String> = connection
+                .query_row(
+                    "Select Hash from AuditLog where Hash is not null order by Id desc limit 1",
+                    [],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Some(audit_hash([
+                previous.as_deref(),
+                Some(&self.actor),
+                Some(action),
+                Some(entity),
+                Some(&entity_id),
+                before.as_deref(),
+                after.as_deref(),
+                Some(&create_date),
+            ]))
+        } else {
+            None
         };
-        self.change_money(&transaction.account, amount).await?;
+        connection.execute(
+            "Insert into AuditLog(Actor, Action, Entity, EntityId, Before, After, CreationDate, Hash) Values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                self.actor.as_ref(),
+                action,
+                entity,
+                entity_id,
+                before,
+                after,
+                create_date,
+                hash,
+            ],
+        )?;
+        Ok(())
+    }
+}
 
-        self.execute_query(|connection| {
-            let sql = "INSERT INTO Transactions VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)";
-            let params = params![
-                Uuid::new_v4().to_string(),
-                transaction.amount,
-                transaction.description.clone(),
-                transaction.account.user_id,
-                transaction.account.id,
-                transaction.payment_type,
-                transaction.payment_target.clone(),
-                transaction.create_date.clone(),
-            ];
-            connection.execute(sql, params)?;
-            Ok(())
-        })
+const AUDIT_COLUMNS: &str =
+    "Id, Actor, Action, Entity, EntityId, Before, After, CreationDate, Hash";
+
+fn audit_entry_from_row(row: &Row<'_>) -> rusqlite::Result<AuditEntry> {
+    let json = |index: usize| -> rusqlite::Result<Option<serde_json::Value>> {
+        row.get::<_, Option<String>>(index)?
+            .map(|text| {
+                serde_json::from_str(&text).map_err(|e| {
+                    rusqlite::Error::FromSqlConversionFailure(
+                        index,
+                        rusqlite::types::Type::Text,
+                        Box::new(e),
+                    )
+                })
+            })
+            .transpose()
+    };
+    Ok(AuditEntry {
+        id: row.get(0)?,
+        actor: row.get(1)?,
+        action: row.get(2)?,
+        entity: row.get(3)?,
+        entity_id: row.get(4)?,
+        before: json(5)?,
+        after: json(6)?,
+        create_date: row.get(7)?,
+        hash: row.get(8)?,
+    })
+}
+
+/// Add signed amount to account balance.
+/// Balance is changed by sql expression, so stale account snapshots can't overwrite it.
+/// Debit fails if resulting balance is not allowed by overdraft policy of account.
+fn apply_money_change(
+    connection: &Connection,
+    account_id: i32,
+    amount: Money,
+) -> MoneyCalcResult<()> {
+    let (balance, overdraft) = connection
+        .query_one(
+            "Select MoneyCount, Currency, OverdraftLimit from Accounts where Id = ?1",
+            [account_id],
+            |row| {
+                Ok((
+                    Money::from_minor(row.get(0)?, row.get(1)?),
+                    overdraft_from_sql(row.get(2)?, row.get(1)?),
+                ))
+            },
+        )
+        .map_err(not_found(format!("account {}", account_id)))?;
+    let new_balance = balance.checked_add(amount)?;
+    if amount.is_negative() && !overdraft.allows(new_balance)? {
+        return Err(MoneyCalcError::InsufficientFunds(format!(
+            "account {} has {}, debit of {} is not allowed",
+            account_id,
+            balance,
+            amount.checked_abs()?
+        )));
     }
+
+    connection.execute(
+        "Update Accounts set MoneyCount = MoneyCount + ?2 where Id = ?1",
+        params![account_id, amount.minor_units()],
+    )?;
+    Ok(())
 }
 
-#[async_trait]
-impl UserProvider for SqliteProvider {
-    async fn add_user(
-        &self,
-        add_user_command: &AddUserCommand,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        self.execute_query(|connection| {
-            let sql = "insert into Users(Name, Number, CreationDate) values (?1,?2, ?3);";
-            connection.execute(
-                sql,
-                params![
-                    add_user_command.user_name,
-                    add_user_command.user_number,
-                    chrono::Utc::now().naive_utc().date(),
-                ],
-            )?;
-            Ok(())
-        })
+fn insert_transaction(
+    connection: &Connection,
+    id: &str,
+    transaction: &MoneyTransaction,
+) -> MoneyCalcResult<()> {
+    if let Some(category_id) = transaction.category_id {
+        check_category_owner(connection, category_id, transaction.account.user_id)?;
     }
+    let sql = "INSERT INTO Transactions(Id, Amount, Description, UserId, AccountId, PaymentType, CreationDate, PaymentTarget, Currency, LinkedTransactionId, CategoryId, CorrectionOf) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)";
+    let params = params![
+        id,
+        transaction.amount.minor_units(),
+        transaction.description.clone(),
+        transaction.account.user_id,
+        transaction.account.id,
+        transaction.payment_type,
+        transaction.create_date.clone(),
+        transaction.payment_target.clone(),
+        transaction.amount.currency(),
+        transaction.linked_transaction_id.clone(),
+        transaction.category_id,
+        transaction.correction_of.clone(),
+    ];
+    connection.execute(sql, params)?;
+    connection.execute(
+        "Delete from BalanceSnapshots where AccountId = ?1 and SnapshotDate >= ?2",
+        params![transaction.account.id, transaction.create_date],
+    )?;
+    Ok(())
+}
 
-    async fn get_users(&self) -> Result<Vec<User>, Box<dyn std::error::Error>> {
-        self.execute_query(|connection| {
-            let mut values = connection.prepare("select * from Users;")?;
-            let rows = values.query_map([], |row| {
-                Ok(User::new(
-                    row.get(0)?,
-                    row.get(1)?,
-                    row.get(2)?,
-                    row.get(3)?,
+/// Balance of account after all transactions dated at or before moment.
+/// Starts from latest snapshot before moment, or from initial balance without one.
+fn balance_at(
+    connection: &Connection,
+    account_id: i32,
+    at: NaiveDateTime,
+) -> MoneyCalcResult<Money> {
+    let (initial, currency) = connection
+        .query_one(
+            "Select InitialBalance, Currency from Accounts where Id = ?1",
+            [account_id],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Currency>(1)?)),
+        )
+        .map_err(not_found(format!("account {}", account_id)))?;
+    let snapshot = connection
+        .query_one(
+            "Select SnapshotDate, Balance from BalanceSnapshots where AccountId = ?1 and SnapshotDate <= ?2 order by SnapshotDate desc limit 1",
+            params![account_id, at],
+            |row| Ok((row.get::<_, NaiveDateTime>(0)?, row.get::<_, i64>(1)?)),
+        )
+        .optional()?;
+    let (since, start) = match snapshot {
+        Some((date, balance)) => (Some(date), balance),
+        None => (None, initial),
+    };
+    let change: i64 = connection.query_one(
+        &format!(
+            "Select IFNULL(sum({LEDGER_AMOUNT}), 0) from Transactions t where t.AccountId = ?1 and t.CreationDate <= ?2 and (?3 is null or t.CreationDate > ?3)"
+        ),
+        params![account_id, at, since],
+        |row| row.get(0),
+    )?;
+    Ok(Money::from_minor(start + change, currency))
+}
+
+fn get_transaction(connection: &Connection, id: &str) -> MoneyCalcResult<MoneyTransaction> {
+    connection
+        .query_one(
+            &format!("{} where t.Id = ?1", TRANSACTION_SELECT),
+            [id],
+            transaction_from_row,
+        )
+        .map_err(not_found(format!("transaction {}", id)))
+}
+
+/// Write Reversal entries for transaction and its transfer pair, returns reversal of transaction.
+/// Reversals of transfer legs are linked to each other like the legs.
+fn reverse_transaction(
+    connection: &Connection,
+    original: &MoneyTransaction,
+    description: &str,
+    create_date: NaiveDateTime,
+) -> MoneyCalcResult<MoneyTransaction> {
+    if let PaymentType::Reversal = original.payment_type {
+        return Err(MoneyCalcError::Validation(format!(
+            "transaction {} is a reversal",
+            original.id
+        )));
+    }
+    let reversed = connection.query_one(
+        "Select exists(Select 1 from Transactions where CorrectionOf = ?1 and PaymentType = ?2)",
+        params![original.id, PaymentType::Reversal],
+        |row| row.get::<_, bool>(0),
+    )?;
+    if reversed {
+        return Err(MoneyCalcError::Conflict(format!(
+            "transaction {} is already reversed",
+            original.id
+        )));
+    }
+
+    let mut legs = vec![original.clone()];
+    if let (PaymentType::Transfer, Some(linked_id)) =
+        (original.payment_type, &original.linked_transaction_id)
+    {
+        legs.push(get_transaction(connection, linked_id)?);
+    }
+    let ids: Vec<String> = legs.iter().map(|_| Uuid::new_v4().to_string()).collect();
+    let mut reversals = vec![];
+    for (index, leg) in legs.iter().enumerate() {
+        let reversal = MoneyTransaction {
+            id: ids[index].clone(),
+            amount: leg.signed_amount()?.checked_neg()?,
+            description: description.to_string(),
+            user: leg.user.clone(),
+            account: leg.account.clone(),
+            payment_type: PaymentType::Reversal,
+            payment_target: leg.payment_target.clone(),
+            create_date,
+            linked_transaction_id: ids.get(1 - index).cloned(),
+            category_id: leg.category_id,
+            correction_of: Some(leg.id.clone()),
+        };
+        apply_money_change(connection, leg.account.id, reversal.amount)?;
+        insert_transaction(connection, &reversal.id, &reversal)?;
+        reversals.push(reversal);
+    }
+    Ok(reversals.swap_remove(0))
+}
+
+const CATEGORY_COLUMNS: &str = "Id, UserId, ParentId, Name";
+
+fn category_from_row(row: &Row<'_>) -> rusqlite::Result<Category> {
+    Ok(Category {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        parent_id: row.get(2)?,
+        name: row.get(3)?,
+    })
+}
+
+fn get_category_by_id(connection: &Connection, id: i32) -> MoneyCalcResult<Category> {
+    connection
+        .query_one(
+            &format!("Select {} from Categories where Id = ?1", CATEGORY_COLUMNS),
+            [id],
+            category_from_row,
+        )
+        .map_err(not_found(format!("category {}", id)))
+}
+
+/// Categories and tags can only be used by their owner.
+fn check_category_owner(
+    connection: &Connection,
+    category_id: i32,
+    user_id: i32,
+) -> MoneyCalcResult<Category> {
+    let category = get_category_by_id(connection, category_id)?;
+    if category.user_id != user_id {
+        return Err(MoneyCalcError::Validation(format!(
+            "category {} belongs to another user",
+            category_id
+        )));
+    }
+    Ok(category)
+}
+
+const TAG_COLUMNS: &str = "Id, UserId, Name";
+
+fn tag_from_row(row: &Row<'_>) -> rusqlite::Result<Tag> {
+    Ok(Tag {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        name: row.get(2)?,
+    })
+}
+
+fn get_transaction_owner(connection: &Connection, transaction_id: &str) -> MoneyCalcResult<i32> {
+    connection
+        .query_one(
+            "Select a.UserId from Transactions t join Accounts a on a.Id = t.AccountId where t.Id = ?1",
+            [transaction_id],
+            |row| row.get(0),
+        )
+        .map_err(not_found(format!("transaction {}", transaction_id)))
+}
+
+fn get_transaction_tags(
+    connection: &Connection,
+    transaction_id: &str,
+) -> MoneyCalcResult<Vec<Tag>> {
+    let mut statement = connection.prepare(
+        "Select t.Id, t.UserId, t.Name from Tags t join TransactionTags tt on tt.TagId = t.Id where tt.TransactionId = ?1 order by t.Name",
+    )?;
+    let tags = statement
+        .query_map([transaction_id], tag_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(tags)
+}
+
+fn get_tag_by_id(connection: &Connection, id: i32) -> MoneyCalcResult<Tag> {
+    connection
+        .query_one(
+            &format!("Select {} from Tags where Id = ?1", TAG_COLUMNS),
+            [id],
+            tag_from_row,
+        )
+        .map_err(not_found(format!("tag {}", id)))
+}
+
+const BUDGET_COLUMNS: &str = "Id, UserId, Name, AccountId, PaymentTargetPattern, CategoryId, PeriodKind, PeriodStart, PeriodDays, LimitAmount, Currency";
+
+fn budget_from_row(row: &Row<'_>) -> rusqlite::Result<Budget> {
+    let scope = match (row.get(3)?, row.get(5)?) {
+        (Some(account_id), _) => BudgetScope::Account(account_id),
+        (None, Some(category_id)) => BudgetScope::Category(category_id),
+        (None, None) => BudgetScope::PaymentTarget(row.get(4)?),
+    };
+    let period = match row.get::<_, i64>(6)? {
+        2 => BudgetPeriod::Weekly,
+        3 => BudgetPeriod::Custom {
+            start: row.get(7)?,
+            days: row.get(8)?,
+        },
+        _ => BudgetPeriod::Monthly,
+    };
+    Ok(Budget {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        name: row.get(2)?,
+        scope,
+        period,
+        limit: Money::from_minor(row.get(9)?, row.get(10)?),
+    })
+}
+
+/// Budget must have positive limit and period, its scope must belong to the user.
+fn validate_budget(
+    connection: &Connection,
+    user_id: i32,
+    scope: &BudgetScope,
+    period: &BudgetPeriod,
+    limit: Money,
+) -> MoneyCalcResult<()> {
+    if limit.is_negative() || limit.is_zero() {
+        return Err(MoneyCalcError::Validation(format!(
+            "budget limit {} must be positive",
+            limit
+        )));
+    }
+    if let BudgetPeriod::Custom { days: 0, .. } = period {
+        return Err(MoneyCalcError::Validation(
+            "custom budget period must be at least one day".to_string(),
+        ));
+    }
+    match scope {
+        BudgetScope::Account(account_id) => {
+            if get_account_by_id(connection, *account_id)?.user_id != user_id {
+                return Err(MoneyCalcError::Validation(format!(
+                    "account {} belongs to another user",
+                    account_id
+                )));
+            }
+        }
+        BudgetScope::PaymentTarget(pattern) => {
+            if pattern.trim().is_empty() {
+                return Err(MoneyCalcError::Validation(
+                    "budget payment target pattern is empty".to_string(),
+                ));
+            }
+        }
+        BudgetScope::Category(category_id) => {
+            check_category_owner(connection, *category_id, user_id)?;
+        }
+    }
+    Ok(())
+}
+
+fn get_budget_by_id(connection: &Connection, id: i32) -> MoneyCalcResult<Budget> {
+    connection
+        .query_one(
+            &format!("Select {} from Budgets where Id = ?1", BUDGET_COLUMNS),
+            [id],
+            budget_from_row,
+        )
+        .map_err(not_found(format!("budget {}", id)))
+}
+
+/// Sum outcome transactions in scope of budget for the period containing date.
+/// Reversed transactions are skipped, amended ones are counted by their corrected copy.
+fn budget_progress(
+    connection: &Connection,
+    budget: &Budget,
+    date: NaiveDate,
+) -> MoneyCalcResult<BudgetProgress> {
+    let (from_date, to_date) = budget.period.range(date).ok_or_else(|| {
+        MoneyCalcError::Validation(format!("budget {} has invalid period", budget.id))
+    })?;
+    let (scope_condition, scope_value): (String, Box<dyn ToSql>) = match &budget.scope {
+        BudgetScope::Account(account_id) => ("t.AccountId = ?4".to_string(), Box::new(*account_id)),
+        BudgetScope::PaymentTarget(pattern) => (
+            "t.PaymentTarget like ?4 escape '\\'".to_string(),
+            Box::new(escape_like(pattern).replace('*', "%")),
+        ),
+        BudgetScope::Category(category_id) => (
+            in_subcategories("t.CategoryId", "?4"),
+            Box::new(*category_id),
+        ),
+    };
+
+    let sql = format!(
+        "Select abs(t.Amount), t.Currency, t.CreationDate from Transactions t join Accounts a on a.Id = t.AccountId where a.UserId = ?1 and t.PaymentType = ?5 and t.CreationDate >= ?2 and t.CreationDate < ?3 and not exists (Select 1 from Transactions r where r.CorrectionOf = t.Id and r.PaymentType = ?6) and {}",
+        scope_condition
+    );
+    let mut statement = connection.prepare(&sql)?;
+    let rows = statement
+        .query_map(
+            params![
+                budget.user_id,
+                from_date.and_time(NaiveTime::MIN),
+                to_date.and_time(NaiveTime::MIN),
+                scope_value,
+                PaymentType::Outcome,
+                PaymentType::Reversal,
+            ],
+            |row| {
+                Ok((
+                    Money::from_minor(row.get(0)?, row.get(1)?),
+                    row.get::<_, NaiveDateTime>(2)?,
                 ))
-            })?;
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
 
-            let mut users: Vec<User> = vec![];
-            rows.into_iter().for_each(|user| match user {
-                Ok(res) => users.push(res),
-                Err(_) => (),
-            });
-            Ok(users)
-        })
+    let currency = budget.limit.currency();
+    let mut spent = Money::zero(currency);
+    for (amount, create_date) in rows {
+        let rate = find_exchange_rate(connection, amount.currency(), currency, create_date.date())?;
+        spent = spent.checked_add(rate.convert(amount)?)?;
     }
+    Ok(BudgetProgress {
+        budget: budget.clone(),
+        from_date,
+        to_date,
+        planned: budget.limit,
+        spent,
+        remaining: budget.limit.checked_sub(spent)?,
+    })
+}
 
-    async fn get_user_by_number(&self, number: &str) -> Result<User, Box<dyn std::error::Error>> {
-        self.execute_query(|connection| {
-            let user =
-                connection.query_one("Select * from users where Number = ?1", [number], |row| {
-                    let user = User::new(row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?);
-                    Ok(user)
-                })?;
+const RECURRING_COLUMNS: &str = "Id, UserId, AccountId, Amount, Currency, PaymentType, PaymentTarget, Description, CategoryId, ScheduleKind, ScheduleValue, StartDate, EndDate";
 
-            Ok(user)
-        })
+fn recurring_from_row(row: &Row<'_>) -> rusqlite::Result<RecurringTransaction> {
+    let schedule = match row.get::<_, i64>(9)? {
+        2 => Schedule::EveryWeeks(row.get(10)?),
+        3 => Schedule::LastBusinessDay,
+        _ => Schedule::MonthlyOnDay(row.get(10)?),
+    };
+    Ok(RecurringTransaction {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        account_id: row.get(2)?,
+        amount: Money::from_minor(row.get(3)?, row.get(4)?),
+        payment_type: row.get(5)?,
+        payment_target: row.get(6)?,
+        description: row.get(7)?,
+        category_id: row.get(8)?,
+        schedule,
+        start_date: row.get(11)?,
+        end_date: row.get(12)?,
+    })
+}
+
+fn validate_recurring(
+    connection: &Connection,
+    command: &AddRecurringCommand,
+) -> MoneyCalcResult<()> {
+    if !matches!(
+        command.payment_type,
+        PaymentType::Income | PaymentType::Outcome
+    ) {
+        return Err(MoneyCalcError::Validation(
+            "recurring transaction must be income or outcome".to_string(),
+        ));
+    }
+    if command.amount.is_negative() || command.amount.is_zero() {
+        return Err(MoneyCalcError::Validation(format!(
+            "recurring amount {} must be positive",
+            command.amount
+        )));
     }
+    if !command.schedule.is_valid() {
+        return Err(MoneyCalcError::Validation(format!(
+            "invalid schedule {:?}",
+            command.schedule
+        )));
+    }
+    if command
+        .end_date
+        .is_some_and(|end_date| end_date < command.start_date)
+    {
+        return Err(MoneyCalcError::Validation(
+            "recurring transaction ends before start".to_string(),
+        ));
+    }
+    let account = get_account_by_id(connection, command.account_id)?;
+    if account.user_id != command.user_id {
+        return Err(MoneyCalcError::Validation(format!(
+            "account {} belongs to another user",
+            account.id
+        )));
+    }
+    account.money.check_currency(&command.amount)?;
+    if let Some(category_id) = command.category_id {
+        check_category_owner(connection, category_id, command.user_id)?;
+    }
+    Ok(())
+}
 
-    async fn delete_user_by_id(&self, id: i32) -> Result<(), Box<dyn std::error::Error>> {
-        self.execute_query(|connection| {
-            connection.execute("Delete from Users where Id = ?1", [id])?;
-            Ok(())
+fn get_recurring_by_id(connection: &Connection, id: i32) -> MoneyCalcResult<RecurringTransaction> {
+    connection
+        .query_one(
+            &format!(
+                "Select {} from RecurringTransactions where Id = ?1",
+                RECURRING_COLUMNS
+            ),
+            [id],
+            recurring_from_row,
+        )
+        .map_err(not_found(format!("recurring transaction {}", id)))
+}
+
+/// Generate transactions for occurrences of template after the last generated one.
+/// Occurrence key (RecurringId, OccurrenceDate) makes every occurrence generated once.
+fn generate_occurrences(
+    connection: &Connection,
+    auditor: &Auditor,
+    recurring_id: i32,
+    today: NaiveDate,
+) -> MoneyCalcResult<Vec<RecurringOccurrence>> {
+    let recurring = get_recurring_by_id(connection, recurring_id)?;
+    let last: Option<NaiveDate> = connection.query_one(
+        "Select max(OccurrenceDate) from RecurringOccurrences where RecurringId = ?1",
+        [recurring_id],
+        |row| row.get(0),
+    )?;
+    let dates = recurring.due_occurrences(last, today);
+    if dates.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let account = get_account_by_id(connection, recurring.account_id)?;
+    let user = get_user_by_id(connection, recurring.user_id)?;
+    let mut occurrences = vec![];
+    for date in dates {
+        let transaction = MoneyTransaction {
+            id: Uuid::new_v4().to_string(),
+            amount: recurring.amount,
+            description: recurring.description.clone(),
+            user: user.clone(),
+            account: account.clone(),
+            payment_type: recurring.payment_type,
+            payment_target: recurring.payment_target.clone(),
+            create_date: date.and_time(NaiveTime::MIN),
+            linked_transaction_id: None,
+            correction_of: None,
+            category_id: recurring.category_id,
+        };
+        apply_money_change(connection, account.id, transaction.signed_amount()?)?;
+        insert_transaction(connection, &transaction.id, &transaction)?;
+        auditor.record(
+            connection,
+            "generate_due",
+            "transaction",
+            &transaction.id,
+            None,
+            Some(&get_transaction(connection, &transaction.id)?),
+        )?;
+        connection.execute(
+            "Insert into RecurringOccurrences(RecurringId, OccurrenceDate, TransactionId) Values (?1, ?2, ?3)",
+            params![recurring_id, date, transaction.id],
+        )?;
+        occurrences.push(RecurringOccurrence {
+            recurring_id,
+            date,
+            transaction_id: transaction.id,
+        });
+    }
+    Ok(occurrences)
+}
+
+fn get_account_by_id(connection: &Connection, id: i32) -> MoneyCalcResult<Account> {
+    connection
+        .query_one(
+            &format!("Select {} from Accounts where Id = ?1", ACCOUNT_COLUMNS),
+            [id],
+            account_from_row,
+        )
+        .map_err(not_found(format!("account {}", id)))
+}
+
+/// Latest rate of pair on or before date, taken from either direction.
+/// Rate of opposite pair is inverted, direct pair wins on the same date.
+fn find_exchange_rate(
+    connection: &Connection,
+    from: Currency,
+    to: Currency,
+    date: NaiveDate,
+) -> MoneyCalcResult<ExchangeRate> {
+    if from == to {
+        return Ok(ExchangeRate::identity(from));
+    }
+
+    let sql = "Select FromCurrency = ?1, Rate from ExchangeRates where ((FromCurrency = ?1 and ToCurrency = ?2) or (FromCurrency = ?2 and ToCurrency = ?1)) and RateDate <= ?3 order by RateDate desc, FromCurrency = ?1 desc limit 1";
+    let latest: Option<(bool, i64)> = connection
+        .query_row(sql, params![from, to, date], |row| {
+            Ok((row.get(0)?, row.get(1)?))
         })
+        .optional()?;
+    match latest {
+        Some((true, rate_units)) => Ok(ExchangeRate::from_units(from, to, rate_units)?),
+        Some((false, rate_units)) => Ok(ExchangeRate::from_units(to, from, rate_units)?.inverse()?),
+        None => Err(MoneyCalcError::NotFound(format!(
+            "exchange rate {}/{} on {}",
+            from, to, date
+        ))),
     }
 }
 
+fn get_user_by_id(connection: &Connection, id: i32) -> MoneyCalcResult<User> {
+    connection
+        .query_one(
+            &format!("Select {} from Users where Id = ?1", USER_COLUMNS),
+            [id],
+            user_from_row,
+        )
+        .map_err(not_found(format!("user {}", id)))
+}
+
 #[async_trait]
-impl AccountProvider for SqliteProvider {
-    async fn add_account(
+impl TransactionWorker for SqliteProvider {
+    async fn execute_transaction(
         &self,
-        add_command: &AddAccountCommand,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        self.execute_query(|connection| {
-            let sql =
-                "Insert into Accounts(Name, UserId, MoneyCount, CreationDate) Values (?1,?2,?3,?4);";
-            connection.execute(
-                sql,
-                params![
-                    add_command.account_name,
-                    add_command.user_id,
-                    add_command.initial_balance,
-                    chrono::Utc::now().naive_utc().date().to_string(),
-                ],
+        transaction: &MoneyTransaction,
+    ) -> MoneyCalcResult<MoneyTransaction> {
+        check_executable(transaction)?;
+        let amount = transaction.signed_amount()?;
+        let transaction = transaction.clone();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            apply_money_change(connection, transaction.account.id, amount)?;
+            let id = Uuid::new_v4().to_string();
+            insert_transaction(connection, &id, &transaction)?;
+            let stored = get_transaction(connection, &id)?;
+            auditor.record(
+                connection,
+                "execute_transaction",
+                "transaction",
+                &id,
+                None,
+                Some(&stored),
             )?;
-            Ok(())
+            Ok(stored)
         })
+        .await
     }
 
-    async fn delete_account(&self, account: &Account) -> Result<(), Box<dyn std::error::Error>> {
-        self.execute_query(|connection| {
-            connection.execute("Delete from Accounts where Id = ?1", [account.id])?;
-            Ok(())
+    async fn execute_external_transaction(
+        &self,
+        transaction: &MoneyTransaction,
+        external_id: &str,
+    ) -> MoneyCalcResult<Option<MoneyTransaction>> {
+        check_executable(transaction)?;
+        let amount = transaction.signed_amount()?;
+        let transaction = transaction.clone();
+        let external_id = external_id.to_string();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let imported = connection
+                .query_one(
+                    "Select 1 from ImportedTransactions where AccountId = ?1 and ExternalId = ?2",
+                    params![transaction.account.id, external_id],
+                    |_| Ok(()),
+                )
+                .optional()?;
+            if imported.is_some() {
+                return Ok(None);
+            }
+            apply_money_change(connection, transaction.account.id, amount)?;
+            let id = Uuid::new_v4().to_string();
+            insert_transaction(connection, &id, &transaction)?;
+            connection.execute(
+                "Insert into ImportedTransactions(AccountId, ExternalId, TransactionId) Values (?1, ?2, ?3)",
+                params![transaction.account.id, external_id, id],
+            )?;
+            let stored = get_transaction(connection, &id)?;
+            auditor.record(
+                connection,
+                "execute_external_transaction",
+                "transaction",
+                &id,
+                None,
+                Some(&stored),
+            )?;
+            Ok(Some(stored))
         })
+        .await
     }
 
-    async fn change_money(
+    async fn get_imported_transactions(
         &self,
         account: &Account,
-        payment_count: f32,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        self.execute_query(|connection| {
+    ) -> MoneyCalcResult<Vec<ImportedTransaction>> {
+        let account_id = account.id;
+        self.execute_query(move |connection| {
+            let mut statement = connection.prepare(
+                "Select AccountId, ExternalId, TransactionId from ImportedTransactions where AccountId = ?1 order by ExternalId",
+            )?;
+            let imported = statement
+                .query_map([account_id], |row| {
+                    Ok(ImportedTransaction {
+                        account_id: row.get(0)?,
+                        external_id: row.get(1)?,
+                        transaction_id: row.get(2)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(imported)
+        })
+        .await
+    }
+
+    async fn restore_imported_transaction(
+        &self,
+        imported: &ImportedTransaction,
+    ) -> MoneyCalcResult<()> {
+        let imported = imported.clone();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let transaction = get_transaction(connection, &imported.transaction_id)?;
+            if transaction.account.id != imported.account_id {
+                return Err(MoneyCalcError::Validation(format!(
+                    "transaction {} does not belong to account {}",
+                    imported.transaction_id, imported.account_id
+                )));
+            }
             connection.execute(
-                "Update Accounts set MoneyCount = ?2 where Id = ?1",
-                params![account.id, (account.money + payment_count)],
+                "Insert into ImportedTransactions(AccountId, ExternalId, TransactionId) Values (?1, ?2, ?3)",
+                params![imported.account_id, imported.external_id, imported.transaction_id],
             )?;
-            Ok(())
+            auditor.record(
+                connection,
+                "restore_imported_transaction",
+                "transaction",
+                &imported.transaction_id,
+                None,
+                Some(&imported),
+            )
         })
+        .await
     }
 
-    async fn get_accounts(&self) -> Result<Vec<Account>, Box<dyn std::error::Error>> {
-        self.execute_query(|connection| {
-            let mut values = connection.prepare("select * from Accounts;")?;
-            let rows = values.query_map([], |row| {
-                Ok(Account::from_exist(
-                    row.get(0)?,
-                    row.get(2)?,
-                    row.get(1)?,
-                    row.get(3)?,
-                    row.get(4)?,
-                ))
-            })?;
+    async fn transfer(&self, transfer_command: &TransferCommand) -> MoneyCalcResult<()> {
+        if transfer_command.from_account_id == transfer_command.to_account_id {
+            return Err(MoneyCalcError::Validation(
+                "transfer source and target accounts must differ".to_string(),
+            ));
+        }
+        if transfer_command.amount.minor_units() <= 0 {
+            return Err(MoneyCalcError::Validation(
+                "transfer amount must be positive".to_string(),
+            ));
+        }
 
-            let mut accounts: Vec<Account> = vec![];
-            rows.into_iter().for_each(|user| match user {
-                Ok(res) => accounts.push(res),
-                Err(_) => (),
-            });
-            Ok(accounts)
+        let transfer_command = transfer_command.clone();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let from_account = get_account_by_id(connection, transfer_command.from_account_id)?;
+            let to_account = get_account_by_id(connection, transfer_command.to_account_id)?;
+            transfer_command
+                .amount
+                .check_currency(&from_account.money)?;
+
+            let credit = match &transfer_command.exchange_rate {
+                Some(rate) => {
+                    if rate.from != from_account.money.currency()
+                        || rate.to != to_account.money.currency()
+                    {
+                        return Err(MoneyCalcError::Validation(format!(
+                            "exchange rate {} does not match accounts currencies",
+                            rate
+                        )));
+                    }
+                    rate.convert(transfer_command.amount)?
+                }
+                None => {
+                    from_account.money.check_currency(&to_account.money)?;
+                    transfer_command.amount
+                }
+            };
+            let debit = transfer_command.amount.checked_neg()?;
+
+            apply_money_change(connection, from_account.id, debit)?;
+            apply_money_change(connection, to_account.id, credit)?;
+
+            let debit_id = Uuid::new_v4().to_string();
+            let credit_id = Uuid::new_v4().to_string();
+            let debit_transaction = MoneyTransaction {
+                id: debit_id.clone(),
+                amount: debit,
+                description: transfer_command.description.clone(),
+                user: get_user_by_id(connection, from_account.user_id)?,
+                payment_type: PaymentType::Transfer,
+                payment_target: to_account.name.clone(),
+                create_date: transfer_command.create_date,
+                linked_transaction_id: Some(credit_id.clone()),
+                correction_of: None,
+                category_id: None,
+                account: from_account.clone(),
+            };
+            let credit_transaction = MoneyTransaction {
+                id: credit_id.clone(),
+                amount: credit,
+                description: transfer_command.description.clone(),
+                user: get_user_by_id(connection, to_account.user_id)?,
+                payment_type: PaymentType::Transfer,
+                payment_target: from_account.name.clone(),
+                create_date: transfer_command.create_date,
+                linked_transaction_id: Some(debit_id.clone()),
+                correction_of: None,
+                category_id: None,
+                account: to_account,
+            };
+            insert_transaction(connection, &debit_id, &debit_transaction)?;
+            insert_transaction(connection, &credit_id, &credit_transaction)?;
+            for id in [debit_id, credit_id] {
+                auditor.record(
+                    connection,
+                    "transfer",
+                    "transaction",
+                    &id,
+                    None,
+                    Some(&get_transaction(connection, &id)?),
+                )?;
+            }
+            Ok(())
         })
+        .await
     }
 
-    async fn search_account_by_user(
+    async fn reverse_transaction(
         &self,
-        user: &User,
-    ) -> Result<Account, Box<dyn std::error::Error>> {
-        self.execute_query(|connection| {
-            let account = connection.query_one(
-                "Select * from Accounts where UserId = ?1",
-                [user.id],
-                |row| {
-                    Ok(Account::from_exist(
-                        row.get(0)?,
-                        row.get(2)?,
-                        row.get(1)?,
-                        row.get(3)?,
-                        row.get(4)?,
-                    ))
-                },
+        reverse_command: &ReverseTransactionCommand,
+    ) -> MoneyCalcResult<MoneyTransaction> {
+        let reverse_command = reverse_command.clone();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let original = get_transaction(connection, &reverse_command.transaction_id)?;
+            let reversal = reverse_transaction(
+                connection,
+                &original,
+                &reverse_command.description,
+                reverse_command.create_date,
             )?;
+            auditor.record(
+                connection,
+                "reverse_transaction",
+                "transaction",
+                &original.id,
+                Some(&original),
+                Some(&get_transaction(connection, &reversal.id)?),
+            )?;
+            Ok(reversal)
+        })
+        .await
+    }
+
+    async fn amend_transaction(
+        &self,
+        amend_command: &AmendTransactionCommand,
+    ) -> MoneyCalcResult<MoneyTransaction> {
+        if amend_command.amount.is_negative() || amend_command.amount.is_zero() {
+            return Err(MoneyCalcError::Validation(format!(
+                "amended amount {} must be positive",
+                amend_command.amount
+            )));
+        }
+        let amend_command = amend_command.clone();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let original = get_transaction(connection, &amend_command.transaction_id)?;
+            if let PaymentType::Transfer = original.payment_type {
+                return Err(MoneyCalcError::Validation(
+                    "transfers can't be amended".to_string(),
+                ));
+            }
+            reverse_transaction(
+                connection,
+                &original,
+                &original.description,
+                amend_command.create_date,
+            )?;
+            let amended = MoneyTransaction {
+                id: Uuid::new_v4().to_string(),
+                amount: amend_command.amount,
+                description: amend_command.description,
+                payment_target: amend_command.payment_target,
+                create_date: amend_command.create_date,
+                linked_transaction_id: None,
+                category_id: amend_command.category_id,
+                correction_of: Some(original.id.clone()),
+                ..original.clone()
+            };
+            apply_money_change(connection, amended.account.id, amended.signed_amount()?)?;
+            insert_transaction(connection, &amended.id, &amended)?;
+            auditor.record(
+                connection,
+                "amend_transaction",
+                "transaction",
+                &original.id,
+                Some(&original),
+                Some(&get_transaction(connection, &amended.id)?),
+            )?;
+            Ok(amended)
+        })
+        .await
+    }
+
+    async fn get_transaction_by_id(&self, id: &str) -> MoneyCalcResult<MoneyTransaction> {
+        let id = id.to_string();
+        self.execute_query(move |connection| get_transaction(connection, &id))
+            .await
+    }
+
+    async fn restore_transaction(
+        &self,
+        transaction: &MoneyTransaction,
+    ) -> MoneyCalcResult<MoneyTransaction> {
+        let transaction = transaction.clone();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let id = if transaction.id.is_empty() {
+                Uuid::new_v4().to_string()
+            } else {
+                transaction.id.clone()
+            };
+            let account = get_account_by_id(connection, transaction.account.id)?;
+            transaction.amount.check_currency(&account.money)?;
+            insert_transaction(
+                connection,
+                &id,
+                &MoneyTransaction {
+                    account,
+                    ..transaction
+                },
+            )?;
+            let restored = get_transaction(connection, &id)?;
+            auditor.record(
+                connection,
+                "restore_transaction",
+                "transaction",
+                &id,
+                None,
+                Some(&restored),
+            )?;
+            Ok(restored)
+        })
+        .await
+    }
+
+    async fn get_transactions(&self, query: &TransactionQuery) -> MoneyCalcResult<TransactionPage> {
+        if query.limit == 0 {
+            return Err(MoneyCalcError::Validation(
+                "transactions query limit must be positive".to_string(),
+            ));
+        }
+        let query = query.clone();
+
+        self.execute_query(move |connection| {
+            let (sql, params) = build_transaction_query(&query)?;
+            let mut statement = connection.prepare(&sql)?;
+            let mut transactions = statement
+                .query_map(params_from_iter(params.iter()), transaction_from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let mut next_cursor = None;
+            if transactions.len() > query.limit as usize {
+                transactions.truncate(query.limit as usize);
+                next_cursor = transactions
+                    .last()
+                    .map(|transaction| transaction_cursor(&query, transaction));
+            }
+            Ok(TransactionPage {
+                transactions,
+                next_cursor,
+            })
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl UserProvider for SqliteProvider {
+    async fn add_user(&self, add_user_command: &AddUserCommand) -> MoneyCalcResult<()> {
+        let add_user_command = add_user_command.clone();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let sql = "insert into Users(Name, Number, CreationDate) values (?1,?2, ?3);";
+            connection.execute(
+                sql,
+                params![
+                    add_user_command.user_name,
+                    add_user_command.user_number,
+                    chrono::Utc::now().naive_utc().date(),
+                ],
+            )?;
+            let user = get_user_by_id(connection, connection.last_insert_rowid() as i32)?;
+            auditor.record(connection, "add_user", "user", user.id, None, Some(&user))
+        })
+        .await
+    }
+
+    async fn restore_user(&self, user: &User) -> MoneyCalcResult<User> {
+        let user = user.clone();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            connection.execute(
+                "insert into Users(Id, Name, Number, CreationDate) values (nullif(?1, 0), ?2, ?3, ?4);",
+                params![user.id, user.name, user.number, user.creation_date],
+            )?;
+            let restored = get_user_by_id(connection, connection.last_insert_rowid() as i32)?;
+            auditor.record(
+                connection,
+                "restore_user",
+                "user",
+                restored.id,
+                None,
+                Some(&restored),
+            )?;
+            Ok(restored)
+        })
+        .await
+    }
+
+    async fn get_users(&self) -> MoneyCalcResult<Vec<User>> {
+        self.execute_query(|connection| {
+            let mut values = connection.prepare(&format!("select {} from Users;", USER_COLUMNS))?;
+            let rows = values.query_map([], user_from_row)?;
+
+            let users: Vec<User> = rows.flatten().collect();
+            Ok(users)
+        })
+        .await
+    }
+
+    async fn get_user_by_number(&self, number: &str) -> MoneyCalcResult<User> {
+        let number = number.to_string();
+        self.execute_query(move |connection| {
+            let user = connection
+                .query_one(
+                    &format!("Select {} from Users where Number = ?1", USER_COLUMNS),
+                    [&number],
+                    user_from_row,
+                )
+                .map_err(not_found(format!("user with number {}", number)))?;
+
+            Ok(user)
+        })
+        .await
+    }
+
+    async fn delete_user_by_id(&self, id: i32) -> MoneyCalcResult<()> {
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let user = get_user_by_id(connection, id)?;
+            connection
+                .execute("Delete from Users where Id = ?1", [id])
+                .map_err(still_referenced(format!("user {}", id)))?;
+            auditor.record(
+                connection,
+                "delete_user_by_id",
+                "user",
+                id,
+                Some(&user),
+                None,
+            )
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl AccountProvider for SqliteProvider {
+    async fn add_account(&self, add_command: &AddAccountCommand) -> MoneyCalcResult<()> {
+        validate_overdraft(
+            &add_command.overdraft,
+            add_command.initial_balance.currency(),
+        )?;
+        let add_command = add_command.clone();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let sql = "Insert into Accounts(Name, UserId, MoneyCount, Currency, CreationDate, IsPrimary, OverdraftLimit, InitialBalance) Values (?1,?2,?3,?4,?5, not exists (Select 1 from Accounts where UserId = ?2 and IsPrimary = 1), ?6, ?3);";
+            connection.execute(
+                sql,
+                params![
+                    add_command.account_name,
+                    add_command.user_id,
+                    add_command.initial_balance.minor_units(),
+                    add_command.initial_balance.currency(),
+                    chrono::Utc::now().naive_utc().date().to_string(),
+                    overdraft_to_sql(&add_command.overdraft),
+                ],
+            )?;
+            let account = get_account_by_id(connection, connection.last_insert_rowid() as i32)?;
+            auditor.record(
+                connection,
+                "add_account",
+                "account",
+                account.id,
+                None,
+                Some(&account),
+            )
+        })
+        .await
+    }
+
+    async fn restore_account(
+        &self,
+        account: &Account,
+        initial_balance: Money,
+    ) -> MoneyCalcResult<Account> {
+        validate_overdraft(&account.overdraft, account.money.currency())?;
+        initial_balance.check_currency(&account.money)?;
+        let account = account.clone();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let sql = "Insert into Accounts(Id, Name, UserId, MoneyCount, Currency, CreationDate, IsPrimary, OverdraftLimit, InitialBalance) Values (nullif(?1, 0), ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9);";
+            connection.execute(
+                sql,
+                params![
+                    account.id,
+                    account.name,
+                    account.user_id,
+                    account.money.minor_units(),
+                    account.money.currency(),
+                    account.creation_date.to_string(),
+                    account.is_primary,
+                    overdraft_to_sql(&account.overdraft),
+                    initial_balance.minor_units(),
+                ],
+            )?;
+            let restored = get_account_by_id(connection, connection.last_insert_rowid() as i32)?;
+            auditor.record(
+                connection,
+                "restore_account",
+                "account",
+                restored.id,
+                None,
+                Some(&restored),
+            )?;
+            Ok(restored)
+        })
+        .await
+    }
+
+    async fn delete_account(&self, account: &Account) -> MoneyCalcResult<()> {
+        let account_id = account.id;
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let account = get_account_by_id(connection, account_id)?;
+            connection.execute(
+                "Delete from BalanceSnapshots where AccountId = ?1",
+                [account.id],
+            )?;
+            connection
+                .execute("Delete from Accounts where Id = ?1", [account.id])
+                .map_err(still_referenced(format!("account {}", account.id)))?;
+            connection.execute(
+                "Update Accounts set IsPrimary = 1 where Id = (Select min(Id) from Accounts where UserId = ?1) and not exists (Select 1 from Accounts where UserId = ?1 and IsPrimary = 1)",
+                [account.user_id],
+            )?;
+            auditor.record(
+                connection,
+                "delete_account",
+                "account",
+                account.id,
+                Some(&account),
+                None,
+            )
+        })
+        .await
+    }
+
+    async fn change_money(&self, account: &Account, payment_count: Money) -> MoneyCalcResult<()> {
+        let account_id = account.id;
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let before = get_account_by_id(connection, account_id)?;
+            apply_money_change(connection, account_id, payment_count)?;
+            auditor.record(
+                connection,
+                "change_money",
+                "account",
+                account_id,
+                Some(&before),
+                Some(&get_account_by_id(connection, account_id)?),
+            )
+        })
+        .await
+    }
+
+    async fn set_overdraft_policy(
+        &self,
+        account: &Account,
+        overdraft: OverdraftPolicy,
+    ) -> MoneyCalcResult<()> {
+        let account_id = account.id;
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let account = get_account_by_id(connection, account_id)?;
+            validate_overdraft(&overdraft, account.money.currency())?;
+            connection.execute(
+                "Update Accounts set OverdraftLimit = ?2 where Id = ?1",
+                params![account.id, overdraft_to_sql(&overdraft)],
+            )?;
+            auditor.record(
+                connection,
+                "set_overdraft_policy",
+                "account",
+                account_id,
+                Some(&account),
+                Some(&get_account_by_id(connection, account_id)?),
+            )
+        })
+        .await
+    }
+
+    async fn get_accounts(&self) -> MoneyCalcResult<Vec<Account>> {
+        self.execute_query(|connection| {
+            let mut values =
+                connection.prepare(&format!("select {} from Accounts;", ACCOUNT_COLUMNS))?;
+            let rows = values.query_map([], account_from_row)?;
+
+            let accounts: Vec<Account> = rows.flatten().collect();
+            Ok(accounts)
+        })
+        .await
+    }
+
+    async fn search_account_by_user(&self, user: &User) -> MoneyCalcResult<Account> {
+        let user_id = user.id;
+        self.execute_query(move |connection| {
+            let account = connection.query_one(
+                &format!(
+                    "Select {} from Accounts where UserId = ?1 order by IsPrimary desc, Id limit 1",
+                    ACCOUNT_COLUMNS
+                ),
+                [user_id],
+                account_from_row,
+            ).map_err(not_found(format!("account of user {}", user_id)))?;
+
+            Ok(account)
+        })
+        .await
+    }
+
+    async fn get_accounts_by_user(&self, user: &User) -> MoneyCalcResult<Vec<Account>> {
+        let user_id = user.id;
+        self.execute_query(move |connection| {
+            let mut values = connection.prepare(&format!(
+                "Select {} from Accounts where UserId = ?1 order by Id",
+                ACCOUNT_COLUMNS
+            ))?;
+            let accounts = values
+                .query_map([user_id], account_from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(accounts)
+        })
+        .await
+    }
+
+    async fn get_account_by_id(&self, id: i32) -> MoneyCalcResult<Account> {
+        self.execute_query(move |connection| get_account_by_id(connection, id))
+            .await
+    }
+
+    async fn get_account_by_name(&self, user: &User, name: &str) -> MoneyCalcResult<Account> {
+        let user_id = user.id;
+        let name = name.to_string();
+        self.execute_query(move |connection| {
+            let account = connection.query_one(
+                &format!(
+                    "Select {} from Accounts where UserId = ?1 and Name = ?2 order by Id limit 1",
+                    ACCOUNT_COLUMNS
+                ),
+                params![user_id, name],
+                account_from_row,
+            ).map_err(not_found(format!("account {} of user {}", name, user_id)))?;
+            Ok(account)
+        })
+        .await
+    }
+
+    async fn set_primary_account(&self, account: &Account) -> MoneyCalcResult<()> {
+        let account_id = account.id;
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let account = get_account_by_id(connection, account_id)?;
+            connection.execute(
+                "Update Accounts set IsPrimary = (Id = ?1) where UserId = ?2",
+                params![account.id, account.user_id],
+            )?;
+            auditor.record(
+                connection,
+                "set_primary_account",
+                "account",
+                account_id,
+                Some(&account),
+                Some(&get_account_by_id(connection, account_id)?),
+            )
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl ExchangeRateProvider for SqliteProvider {
+    async fn add_exchange_rate(&self, rate: &ExchangeRate, date: NaiveDate) -> MoneyCalcResult<()> {
+        if rate.from == rate.to {
+            return Err(MoneyCalcError::Validation(format!(
+                "exchange rate {} must be between different currencies",
+                rate
+            )));
+        }
+        let rate = *rate;
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let before = connection
+                .query_row(
+                    "Select Rate from ExchangeRates where FromCurrency = ?1 and ToCurrency = ?2 and RateDate = ?3",
+                    params![rate.from, rate.to, date],
+                    |row| row.get::<_, i64>(0),
+                )
+                .optional()?
+                .map(|units| ExchangeRate::from_units(rate.from, rate.to, units))
+                .transpose()?;
+            connection.execute(
+                "Insert or replace into ExchangeRates(FromCurrency, ToCurrency, RateDate, Rate) Values (?1, ?2, ?3, ?4)",
+                params![rate.from, rate.to, date, rate.rate_units()],
+            )?;
+            auditor.record(
+                connection,
+                "add_exchange_rate",
+                "exchange_rate",
+                format!("{}/{} {}", rate.from, rate.to, date),
+                before.as_ref(),
+                Some(&rate),
+            )
+        })
+        .await
+    }
+
+    async fn get_exchange_rate(
+        &self,
+        from: Currency,
+        to: Currency,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<ExchangeRate> {
+        self.execute_query(move |connection| find_exchange_rate(connection, from, to, date))
+            .await
+    }
+
+    async fn convert_money(
+        &self,
+        money: Money,
+        currency: Currency,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<Money> {
+        let rate = self
+            .get_exchange_rate(money.currency(), currency, date)
+            .await?;
+        Ok(rate.convert(money)?)
+    }
+
+    async fn get_total_balance(
+        &self,
+        user: &User,
+        currency: Currency,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<Money> {
+        let user_id = user.id;
+        self.execute_query(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "Select {} from Accounts where UserId = ?1",
+                ACCOUNT_COLUMNS
+            ))?;
+            let accounts = statement
+                .query_map([user_id], account_from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let mut total = Money::zero(currency);
+            for account in accounts {
+                let rate =
+                    find_exchange_rate(connection, account.money.currency(), currency, date)?;
+                total = total.checked_add(rate.convert(account.money)?)?;
+            }
+            Ok(total)
+        })
+        .await
+    }
+
+    async fn convert_transactions(
+        &self,
+        transactions: &[MoneyTransaction],
+        currency: Currency,
+    ) -> MoneyCalcResult<Vec<MoneyTransaction>> {
+        let mut transactions = transactions.to_vec();
+        self.execute_query(move |connection| {
+            for transaction in transactions.iter_mut() {
+                let rate = find_exchange_rate(
+                    connection,
+                    transaction.amount.currency(),
+                    currency,
+                    transaction.create_date.date(),
+                )?;
+                transaction.amount = rate.convert(transaction.amount)?;
+            }
+            Ok(transactions)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl CategoryProvider for SqliteProvider {
+    async fn add_category(&self, add_category_command: &AddCategoryCommand) -> MoneyCalcResult<()> {
+        let add_category_command = add_category_command.clone();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            if let Some(parent_id) = add_category_command.parent_id {
+                check_category_owner(connection, parent_id, add_category_command.user_id)?;
+            }
+            connection.execute(
+                "Insert into Categories(UserId, ParentId, Name) Values (?1, ?2, ?3)",
+                params![
+                    add_category_command.user_id,
+                    add_category_command.parent_id,
+                    add_category_command.name,
+                ],
+            )?;
+            let category = get_category_by_id(connection, connection.last_insert_rowid() as i32)?;
+            auditor.record(
+                connection,
+                "add_category",
+                "category",
+                category.id,
+                None,
+                Some(&category),
+            )
+        })
+        .await
+    }
+
+    async fn get_categories(&self, user: &User) -> MoneyCalcResult<Vec<Category>> {
+        let user_id = user.id;
+        self.execute_query(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "Select {} from Categories where UserId = ?1 order by Id",
+                CATEGORY_COLUMNS
+            ))?;
+            let categories = statement
+                .query_map([user_id], category_from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(categories)
+        })
+        .await
+    }
+
+    async fn get_category_by_id(&self, id: i32) -> MoneyCalcResult<Category> {
+        self.execute_query(move |connection| get_category_by_id(connection, id))
+            .await
+    }
+
+    async fn update_category(&self, category: &Category) -> MoneyCalcResult<()> {
+        let category = category.clone();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let stored = get_category_by_id(connection, category.id)?;
+            let mut parent_id = category.parent_id;
+            while let Some(id) = parent_id {
+                if id == category.id {
+                    return Err(MoneyCalcError::Validation(format!(
+                        "category {} can't be moved under itself",
+                        category.id
+                    )));
+                }
+                parent_id = check_category_owner(connection, id, stored.user_id)?.parent_id;
+            }
+            connection.execute(
+                "Update Categories set ParentId = ?2, Name = ?3 where Id = ?1",
+                params![category.id, category.parent_id, category.name],
+            )?;
+            auditor.record(
+                connection,
+                "update_category",
+                "category",
+                category.id,
+                Some(&stored),
+                Some(&get_category_by_id(connection, category.id)?),
+            )
+        })
+        .await
+    }
+
+    async fn delete_category(&self, category: &Category) -> MoneyCalcResult<()> {
+        let category_id = category.id;
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let category = get_category_by_id(connection, category_id)?;
+            connection.execute(
+                "Update Categories set ParentId = ?2 where ParentId = ?1",
+                params![category.id, category.parent_id],
+            )?;
+            connection.execute(
+                "Update Transactions set CategoryId = NULL where CategoryId = ?1",
+                [category.id],
+            )?;
+            connection
+                .execute("Delete from Categories where Id = ?1", [category.id])
+                .map_err(still_referenced(format!("category {}", category.id)))?;
+            auditor.record(
+                connection,
+                "delete_category",
+                "category",
+                category.id,
+                Some(&category),
+                None,
+            )
+        })
+        .await
+    }
+
+    async fn set_transaction_category(
+        &self,
+        transaction_id: &str,
+        category: Option<&Category>,
+    ) -> MoneyCalcResult<()> {
+        let transaction_id = transaction_id.to_string();
+        let category_id = category.map(|category| category.id);
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let before = get_transaction(connection, &transaction_id)?;
+            if let Some(category_id) = category_id {
+                check_category_owner(connection, category_id, before.account.user_id)?;
+            }
+            connection.execute(
+                "Update Transactions set CategoryId = ?2 where Id = ?1",
+                params![transaction_id, category_id],
+            )?;
+            auditor.record(
+                connection,
+                "set_transaction_category",
+                "transaction",
+                &transaction_id,
+                Some(&before),
+                Some(&get_transaction(connection, &transaction_id)?),
+            )
+        })
+        .await
+    }
+
+    async fn restore_category(&self, category: &Category) -> MoneyCalcResult<Category> {
+        let category = category.clone();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            if let Some(parent_id) = category.parent_id {
+                check_category_owner(connection, parent_id, category.user_id)?;
+            }
+            connection.execute(
+                "Insert into Categories(Id, UserId, ParentId, Name) Values (nullif(?1, 0), ?2, ?3, ?4)",
+                params![category.id, category.user_id, category.parent_id, category.name],
+            )?;
+            let restored = get_category_by_id(connection, connection.last_insert_rowid() as i32)?;
+            auditor.record(
+                connection,
+                "restore_category",
+                "category",
+                restored.id,
+                None,
+                Some(&restored),
+            )?;
+            Ok(restored)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl TagProvider for SqliteProvider {
+    async fn add_tag(&self, add_tag_command: &AddTagCommand) -> MoneyCalcResult<()> {
+        let add_tag_command = add_tag_command.clone();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            connection.execute(
+                "Insert into Tags(UserId, Name) Values (?1, ?2)",
+                params![add_tag_command.user_id, add_tag_command.name],
+            )?;
+            let tag = get_tag_by_id(connection, connection.last_insert_rowid() as i32)?;
+            auditor.record(connection, "add_tag", "tag", tag.id, None, Some(&tag))
+        })
+        .await
+    }
+
+    async fn get_tags(&self, user: &User) -> MoneyCalcResult<Vec<Tag>> {
+        let user_id = user.id;
+        self.execute_query(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "Select {} from Tags where UserId = ?1 order by Name",
+                TAG_COLUMNS
+            ))?;
+            let tags = statement
+                .query_map([user_id], tag_from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(tags)
+        })
+        .await
+    }
+
+    async fn rename_tag(&self, tag: &Tag, name: &str) -> MoneyCalcResult<()> {
+        let tag_id = tag.id;
+        let name = name.to_string();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let before = get_tag_by_id(connection, tag_id)?;
+            connection.execute(
+                "Update Tags set Name = ?2 where Id = ?1",
+                params![tag_id, name],
+            )?;
+            auditor.record(
+                connection,
+                "rename_tag",
+                "tag",
+                tag_id,
+                Some(&before),
+                Some(&get_tag_by_id(connection, tag_id)?),
+            )
+        })
+        .await
+    }
+
+    async fn delete_tag(&self, tag: &Tag) -> MoneyCalcResult<()> {
+        let tag_id = tag.id;
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let tag = get_tag_by_id(connection, tag_id)?;
+            connection.execute("Delete from TransactionTags where TagId = ?1", [tag_id])?;
+            connection.execute("Delete from Tags where Id = ?1", [tag_id])?;
+            auditor.record(connection, "delete_tag", "tag", tag_id, Some(&tag), None)
+        })
+        .await
+    }
+
+    async fn restore_tag(&self, tag: &Tag) -> MoneyCalcResult<Tag> {
+        let tag = tag.clone();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            connection.execute(
+                "Insert into Tags(Id, UserId, Name) Values (nullif(?1, 0), ?2, ?3)",
+                params![tag.id, tag.user_id, tag.name],
+            )?;
+            let restored = get_tag_by_id(connection, connection.last_insert_rowid() as i32)?;
+            auditor.record(
+                connection,
+                "restore_tag",
+                "tag",
+                restored.id,
+                None,
+                Some(&restored),
+            )?;
+            Ok(restored)
+        })
+        .await
+    }
+
+    async fn set_transaction_tags(
+        &self,
+        transaction_id: &str,
+        tags: &[Tag],
+    ) -> MoneyCalcResult<()> {
+        let transaction_id = transaction_id.to_string();
+        let tag_ids: Vec<i32> = tags.iter().map(|tag| tag.id).collect();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let user_id = get_transaction_owner(connection, &transaction_id)?;
+            let before = get_transaction_tags(connection, &transaction_id)?;
+            connection.execute(
+                "Delete from TransactionTags where TransactionId = ?1",
+                [&transaction_id],
+            )?;
+            for tag_id in tag_ids {
+                let owner: i32 = connection
+                    .query_one("Select UserId from Tags where Id = ?1", [tag_id], |row| {
+                        row.get(0)
+                    })
+                    .map_err(not_found(format!("tag {}", tag_id)))?;
+                if owner != user_id {
+                    return Err(MoneyCalcError::Validation(format!(
+                        "tag {} belongs to another user",
+                        tag_id
+                    )));
+                }
+                connection.execute(
+                    "Insert or ignore into TransactionTags(TransactionId, TagId) Values (?1, ?2)",
+                    params![transaction_id, tag_id],
+                )?;
+            }
+            auditor.record(
+                connection,
+                "set_transaction_tags",
+                "transaction",
+                &transaction_id,
+                Some(&before),
+                Some(&get_transaction_tags(connection, &transaction_id)?),
+            )
+        })
+        .await
+    }
+
+    async fn get_transaction_tags(&self, transaction_id: &str) -> MoneyCalcResult<Vec<Tag>> {
+        let transaction_id = transaction_id.to_string();
+        self.execute_query(move |connection| {
+            get_transaction_owner(connection, &transaction_id)?;
+            get_transaction_tags(connection, &transaction_id)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl BudgetProvider for SqliteProvider {
+    async fn add_budget(&self, add_budget_command: &AddBudgetCommand) -> MoneyCalcResult<()> {
+        let add_budget_command = add_budget_command.clone();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            validate_budget(
+                connection,
+                add_budget_command.user_id,
+                &add_budget_command.scope,
+                &add_budget_command.period,
+                add_budget_command.limit,
+            )?;
+            let (account_id, pattern, category_id, kind, start, days) =
+                budget_to_sql(&add_budget_command.scope, &add_budget_command.period);
+            connection.execute(
+                "Insert into Budgets(UserId, Name, AccountId, PaymentTargetPattern, CategoryId, PeriodKind, PeriodStart, PeriodDays, LimitAmount, Currency) Values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    add_budget_command.user_id,
+                    add_budget_command.name,
+                    account_id,
+                    pattern,
+                    category_id,
+                    kind,
+                    start,
+                    days,
+                    add_budget_command.limit.minor_units(),
+                    add_budget_command.limit.currency(),
+                ],
+            )?;
+            let budget = get_budget_by_id(connection, connection.last_insert_rowid() as i32)?;
+            auditor.record(connection, "add_budget", "budget", budget.id, None, Some(&budget))
+        })
+        .await
+    }
+
+    async fn get_budgets(&self, user: &User) -> MoneyCalcResult<Vec<Budget>> {
+        let user_id = user.id;
+        self.execute_query(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "Select {} from Budgets where UserId = ?1 order by Id",
+                BUDGET_COLUMNS
+            ))?;
+            let budgets = statement
+                .query_map([user_id], budget_from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(budgets)
+        })
+        .await
+    }
+
+    async fn get_budget_by_id(&self, id: i32) -> MoneyCalcResult<Budget> {
+        self.execute_query(move |connection| get_budget_by_id(connection, id))
+            .await
+    }
+
+    async fn update_budget(&self, budget: &Budget) -> MoneyCalcResult<()> {
+        let budget = budget.clone();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let stored = get_budget_by_id(connection, budget.id)?;
+            validate_budget(
+                connection,
+                stored.user_id,
+                &budget.scope,
+                &budget.period,
+                budget.limit,
+            )?;
+            let (account_id, pattern, category_id, kind, start, days) =
+                budget_to_sql(&budget.scope, &budget.period);
+            connection.execute(
+                "Update Budgets set Name = ?2, AccountId = ?3, PaymentTargetPattern = ?4, CategoryId = ?5, PeriodKind = ?6, PeriodStart = ?7, PeriodDays = ?8, LimitAmount = ?9, Currency = ?10 where Id = ?1",
+                params![
+                    budget.id,
+                    budget.name,
+                    account_id,
+                    pattern,
+                    category_id,
+                    kind,
+                    start,
+                    days,
+                    budget.limit.minor_units(),
+                    budget.limit.currency(),
+                ],
+            )?;
+            auditor.record(
+                connection,
+                "update_budget",
+                "budget",
+                budget.id,
+                Some(&stored),
+                Some(&get_budget_by_id(connection, budget.id)?),
+            )
+        })
+        .await
+    }
+
+    async fn delete_budget(&self, budget: &Budget) -> MoneyCalcResult<()> {
+        let budget_id = budget.id;
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let budget = get_budget_by_id(connection, budget_id)?;
+            connection.execute("Delete from Budgets where Id = ?1", [budget_id])?;
+            auditor.record(
+                connection,
+                "delete_budget",
+                "budget",
+                budget_id,
+                Some(&budget),
+                None,
+            )
+        })
+        .await
+    }
+
+    async fn get_budget_progress(
+        &self,
+        budget: &Budget,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<BudgetProgress> {
+        let budget_id = budget.id;
+        self.execute_query(move |connection| {
+            let budget = get_budget_by_id(connection, budget_id)?;
+            budget_progress(connection, &budget, date)
+        })
+        .await
+    }
+
+    async fn get_budgets_progress(
+        &self,
+        user: &User,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<Vec<BudgetProgress>> {
+        let user_id = user.id;
+        self.execute_query(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "Select {} from Budgets where UserId = ?1 order by Id",
+                BUDGET_COLUMNS
+            ))?;
+            let budgets = statement
+                .query_map([user_id], budget_from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            budgets
+                .iter()
+                .map(|budget| budget_progress(connection, budget, date))
+                .collect()
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl RecurringProvider for SqliteProvider {
+    async fn add_recurring(
+        &self,
+        add_recurring_command: &AddRecurringCommand,
+    ) -> MoneyCalcResult<()> {
+        let command = add_recurring_command.clone();
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            validate_recurring(connection, &command)?;
+            let (kind, value) = schedule_to_sql(&command.schedule);
+            connection.execute(
+                "Insert into RecurringTransactions(UserId, AccountId, Amount, Currency, PaymentType, PaymentTarget, Description, CategoryId, ScheduleKind, ScheduleValue, StartDate, EndDate) Values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                params![
+                    command.user_id,
+                    command.account_id,
+                    command.amount.minor_units(),
+                    command.amount.currency(),
+                    command.payment_type,
+                    command.payment_target,
+                    command.description,
+                    command.category_id,
+                    kind,
+                    value,
+                    command.start_date,
+                    command.end_date,
+                ],
+            )?;
+            let recurring =
+                get_recurring_by_id(connection, connection.last_insert_rowid() as i32)?;
+            auditor.record(
+                connection,
+                "add_recurring",
+                "recurring",
+                recurring.id,
+                None,
+                Some(&recurring),
+            )
+        })
+        .await
+    }
+
+    async fn get_recurring(&self, user: &User) -> MoneyCalcResult<Vec<RecurringTransaction>> {
+        let user_id = user.id;
+        self.execute_query(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "Select {} from RecurringTransactions where UserId = ?1 order by Id",
+                RECURRING_COLUMNS
+            ))?;
+            let recurring = statement
+                .query_map([user_id], recurring_from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(recurring)
+        })
+        .await
+    }
+
+    async fn get_recurring_by_id(&self, id: i32) -> MoneyCalcResult<RecurringTransaction> {
+        self.execute_query(move |connection| get_recurring_by_id(connection, id))
+            .await
+    }
+
+    async fn delete_recurring(&self, recurring: &RecurringTransaction) -> MoneyCalcResult<()> {
+        let recurring_id = recurring.id;
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let recurring = get_recurring_by_id(connection, recurring_id)?;
+            connection.execute(
+                "Delete from RecurringOccurrences where RecurringId = ?1",
+                [recurring_id],
+            )?;
+            connection.execute(
+                "Delete from RecurringTransactions where Id = ?1",
+                [recurring_id],
+            )?;
+            auditor.record(
+                connection,
+                "delete_recurring",
+                "recurring",
+                recurring_id,
+                Some(&recurring),
+                None,
+            )
+        })
+        .await
+    }
+
+    async fn get_occurrences(
+        &self,
+        recurring: &RecurringTransaction,
+    ) -> MoneyCalcResult<Vec<RecurringOccurrence>> {
+        let recurring_id = recurring.id;
+        self.execute_query(move |connection| {
+            let mut statement = connection.prepare(
+                "Select RecurringId, OccurrenceDate, TransactionId from RecurringOccurrences where RecurringId = ?1 order by OccurrenceDate",
+            )?;
+            let occurrences = statement
+                .query_map([recurring_id], |row| {
+                    Ok(RecurringOccurrence {
+                        recurring_id: row.get(0)?,
+                        date: row.get(1)?,
+                        transaction_id: row.get(2)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(occurrences)
+        })
+        .await
+    }
+
+    async fn generate_due(&self, now: NaiveDateTime) -> MoneyCalcResult<GenerationReport> {
+        let ids = self
+            .execute_query(|connection| {
+                let mut statement =
+                    connection.prepare("Select Id from RecurringTransactions order by Id")?;
+                let ids = statement
+                    .query_map([], |row| row.get::<_, i32>(0))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(ids)
+            })
+            .await?;
+
+        let mut report = GenerationReport::default();
+        for id in ids {
+            let auditor = self.auditor.clone();
+            let generated = self
+                .execute_in_transaction(move |connection| {
+                    generate_occurrences(connection, &auditor, id, now.date())
+                })
+                .await;
+            match generated {
+                Ok(generated) => report.occurrences.extend(generated),
+                Err(error) => report.failures.push(RecurringFailure {
+                    recurring_id: id,
+                    message: error.to_string(),
+                }),
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[async_trait]
+impl ReconciliationProvider for SqliteProvider {
+    async fn reconcile_balances(&self, repair: bool) -> MoneyCalcResult<ReconciliationReport> {
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let sql = format!(
+                "Select a.Id, a.MoneyCount, a.Currency, a.InitialBalance + IFNULL((Select sum({LEDGER_AMOUNT}) from Transactions t where t.AccountId = a.Id), 0) from Accounts a order by a.Id"
+            );
+            let balances = connection
+                .prepare(&sql)?
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, i32>(0)?,
+                        Money::from_minor(row.get(1)?, row.get(2)?),
+                        Money::from_minor(row.get(3)?, row.get(2)?),
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let mut discrepancies = vec![];
+            for &(account_id, stored, expected) in &balances {
+                if stored == expected {
+                    continue;
+                }
+                discrepancies.push(BalanceDiscrepancy {
+                    account_id,
+                    stored,
+                    expected,
+                    difference: stored.checked_sub(expected)?,
+                });
+                if repair {
+                    let before = get_account_by_id(connection, account_id)?;
+                    connection.execute(
+                        "Update Accounts set MoneyCount = ?2 where Id = ?1",
+                        params![account_id, expected.minor_units()],
+                    )?;
+                    auditor.record(
+                        connection,
+                        "reconcile_balances",
+                        "account",
+                        account_id,
+                        Some(&before),
+                        Some(&get_account_by_id(connection, account_id)?),
+                    )?;
+                }
+            }
+            Ok(ReconciliationReport {
+                checked_accounts: balances.len(),
+                discrepancies,
+                repaired: repair,
+            })
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl BalanceHistoryProvider for SqliteProvider {
+    async fn get_balance_at(&self, account: &Account, at: NaiveDateTime) -> MoneyCalcResult<Money> {
+        let account_id = account.id;
+        self.execute_query(move |connection| balance_at(connection, account_id, at))
+            .await
+    }
+
+    async fn get_daily_balances(
+        &self,
+        account: &Account,
+        from_date: NaiveDate,
+        to_date: NaiveDate,
+    ) -> MoneyCalcResult<Vec<DailyBalance>> {
+        if from_date > to_date {
+            return Err(MoneyCalcError::Validation(format!(
+                "balance history from {} is after {}",
+                from_date, to_date
+            )));
+        }
+        let account_id = account.id;
+        self.execute_query(move |connection| {
+            let opening = match from_date.pred_opt() {
+                Some(previous) => balance_at(connection, account_id, end_of_day(previous))?,
+                None => balance_at(connection, account_id, NaiveDateTime::MIN)?,
+            };
+            let mut statement = connection.prepare(&format!(
+                "Select date(t.CreationDate), sum({LEDGER_AMOUNT}) from Transactions t where t.AccountId = ?1 and t.CreationDate >= ?2 and t.CreationDate <= ?3 group by date(t.CreationDate) order by 1"
+            ))?;
+            let changes = statement
+                .query_map(
+                    params![
+                        account_id,
+                        from_date.and_time(NaiveTime::MIN),
+                        end_of_day(to_date)
+                    ],
+                    |row| Ok((row.get::<_, NaiveDate>(0)?, row.get::<_, i64>(1)?)),
+                )?
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let mut changes = changes.into_iter().peekable();
+            let mut balance = opening.minor_units();
+            let mut history = vec![];
+            for date in from_date.iter_days().take_while(|date| *date <= to_date) {
+                if let Some((_, change)) = changes.next_if(|(day, _)| *day == date) {
+                    balance += change;
+                }
+                history.push(DailyBalance {
+                    date,
+                    balance: Money::from_minor(balance, opening.currency()),
+                });
+            }
+            Ok(history)
+        })
+        .await
+    }
+
+    async fn add_balance_snapshot(
+        &self,
+        account: &Account,
+        at: NaiveDateTime,
+    ) -> MoneyCalcResult<BalanceSnapshot> {
+        let account_id = account.id;
+        let auditor = self.auditor.clone();
+        self.execute_in_transaction(move |connection| {
+            let snapshot = BalanceSnapshot {
+                account_id,
+                date: at,
+                balance: balance_at(connection, account_id, at)?,
+            };
+            connection.execute(
+                "Insert or replace into BalanceSnapshots(AccountId, SnapshotDate, Balance) Values (?1, ?2, ?3)",
+                params![account_id, at, snapshot.balance.minor_units()],
+            )?;
+            auditor.record(
+                connection,
+                "add_balance_snapshot",
+                "balance_snapshot",
+                account_id,
+                None,
+                Some(&snapshot),
+            )?;
+            Ok(snapshot)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl AuditProvider for SqliteProvider {
+    async fn get_audit_log(&self, query: &AuditQuery) -> MoneyCalcResult<Vec<AuditEntry>> {
+        if query.limit == 0 {
+            return Err(MoneyCalcError::Validation(
+                "audit query limit must be positive".to_string(),
+            ));
+        }
+        let query = query.clone();
+        self.execute_query(move |connection| {
+            let mut conditions: Vec<&str> = vec![];
+            let mut params: Vec<Box<dyn ToSql>> = vec![];
+            let text_filters = [
+                ("Actor = ?", query.actor),
+                ("Action = ?", query.action),
+                ("Entity = ?", query.entity),
+                ("EntityId = ?", query.entity_id),
+            ];
+            for (condition, value) in text_filters {
+                if let Some(value) = value {
+                    conditions.push(condition);
+                    params.push(Box::new(value));
+                }
+            }
+            if let Some(from_date) = query.from_date {
+                conditions.push("CreationDate >= ?");
+                params.push(Box::new(from_date.format(AUDIT_DATE_FORMAT).to_string()));
+            }
+            if let Some(to_date) = query.to_date {
+                conditions.push("CreationDate < ?");
+                params.push(Box::new(to_date.format(AUDIT_DATE_FORMAT).to_string()));
+            }
+            if let Some(after_id) = query.after_id {
+                conditions.push("Id > ?");
+                params.push(Box::new(after_id));
+            }
+            let mut sql = format!("Select {} from AuditLog", AUDIT_COLUMNS);
+            if !conditions.is_empty() {
+                sql.push_str(" where ");
+                sql.push_str(&conditions.join(" and "));
+            }
+            sql.push_str(" order by Id limit ?");
+            params.push(Box::new(query.limit));
+
+            let mut statement = connection.prepare(&sql)?;
+            let entries = statement
+                .query_map(params_from_iter(params.iter()), audit_entry_from_row)?
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(entries)
+        })
+        .await
+    }
+
+    async fn verify_audit_log(&self) -> MoneyCalcResult<Option<i64>> {
+        self.execute_query(|connection| {
+            let mut statement = connection.prepare(
+                "Select Id, Actor, Action, Entity, EntityId, Before, After, CreationDate, Hash from AuditLog order by Id",
+            )?;
+            let mut rows = statement.query([])?;
+            let mut previous: Option<String> = None;
+            while let Some(row) = rows.next()? {
+                let Some(hash) = row.get::<_, Option<String>>(8)? else {
+                    if previous.is_some() {
+                        return Ok(Some(row.get(0)?));
+                    }
+                    continue;
+                };
+                let fields: Vec<Option<String>> =
+                    (1..8).map(|index| row.get(index)).collect::<Result<_, _>>()?;
+                let expected = audit_hash([
+                    previous.as_deref(),
+                    fields[0].as_deref(),
+                    fields[1].as_deref(),
+                    fields[2].as_deref(),
+                    fields[3].as_deref(),
+                    fields[4].as_deref(),
+                    fields[5].as_deref(),
+                    fields[6].as_deref(),
+                ]);
+                if hash != expected {
+                    return Ok(Some(row.get(0)?));
+                }
+                previous = Some(hash);
+            }
+            Ok(None)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use std::str::FromStr;
+    use tokio::fs;
+
+    use crate::{
+        commands::{
+            accounts::addaccountcommand::AddAccountCommand,
+            audit::auditquery::AuditQuery,
+            budgets::addbudgetcommand::AddBudgetCommand,
+            categories::addcategorycommand::AddCategoryCommand,
+            recurring::addrecurringcommand::AddRecurringCommand,
+            tags::addtagcommand::AddTagCommand,
+            transactions::{
+                amendtransactioncommand::AmendTransactionCommand,
+                reversetransactioncommand::ReverseTransactionCommand,
+                transactionquery::{TransactionOrder, TransactionQuery},
+                transfercommand::TransferCommand,
+            },
+            users::addusercommand::AddUserCommand,
+        },
+        config::SqliteConfiguration,
+        errors::MoneyCalcError,
+        models::{
+            account::{Account, OverdraftPolicy},
+            budget::{BudgetPeriod, BudgetScope},
+            category::Category,
+            exchangerate::ExchangeRate,
+            money::{Currency, Money},
+            moneytransaction::{MoneyTransaction, PaymentType},
+            recurring::{GenerationReport, Schedule},
+            user::User,
+        },
+        providers::{
+            AccountProvider, AuditProvider, BalanceHistoryProvider, BudgetProvider,
+            CategoryProvider, ExchangeRateProvider, ReconciliationProvider, RecurringProvider,
+            TagProvider, TransactionWorker, UserProvider, bases::sqlite::SqliteProvider,
+        },
+    };
+
+    #[tokio::test]
+    async fn create_test_file_base() {
+        let config = SqliteConfiguration::new("./testbases/testbase.db3");
+        let _ = SqliteProvider::new(&config, false);
+        assert!(check_exist(config.connection_string.as_str()).await);
+    }
+
+    #[test]
+    fn create_test_memory_base() {
+        let config = SqliteConfiguration::memory_base();
+        let _ = SqliteProvider::new(&config, false).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn clone_shares_memory_base_test() {
+        let config = SqliteConfiguration::memory_base().with_pool_size(3);
+        let sqlite_provider = SqliteProvider::new(&config, true).unwrap();
+        assert_eq!(sqlite_provider.config().pool_size, 3);
+
+        let handles: Vec<_> = (0..8)
+            .map(|index| {
+                let sqlite_provider = sqlite_provider.clone();
+                tokio::spawn(async move {
+                    sqlite_provider
+                        .add_user(&AddUserCommand {
+                            user_name: format!("user {}", index),
+                            user_number: index.to_string(),
+                        })
+                        .await
+                        .unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        let clone = sqlite_provider.clone();
+        drop(sqlite_provider);
+        assert_eq!(clone.get_users().await.unwrap().len(), 8);
+        assert!(
+            SqliteProvider::new(&SqliteConfiguration::memory_base().with_pool_size(0), true)
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn add_migration_test() {
+        let config = SqliteConfiguration::new("./testbases/testbase_mig.db3");
+        let _ = SqliteProvider::new(&config, true);
+        assert!(check_exist(config.connection_string.as_str()).await);
+    }
+
+    #[tokio::test]
+    async fn add_user_test() {
+        let config = SqliteConfiguration::memory_base();
+        let sqlite_provider = SqliteProvider::new(&config, true).unwrap();
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scam").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        sqlite_provider.add_user(&add_user_command).await.unwrap();
+        let user = sqlite_provider
+            .get_user_by_number(add_user_command.user_number.as_str())
+            .await
+            .unwrap();
+
+        sqlite_provider.delete_user_by_id(user.id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn add_user_notunique_test() {
+        let config = SqliteConfiguration::new("./testbases/testbase_user.db3");
+        let sqlite_provider = SqliteProvider::new(&config, true).unwrap();
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scam").unwrap(),
+            user_number: String::from_str("88005553535").unwrap(),
+        };
+
+        assert!(matches!(
+            sqlite_provider.add_user(&add_user_command).await,
+            Err(MoneyCalcError::Conflict(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn errors_mapping_test() {
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scam").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        let sqlite_provider = configure_sql_with_user(&add_user_command).await;
+
+        assert!(matches!(
+            sqlite_provider.get_user_by_number("missing").await,
+            Err(MoneyCalcError::NotFound(_))
+        ));
+        assert!(matches!(
+            sqlite_provider.get_account_by_id(42).await,
+            Err(MoneyCalcError::NotFound(_))
+        ));
+        assert!(matches!(
+            sqlite_provider.delete_user_by_id(42).await,
+            Err(MoneyCalcError::NotFound(_))
+        ));
+        assert!(matches!(
+            sqlite_provider.add_user(&add_user_command).await,
+            Err(MoneyCalcError::Conflict(_))
+        ));
+        assert!(matches!(
+            sqlite_provider
+                .transfer(&create_transfer_command(1, 1, rub("1.00"), None))
+                .await,
+            Err(MoneyCalcError::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    #[allow(clippy::len_zero)]
+    async fn get_users_test() {
+        let config = SqliteConfiguration::new("./testbases/testbase_users.db3");
+        let sqlite_provider = SqliteProvider::new(&config, true).unwrap();
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scam").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        sqlite_provider.add_user(&add_user_command).await.unwrap();
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scamjr").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        sqlite_provider.add_user(&add_user_command).await.unwrap();
+
+        let users = sqlite_provider.get_users().await.unwrap();
+        assert!(users.len() > 0);
+
+        drop(sqlite_provider);
+        fs::remove_file("./testbases/testbase_users.db3")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn get_user_by_number_test() {
+        let config = SqliteConfiguration::new("./testbases/testbase_user.db3");
+        let sqlite_provider = SqliteProvider::new(&config, true).unwrap();
+        let user_res = sqlite_provider.get_user_by_number("88005553535").await;
+        match user_res {
+            Ok(user) => {
+                assert!(user.name.eq("scam"));
+            }
+            Err(err) => {
+                panic!("{}", err)
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn add_account_to_db() {
+        let config = SqliteConfiguration::new("./testbases/testbase_accounts.db3");
+        let sqlite_provider = SqliteProvider::new(&config, true).unwrap();
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scam").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        sqlite_provider.add_user(&add_user_command).await.unwrap();
+        let add_account_command = create_add_account_command(1, rub("50000.00"));
+        sqlite_provider
+            .add_account(&add_account_command)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[allow(unused_variables, clippy::len_zero)]
+    async fn delete_users_test() {
+        let config = SqliteConfiguration::new("./testbases/testbase_user_delete.db3");
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scam").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        let sqlite_provider = SqliteProvider::new(&config, true).unwrap();
+
+        sqlite_provider.add_user(&add_user_command).await.unwrap();
+        let user = sqlite_provider
+            .get_user_by_number(add_user_command.user_number.as_str())
+            .await
+            .unwrap();
+
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scamer").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        sqlite_provider.add_user(&add_user_command).await.unwrap();
+
+        let users = sqlite_provider.get_users().await.unwrap();
+        assert!(users.len() > 0);
+
+        for user in users {
+            sqlite_provider.delete_user_by_id(user.id).await.unwrap();
+        }
+        let users = sqlite_provider.get_users().await.unwrap();
+
+        assert!(users.len() == 0);
+
+        drop(sqlite_provider);
+        std::fs::remove_file("./testbases/testbase_user_delete.db3").unwrap();
+    }
+
+    #[tokio::test]
+    async fn account_lifecircle_test() {
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scam").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        let sqlite_provider = configure_sql_with_user(&add_user_command).await;
+        let user = sqlite_provider
+            .get_user_by_number(add_user_command.user_number.as_str())
+            .await
+            .unwrap();
+        let add_account_command = create_add_account_command(1, rub("50000.00"));
+        sqlite_provider
+            .add_account(&add_account_command)
+            .await
+            .unwrap();
+        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        sqlite_provider
+            .change_money(&account, rub("100000.00"))
+            .await
+            .unwrap();
+        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        assert_eq!(account.money, rub("150000.00"));
+        assert_eq!(account.name, add_account_command.account_name);
+        sqlite_provider.delete_account(&account).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn multiple_accounts_test() {
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scam").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        let sqlite_provider = configure_sql_with_user(&add_user_command).await;
+        let user = sqlite_provider
+            .get_user_by_number(add_user_command.user_number.as_str())
+            .await
+            .unwrap();
+        for name in ["Checking", "Savings", "Cash"] {
+            sqlite_provider
+                .add_account(&AddAccountCommand {
+                    user_id: user.id,
+                    account_name: name.to_string(),
+                    initial_balance: rub("10.00"),
+                    overdraft: OverdraftPolicy::default(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let accounts = sqlite_provider.get_accounts_by_user(&user).await.unwrap();
+        assert_eq!(accounts.len(), 3);
+        assert!(accounts[0].is_primary);
+        assert!(!accounts[1].is_primary && !accounts[2].is_primary);
+        let primary = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        assert_eq!(primary.name, "Checking");
+
+        let savings = sqlite_provider
+            .get_account_by_name(&user, "Savings")
+            .await
+            .unwrap();
+        assert_eq!(
+            sqlite_provider
+                .get_account_by_id(savings.id)
+                .await
+                .unwrap()
+                .name,
+            "Savings"
+        );
+        assert!(
+            sqlite_provider
+                .get_account_by_name(&user, "Missing")
+                .await
+                .is_err()
+        );
+
+        sqlite_provider.set_primary_account(&savings).await.unwrap();
+        let primary = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        assert_eq!(primary.id, savings.id);
+        let accounts = sqlite_provider.get_accounts_by_user(&user).await.unwrap();
+        assert_eq!(accounts.iter().filter(|a| a.is_primary).count(), 1);
+
+        sqlite_provider.delete_account(&savings).await.unwrap();
+        let primary = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        assert_eq!(primary.name, "Checking");
+        assert!(primary.is_primary);
+    }
+
+    #[tokio::test]
+    async fn transaction_execute_test() {
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scam").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        let sqlite_provider = configure_sql_with_user(&add_user_command).await;
+        let user = sqlite_provider
+            .get_user_by_number(add_user_command.user_number.as_str())
+            .await
+            .unwrap();
+        let add_account_command = create_add_account_command(1, rub("50000.00"));
+        sqlite_provider
+            .add_account(&add_account_command)
+            .await
+            .unwrap();
+        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        sqlite_provider
+            .execute_transaction(&MoneyTransaction {
+                description: "Test transcation".to_string(),
+                amount: rub("200000.00"),
+                user: user.clone(),
+                account: account.clone(),
+                payment_type: PaymentType::Income,
+                payment_target: "Test".to_string(),
+                id: "".to_string(),
+                create_date: chrono::Utc::now().naive_utc(),
+                linked_transaction_id: None,
+                correction_of: None,
+                category_id: None,
+            })
+            .await
+            .unwrap();
+        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        assert_eq!(account.money, rub("250000.00"));
+    }
+
+    #[tokio::test]
+    async fn transaction_precision_test() {
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scam").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        let sqlite_provider = configure_sql_with_user(&add_user_command).await;
+        let user = sqlite_provider
+            .get_user_by_number(add_user_command.user_number.as_str())
+            .await
+            .unwrap();
+        let add_account_command = create_add_account_command(1, rub("16777216.00"));
+        sqlite_provider
+            .add_account(&add_account_command)
+            .await
+            .unwrap();
+        for _ in 0..100 {
+            let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
+            sqlite_provider
+                .execute_transaction(&MoneyTransaction {
+                    description: "Cent".to_string(),
+                    amount: rub("0.01"),
+                    user: user.clone(),
+                    account,
+                    payment_type: PaymentType::Income,
+                    payment_target: "Test".to_string(),
+                    id: "".to_string(),
+                    create_date: chrono::Utc::now().naive_utc(),
+                    linked_transaction_id: None,
+                    correction_of: None,
+                    category_id: None,
+                })
+                .await
+                .unwrap();
+        }
+        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        assert_eq!(account.money, rub("16777217.00"));
+    }
+
+    #[tokio::test]
+    async fn transaction_currency_mismatch_test() {
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scam").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        let sqlite_provider = configure_sql_with_user(&add_user_command).await;
+        let user = sqlite_provider
+            .get_user_by_number(add_user_command.user_number.as_str())
+            .await
+            .unwrap();
+        let add_account_command = create_add_account_command(1, rub("100.00"));
+        sqlite_provider
+            .add_account(&add_account_command)
+            .await
+            .unwrap();
+        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        let result = sqlite_provider
+            .execute_transaction(&MoneyTransaction {
+                description: "Dollars".to_string(),
+                amount: Money::parse("10.00", Currency::USD).unwrap(),
+                user: user.clone(),
+                account,
+                payment_type: PaymentType::Income,
+                payment_target: "Test".to_string(),
+                id: "".to_string(),
+                create_date: chrono::Utc::now().naive_utc(),
+                linked_transaction_id: None,
+                correction_of: None,
+                category_id: None,
+            })
+            .await;
+        assert!(result.is_err());
+        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        assert_eq!(account.money, rub("100.00"));
+    }
+
+    #[tokio::test]
+    async fn transaction_stale_account_test() {
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scam").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        let sqlite_provider = configure_sql_with_user(&add_user_command).await;
+        let user = sqlite_provider
+            .get_user_by_number(add_user_command.user_number.as_str())
+            .await
+            .unwrap();
+        let add_account_command = create_add_account_command(1, rub("100.00"));
+        sqlite_provider
+            .add_account(&add_account_command)
+            .await
+            .unwrap();
+        let stale_account = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        for payment_type in [
+            PaymentType::Income,
+            PaymentType::Outcome,
+            PaymentType::Income,
+        ] {
+            sqlite_provider
+                .execute_transaction(&MoneyTransaction {
+                    description: "Stale".to_string(),
+                    amount: rub("30.00"),
+                    user: user.clone(),
+                    account: stale_account.clone(),
+                    payment_type,
+                    payment_target: "Test".to_string(),
+                    id: "".to_string(),
+                    create_date: chrono::Utc::now().naive_utc(),
+                    linked_transaction_id: None,
+                    correction_of: None,
+                    category_id: None,
+                })
+                .await
+                .unwrap();
+        }
+        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        assert_eq!(account.money, rub("130.00"));
+    }
+
+    #[tokio::test]
+    async fn transaction_rollback_test() {
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scam").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        let sqlite_provider = configure_sql_with_user(&add_user_command).await;
+        let user = sqlite_provider
+            .get_user_by_number(add_user_command.user_number.as_str())
+            .await
+            .unwrap();
+        let add_account_command = create_add_account_command(1, rub("100.00"));
+        sqlite_provider
+            .add_account(&add_account_command)
+            .await
+            .unwrap();
+        sqlite_provider
+            .execute_query(|connection| {
+                connection.execute(
+                    "CREATE TRIGGER fail_insert BEFORE INSERT ON Transactions BEGIN SELECT RAISE(ABORT, 'insert failed'); END;",
+                    [],
+                )?;
+                Ok(())
+            })
+            .await
+            .unwrap();
+        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        let result = sqlite_provider
+            .execute_transaction(&MoneyTransaction {
+                description: "Lost".to_string(),
+                amount: rub("50.00"),
+                user: user.clone(),
+                account,
+                payment_type: PaymentType::Outcome,
+                payment_target: "Test".to_string(),
+                id: "".to_string(),
+                create_date: chrono::Utc::now().naive_utc(),
+                linked_transaction_id: None,
+                correction_of: None,
+                category_id: None,
+            })
+            .await;
+        assert!(result.is_err());
+        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        assert_eq!(account.money, rub("100.00"));
+    }
+
+    #[tokio::test]
+    async fn overdraft_policy_test() {
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scam").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        let sqlite_provider = configure_sql_with_user(&add_user_command).await;
+        let user = sqlite_provider
+            .get_user_by_number(add_user_command.user_number.as_str())
+            .await
+            .unwrap();
+        for balance in ["100.00", "0.00"] {
+            sqlite_provider
+                .add_account(&create_add_account_command(1, rub(balance)))
+                .await
+                .unwrap();
+        }
+        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        assert_eq!(account.overdraft, OverdraftPolicy::Forbid);
+        let outcome = |amount: &str| MoneyTransaction {
+            description: "Outcome".to_string(),
+            amount: rub(amount),
+            user: user.clone(),
+            account: account.clone(),
+            payment_type: PaymentType::Outcome,
+            payment_target: "Shop".to_string(),
+            id: "".to_string(),
+            create_date: chrono::Utc::now().naive_utc(),
+            linked_transaction_id: None,
+            correction_of: None,
+            category_id: None,
+        };
+
+        assert!(matches!(
+            sqlite_provider
+                .execute_transaction(&outcome("100.01"))
+                .await,
+            Err(MoneyCalcError::InsufficientFunds(_))
+        ));
+        assert!(matches!(
+            sqlite_provider
+                .transfer(&create_transfer_command(1, 2, rub("150.00"), None))
+                .await,
+            Err(MoneyCalcError::InsufficientFunds(_))
+        ));
+        assert!(matches!(
+            sqlite_provider.change_money(&account, rub("-100.01")).await,
+            Err(MoneyCalcError::InsufficientFunds(_))
+        ));
+        sqlite_provider
+            .execute_transaction(&outcome("100.00"))
+            .await
+            .unwrap();
+
+        sqlite_provider
+            .set_overdraft_policy(&account, OverdraftPolicy::Limit(rub("50.00")))
+            .await
+            .unwrap();
+        sqlite_provider
+            .execute_transaction(&outcome("50.00"))
+            .await
+            .unwrap();
+        assert!(
+            sqlite_provider
+                .execute_transaction(&outcome("0.01"))
+                .await
+                .is_err()
+        );
+        let account = sqlite_provider.get_account_by_id(account.id).await.unwrap();
+        assert_eq!(account.money, rub("-50.00"));
+        assert_eq!(account.overdraft, OverdraftPolicy::Limit(rub("50.00")));
+
+        assert!(matches!(
+            sqlite_provider
+                .set_overdraft_policy(&account, OverdraftPolicy::Limit(rub("-1.00")))
+                .await,
+            Err(MoneyCalcError::Validation(_))
+        ));
+        assert!(
+            sqlite_provider
+                .set_overdraft_policy(
+                    &account,
+                    OverdraftPolicy::Limit(Money::parse("1.00", Currency::USD).unwrap())
+                )
+                .await
+                .is_err()
+        );
+        sqlite_provider
+            .set_overdraft_policy(&account, OverdraftPolicy::Unlimited)
+            .await
+            .unwrap();
+        sqlite_provider
+            .execute_transaction(&outcome("1000.00"))
+            .await
+            .unwrap();
+        let account = sqlite_provider.get_account_by_id(account.id).await.unwrap();
+        assert_eq!(account.money, rub("-1050.00"));
+    }
+
+    #[tokio::test]
+    async fn transfer_test() {
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scam").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        let sqlite_provider = configure_sql_with_user(&add_user_command).await;
+        for balance in ["100.00", "5.00"] {
+            sqlite_provider
+                .add_account(&create_add_account_command(1, rub(balance)))
+                .await
+                .unwrap();
+        }
+
+        sqlite_provider
+            .transfer(&create_transfer_command(1, 2, rub("30.50"), None))
+            .await
+            .unwrap();
+
+        let accounts = sqlite_provider.get_accounts().await.unwrap();
+        assert_eq!(accounts[0].money, rub("69.50"));
+        assert_eq!(accounts[1].money, rub("35.50"));
+        let links: Vec<(String, Option<String>, i64)> = sqlite_provider
+            .execute_query(|connection| {
+                let mut statement = connection.prepare(
+                    "Select Id, LinkedTransactionId, Amount from Transactions order by AccountId",
+                )?;
+                let rows = statement
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(rows)
+            })
+            .await
+            .unwrap();
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].1.as_ref(), Some(&links[1].0));
+        assert_eq!(links[1].1.as_ref(), Some(&links[0].0));
+        assert_eq!(links[0].2, -3050);
+        assert_eq!(links[1].2, 3050);
+
+        assert!(
+            sqlite_provider
+                .transfer(&create_transfer_command(1, 1, rub("1.00"), None))
+                .await
+                .is_err()
+        );
+        assert!(
+            sqlite_provider
+                .transfer(&create_transfer_command(1, 2, rub("-1.00"), None))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn transfer_exchange_rate_test() {
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scam").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        let sqlite_provider = configure_sql_with_user(&add_user_command).await;
+        sqlite_provider
+            .add_account(&create_add_account_command(1, rub("1000.00")))
+            .await
+            .unwrap();
+        sqlite_provider
+            .add_account(&create_add_account_command(1, Money::zero(Currency::USD)))
+            .await
+            .unwrap();
+
+        let result = sqlite_provider
+            .transfer(&create_transfer_command(1, 2, rub("925.00"), None))
+            .await;
+        assert!(result.is_err());
+        let wrong_rate = ExchangeRate::new(Currency::EUR, Currency::USD, "1.1").unwrap();
+        let result = sqlite_provider
+            .transfer(&create_transfer_command(
+                1,
+                2,
+                rub("925.00"),
+                Some(wrong_rate),
+            ))
+            .await;
+        assert!(result.is_err());
+
+        let rate = ExchangeRate::new(Currency::RUB, Currency::USD, "0.01081081").unwrap();
+        sqlite_provider
+            .transfer(&create_transfer_command(1, 2, rub("925.00"), Some(rate)))
+            .await
+            .unwrap();
+        let accounts = sqlite_provider.get_accounts().await.unwrap();
+        assert_eq!(accounts[0].money, rub("75.00"));
+        assert_eq!(
+            accounts[1].money,
+            Money::parse("10.00", Currency::USD).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn exchange_rates_test() {
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scam").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        let sqlite_provider = configure_sql_with_user(&add_user_command).await;
+        let user = sqlite_provider
+            .get_user_by_number(add_user_command.user_number.as_str())
+            .await
+            .unwrap();
+        sqlite_provider
+            .add_account(&create_add_account_command(1, rub("1000.00")))
+            .await
+            .unwrap();
+        sqlite_provider
+            .add_account(&create_add_account_command(
+                1,
+                Money::parse("10.00", Currency::USD).unwrap(),
+            ))
+            .await
+            .unwrap();
+        let date = |day| chrono::NaiveDate::from_ymd_opt(2025, 3, day).unwrap();
+        for (day, rate) in [(1, "80"), (15, "100")] {
+            sqlite_provider
+                .add_exchange_rate(
+                    &ExchangeRate::new(Currency::USD, Currency::RUB, rate).unwrap(),
+                    date(day),
+                )
+                .await
+                .unwrap();
+        }
+
+        assert!(matches!(
+            sqlite_provider
+                .get_exchange_rate(Currency::USD, Currency::RUB, date(1).pred_opt().unwrap())
+                .await,
+            Err(MoneyCalcError::NotFound(_))
+        ));
+        let rate = sqlite_provider
+            .get_exchange_rate(Currency::USD, Currency::RUB, date(14))
+            .await
+            .unwrap();
+        assert_eq!(rate.to_string(), "USD/RUB 80");
+        assert_eq!(
+            sqlite_provider
+                .get_total_balance(&user, Currency::RUB, date(14))
+                .await
+                .unwrap(),
+            rub("1800.00")
+        );
+        assert_eq!(
+            sqlite_provider
+                .get_total_balance(&user, Currency::USD, date(31))
+                .await
+                .unwrap(),
+            Money::parse("20.00", Currency::USD).unwrap()
+        );
+        assert!(
+            sqlite_provider
+                .get_total_balance(&user, Currency::EUR, date(31))
+                .await
+                .is_err()
+        );
+
+        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        let transaction = MoneyTransaction {
+            description: "Dinner".to_string(),
+            amount: rub("500.00"),
+            user: user.clone(),
+            account,
+            payment_type: PaymentType::Outcome,
+            payment_target: "Cafe".to_string(),
+            id: "".to_string(),
+            create_date: date(2).and_hms_opt(20, 0, 0).unwrap(),
+            linked_transaction_id: None,
+            correction_of: None,
+            category_id: None,
+        };
+        let converted = sqlite_provider
+            .convert_transactions(&[transaction], Currency::USD)
+            .await
+            .unwrap();
+        assert_eq!(
+            converted[0].amount,
+            Money::parse("6.25", Currency::USD).unwrap()
+        );
+        assert_eq!(
+            sqlite_provider
+                .convert_money(rub("1.00"), Currency::RUB, date(1))
+                .await
+                .unwrap(),
+            rub("1.00")
+        );
+    }
+
+    #[tokio::test]
+    async fn exchange_rate_direction_test() {
+        let sqlite_provider =
+            SqliteProvider::new(&SqliteConfiguration::memory_base(), true).unwrap();
+        let date = |year, month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        for (from, to, rate, day) in [
+            (Currency::USD, Currency::EUR, "0.9", date(2020, 1, 1)),
+            (Currency::EUR, Currency::USD, "1.25", date(2025, 3, 1)),
+            (Currency::USD, Currency::EUR, "0.75", date(2025, 3, 10)),
+            (Currency::EUR, Currency::USD, "2", date(2025, 3, 10)),
+        ] {
+            sqlite_provider
+                .add_exchange_rate(&ExchangeRate::new(from, to, rate).unwrap(), day)
+                .await
+                .unwrap();
+        }
+        let rate = async |from, to, day| {
+            sqlite_provider
+                .get_exchange_rate(from, to, day)
+                .await
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(
+            rate(Currency::USD, Currency::EUR, date(2024, 12, 31)).await,
+            "USD/EUR 0.9"
+        );
+        // Newer inverse rate wins over older direct one.
+        assert_eq!(
+            rate(Currency::USD, Currency::EUR, date(2025, 3, 2)).await,
+            "USD/EUR 0.8"
+        );
+        // Direct rate wins on the same date.
+        assert_eq!(
+            rate(Currency::USD, Currency::EUR, date(2025, 3, 10)).await,
+            "USD/EUR 0.75"
+        );
+        assert_eq!(
+            rate(Currency::EUR, Currency::USD, date(2025, 3, 10)).await,
+            "EUR/USD 2"
+        );
+    }
+
+    #[tokio::test]
+    async fn category_hierarchy_test() {
+        let (sqlite_provider, user, accounts) = configure_sql_with_accounts(&["1000.00"]).await;
+        let account = &accounts[0];
+        let add_category = |parent_id, name: &str| AddCategoryCommand {
+            user_id: user.id,
+            parent_id,
+            name: name.to_string(),
+        };
+        sqlite_provider
+            .add_category(&add_category(None, "Food"))
+            .await
+            .unwrap();
+        sqlite_provider
+            .add_category(&add_category(Some(1), "Groceries"))
+            .await
+            .unwrap();
+        sqlite_provider
+            .add_category(&add_category(None, "Transport"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            sqlite_provider
+                .add_category(&add_category(Some(1), "Groceries"))
+                .await,
+            Err(MoneyCalcError::Conflict(_))
+        ));
+        assert!(matches!(
+            sqlite_provider
+                .add_category(&add_category(Some(42), "Lost"))
+                .await,
+            Err(MoneyCalcError::NotFound(_))
+        ));
+        let categories = sqlite_provider.get_categories(&user).await.unwrap();
+        assert_eq!(categories.len(), 3);
+        let (food, groceries, transport) = (
+            categories[0].clone(),
+            categories[1].clone(),
+            categories[2].clone(),
+        );
+        assert_eq!(groceries.parent_id, Some(food.id));
+
+        for (amount, category_id) in [("10.00", Some(groceries.id)), ("20.00", Some(food.id))] {
+            sqlite_provider
+                .execute_transaction(&MoneyTransaction {
+                    category_id,
+                    ..create_outcome(&user, account, amount)
+                })
+                .await
+                .unwrap();
+        }
+        let taxi = sqlite_provider
+            .execute_transaction(&create_outcome(&user, account, "30.00"))
+            .await
+            .unwrap();
+        assert_eq!(taxi.category_id, None);
+        let food_query = TransactionQuery {
+            category_id: Some(food.id),
+            order: TransactionOrder::AmountAsc,
+            ..Default::default()
+        };
+        let page = sqlite_provider.get_transactions(&food_query).await.unwrap();
+        assert_eq!(page.transactions.len(), 2);
+        assert_eq!(page.transactions[0].category_id, Some(groceries.id));
+
+        sqlite_provider
+            .set_transaction_category(&taxi.id, Some(&transport))
+            .await
+            .unwrap();
+        let moved = Category {
+            parent_id: Some(groceries.id),
+            ..food.clone()
+        };
+        assert!(matches!(
+            sqlite_provider.update_category(&moved).await,
+            Err(MoneyCalcError::Validation(_))
+        ));
+        let moved = Category {
+            parent_id: Some(food.id),
+            ..transport.clone()
+        };
+        sqlite_provider.update_category(&moved).await.unwrap();
+        let page = sqlite_provider.get_transactions(&food_query).await.unwrap();
+        assert_eq!(page.transactions.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn category_of_another_user_test() {
+        let (sqlite_provider, user, accounts) = configure_sql_with_accounts(&["1000.00"]).await;
+        let other = AddUserCommand {
+            user_name: String::from_str("other").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        sqlite_provider.add_user(&other).await.unwrap();
+        let other = sqlite_provider
+            .get_user_by_number(&other.user_number)
+            .await
+            .unwrap();
+        sqlite_provider
+            .add_category(&AddCategoryCommand {
+                user_id: other.id,
+                parent_id: None,
+                name: "Food".to_string(),
+            })
+            .await
+            .unwrap();
+        let foreign = sqlite_provider
+            .get_categories(&other)
+            .await
+            .unwrap()
+            .remove(0);
+
+        assert!(matches!(
+            sqlite_provider
+                .add_category(&AddCategoryCommand {
+                    user_id: user.id,
+                    parent_id: Some(foreign.id),
+                    name: "Groceries".to_string(),
+                })
+                .await,
+            Err(MoneyCalcError::Validation(_))
+        ));
+        assert!(matches!(
+            sqlite_provider
+                .execute_transaction(&MoneyTransaction {
+                    category_id: Some(foreign.id),
+                    ..create_outcome(&user, &accounts[0], "10.00")
+                })
+                .await,
+            Err(MoneyCalcError::Validation(_))
+        ));
+        let transaction = sqlite_provider
+            .execute_transaction(&create_outcome(&user, &accounts[0], "10.00"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            sqlite_provider
+                .set_transaction_category(&transaction.id, Some(&foreign))
+                .await,
+            Err(MoneyCalcError::Validation(_))
+        ));
+        assert_eq!(
+            sqlite_provider
+                .get_transaction_by_id(&transaction.id)
+                .await
+                .unwrap()
+                .category_id,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn delete_category_in_use_test() {
+        let (sqlite_provider, user, accounts) = configure_sql_with_accounts(&["1000.00"]).await;
+        for (parent_id, name) in [(None, "Food"), (Some(1), "Groceries")] {
+            sqlite_provider
+                .add_category(&AddCategoryCommand {
+                    user_id: user.id,
+                    parent_id,
+                    name: name.to_string(),
+                })
+                .await
+                .unwrap();
+        }
+        let categories = sqlite_provider.get_categories(&user).await.unwrap();
+        let (food, groceries) = (categories[0].clone(), categories[1].clone());
+        let mut transactions = vec![];
+        for category in [&food, &groceries] {
+            transactions.push(
+                sqlite_provider
+                    .execute_transaction(&MoneyTransaction {
+                        category_id: Some(category.id),
+                        ..create_outcome(&user, &accounts[0], "10.00")
+                    })
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        sqlite_provider.delete_category(&food).await.unwrap();
+        let categories = sqlite_provider.get_categories(&user).await.unwrap();
+        assert_eq!(categories.len(), 1);
+        assert_eq!(categories[0].parent_id, None);
+        let category_of = async |transaction: &MoneyTransaction| {
+            sqlite_provider
+                .get_transaction_by_id(&transaction.id)
+                .await
+                .unwrap()
+                .category_id
+        };
+        assert_eq!(category_of(&transactions[0]).await, None);
+        assert_eq!(category_of(&transactions[1]).await, Some(groceries.id));
+        assert!(matches!(
+            sqlite_provider.delete_category(&food).await,
+            Err(MoneyCalcError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn transaction_tags_test() {
+        let (sqlite_provider, user, accounts) = configure_sql_with_accounts(&["1000.00"]).await;
+        let transaction = sqlite_provider
+            .execute_transaction(&create_outcome(&user, &accounts[0], "10.00"))
+            .await
+            .unwrap();
+        for name in ["trip", "family", "work"] {
+            sqlite_provider
+                .add_tag(&AddTagCommand {
+                    user_id: user.id,
+                    name: name.to_string(),
+                })
+                .await
+                .unwrap();
+        }
+        let tags = sqlite_provider.get_tags(&user).await.unwrap();
+        assert_eq!(tags.len(), 3);
+        let tag_names = async || -> Vec<String> {
+            sqlite_provider
+                .get_transaction_tags(&transaction.id)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|tag| tag.name)
+                .collect()
+        };
+
+        sqlite_provider
+            .set_transaction_tags(&transaction.id, &tags[..2])
+            .await
+            .unwrap();
+        assert_eq!(tag_names().await, vec!["family", "trip"]);
+        // Tags are replaced, not added.
+        sqlite_provider
+            .set_transaction_tags(&transaction.id, &tags[1..])
+            .await
+            .unwrap();
+        assert_eq!(tag_names().await, vec!["trip", "work"]);
+
+        sqlite_provider
+            .rename_tag(&tags[1], "vacation")
+            .await
+            .unwrap();
+        assert_eq!(tag_names().await, vec!["vacation", "work"]);
+        sqlite_provider.delete_tag(&tags[2]).await.unwrap();
+        assert_eq!(tag_names().await, vec!["vacation"]);
+        sqlite_provider
+            .set_transaction_tags(&transaction.id, &[])
+            .await
+            .unwrap();
+        assert!(tag_names().await.is_empty());
+        assert!(matches!(
+            sqlite_provider.get_transaction_tags("missing").await,
+            Err(MoneyCalcError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn budgets_test() {
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scam").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        let sqlite_provider = configure_sql_with_user(&add_user_command).await;
+        let user = sqlite_provider
+            .get_user_by_number(add_user_command.user_number.as_str())
+            .await
+            .unwrap();
+        sqlite_provider
+            .add_account(&create_add_account_command(user.id, rub("1000.00")))
+            .await
+            .unwrap();
+        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        sqlite_provider
+            .add_category(&AddCategoryCommand {
+                user_id: user.id,
+                name: "Food".to_string(),
+                parent_id: None,
+            })
+            .await
+            .unwrap();
+        let food = sqlite_provider
+            .get_categories(&user)
+            .await
+            .unwrap()
+            .remove(0);
+
+        let day = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+        for (date, amount, target, category_id) in [
+            (day(3, 2), "100.00", "Grocery 24", Some(food.id)),
+            (day(3, 15), "50.00", "grocery market", None),
+            (day(3, 31), "30.00", "Cinema", Some(food.id)),
+            (day(4, 1), "70.00", "Grocery 24", Some(food.id)),
+        ] {
+            sqlite_provider
+                .execute_transaction(&MoneyTransaction {
+                    description: "Purchase".to_string(),
+                    amount: rub(amount),
+                    user: user.clone(),
+                    account: account.clone(),
+                    payment_type: PaymentType::Outcome,
+                    payment_target: target.to_string(),
+                    id: "".to_string(),
+                    create_date: date.and_hms_opt(12, 0, 0).unwrap(),
+                    linked_transaction_id: None,
+                    correction_of: None,
+                    category_id,
+                })
+                .await
+                .unwrap();
+        }
+
+        for (name, scope) in [
+            ("Food", BudgetScope::Category(food.id)),
+            (
+                "Groceries",
+                BudgetScope::PaymentTarget("grocery*".to_string()),
+            ),
+            ("Account", BudgetScope::Account(account.id)),
+        ] {
+            sqlite_provider
+                .add_budget(&AddBudgetCommand {
+                    user_id: user.id,
+                    name: name.to_string(),
+                    scope,
+                    period: BudgetPeriod::Monthly,
+                    limit: rub("150.00"),
+                })
+                .await
+                .unwrap();
+        }
+        let progress = sqlite_provider
+            .get_budgets_progress(&user, day(3, 10))
+            .await
+            .unwrap();
+        let spent: Vec<Money> = progress.iter().map(|p| p.spent).collect();
+        assert_eq!(spent, vec![rub("130.00"), rub("150.00"), rub("180.00")]);
+        assert_eq!(progress[2].remaining, rub("-30.00"));
+        assert_eq!(progress[2].from_date, day(3, 1));
+        assert_eq!(progress[2].to_date, day(4, 1));
+
+        let mut budget = progress[0].budget.clone();
+        budget.period = BudgetPeriod::Custom {
+            start: day(3, 31),
+            days: 2,
+        };
+        sqlite_provider.update_budget(&budget).await.unwrap();
+        let progress = sqlite_provider
+            .get_budget_progress(&budget, day(4, 1))
+            .await
+            .unwrap();
+        assert_eq!(progress.spent, rub("100.00"));
+        assert_eq!(progress.remaining, rub("50.00"));
+
+        let invalid = AddBudgetCommand {
+            user_id: user.id,
+            name: "Invalid".to_string(),
+            scope: BudgetScope::PaymentTarget(" ".to_string()),
+            period: BudgetPeriod::Weekly,
+            limit: rub("10.00"),
+        };
+        assert!(matches!(
+            sqlite_provider.add_budget(&invalid).await,
+            Err(MoneyCalcError::Validation(_))
+        ));
+        assert!(matches!(
+            sqlite_provider
+                .add_budget(&AddBudgetCommand {
+                    scope: BudgetScope::Account(account.id),
+                    limit: rub("0.00"),
+                    ..invalid.clone()
+                })
+                .await,
+            Err(MoneyCalcError::Validation(_))
+        ));
+        assert!(matches!(
+            sqlite_provider.delete_category(&food).await,
+            Err(MoneyCalcError::Conflict(_))
+        ));
+
+        sqlite_provider.delete_budget(&budget).await.unwrap();
+        assert_eq!(sqlite_provider.get_budgets(&user).await.unwrap().len(), 2);
+        assert!(matches!(
+            sqlite_provider.get_budget_by_id(budget.id).await,
+            Err(MoneyCalcError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn recurring_test() {
+        let add_user_command = AddUserCommand {
+            user_name: String::from_str("scam").unwrap(),
+            user_number: uuid::Uuid::new_v4().to_string(),
+        };
+        let sqlite_provider = configure_sql_with_user(&add_user_command).await;
+        let user = sqlite_provider
+            .get_user_by_number(add_user_command.user_number.as_str())
+            .await
+            .unwrap();
+        sqlite_provider
+            .add_account(&create_add_account_command(user.id, rub("1000.00")))
+            .await
+            .unwrap();
+        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
+
+        let day = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+        let rent = AddRecurringCommand {
+            user_id: user.id,
+            account_id: account.id,
+            amount: rub("300.00"),
+            payment_type: PaymentType::Outcome,
+            payment_target: "Landlord".to_string(),
+            description: "Rent".to_string(),
+            category_id: None,
+            schedule: Schedule::MonthlyOnDay(5),
+            start_date: day(1, 1),
+            end_date: None,
+        };
+        sqlite_provider.add_recurring(&rent).await.unwrap();
+        sqlite_provider
+            .add_recurring(&AddRecurringCommand {
+                amount: rub("100.00"),
+                payment_type: PaymentType::Income,
+                payment_target: "Employer".to_string(),
+                description: "Salary".to_string(),
+                schedule: Schedule::EveryWeeks(2),
+                start_date: day(1, 3),
+                end_date: Some(day(2, 1)),
+                ..rent.clone()
+            })
+            .await
+            .unwrap();
+        assert!(matches!(
+            sqlite_provider
+                .add_recurring(&AddRecurringCommand {
+                    payment_type: PaymentType::Transfer,
+                    ..rent.clone()
+                })
+                .await,
+            Err(MoneyCalcError::Validation(_))
+        ));
+        assert!(matches!(
+            sqlite_provider
+                .add_recurring(&AddRecurringCommand {
+                    amount: Money::parse("1.00", Currency::USD).unwrap(),
+                    ..rent.clone()
+                })
+                .await,
+            Err(MoneyCalcError::Money(_))
+        ));
+
+        let now = day(3, 10).and_hms_opt(12, 0, 0).unwrap();
+        let report = sqlite_provider.generate_due(now).await.unwrap();
+        assert!(report.failures.is_empty());
+        let generated = report.occurrences;
+        let dates: Vec<NaiveDate> = generated.iter().map(|o| o.date).collect();
+        assert_eq!(
+            dates,
+            vec![
+                day(1, 5),
+                day(2, 5),
+                day(3, 5),
+                day(1, 3),
+                day(1, 17),
+                day(1, 31)
+            ]
+        );
+        assert_eq!(
+            sqlite_provider.generate_due(now).await.unwrap(),
+            GenerationReport::default()
+        );
+        let account = sqlite_provider.get_account_by_id(account.id).await.unwrap();
+        assert_eq!(account.money, rub("400.00"));
+
+        let templates = sqlite_provider.get_recurring(&user).await.unwrap();
+        assert_eq!(templates.len(), 2);
+        let occurrences = sqlite_provider
+            .get_occurrences(&templates[0])
+            .await
+            .unwrap();
+        assert_eq!(occurrences, generated[..3].to_vec());
+        let transaction = sqlite_provider
+            .get_transaction_by_id(&occurrences[0].transaction_id)
+            .await
+            .unwrap();
+        assert_eq!(transaction.description, "Rent");
+        assert_eq!(
+            transaction.create_date,
+            day(1, 5).and_hms_opt(0, 0, 0).unwrap()
+        );
+
+        let later = day(5, 6).and_hms_opt(0, 0, 0).unwrap();
+        let report = sqlite_provider.generate_due(later).await.unwrap();
+        assert!(report.occurrences.is_empty());
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].recurring_id, templates[0].id);
+        assert_eq!(
+            sqlite_provider
+                .get_occurrences(&templates[0])
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+
+        sqlite_provider
+            .delete_recurring(&templates[0])
+            .await
+            .unwrap();
+        assert_eq!(
+            sqlite_provider.generate_due(later).await.unwrap(),
+            GenerationReport::default()
+        );
+        assert!(matches!(
+            sqlite_provider.get_recurring_by_id(templates[0].id).await,
+            Err(MoneyCalcError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn recurring_failure_test() {
+        let (sqlite_provider, user, accounts) =
+            configure_sql_with_accounts(&["100.00", "1000.00"]).await;
+        let day = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+        let rent = AddRecurringCommand {
+            user_id: user.id,
+            account_id: accounts[0].id,
+            amount: rub("300.00"),
+            payment_type: PaymentType::Outcome,
+            payment_target: "Landlord".to_string(),
+            description: "Rent".to_string(),
+            category_id: None,
+            schedule: Schedule::MonthlyOnDay(5),
+            start_date: day(1, 1),
+            end_date: None,
+        };
+        sqlite_provider.add_recurring(&rent).await.unwrap();
+        sqlite_provider
+            .add_recurring(&AddRecurringCommand {
+                account_id: accounts[1].id,
+                amount: rub("50.00"),
+                description: "Phone".to_string(),
+                ..rent.clone()
+            })
+            .await
+            .unwrap();
+        let templates = sqlite_provider.get_recurring(&user).await.unwrap();
+
+        let now = day(2, 10).and_hms_opt(0, 0, 0).unwrap();
+        let report = sqlite_provider.generate_due(now).await.unwrap();
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].recurring_id, templates[0].id);
+        let generated: Vec<i32> = report.occurrences.iter().map(|o| o.recurring_id).collect();
+        assert_eq!(generated, vec![templates[1].id, templates[1].id]);
+        assert!(
+            sqlite_provider
+                .get_occurrences(&templates[0])
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        let account = sqlite_provider
+            .get_account_by_id(accounts[0].id)
+            .await
+            .unwrap();
+        assert_eq!(account.money, rub("100.00"));
+
+        sqlite_provider
+            .execute_transaction(&MoneyTransaction {
+                amount: rub("500.00"),
+                payment_type: PaymentType::Income,
+                ..create_outcome(&user, &accounts[0], "500.00")
+            })
+            .await
+            .unwrap();
+        let report = sqlite_provider.generate_due(now).await.unwrap();
+        assert!(report.failures.is_empty());
+        let dates: Vec<NaiveDate> = report.occurrences.iter().map(|o| o.date).collect();
+        assert_eq!(dates, vec![day(1, 5), day(2, 5)]);
+        assert!(
+            report
+                .occurrences
+                .iter()
+                .all(|o| o.recurring_id == templates[0].id)
+        );
+    }
+
+    #[tokio::test]
+    async fn amend_transaction_test() {
+        let (sqlite_provider, user, accounts) = configure_sql_with_accounts(&["1000.00"]).await;
+        let original = sqlite_provider
+            .execute_transaction(&create_outcome(&user, &accounts[0], "100.00"))
+            .await
+            .unwrap();
+
+        let amended = sqlite_provider
+            .amend_transaction(&create_amend_command(&original.id, "120.00"))
+            .await
+            .unwrap();
+        assert_eq!(amended.correction_of, Some(original.id.clone()));
+        assert_eq!(amended.amount, rub("120.00"));
+        assert_eq!(
+            balance_of(&sqlite_provider, &accounts[0]).await,
+            rub("880.00")
+        );
+        let stored = sqlite_provider
+            .get_transaction_by_id(&original.id)
+            .await
+            .unwrap();
+        assert_eq!(stored.amount, rub("100.00"));
+    }
+
+    #[tokio::test]
+    async fn amend_twice_test() {
+        let (sqlite_provider, user, accounts) = configure_sql_with_accounts(&["1000.00"]).await;
+        let original = sqlite_provider
+            .execute_transaction(&create_outcome(&user, &accounts[0], "100.00"))
+            .await
+            .unwrap();
+        let amend_command = create_amend_command(&original.id, "120.00");
+        sqlite_provider
+            .amend_transaction(&amend_command)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            sqlite_provider.amend_transaction(&amend_command).await,
+            Err(MoneyCalcError::Conflict(_))
+        ));
+        assert_eq!(
+            balance_of(&sqlite_provider, &accounts[0]).await,
+            rub("880.00")
+        );
+    }
+
+    #[tokio::test]
+    async fn reverse_transaction_test() {
+        let (sqlite_provider, user, accounts) = configure_sql_with_accounts(&["1000.00"]).await;
+        let original = sqlite_provider
+            .execute_transaction(&create_outcome(&user, &accounts[0], "120.00"))
+            .await
+            .unwrap();
 
-            Ok(account)
-        })
+        let reversal = sqlite_provider
+            .reverse_transaction(&ReverseTransactionCommand {
+                transaction_id: original.id.clone(),
+                description: "Refund".to_string(),
+                create_date: original.create_date,
+            })
+            .await
+            .unwrap();
+        assert_eq!(reversal.payment_type, PaymentType::Reversal);
+        assert_eq!(reversal.amount, rub("120.00"));
+        assert_eq!(
+            balance_of(&sqlite_provider, &accounts[0]).await,
+            rub("1000.00")
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::str::FromStr;
-    use tokio::fs;
+    #[tokio::test]
+    async fn reverse_reversal_test() {
+        let (sqlite_provider, user, accounts) = configure_sql_with_accounts(&["1000.00"]).await;
+        let original = sqlite_provider
+            .execute_transaction(&create_outcome(&user, &accounts[0], "120.00"))
+            .await
+            .unwrap();
+        let reversal = sqlite_provider
+            .reverse_transaction(&ReverseTransactionCommand {
+                transaction_id: original.id.clone(),
+                description: "Refund".to_string(),
+                create_date: original.create_date,
+            })
+            .await
+            .unwrap();
 
-    use crate::{
-        commands::{
-            accounts::addaccountcommand::AddAccountCommand, users::addusercommand::AddUserCommand,
-        },
-        config::SqliteConfiguration,
-        models::moneytransaction::{MoneyTransaction, PaymentType},
-        providers::{
-            AccountProvider, TransactionWorker, UserProvider, bases::sqlite::SqliteProvider,
-        },
-    };
+        assert!(matches!(
+            sqlite_provider
+                .reverse_transaction(&ReverseTransactionCommand {
+                    transaction_id: reversal.id.clone(),
+                    description: "Again".to_string(),
+                    create_date: reversal.create_date,
+                })
+                .await,
+            Err(MoneyCalcError::Validation(_))
+        ));
+    }
 
     #[tokio::test]
-    async fn create_test_file_base() {
-        let config = SqliteConfiguration::new("./testbases/testbase.db3");
-        let _ = SqliteProvider::new(&config, false);
-        assert!(check_exist(config.connection_string.as_str()).await);
+    async fn reverse_transfer_test() {
+        let (sqlite_provider, _, accounts) =
+            configure_sql_with_accounts(&["1000.00", "1000.00"]).await;
+        let (checking, savings) = (&accounts[0], &accounts[1]);
+        let transfer = create_transfer(&sqlite_provider, checking, savings).await;
+
+        let reversal = sqlite_provider
+            .reverse_transaction(&ReverseTransactionCommand {
+                transaction_id: transfer.id.clone(),
+                description: "Wrong account".to_string(),
+                create_date: transfer.create_date,
+            })
+            .await
+            .unwrap();
+        assert_eq!(reversal.amount, rub("-200.00"));
+        assert!(reversal.linked_transaction_id.is_some());
+        assert_eq!(balance_of(&sqlite_provider, checking).await, rub("1000.00"));
+        assert_eq!(balance_of(&sqlite_provider, savings).await, rub("1000.00"));
     }
 
-    #[test]
-    fn create_test_memory_base() {
-        let config = SqliteConfiguration::memory_base();
-        let _ = SqliteProvider::new(&config, false).unwrap();
+    #[tokio::test]
+    async fn amend_transfer_test() {
+        let (sqlite_provider, _, accounts) =
+            configure_sql_with_accounts(&["1000.00", "1000.00"]).await;
+        let transfer = create_transfer(&sqlite_provider, &accounts[0], &accounts[1]).await;
+
+        assert!(matches!(
+            sqlite_provider
+                .amend_transaction(&create_amend_command(&transfer.id, "120.00"))
+                .await,
+            Err(MoneyCalcError::Validation(_))
+        ));
     }
 
     #[tokio::test]
-    async fn add_migration_test() {
-        let config = SqliteConfiguration::new("./testbases/testbase_mig.db3");
-        let _ = SqliteProvider::new(&config, true);
-        assert!(check_exist(config.connection_string.as_str()).await);
+    async fn reverse_missing_transaction_test() {
+        let (sqlite_provider, _, _) = configure_sql_with_accounts(&[]).await;
+
+        assert!(matches!(
+            sqlite_provider
+                .reverse_transaction(&ReverseTransactionCommand {
+                    transaction_id: "missing".to_string(),
+                    description: "Missing".to_string(),
+                    create_date: chrono::Utc::now().naive_utc(),
+                })
+                .await,
+            Err(MoneyCalcError::NotFound(_))
+        ));
     }
 
     #[tokio::test]
-    async fn add_user_test() {
-        let config = SqliteConfiguration::memory_base();
-        let sqlite_provider = SqliteProvider::new(&config, true).unwrap();
+    async fn audit_log_test() {
+        let config = SqliteConfiguration::memory_base().with_audit_hash_chain(true);
+        let sqlite_provider = SqliteProvider::new(&config, true)
+            .unwrap()
+            .with_actor("alice");
         let add_user_command = AddUserCommand {
             user_name: String::from_str("scam").unwrap(),
             user_number: uuid::Uuid::new_v4().to_string(),
@@ -321,112 +4317,396 @@ mod tests {
             .get_user_by_number(add_user_command.user_number.as_str())
             .await
             .unwrap();
+        sqlite_provider
+            .add_account(&create_add_account_command(user.id, rub("100.00")))
+            .await
+            .unwrap();
+        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        sqlite_provider
+            .with_actor("bob")
+            .change_money(&account, rub("-30.00"))
+            .await
+            .unwrap();
+        sqlite_provider.delete_account(&account).await.unwrap();
 
-        sqlite_provider.delete_user_by_id(user.id).await.unwrap();
+        let entries = sqlite_provider
+            .get_audit_log(&AuditQuery {
+                entity: Some("account".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let actions: Vec<(&str, &str)> = entries
+            .iter()
+            .map(|entry| (entry.actor.as_str(), entry.action.as_str()))
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                ("alice", "add_account"),
+                ("bob", "change_money"),
+                ("alice", "delete_account")
+            ]
+        );
+        assert!(entries[0].before.is_none());
+        assert_eq!(entries[1].entity_id, account.id.to_string());
+        assert_ne!(entries[1].before, entries[1].after);
+        assert!(entries[2].after.is_none());
+
+        let page = sqlite_provider
+            .get_audit_log(&AuditQuery {
+                after_id: Some(entries[0].id),
+                limit: 1,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(page, entries[1..2].to_vec());
+        assert_eq!(sqlite_provider.verify_audit_log().await.unwrap(), None);
+
+        let connection = sqlite_provider.pool.get().unwrap();
+        assert!(connection.execute("Delete from AuditLog", []).is_err());
+        connection
+            .execute("Drop trigger audit_no_update", [])
+            .unwrap();
+        connection
+            .execute(
+                "Update AuditLog set Actor = 'alice' where Id = ?1",
+                [entries[1].id],
+            )
+            .unwrap();
+        drop(connection);
+        assert_eq!(
+            sqlite_provider.verify_audit_log().await.unwrap(),
+            Some(entries[1].id)
+        );
     }
 
     #[tokio::test]
-    async fn add_user_notunique_test() {
-        let config = SqliteConfiguration::new("./testbases/testbase_user.db3");
+    async fn audit_log_unhashed_record_test() {
+        let config = SqliteConfiguration::memory_base().with_audit_hash_chain(true);
         let sqlite_provider = SqliteProvider::new(&config, true).unwrap();
-        let add_user_command = AddUserCommand {
-            user_name: String::from_str("scam").unwrap(),
-            user_number: String::from_str("88005553535").unwrap(),
-        };
+        for _ in 0..3 {
+            sqlite_provider
+                .add_user(&AddUserCommand {
+                    user_name: String::from_str("scam").unwrap(),
+                    user_number: uuid::Uuid::new_v4().to_string(),
+                })
+                .await
+                .unwrap();
+        }
+        let entries = sqlite_provider
+            .get_audit_log(&AuditQuery::default())
+            .await
+            .unwrap();
+        assert_eq!(sqlite_provider.verify_audit_log().await.unwrap(), None);
 
-        assert!(sqlite_provider.add_user(&add_user_command).await.is_err());
+        let connection = sqlite_provider.pool.get().unwrap();
+        connection
+            .execute("Drop trigger audit_no_update", [])
+            .unwrap();
+        connection
+            .execute(
+                "Update AuditLog set Hash = null where Id = ?1",
+                [entries[2].id],
+            )
+            .unwrap();
+        drop(connection);
+        assert_eq!(
+            sqlite_provider.verify_audit_log().await.unwrap(),
+            Some(entries[2].id)
+        );
     }
 
     #[tokio::test]
-    async fn get_users_test() {
-        let config = SqliteConfiguration::new("./testbases/testbase_users.db3");
-        let sqlite_provider = SqliteProvider::new(&config, true).unwrap();
-        let add_user_command = AddUserCommand {
-            user_name: String::from_str("scam").unwrap(),
-            user_number: uuid::Uuid::new_v4().to_string(),
-        };
-        sqlite_provider.add_user(&add_user_command).await.unwrap();
-        let add_user_command = AddUserCommand {
-            user_name: String::from_str("scamjr").unwrap(),
-            user_number: uuid::Uuid::new_v4().to_string(),
+    async fn reconcile_balances_test() {
+        let (sqlite_provider, accounts) = configure_reconciled_accounts().await;
+
+        let report = sqlite_provider.reconcile_balances(false).await.unwrap();
+        assert_eq!(report.checked_accounts, 2);
+        assert!(report.discrepancies.is_empty());
+        assert!(!report.repaired);
+        assert_eq!(
+            balance_of(&sqlite_provider, &accounts[0]).await,
+            rub("50.00")
+        );
+    }
+
+    #[tokio::test]
+    async fn reconcile_drifted_balance_test() {
+        let (sqlite_provider, accounts) = configure_reconciled_accounts().await;
+        drift_balance(&sqlite_provider, &accounts[0]).await;
+
+        let report = sqlite_provider.reconcile_balances(false).await.unwrap();
+        assert_eq!(report.discrepancies.len(), 1);
+        let discrepancy = &report.discrepancies[0];
+        assert_eq!(discrepancy.account_id, accounts[0].id);
+        assert_eq!(discrepancy.stored, rub("65.00"));
+        assert_eq!(discrepancy.expected, rub("50.00"));
+        assert_eq!(discrepancy.difference, rub("15.00"));
+        assert!(!report.repaired);
+        assert_eq!(
+            balance_of(&sqlite_provider, &accounts[0]).await,
+            rub("65.00")
+        );
+    }
+
+    #[tokio::test]
+    async fn reconcile_repair_test() {
+        let (sqlite_provider, accounts) = configure_reconciled_accounts().await;
+        drift_balance(&sqlite_provider, &accounts[0]).await;
+
+        let report = sqlite_provider.reconcile_balances(true).await.unwrap();
+        assert_eq!(report.discrepancies.len(), 1);
+        assert!(report.repaired);
+        assert_eq!(
+            balance_of(&sqlite_provider, &accounts[0]).await,
+            rub("50.00")
+        );
+        let report = sqlite_provider.reconcile_balances(false).await.unwrap();
+        assert!(report.discrepancies.is_empty());
+    }
+
+    #[tokio::test]
+    async fn balance_at_test() {
+        let (sqlite_provider, _, account) = configure_history_account().await;
+        let balance_at = |date: NaiveDate, hour| {
+            sqlite_provider.get_balance_at(&account, date.and_hms_opt(hour, 0, 0).unwrap())
         };
-        sqlite_provider.add_user(&add_user_command).await.unwrap();
 
-        let users = sqlite_provider.get_users().await.unwrap();
-        assert!(users.len() > 0);
+        assert_eq!(balance_at(date(2, 28), 0).await.unwrap(), rub("100.00"));
+        assert_eq!(balance_at(date(3, 1), 11).await.unwrap(), rub("100.00"));
+        assert_eq!(balance_at(date(3, 1), 12).await.unwrap(), rub("150.00"));
+        assert_eq!(balance_at(date(3, 31), 23).await.unwrap(), rub("110.00"));
+        assert_eq!(balance_at(date(5, 1), 0).await.unwrap(), rub("115.00"));
+    }
 
-        fs::remove_file("./testbases/testbase_users.db3")
+    #[tokio::test]
+    async fn daily_balances_test() {
+        let (sqlite_provider, _, account) = configure_history_account().await;
+
+        let history = sqlite_provider
+            .get_daily_balances(&account, date(2, 28), date(3, 4))
             .await
             .unwrap();
+        let balances: Vec<_> = history.iter().map(|daily| daily.balance).collect();
+        assert_eq!(history[0].date, date(2, 28));
+        assert_eq!(history[4].date, date(3, 4));
+        assert_eq!(
+            balances,
+            vec![
+                rub("100.00"),
+                rub("150.00"),
+                rub("150.00"),
+                rub("110.00"),
+                rub("110.00")
+            ]
+        );
     }
 
     #[tokio::test]
-    async fn get_user_by_number_test() {
-        let config = SqliteConfiguration::new("./testbases/testbase_user.db3");
-        let sqlite_provider = SqliteProvider::new(&config, true).unwrap();
-        let user_res = sqlite_provider.get_user_by_number("88005553535").await;
-        match user_res {
-            Ok(user) => {
-                assert!(user.name.eq("scam"));
-            }
-            Err(err) => {
-                panic!("{}", err)
-            }
-        }
+    async fn daily_balances_reversed_range_test() {
+        let (sqlite_provider, _, account) = configure_history_account().await;
+
+        assert!(matches!(
+            sqlite_provider
+                .get_daily_balances(&account, date(3, 4), date(3, 1))
+                .await,
+            Err(MoneyCalcError::Validation(_))
+        ));
     }
 
     #[tokio::test]
-    async fn add_account_to_db() {
-        let config = SqliteConfiguration::new("./testbases/testbase_accounts.db3");
-        let sqlite_provider = SqliteProvider::new(&config, true).unwrap();
-        let add_user_command = AddUserCommand {
-            user_name: String::from_str("scam").unwrap(),
-            user_number: uuid::Uuid::new_v4().to_string(),
-        };
-        sqlite_provider.add_user(&add_user_command).await.unwrap();
-        let add_account_command = create_add_account_command(1, 50000.0);
+    async fn balance_snapshot_test() {
+        let (sqlite_provider, _, account) = configure_history_account().await;
+
+        let snapshot = sqlite_provider
+            .add_balance_snapshot(&account, date(3, 31).and_hms_opt(0, 0, 0).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(snapshot.balance, rub("110.00"));
+        assert_eq!(
+            sqlite_provider
+                .get_balance_at(&account, date(5, 1).and_hms_opt(0, 0, 0).unwrap())
+                .await
+                .unwrap(),
+            rub("115.00")
+        );
+    }
+
+    #[tokio::test]
+    async fn backdated_transaction_drops_snapshot_test() {
+        let (sqlite_provider, user, account) = configure_history_account().await;
         sqlite_provider
-            .add_account(&add_account_command)
+            .add_balance_snapshot(&account, date(3, 31).and_hms_opt(0, 0, 0).unwrap())
+            .await
+            .unwrap();
+
+        sqlite_provider
+            .execute_transaction(&create_history_transaction(
+                &user,
+                &account,
+                "1.00",
+                PaymentType::Outcome,
+                date(3, 2),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            sqlite_provider
+                .get_balance_at(&account, date(5, 1).and_hms_opt(0, 0, 0).unwrap())
+                .await
+                .unwrap(),
+            rub("114.00")
+        );
+        let history = sqlite_provider
+            .get_daily_balances(&account, date(3, 31), date(4, 1))
             .await
             .unwrap();
+        assert_eq!(history[0].balance, rub("109.00"));
+        assert_eq!(history[1].balance, rub("114.00"));
     }
 
     #[tokio::test]
-    async fn delete_users_test() {
-        let config = SqliteConfiguration::new("./testbases/testbase_user_delete.db3");
+    async fn get_transactions_test() {
         let add_user_command = AddUserCommand {
             user_name: String::from_str("scam").unwrap(),
             user_number: uuid::Uuid::new_v4().to_string(),
         };
-        let sqlite_provider = SqliteProvider::new(&config, true).unwrap();
-
-        sqlite_provider.add_user(&add_user_command).await.unwrap();
+        let sqlite_provider = configure_sql_with_user(&add_user_command).await;
         let user = sqlite_provider
             .get_user_by_number(add_user_command.user_number.as_str())
             .await
             .unwrap();
+        sqlite_provider
+            .add_account(&create_add_account_command(1, rub("1000.00")))
+            .await
+            .unwrap();
+        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        let start = chrono::NaiveDate::from_ymd_opt(2025, 1, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        for day in 0..10 {
+            let (payment_type, payment_target) = if day % 2 == 0 {
+                (PaymentType::Outcome, "Shop")
+            } else {
+                (PaymentType::Income, "Salary")
+            };
+            sqlite_provider
+                .execute_transaction(&MoneyTransaction {
+                    description: format!("Payment 100% number {}", day),
+                    amount: Money::from_minor((day + 1) * 100, Currency::RUB),
+                    user: user.clone(),
+                    account: account.clone(),
+                    payment_type,
+                    payment_target: payment_target.to_string(),
+                    id: "".to_string(),
+                    create_date: start + chrono::Duration::days(day),
+                    linked_transaction_id: None,
+                    correction_of: None,
+                    category_id: None,
+                })
+                .await
+                .unwrap();
+        }
 
-        let add_user_command = AddUserCommand {
-            user_name: String::from_str("scamer").unwrap(),
-            user_number: uuid::Uuid::new_v4().to_string(),
+        let mut query = TransactionQuery {
+            account_id: Some(account.id),
+            limit: 4,
+            ..Default::default()
         };
-        sqlite_provider.add_user(&add_user_command).await.unwrap();
+        let mut dates = vec![];
+        loop {
+            let page = sqlite_provider.get_transactions(&query).await.unwrap();
+            assert!(page.transactions.len() <= 4);
+            dates.extend(page.transactions.iter().map(|t| t.create_date));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(dates.len(), 10);
+        assert!(dates.windows(2).all(|pair| pair[0] > pair[1]));
 
-        let users = sqlite_provider.get_users().await.unwrap();
-        assert!(users.len() > 0);
+        let page = sqlite_provider
+            .get_transactions(&TransactionQuery {
+                user_id: Some(user.id),
+                payment_type: Some(PaymentType::Outcome),
+                from_date: Some(start + chrono::Duration::days(2)),
+                to_date: Some(start + chrono::Duration::days(8)),
+                order: TransactionOrder::AmountAsc,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let amounts: Vec<i64> = page
+            .transactions
+            .iter()
+            .map(|t| t.amount.minor_units())
+            .collect();
+        assert_eq!(amounts, vec![300, 500, 700]);
+        assert_eq!(page.transactions[0].user.id, user.id);
+        assert_eq!(page.transactions[0].account.name, account.name);
+        assert!(page.next_cursor.is_none());
 
-        for user in users {
-            sqlite_provider.delete_user_by_id(user.id).await.unwrap();
-        }
-        let users = sqlite_provider.get_users().await.unwrap();
+        let page = sqlite_provider
+            .get_transactions(&TransactionQuery {
+                payment_target: Some("Salary".to_string()),
+                min_amount: Some(rub("4.00")),
+                max_amount: Some(rub("8.00")),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(page.transactions.len(), 3);
 
-        assert!(users.len() == 0);
+        let page = sqlite_provider
+            .get_transactions(&TransactionQuery {
+                description_contains: Some("100% number 7".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(page.transactions.len(), 1);
+        let transaction = sqlite_provider
+            .get_transaction_by_id(&page.transactions[0].id)
+            .await
+            .unwrap();
+        assert_eq!(transaction.amount, rub("8.00"));
+        assert_eq!(transaction.payment_type, PaymentType::Income);
 
-        std::fs::remove_file("./testbases/testbase_user_delete.db3").unwrap();
+        let page = sqlite_provider
+            .get_transactions(&TransactionQuery {
+                description_contains: Some("_".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(page.transactions.is_empty());
+        assert!(
+            sqlite_provider
+                .get_transactions(&TransactionQuery {
+                    cursor: Some("broken".to_string()),
+                    ..Default::default()
+                })
+                .await
+                .is_err()
+        );
     }
 
-    #[tokio::test]
-    async fn account_lifecircle_test() {
+    async fn configure_sql_with_user(add_user_command: &AddUserCommand) -> SqliteProvider {
+        let config = SqliteConfiguration::memory_base();
+        let sqlite_provider = SqliteProvider::new(&config, true).unwrap();
+        sqlite_provider.add_user(add_user_command).await.unwrap();
+
+        sqlite_provider
+    }
+
+    /// Memory base with one user and accounts with the given initial balances in RUB.
+    async fn configure_sql_with_accounts(
+        balances: &[&str],
+    ) -> (SqliteProvider, User, Vec<Account>) {
         let add_user_command = AddUserCommand {
             user_name: String::from_str("scam").unwrap(),
             user_number: uuid::Uuid::new_v4().to_string(),
@@ -436,74 +4716,188 @@ mod tests {
             .get_user_by_number(add_user_command.user_number.as_str())
             .await
             .unwrap();
-        let add_account_command = create_add_account_command(1, 50000.0);
+        for balance in balances {
+            sqlite_provider
+                .add_account(&create_add_account_command(user.id, rub(balance)))
+                .await
+                .unwrap();
+        }
+        let accounts = sqlite_provider.get_accounts_by_user(&user).await.unwrap();
+        (sqlite_provider, user, accounts)
+    }
+
+    async fn check_exist(path: &str) -> bool {
+        let meta = fs::metadata(path).await.ok();
+        meta.is_some()
+    }
+
+    fn create_transfer_command(
+        from_account_id: i32,
+        to_account_id: i32,
+        amount: Money,
+        exchange_rate: Option<ExchangeRate>,
+    ) -> TransferCommand {
+        TransferCommand {
+            from_account_id,
+            to_account_id,
+            amount,
+            exchange_rate,
+            description: "Transfer".to_string(),
+            create_date: chrono::Utc::now().naive_utc(),
+        }
+    }
+
+    fn create_outcome(user: &User, account: &Account, amount: &str) -> MoneyTransaction {
+        MoneyTransaction {
+            description: "Purchase".to_string(),
+            amount: rub(amount),
+            user: user.clone(),
+            account: account.clone(),
+            payment_type: PaymentType::Outcome,
+            payment_target: "Shop".to_string(),
+            id: "".to_string(),
+            create_date: chrono::Utc::now().naive_utc(),
+            linked_transaction_id: None,
+            category_id: None,
+            correction_of: None,
+        }
+    }
+
+    fn create_amend_command(transaction_id: &str, amount: &str) -> AmendTransactionCommand {
+        AmendTransactionCommand {
+            transaction_id: transaction_id.to_string(),
+            amount: rub(amount),
+            description: "Coffee and cake".to_string(),
+            payment_target: "Cafe".to_string(),
+            category_id: None,
+            create_date: chrono::Utc::now().naive_utc(),
+        }
+    }
+
+    /// Transfers 200.00 and returns its incoming side.
+    async fn create_transfer(
+        sqlite_provider: &SqliteProvider,
+        from: &Account,
+        to: &Account,
+    ) -> MoneyTransaction {
         sqlite_provider
-            .add_account(&add_account_command)
+            .transfer(&create_transfer_command(
+                from.id,
+                to.id,
+                rub("200.00"),
+                None,
+            ))
             .await
             .unwrap();
-        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
         sqlite_provider
-            .change_money(&account, 100000.0)
+            .get_transactions(&TransactionQuery {
+                account_id: Some(to.id),
+                ..Default::default()
+            })
             .await
-            .unwrap();
-        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
-        assert_eq!(account.money, 150000.0);
-        assert_eq!(account.name, add_account_command.account_name);
-        sqlite_provider.delete_account(&account).await.unwrap();
+            .unwrap()
+            .transactions
+            .remove(0)
     }
 
-    #[tokio::test]
-    async fn transaction_execute_test() {
-        let add_user_command = AddUserCommand {
-            user_name: String::from_str("scam").unwrap(),
-            user_number: uuid::Uuid::new_v4().to_string(),
-        };
-        let sqlite_provider = configure_sql_with_user(&add_user_command).await;
-        let user = sqlite_provider
-            .get_user_by_number(add_user_command.user_number.as_str())
+    /// Two accounts of 100.00 with a purchase of 30.00 and a transfer of 20.00 between them.
+    async fn configure_reconciled_accounts() -> (SqliteProvider, Vec<Account>) {
+        let (sqlite_provider, user, accounts) =
+            configure_sql_with_accounts(&["100.00", "100.00"]).await;
+        sqlite_provider
+            .execute_transaction(&create_outcome(&user, &accounts[0], "30.00"))
             .await
             .unwrap();
-        let add_account_command = create_add_account_command(1, 50000.0);
         sqlite_provider
-            .add_account(&add_account_command)
+            .transfer(&create_transfer_command(
+                accounts[0].id,
+                accounts[1].id,
+                rub("20.00"),
+                None,
+            ))
             .await
             .unwrap();
-        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
+        (sqlite_provider, accounts)
+    }
+
+    /// Drift balance by 15.00 outside of transactions.
+    async fn drift_balance(sqlite_provider: &SqliteProvider, account: &Account) {
         sqlite_provider
-            .execute_transaction(&MoneyTransaction {
-                description: "Test transcation".to_string(),
-                amount: 200000.0,
-                user: user.clone(),
-                account: account.clone(),
-                payment_type: PaymentType::Income,
-                payment_target: "Test".to_string(),
-                id: "".to_string(),
-                create_date: chrono::Utc::now().naive_utc(),
-            })
+            .change_money(account, rub("10.00"))
             .await
             .unwrap();
-        let account = sqlite_provider.search_account_by_user(&user).await.unwrap();
-        assert_eq!(account.money, 250000.0);
+        sqlite_provider
+            .pool
+            .get()
+            .unwrap()
+            .execute(
+                "Update Accounts set MoneyCount = MoneyCount + 500 where Id = ?1",
+                [account.id],
+            )
+            .unwrap();
     }
 
-    async fn configure_sql_with_user(add_user_command: &AddUserCommand) -> SqliteProvider {
-        let config = SqliteConfiguration::memory_base();
-        let sqlite_provider = SqliteProvider::new(&config, true).unwrap();
-        sqlite_provider.add_user(add_user_command).await.unwrap();
+    /// Account of 100.00 with income and outcomes in March and April 2025.
+    async fn configure_history_account() -> (SqliteProvider, User, Account) {
+        let (sqlite_provider, user, mut accounts) = configure_sql_with_accounts(&["100.00"]).await;
+        let account = accounts.remove(0);
+        for (amount, payment_type, date) in [
+            ("50.00", PaymentType::Income, date(3, 1)),
+            ("30.00", PaymentType::Outcome, date(3, 3)),
+            ("10.00", PaymentType::Outcome, date(3, 3)),
+            ("5.00", PaymentType::Income, date(4, 1)),
+        ] {
+            sqlite_provider
+                .execute_transaction(&create_history_transaction(
+                    &user,
+                    &account,
+                    amount,
+                    payment_type,
+                    date,
+                ))
+                .await
+                .unwrap();
+        }
+        (sqlite_provider, user, account)
+    }
+
+    fn create_history_transaction(
+        user: &User,
+        account: &Account,
+        amount: &str,
+        payment_type: PaymentType,
+        date: NaiveDate,
+    ) -> MoneyTransaction {
+        MoneyTransaction {
+            description: "History".to_string(),
+            payment_type,
+            create_date: date.and_hms_opt(12, 0, 0).unwrap(),
+            ..create_outcome(user, account, amount)
+        }
+    }
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, month, day).unwrap()
+    }
 
+    async fn balance_of(sqlite_provider: &SqliteProvider, account: &Account) -> Money {
         sqlite_provider
+            .get_account_by_id(account.id)
+            .await
+            .unwrap()
+            .money
     }
 
-    async fn check_exist(path: &str) -> bool {
-        let meta = fs::metadata(path).await.ok();
-        if let Some(_) = meta { true } else { false }
+    fn rub(amount: &str) -> Money {
+        Money::parse(amount, Currency::RUB).unwrap()
     }
 
-    fn create_add_account_command(user_id: i32, initial_balance: f32) -> AddAccountCommand {
+    fn create_add_account_command(user_id: i32, initial_balance: Money) -> AddAccountCommand {
         AddAccountCommand {
             user_id,
             account_name: String::from_str("TEST ACCOUNT").unwrap(),
             initial_balance,
+            overdraft: OverdraftPolicy::default(),
         }
     }
 }
@@ -0,0 +1,219 @@
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{Condvar, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use rusqlite::{Connection, OpenFlags};
+use uuid::Uuid;
+
+use crate::{
+    config::SqliteConfiguration,
+    errors::{MoneyCalcError, MoneyCalcResult},
+};
+
+/// Count of connections opened when configuration doesn't set it.
+pub const DEFAULT_POOL_SIZE: usize = 4;
+
+/// How long a connection waits for a lock held by another connection,
+/// when configuration doesn't set it.
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Fixed set of sqlite connections.
+/// Connection is taken for one operation and returned on drop of the guard,
+/// callers wait while all connections are taken.
+#[derive(Debug)]
+pub struct ConnectionPool {
+    connections: Mutex<Vec<Connection>>,
+    available: Condvar,
+}
+
+impl ConnectionPool {
+    /// Open connections for configuration.
+    /// File databases are switched to journal mode of configuration, WAL by default.
+    /// In-memory database is a named memdb shared by connections of this pool only,
+    /// it is dropped with the last connection.
+    pub fn open(config: &SqliteConfiguration) -> MoneyCalcResult<Self> {
+        if config.pool_size == 0 {
+            return Err(MoneyCalcError::Validation(
+                "connection pool size must be positive".to_string(),
+            ));
+        }
+        let path = if config.memory_base {
+            format!("file:/moneycalc-{}?vfs=memdb", Uuid::new_v4())
+        } else {
+            config.connection_string.clone()
+        };
+
+        let mut connections = Vec::with_capacity(config.pool_size);
+        for _ in 0..config.pool_size {
+            connections.push(open_connection(&path, config)?);
+        }
+        Ok(Self {
+            connections: Mutex::new(connections),
+            available: Condvar::new(),
+        })
+    }
+
+    /// Take free connection, blocks current thread until one is returned.
+    pub fn get(&self) -> MoneyCalcResult<PooledConnection<'_>> {
+        let mut connections = self.lock()?;
+        loop {
+            if let Some(connection) = connections.pop() {
+                return Ok(PooledConnection {
+                    pool: self,
+                    connection: Some(connection),
+                });
+            }
+            connections = self
+                .available
+                .wait(connections)
+                .map_err(|e| MoneyCalcError::Storage(e.to_string().into()))?;
+        }
+    }
+
+    fn lock(&self) -> MoneyCalcResult<MutexGuard<'_, Vec<Connection>>> {
+        self.connections
+            .lock()
+            .map_err(|e| MoneyCalcError::Storage(e.to_string().into()))
+    }
+
+    fn put_back(&self, connection: Connection) {
+        if let Ok(mut connections) = self.lock() {
+            connections.push(connection);
+            self.available.notify_one();
+        }
+    }
+}
+
+fn open_connection(path: &str, config: &SqliteConfiguration) -> MoneyCalcResult<Connection> {
+    let connection =
+        Connection::open_with_flags(path, OpenFlags::default() | OpenFlags::SQLITE_OPEN_URI)?;
+
+    connection.busy_timeout(config.busy_timeout)?;
+    connection.pragma_update(None, "foreign_keys", "ON")?;
+    if !config.memory_base {
+        connection.pragma_update_and_check(
+            None,
+            "journal_mode",
+            config.journal_mode.as_str(),
+            |row| row.get::<_, String>(0),
+        )?;
+    }
+    Ok(connection)
+}
+
+/// Connection taken from the pool.
+pub struct PooledConnection<'a> {
+    pool: &'a ConnectionPool,
+    connection: Option<Connection>,
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Self::Target {
+        // Connection is only taken out in drop.
+        self.connection.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledConnection<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.connection.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        if let Some(connection) = self.connection.take() {
+            self.pool.put_back(connection);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use crate::{
+        config::SqliteConfiguration,
+        providers::bases::sqlitepool::{ConnectionPool, DEFAULT_POOL_SIZE},
+    };
+
+    #[test]
+    fn pool_reuses_connections_test() {
+        let config = SqliteConfiguration::memory_base().with_pool_size(1);
+        let pool = Arc::new(ConnectionPool::open(&config).unwrap());
+        pool.get()
+            .unwrap()
+            .execute("Create table Test (Id INTEGER)", [])
+            .unwrap();
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let pool = pool.clone();
+                thread::spawn(move || {
+                    pool.get()
+                        .unwrap()
+                        .execute("Insert into Test(Id) values (1)", [])
+                        .unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let count: i64 = pool
+            .get()
+            .unwrap()
+            .query_one("Select count(*) from Test", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 8);
+        assert_eq!(pool.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn pool_shared_memory_test() {
+        let pool = ConnectionPool::open(&SqliteConfiguration::memory_base()).unwrap();
+        let first = pool.get().unwrap();
+        let second = pool.get().unwrap();
+        first.execute("Create table Test (Id INTEGER)", []).unwrap();
+        second
+            .execute("Insert into Test(Id) values (1)", [])
+            .unwrap();
+        let count: i64 = first
+            .query_one("Select count(*) from Test", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+
+        let other = ConnectionPool::open(&SqliteConfiguration::memory_base()).unwrap();
+        assert!(
+            other
+                .get()
+                .unwrap()
+                .query_one("Select count(*) from Test", [], |row| row.get::<_, i64>(0))
+                .is_err()
+        );
+        assert!(
+            ConnectionPool::open(&SqliteConfiguration::memory_base().with_pool_size(0)).is_err()
+        );
+    }
+
+    #[test]
+    fn pool_file_wal_test() {
+        let path = "./testbases/testbase_pool.db3";
+        let pool = ConnectionPool::open(&SqliteConfiguration::new(path)).unwrap();
+        let connections: Vec<_> = (0..DEFAULT_POOL_SIZE)
+            .map(|_| pool.get().unwrap())
+            .collect();
+        let mode: String = connections[0]
+            .query_one("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "wal");
+        drop(connections);
+        drop(pool);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,60 +1,358 @@
 use crate::{
     commands::{
-        accounts::addaccountcommand::AddAccountCommand, users::addusercommand::AddUserCommand,
+        accounts::addaccountcommand::AddAccountCommand,
+        audit::auditquery::AuditQuery,
+        budgets::addbudgetcommand::AddBudgetCommand,
+        categories::addcategorycommand::AddCategoryCommand,
+        recurring::addrecurringcommand::AddRecurringCommand,
+        tags::addtagcommand::AddTagCommand,
+        transactions::{
+            amendtransactioncommand::AmendTransactionCommand,
+            reversetransactioncommand::ReverseTransactionCommand,
+            transactionquery::TransactionQuery, transfercommand::TransferCommand,
+        },
+        users::addusercommand::AddUserCommand,
+    },
+    errors::MoneyCalcResult,
+    models::{
+        account::{Account, OverdraftPolicy},
+        auditentry::AuditEntry,
+        balance::{BalanceSnapshot, DailyBalance},
+        budget::{Budget, BudgetProgress},
+        category::Category,
+        exchangerate::ExchangeRate,
+        importedtransaction::ImportedTransaction,
+        money::{Currency, Money},
+        moneytransaction::MoneyTransaction,
+        reconciliation::ReconciliationReport,
+        recurring::{GenerationReport, RecurringOccurrence, RecurringTransaction},
+        tag::Tag,
+        transactionpage::TransactionPage,
+        user::User,
     },
-    models::{account::Account, moneytransaction::MoneyTransaction, user::User},
 };
 use async_trait::async_trait;
-use std::error;
+use chrono::{NaiveDate, NaiveDateTime};
 
 pub mod bases;
 
-#[async_trait]
+/// Storage of users, accounts and their transactions.
+/// Implemented for every provider of them, so any provider can be held as `Arc<dyn DataProvider>`.
 pub trait DataProvider: UserProvider + AccountProvider + TransactionWorker {}
 
+impl<T> DataProvider for T where T: UserProvider + AccountProvider + TransactionWorker + ?Sized {}
+
 /// User provider interface.
 /// Get functions for get or add users.
 #[async_trait]
 pub trait UserProvider: Send + Sync {
-    async fn add_user(
-        &self,
-        add_user_command: &AddUserCommand,
-    ) -> Result<(), Box<dyn error::Error>>;
+    async fn add_user(&self, add_user_command: &AddUserCommand) -> MoneyCalcResult<()>;
 
-    async fn get_users(&self) -> Result<Vec<User>, Box<dyn error::Error>>;
+    async fn get_users(&self) -> MoneyCalcResult<Vec<User>>;
 
-    async fn get_user_by_number(&self, number: &str) -> Result<User, Box<dyn error::Error>>;
+    async fn get_user_by_number(&self, number: &str) -> MoneyCalcResult<User>;
 
-    async fn delete_user_by_id(&self, id: i32) -> Result<(), Box<dyn error::Error>>;
+    async fn delete_user_by_id(&self, id: i32) -> MoneyCalcResult<()>;
+
+    /// Insert user from backup as is, new id is given when id is 0.
+    async fn restore_user(&self, user: &User) -> MoneyCalcResult<User>;
 }
 
 /// Account provider interface.
 /// Functionality for account actions.
 #[async_trait]
 pub trait AccountProvider: Send + Sync {
-    async fn search_account_by_user(&self, user: &User) -> Result<Account, Box<dyn error::Error>>;
+    /// Primary account of user.
+    /// If user has no primary account, the oldest one is returned.
+    async fn search_account_by_user(&self, user: &User) -> MoneyCalcResult<Account>;
 
-    async fn add_account(
-        &self,
-        add_account_command: &AddAccountCommand,
-    ) -> Result<(), Box<dyn error::Error>>;
+    async fn get_accounts_by_user(&self, user: &User) -> MoneyCalcResult<Vec<Account>>;
+
+    async fn get_account_by_id(&self, id: i32) -> MoneyCalcResult<Account>;
 
-    async fn delete_account(&self, account: &Account) -> Result<(), Box<dyn error::Error>>;
+    /// Account of user with given name, the oldest one if names repeat.
+    async fn get_account_by_name(&self, user: &User, name: &str) -> MoneyCalcResult<Account>;
 
-    async fn change_money(
+    /// Make account primary for its user, previous primary account is unmarked.
+    async fn set_primary_account(&self, account: &Account) -> MoneyCalcResult<()>;
+
+    async fn add_account(&self, add_account_command: &AddAccountCommand) -> MoneyCalcResult<()>;
+
+    async fn delete_account(&self, account: &Account) -> MoneyCalcResult<()>;
+
+    /// Add signed amount to balance, debit is checked by overdraft policy.
+    /// Change is not written to transactions, so reconciliation reports it as discrepancy.
+    async fn change_money(&self, account: &Account, payment_count: Money) -> MoneyCalcResult<()>;
+
+    /// Replace overdraft policy, limit must be in account currency.
+    async fn set_overdraft_policy(
         &self,
         account: &Account,
-        payment_count: f32,
-    ) -> Result<(), Box<dyn error::Error>>;
+        overdraft: OverdraftPolicy,
+    ) -> MoneyCalcResult<()>;
+
+    async fn get_accounts(&self) -> MoneyCalcResult<Vec<Account>>;
 
-    async fn get_accounts(&self) -> Result<Vec<Account>, Box<dyn error::Error>>;
+    /// Insert account from backup with its balance as is, new id is given when id is 0.
+    async fn restore_account(
+        &self,
+        account: &Account,
+        initial_balance: Money,
+    ) -> MoneyCalcResult<Account>;
 }
 
 /// Transaction Worker.
 #[async_trait]
 pub trait TransactionWorker: Send + Sync {
+    /// Change balance by transaction and store it, returns stored transaction with its new id.
     async fn execute_transaction(
         &self,
         transaction: &MoneyTransaction,
-    ) -> Result<(), Box<dyn error::Error>>;
+    ) -> MoneyCalcResult<MoneyTransaction>;
+
+    /// Execute transaction imported from bank statement with bank id of it, returns stored transaction.
+    /// Returns None without changes if the id was already imported into the account.
+    async fn execute_external_transaction(
+        &self,
+        transaction: &MoneyTransaction,
+        external_id: &str,
+    ) -> MoneyCalcResult<Option<MoneyTransaction>>;
+
+    /// Bank ids imported into account, ordered by bank id.
+    async fn get_imported_transactions(
+        &self,
+        account: &Account,
+    ) -> MoneyCalcResult<Vec<ImportedTransaction>>;
+
+    /// Insert bank id of restored transaction from backup, balance is not changed.
+    async fn restore_imported_transaction(
+        &self,
+        imported: &ImportedTransaction,
+    ) -> MoneyCalcResult<()>;
+
+    /// Debit one account and credit another in one operation.
+    async fn transfer(&self, transfer_command: &TransferCommand) -> MoneyCalcResult<()>;
+
+    /// Write Reversal entry compensating transaction and change balance back.
+    /// Transfer is reversed on both accounts. Original row is kept,
+    /// reversal is linked to it by correction_of. Fails with Conflict if already reversed.
+    async fn reverse_transaction(
+        &self,
+        reverse_command: &ReverseTransactionCommand,
+    ) -> MoneyCalcResult<MoneyTransaction>;
+
+    /// Reverse transaction and write corrected copy linked to it in one operation.
+    /// Transfers can't be amended, they are reversed and executed again.
+    async fn amend_transaction(
+        &self,
+        amend_command: &AmendTransactionCommand,
+    ) -> MoneyCalcResult<MoneyTransaction>;
+
+    async fn get_transaction_by_id(&self, id: &str) -> MoneyCalcResult<MoneyTransaction>;
+
+    /// Insert transaction from backup as is without changing balance.
+    /// New id is given when id is empty.
+    async fn restore_transaction(
+        &self,
+        transaction: &MoneyTransaction,
+    ) -> MoneyCalcResult<MoneyTransaction>;
+
+    /// Read transactions history page by page.
+    async fn get_transactions(&self, query: &TransactionQuery) -> MoneyCalcResult<TransactionPage>;
+}
+
+/// Exchange rates by date and conversion to reporting currency.
+/// Rate for date is the latest one set on or before it in either direction,
+/// inverse of opposite pair is used when it is newer, direct pair wins on the same date.
+#[async_trait]
+pub trait ExchangeRateProvider: Send + Sync {
+    /// Set rate for date, replaces rate of the same pair and date.
+    async fn add_exchange_rate(&self, rate: &ExchangeRate, date: NaiveDate) -> MoneyCalcResult<()>;
+
+    async fn get_exchange_rate(
+        &self,
+        from: Currency,
+        to: Currency,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<ExchangeRate>;
+
+    async fn convert_money(
+        &self,
+        money: Money,
+        currency: Currency,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<Money>;
+
+    /// Sum of current balances of all user accounts in reporting currency.
+    /// Balances are converted by the latest rates on or before date, not by historical rates
+    /// of the days they changed.
+    async fn get_total_balance(
+        &self,
+        user: &User,
+        currency: Currency,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<Money>;
+
+    /// Transactions with amounts in reporting currency by rates of their dates.
+    async fn convert_transactions(
+        &self,
+        transactions: &[MoneyTransaction],
+        currency: Currency,
+    ) -> MoneyCalcResult<Vec<MoneyTransaction>>;
+}
+
+/// Hierarchical spending categories of user.
+#[async_trait]
+pub trait CategoryProvider: Send + Sync {
+    /// Parent category must belong to the same user.
+    async fn add_category(&self, add_category_command: &AddCategoryCommand) -> MoneyCalcResult<()>;
+
+    async fn get_categories(&self, user: &User) -> MoneyCalcResult<Vec<Category>>;
+
+    async fn get_category_by_id(&self, id: i32) -> MoneyCalcResult<Category>;
+
+    /// Rename or move category, category can't be moved under its own subcategory.
+    async fn update_category(&self, category: &Category) -> MoneyCalcResult<()>;
+
+    /// Subcategories are moved to parent of deleted category,
+    /// its transactions are left without category.
+    /// Fails with Conflict while budgets use the category.
+    async fn delete_category(&self, category: &Category) -> MoneyCalcResult<()>;
+
+    /// Assign category to transaction or clear it with None.
+    async fn set_transaction_category(
+        &self,
+        transaction_id: &str,
+        category: Option<&Category>,
+    ) -> MoneyCalcResult<()>;
+
+    /// Insert category from backup as is, new id is given when id is 0.
+    /// Parent category must be restored before its subcategories.
+    async fn restore_category(&self, category: &Category) -> MoneyCalcResult<Category>;
+}
+
+/// Tags of user and their assignment to transactions.
+#[async_trait]
+pub trait TagProvider: Send + Sync {
+    async fn add_tag(&self, add_tag_command: &AddTagCommand) -> MoneyCalcResult<()>;
+
+    async fn get_tags(&self, user: &User) -> MoneyCalcResult<Vec<Tag>>;
+
+    async fn rename_tag(&self, tag: &Tag, name: &str) -> MoneyCalcResult<()>;
+
+    /// Tag is removed from all transactions.
+    async fn delete_tag(&self, tag: &Tag) -> MoneyCalcResult<()>;
+
+    /// Insert tag from backup as is, new id is given when id is 0.
+    async fn restore_tag(&self, tag: &Tag) -> MoneyCalcResult<Tag>;
+
+    /// Replace tags of transaction.
+    async fn set_transaction_tags(&self, transaction_id: &str, tags: &[Tag])
+    -> MoneyCalcResult<()>;
+
+    async fn get_transaction_tags(&self, transaction_id: &str) -> MoneyCalcResult<Vec<Tag>>;
+}
+
+/// Spending limits of user and their progress.
+/// Spent amount is a sum of Outcome transactions of user accounts in budget scope,
+/// other currencies are converted by exchange rates of transaction dates.
+#[async_trait]
+pub trait BudgetProvider: Send + Sync {
+    async fn add_budget(&self, add_budget_command: &AddBudgetCommand) -> MoneyCalcResult<()>;
+
+    async fn get_budgets(&self, user: &User) -> MoneyCalcResult<Vec<Budget>>;
+
+    async fn get_budget_by_id(&self, id: i32) -> MoneyCalcResult<Budget>;
+
+    async fn update_budget(&self, budget: &Budget) -> MoneyCalcResult<()>;
+
+    async fn delete_budget(&self, budget: &Budget) -> MoneyCalcResult<()>;
+
+    /// Planned, spent and remaining money in the period containing date.
+    async fn get_budget_progress(
+        &self,
+        budget: &Budget,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<BudgetProgress>;
+
+    /// Progress of every user budget in the period containing date.
+    async fn get_budgets_progress(
+        &self,
+        user: &User,
+        date: NaiveDate,
+    ) -> MoneyCalcResult<Vec<BudgetProgress>>;
+}
+
+/// Recurring transaction templates and generation of their occurrences.
+/// Every occurrence is generated once, generated transactions are dated by occurrence date.
+#[async_trait]
+pub trait RecurringProvider: Send + Sync {
+    async fn add_recurring(
+        &self,
+        add_recurring_command: &AddRecurringCommand,
+    ) -> MoneyCalcResult<()>;
+
+    async fn get_recurring(&self, user: &User) -> MoneyCalcResult<Vec<RecurringTransaction>>;
+
+    async fn get_recurring_by_id(&self, id: i32) -> MoneyCalcResult<RecurringTransaction>;
+
+    /// Template stops generating, already generated transactions are kept.
+    async fn delete_recurring(&self, recurring: &RecurringTransaction) -> MoneyCalcResult<()>;
+
+    /// Generated occurrences of template ordered by date.
+    async fn get_occurrences(
+        &self,
+        recurring: &RecurringTransaction,
+    ) -> MoneyCalcResult<Vec<RecurringOccurrence>>;
+
+    /// Generate transactions for all occurrences due on or before now, including missed ones.
+    /// Templates are processed one by one, each in own database transaction.
+    /// Failed template is reported and skipped, other templates are still generated,
+    /// repeated call retries the failed one from where it stopped.
+    async fn generate_due(&self, now: NaiveDateTime) -> MoneyCalcResult<GenerationReport>;
+}
+
+/// Append-only log of changes made through providers.
+/// Every change is logged in the same database transaction as the change itself.
+#[async_trait]
+pub trait AuditProvider: Send + Sync {
+    /// Records matching query ordered by id.
+    async fn get_audit_log(&self, query: &AuditQuery) -> MoneyCalcResult<Vec<AuditEntry>>;
+
+    /// Check hash chain of the log, returns id of the first record which breaks it.
+    /// Records written before hash chaining was enabled are not checked,
+    /// record without hash after the first hashed one breaks the chain.
+    async fn verify_audit_log(&self) -> MoneyCalcResult<Option<i64>>;
+}
+
+/// Check of stored account balances against transactions.
+/// Expected balance is initial balance of account plus signed amounts of its transactions.
+#[async_trait]
+pub trait ReconciliationProvider: Send + Sync {
+    /// Compare every account balance with expected one.
+    /// With repair stored balances of discrepancies are replaced by expected ones.
+    async fn reconcile_balances(&self, repair: bool) -> MoneyCalcResult<ReconciliationReport>;
+}
+
+/// Balances of account in the past, calculated from transactions.
+/// Balance changes made by change_money are not in transactions and are not seen here.
+#[async_trait]
+pub trait BalanceHistoryProvider: Send + Sync {
+    /// Balance after all transactions dated at or before moment.
+    async fn get_balance_at(&self, account: &Account, at: NaiveDateTime) -> MoneyCalcResult<Money>;
+
+    /// Balance at the end of every day from from_date to to_date inclusive.
+    async fn get_daily_balances(
+        &self,
+        account: &Account,
+        from_date: NaiveDate,
+        to_date: NaiveDate,
+    ) -> MoneyCalcResult<Vec<DailyBalance>>;
+
+    /// Store balance at moment so later calculations start from it instead of whole history.
+    async fn add_balance_snapshot(
+        &self,
+        account: &Account,
+        at: NaiveDateTime,
+    ) -> MoneyCalcResult<BalanceSnapshot>;
 }
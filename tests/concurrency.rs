@@ -0,0 +1,140 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::Instant,
+};
+
+use moneycalc::prelude::*;
+
+fn rub(amount: &str) -> Money {
+    Money::parse(amount, Currency::RUB).unwrap()
+}
+
+async fn seed(provider: &SqliteProvider, number: &str) -> (User, Account) {
+    provider
+        .add_user(&AddUserCommand {
+            user_name: "bench".to_string(),
+            user_number: number.to_string(),
+        })
+        .await
+        .unwrap();
+    let user = provider.get_user_by_number(number).await.unwrap();
+    provider
+        .add_account(&AddAccountCommand {
+            user_id: user.id,
+            account_name: "Bench".to_string(),
+            initial_balance: rub("0.00"),
+            overdraft: OverdraftPolicy::default(),
+        })
+        .await
+        .unwrap();
+    let account = provider.search_account_by_user(&user).await.unwrap();
+    (user, account)
+}
+
+fn income(user: &User, account: &Account) -> MoneyTransaction {
+    MoneyTransaction {
+        id: String::new(),
+        amount: rub("1.00"),
+        description: "Bench".to_string(),
+        user: user.clone(),
+        account: account.clone(),
+        payment_type: PaymentType::Income,
+        payment_target: "Bench".to_string(),
+        create_date: chrono::Utc::now().naive_utc(),
+        linked_transaction_id: None,
+        correction_of: None,
+        category_id: None,
+    }
+}
+
+/// Many callers read and write at once through one file database.
+/// Prints throughput, run with `--nocapture` to see it.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_throughput_bench() {
+    const CALLERS: usize = 32;
+    const OPERATIONS: usize = 25;
+
+    let path = "./testbases/testbase_bench.db3";
+    let provider = SqliteConfiguration::new(path).configure().unwrap();
+    let (user, account) = seed(&provider, &uuid::Uuid::new_v4().to_string()).await;
+
+    let provider = Arc::new(provider);
+    let started = Instant::now();
+    let handles: Vec<_> = (0..CALLERS)
+        .map(|caller| {
+            let provider = provider.clone();
+            let transaction = income(&user, &account);
+            tokio::spawn(async move {
+                for _ in 0..OPERATIONS {
+                    if caller % 4 == 0 {
+                        provider.execute_transaction(&transaction).await.unwrap();
+                    } else {
+                        provider
+                            .get_transactions(&TransactionQuery {
+                                account_id: Some(transaction.account.id),
+                                limit: 20,
+                                ..Default::default()
+                            })
+                            .await
+                            .unwrap();
+                    }
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    let elapsed = started.elapsed();
+    println!(
+        "{} operations by {} callers in {:?}, {:.0} ops/s",
+        CALLERS * OPERATIONS,
+        CALLERS,
+        elapsed,
+        (CALLERS * OPERATIONS) as f64 / elapsed.as_secs_f64()
+    );
+
+    let account = provider.get_account_by_id(account.id).await.unwrap();
+    let writes = (CALLERS / 4 * OPERATIONS) as i64;
+    assert_eq!(
+        account.money,
+        Money::from_minor(writes * 100, Currency::RUB)
+    );
+
+    drop(provider);
+    std::fs::remove_file(path).unwrap();
+}
+
+/// Single threaded runtime keeps running other tasks while queries wait for sqlite.
+#[tokio::test]
+async fn queries_do_not_block_runtime_test() {
+    let provider = SqliteConfiguration::memory_base().configure().unwrap();
+    let (user, account) = seed(&provider, "1").await;
+
+    let ticks = Arc::new(AtomicUsize::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+    let heartbeat = {
+        let ticks = ticks.clone();
+        let stop = stop.clone();
+        tokio::spawn(async move {
+            while !stop.load(Ordering::Relaxed) {
+                ticks.fetch_add(1, Ordering::Relaxed);
+                tokio::task::yield_now().await;
+            }
+        })
+    };
+
+    for _ in 0..20 {
+        provider
+            .execute_transaction(&income(&user, &account))
+            .await
+            .unwrap();
+    }
+    assert!(ticks.load(Ordering::Relaxed) > 0);
+
+    stop.store(true, Ordering::Relaxed);
+    heartbeat.await.unwrap();
+}
@@ -0,0 +1,1165 @@
+//! Behaviour every data provider must share.
+//! Each check is generic over provider and runs for all of them by `conformance!`.
+//! Postgres checks are ignored by default, run them with MONEYCALC_POSTGRES_URL set and `--ignored`.
+use chrono::{NaiveDate, NaiveDateTime};
+use moneycalc::prelude::*;
+
+fn rub(amount: &str) -> Money {
+    Money::parse(amount, Currency::RUB).unwrap()
+}
+
+fn date(day: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2025, 3, day)
+        .unwrap()
+        .and_hms_opt(12, 0, 0)
+        .unwrap()
+}
+
+async fn seed<P: DataProvider>(provider: &P, number: &str) -> (User, Account, Account) {
+    provider
+        .add_user(&AddUserCommand {
+            user_name: "conformance".to_string(),
+            user_number: number.to_string(),
+        })
+        .await
+        .unwrap();
+    let user = provider.get_user_by_number(number).await.unwrap();
+    for name in ["Checking", "Savings"] {
+        provider
+            .add_account(&AddAccountCommand {
+                user_id: user.id,
+                account_name: name.to_string(),
+                initial_balance: rub("100.00"),
+                overdraft: OverdraftPolicy::default(),
+            })
+            .await
+            .unwrap();
+    }
+    let checking = provider
+        .get_account_by_name(&user, "Checking")
+        .await
+        .unwrap();
+    let savings = provider
+        .get_account_by_name(&user, "Savings")
+        .await
+        .unwrap();
+    (user, checking, savings)
+}
+
+fn outcome(user: &User, account: &Account, amount: &str, day: u32) -> MoneyTransaction {
+    MoneyTransaction {
+        id: String::new(),
+        amount: rub(amount),
+        description: format!("Shop {}", day),
+        user: user.clone(),
+        account: account.clone(),
+        payment_type: PaymentType::Outcome,
+        payment_target: "Shop".to_string(),
+        create_date: date(day),
+        linked_transaction_id: None,
+        correction_of: None,
+        category_id: None,
+    }
+}
+
+async fn balance<P: DataProvider>(provider: &P, account: &Account) -> Money {
+    provider.get_account_by_id(account.id).await.unwrap().money
+}
+
+pub async fn users_and_accounts<P: DataProvider>(provider: P) {
+    let (user, checking, savings) = seed(&provider, "1").await;
+    let users = provider.get_users().await.unwrap();
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0].number, user.number);
+    assert!(checking.is_primary);
+    assert!(!savings.is_primary);
+    assert_eq!(
+        provider.search_account_by_user(&user).await.unwrap().id,
+        checking.id
+    );
+
+    let duplicate = provider
+        .add_user(&AddUserCommand {
+            user_name: "copy".to_string(),
+            user_number: "1".to_string(),
+        })
+        .await;
+    assert!(matches!(duplicate, Err(MoneyCalcError::Conflict(_))));
+    assert!(matches!(
+        provider.get_user_by_number("missing").await,
+        Err(MoneyCalcError::NotFound(_))
+    ));
+    assert!(matches!(
+        provider.get_account_by_id(1000).await,
+        Err(MoneyCalcError::NotFound(_))
+    ));
+
+    provider.set_primary_account(&savings).await.unwrap();
+    assert_eq!(
+        provider.search_account_by_user(&user).await.unwrap().id,
+        savings.id
+    );
+    provider.delete_account(&savings).await.unwrap();
+    let accounts = provider.get_accounts_by_user(&user).await.unwrap();
+    assert_eq!(accounts.len(), 1);
+    assert!(accounts[0].is_primary);
+}
+
+pub async fn transactions_and_transfers<P: DataProvider>(provider: P) {
+    let (user, checking, savings) = seed(&provider, "1").await;
+    let executed = provider
+        .execute_transaction(&outcome(&user, &checking, "25.50", 1))
+        .await
+        .unwrap();
+    assert!(!executed.id.is_empty());
+    assert_eq!(executed.amount, rub("25.50"));
+    let stored = provider.get_transaction_by_id(&executed.id).await.unwrap();
+    assert_eq!(stored.description, executed.description);
+    assert_eq!(balance(&provider, &checking).await, rub("74.50"));
+
+    let overdraft = provider
+        .execute_transaction(&outcome(&user, &checking, "1000.00", 2))
+        .await;
+    assert!(matches!(
+        overdraft,
+        Err(MoneyCalcError::InsufficientFunds(_))
+    ));
+    assert_eq!(balance(&provider, &checking).await, rub("74.50"));
+
+    for (amount, payment_type) in [
+        ("-100.00", PaymentType::Outcome),
+        ("0.00", PaymentType::Income),
+    ] {
+        let invalid = provider
+            .execute_transaction(&MoneyTransaction {
+                payment_type,
+                ..outcome(&user, &checking, amount, 2)
+            })
+            .await;
+        assert!(matches!(invalid, Err(MoneyCalcError::Validation(_))));
+    }
+    assert_eq!(balance(&provider, &checking).await, rub("74.50"));
+
+    provider
+        .transfer(&TransferCommand {
+            from_account_id: checking.id,
+            to_account_id: savings.id,
+            amount: rub("50.00"),
+            exchange_rate: None,
+            description: "Save".to_string(),
+            create_date: date(3),
+        })
+        .await
+        .unwrap();
+    assert_eq!(balance(&provider, &checking).await, rub("24.50"));
+    assert_eq!(balance(&provider, &savings).await, rub("150.00"));
+
+    let page = provider
+        .get_transactions(&TransactionQuery {
+            account_id: Some(savings.id),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let credit = &page.transactions[0];
+    assert_eq!(credit.payment_type, PaymentType::Transfer);
+    assert_eq!(credit.payment_target, "Checking");
+    let debit = provider
+        .get_transaction_by_id(credit.linked_transaction_id.as_ref().unwrap())
+        .await
+        .unwrap();
+    assert_eq!(debit.linked_transaction_id.as_ref(), Some(&credit.id));
+    assert_eq!(debit.amount, rub("-50.00"));
+
+    let same_account = provider
+        .transfer(&TransferCommand {
+            from_account_id: checking.id,
+            to_account_id: checking.id,
+            amount: rub("1.00"),
+            exchange_rate: None,
+            description: "Loop".to_string(),
+            create_date: date(4),
+        })
+        .await;
+    assert!(matches!(same_account, Err(MoneyCalcError::Validation(_))));
+}
+
+pub async fn reversals_and_amendments<P: DataProvider>(provider: P) {
+    let (user, checking, _) = seed(&provider, "1").await;
+    provider
+        .execute_transaction(&outcome(&user, &checking, "30.00", 1))
+        .await
+        .unwrap();
+    let original = provider
+        .get_transactions(&TransactionQuery::default())
+        .await
+        .unwrap()
+        .transactions
+        .remove(0);
+
+    let amended = provider
+        .amend_transaction(&AmendTransactionCommand {
+            transaction_id: original.id.clone(),
+            amount: rub("20.00"),
+            description: "Shop fixed".to_string(),
+            payment_target: "Shop".to_string(),
+            category_id: None,
+            create_date: date(2),
+        })
+        .await
+        .unwrap();
+    assert_eq!(amended.correction_of.as_ref(), Some(&original.id));
+    assert_eq!(balance(&provider, &checking).await, rub("80.00"));
+
+    let reversal = provider
+        .reverse_transaction(&ReverseTransactionCommand {
+            transaction_id: amended.id.clone(),
+            description: "Refund".to_string(),
+            create_date: date(3),
+        })
+        .await
+        .unwrap();
+    assert_eq!(reversal.correction_of.as_ref(), Some(&amended.id));
+    assert_eq!(balance(&provider, &checking).await, rub("100.00"));
+
+    let again = provider
+        .reverse_transaction(&ReverseTransactionCommand {
+            transaction_id: amended.id.clone(),
+            description: "Refund".to_string(),
+            create_date: date(4),
+        })
+        .await;
+    assert!(matches!(again, Err(MoneyCalcError::Conflict(_))));
+    assert!(matches!(
+        provider.get_transaction_by_id("missing").await,
+        Err(MoneyCalcError::NotFound(_))
+    ));
+}
+
+pub async fn external_transactions<P: DataProvider>(provider: P) {
+    let (user, checking, savings) = seed(&provider, "1").await;
+    let transaction = outcome(&user, &checking, "10.00", 1);
+    let executed = provider
+        .execute_external_transaction(&transaction, "FITID1")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(
+        provider
+            .execute_external_transaction(&transaction, "FITID1")
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let other_account = outcome(&user, &savings, "10.00", 1);
+    assert!(
+        provider
+            .execute_external_transaction(&other_account, "FITID1")
+            .await
+            .unwrap()
+            .is_some()
+    );
+    assert_eq!(balance(&provider, &checking).await, rub("90.00"));
+    assert_eq!(balance(&provider, &savings).await, rub("90.00"));
+
+    let imported = provider.get_imported_transactions(&checking).await.unwrap();
+    assert_eq!(imported.len(), 1);
+    assert_eq!(imported[0].external_id, "FITID1");
+    assert_eq!(imported[0].transaction_id, executed.id);
+    let stored = provider
+        .get_transaction_by_id(&imported[0].transaction_id)
+        .await
+        .unwrap();
+    assert_eq!(stored.account.id, checking.id);
+    assert!(matches!(
+        provider.restore_imported_transaction(&imported[0]).await,
+        Err(MoneyCalcError::Conflict(_))
+    ));
+    assert!(matches!(
+        provider
+            .restore_imported_transaction(&ImportedTransaction {
+                account_id: savings.id,
+                external_id: "FITID2".to_string(),
+                ..imported[0].clone()
+            })
+            .await,
+        Err(MoneyCalcError::Validation(_))
+    ));
+}
+
+pub async fn paging<P: DataProvider>(provider: P) {
+    let (user, checking, _) = seed(&provider, "1").await;
+    for (amount, day) in [
+        ("3.00", 5),
+        ("1.00", 2),
+        ("2.00", 9),
+        ("4.00", 1),
+        ("5.00", 7),
+    ] {
+        provider
+            .execute_transaction(&outcome(&user, &checking, amount, day))
+            .await
+            .unwrap();
+    }
+
+    for (order, expected) in [
+        (TransactionOrder::DateDesc, [9, 7, 5, 2, 1]),
+        (TransactionOrder::DateAsc, [1, 2, 5, 7, 9]),
+        (TransactionOrder::AmountAsc, [2, 9, 5, 1, 7]),
+        (TransactionOrder::AmountDesc, [7, 1, 5, 9, 2]),
+    ] {
+        let mut days = vec![];
+        let mut cursor = None;
+        loop {
+            let page = provider
+                .get_transactions(&TransactionQuery {
+                    order,
+                    limit: 2,
+                    cursor,
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            days.extend(page.transactions.iter().map(|transaction| {
+                transaction.description["Shop ".len()..]
+                    .parse::<u32>()
+                    .unwrap()
+            }));
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(days, expected, "{:?}", order);
+    }
+
+    let filtered = provider
+        .get_transactions(&TransactionQuery {
+            from_date: Some(date(2)),
+            to_date: Some(date(7)),
+            description_contains: Some("shop".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(filtered.transactions.len(), 2);
+
+    let zero_limit = provider
+        .get_transactions(&TransactionQuery {
+            limit: 0,
+            ..Default::default()
+        })
+        .await;
+    assert!(matches!(zero_limit, Err(MoneyCalcError::Validation(_))));
+    let bad_cursor = provider
+        .get_transactions(&TransactionQuery {
+            cursor: Some("garbage".to_string()),
+            ..Default::default()
+        })
+        .await;
+    assert!(matches!(bad_cursor, Err(MoneyCalcError::Validation(_))));
+}
+
+pub async fn restore_and_delete<P: DataProvider>(provider: P) {
+    let (user, checking, _) = seed(&provider, "1").await;
+    let restored = provider
+        .restore_transaction(&MoneyTransaction {
+            id: "restored".to_string(),
+            ..outcome(&user, &checking, "-5.00", 1)
+        })
+        .await
+        .unwrap();
+    assert_eq!(restored.id, "restored");
+    assert_eq!(balance(&provider, &checking).await, rub("100.00"));
+    let duplicate = provider
+        .restore_transaction(&MoneyTransaction {
+            id: "restored".to_string(),
+            ..outcome(&user, &checking, "-5.00", 1)
+        })
+        .await;
+    assert!(matches!(duplicate, Err(MoneyCalcError::Conflict(_))));
+    let orphan = provider
+        .restore_account(
+            &Account {
+                id: 0,
+                user_id: user.id + 1000,
+                ..checking.clone()
+            },
+            rub("1.00"),
+        )
+        .await;
+    assert!(matches!(orphan, Err(MoneyCalcError::Validation(_))));
+
+    assert!(matches!(
+        provider.delete_account(&checking).await,
+        Err(MoneyCalcError::Conflict(_))
+    ));
+    assert!(matches!(
+        provider.delete_user_by_id(user.id).await,
+        Err(MoneyCalcError::Conflict(_))
+    ));
+
+    let copy = provider
+        .restore_user(&User {
+            id: 0,
+            number: "2".to_string(),
+            ..user.clone()
+        })
+        .await
+        .unwrap();
+    assert_ne!(copy.id, user.id);
+    provider.delete_user_by_id(copy.id).await.unwrap();
+}
+
+async fn add_category<P: CategoryProvider>(
+    provider: &P,
+    user: &User,
+    parent_id: Option<i32>,
+    name: &str,
+) -> Category {
+    provider
+        .add_category(&AddCategoryCommand {
+            user_id: user.id,
+            parent_id,
+            name: name.to_string(),
+        })
+        .await
+        .unwrap();
+    provider
+        .get_categories(user)
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|category| category.name == name && category.parent_id == parent_id)
+        .unwrap()
+}
+
+async fn category_count<P: DataProvider>(provider: &P, category_id: i32) -> usize {
+    provider
+        .get_transactions(&TransactionQuery {
+            category_id: Some(category_id),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .transactions
+        .len()
+}
+
+pub async fn categories<P: DataProvider + CategoryProvider>(provider: P) {
+    let (user, checking, _) = seed(&provider, "1").await;
+    let food = add_category(&provider, &user, None, "Food").await;
+    let cafe = add_category(&provider, &user, Some(food.id), "Cafe").await;
+    for (amount, day, category_id) in [
+        ("1.00", 1, Some(food.id)),
+        ("2.00", 2, Some(cafe.id)),
+        ("3.00", 3, None),
+    ] {
+        provider
+            .execute_transaction(&MoneyTransaction {
+                category_id,
+                ..outcome(&user, &checking, amount, day)
+            })
+            .await
+            .unwrap();
+    }
+    assert_eq!(category_count(&provider, food.id).await, 2);
+    assert_eq!(category_count(&provider, cafe.id).await, 1);
+
+    let duplicate = provider
+        .add_category(&AddCategoryCommand {
+            user_id: user.id,
+            parent_id: None,
+            name: "Food".to_string(),
+        })
+        .await;
+    assert!(matches!(duplicate, Err(MoneyCalcError::Conflict(_))));
+    let under_itself = provider
+        .update_category(&Category {
+            parent_id: Some(cafe.id),
+            ..food.clone()
+        })
+        .await;
+    assert!(matches!(under_itself, Err(MoneyCalcError::Validation(_))));
+    let missing = provider
+        .execute_transaction(&MoneyTransaction {
+            category_id: Some(1000),
+            ..outcome(&user, &checking, "1.00", 4)
+        })
+        .await;
+    assert!(matches!(missing, Err(MoneyCalcError::NotFound(_))));
+    let (other, _, _) = seed(&provider, "2").await;
+    let foreign = add_category(&provider, &other, None, "Food").await;
+    let foreign_category = provider
+        .execute_transaction(&MoneyTransaction {
+            category_id: Some(foreign.id),
+            ..outcome(&user, &checking, "1.00", 4)
+        })
+        .await;
+    assert!(matches!(
+        foreign_category,
+        Err(MoneyCalcError::Validation(_))
+    ));
+
+    provider.delete_category(&food).await.unwrap();
+    assert_eq!(
+        provider
+            .get_category_by_id(cafe.id)
+            .await
+            .unwrap()
+            .parent_id,
+        None
+    );
+    assert_eq!(category_count(&provider, food.id).await, 0);
+    assert_eq!(category_count(&provider, cafe.id).await, 1);
+}
+
+pub async fn tags<P: DataProvider + TagProvider>(provider: P) {
+    let (user, checking, _) = seed(&provider, "1").await;
+    for name in ["travel", "food"] {
+        provider
+            .add_tag(&AddTagCommand {
+                user_id: user.id,
+                name: name.to_string(),
+            })
+            .await
+            .unwrap();
+    }
+    let tags = provider.get_tags(&user).await.unwrap();
+    let names: Vec<&str> = tags.iter().map(|tag| tag.name.as_str()).collect();
+    assert_eq!(names, ["food", "travel"]);
+    let duplicate = provider
+        .add_tag(&AddTagCommand {
+            user_id: user.id,
+            name: "food".to_string(),
+        })
+        .await;
+    assert!(matches!(duplicate, Err(MoneyCalcError::Conflict(_))));
+
+    let transaction = provider
+        .execute_transaction(&outcome(&user, &checking, "1.00", 1))
+        .await
+        .unwrap();
+    provider
+        .set_transaction_tags(&transaction.id, &tags)
+        .await
+        .unwrap();
+    assert_eq!(
+        provider
+            .get_transaction_tags(&transaction.id)
+            .await
+            .unwrap(),
+        tags
+    );
+    provider.rename_tag(&tags[1], "trips").await.unwrap();
+    provider.delete_tag(&tags[0]).await.unwrap();
+    let left = provider
+        .get_transaction_tags(&transaction.id)
+        .await
+        .unwrap();
+    assert_eq!(left.len(), 1);
+    assert_eq!(left[0].name, "trips");
+
+    assert!(matches!(
+        provider.get_transaction_tags("missing").await,
+        Err(MoneyCalcError::NotFound(_))
+    ));
+    let missing_tag = provider
+        .set_transaction_tags(
+            &transaction.id,
+            &[Tag {
+                id: 1000,
+                ..tags[0].clone()
+            }],
+        )
+        .await;
+    assert!(matches!(missing_tag, Err(MoneyCalcError::NotFound(_))));
+}
+
+pub async fn exchange_rates<P: DataProvider + ExchangeRateProvider>(provider: P) {
+    let (user, _, _) = seed(&provider, "1").await;
+    let rate = |from, to, rate| ExchangeRate::new(from, to, rate).unwrap();
+    let usd = |amount| Money::parse(amount, Currency::USD).unwrap();
+    provider
+        .add_exchange_rate(&rate(Currency::USD, Currency::RUB, "90"), date(1).date())
+        .await
+        .unwrap();
+    for (from, to, value) in [
+        (Currency::USD, Currency::RUB, "100"),
+        (Currency::RUB, Currency::USD, "0.02"),
+    ] {
+        provider
+            .add_exchange_rate(&rate(from, to, value), date(5).date())
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(
+        provider
+            .convert_money(usd("2.00"), Currency::RUB, date(3).date())
+            .await
+            .unwrap(),
+        rub("180.00")
+    );
+    assert_eq!(
+        provider
+            .convert_money(rub("900.00"), Currency::USD, date(3).date())
+            .await
+            .unwrap(),
+        usd("10.00")
+    );
+    for (from, to, value) in [
+        (Currency::USD, Currency::RUB, "100"),
+        (Currency::RUB, Currency::USD, "0.02"),
+    ] {
+        assert_eq!(
+            provider
+                .get_exchange_rate(from, to, date(6).date())
+                .await
+                .unwrap(),
+            rate(from, to, value)
+        );
+    }
+    assert_eq!(
+        provider
+            .get_total_balance(&user, Currency::USD, date(5).date())
+            .await
+            .unwrap(),
+        usd("4.00")
+    );
+
+    let before_first = provider
+        .get_exchange_rate(
+            Currency::USD,
+            Currency::RUB,
+            NaiveDate::from_ymd_opt(2025, 2, 1).unwrap(),
+        )
+        .await;
+    assert!(matches!(before_first, Err(MoneyCalcError::NotFound(_))));
+    let same_currency = provider
+        .add_exchange_rate(&ExchangeRate::identity(Currency::RUB), date(1).date())
+        .await;
+    assert!(matches!(same_currency, Err(MoneyCalcError::Validation(_))));
+}
+
+pub async fn budgets<P: DataProvider + CategoryProvider + BudgetProvider>(provider: P) {
+    let (user, checking, savings) = seed(&provider, "1").await;
+    let food = add_category(&provider, &user, None, "Food").await;
+    let cafe = add_category(&provider, &user, Some(food.id), "Cafe").await;
+    for (account, amount, day, target, category_id) in [
+        (&checking, "10.00", 1, "Shop", Some(cafe.id)),
+        (&savings, "20.00", 2, "shop", None),
+        (&checking, "5.00", 3, "Bakery", Some(food.id)),
+    ] {
+        provider
+            .execute_transaction(&MoneyTransaction {
+                payment_target: target.to_string(),
+                category_id,
+                ..outcome(&user, account, amount, day)
+            })
+            .await
+            .unwrap();
+    }
+    let bakery = provider
+        .get_transactions(&TransactionQuery {
+            payment_target: Some("Bakery".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .transactions
+        .remove(0);
+    provider
+        .reverse_transaction(&ReverseTransactionCommand {
+            transaction_id: bakery.id,
+            description: "Refund".to_string(),
+            create_date: date(4),
+        })
+        .await
+        .unwrap();
+
+    for (name, scope) in [
+        ("Shops", BudgetScope::PaymentTarget("SH*".to_string())),
+        ("Food", BudgetScope::Category(food.id)),
+        ("Checking", BudgetScope::Account(checking.id)),
+    ] {
+        provider
+            .add_budget(&AddBudgetCommand {
+                user_id: user.id,
+                name: name.to_string(),
+                scope,
+                period: BudgetPeriod::Monthly,
+                limit: rub("100.00"),
+            })
+            .await
+            .unwrap();
+    }
+    let progress = provider
+        .get_budgets_progress(&user, date(15).date())
+        .await
+        .unwrap();
+    let spent: Vec<Money> = progress.iter().map(|progress| progress.spent).collect();
+    assert_eq!(spent, [rub("30.00"), rub("10.00"), rub("10.00")]);
+    assert_eq!(progress[0].remaining, rub("70.00"));
+    let next_month = provider
+        .get_budget_progress(
+            &progress[0].budget,
+            NaiveDate::from_ymd_opt(2025, 4, 15).unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(next_month.spent, rub("0.00"));
+
+    for (scope, limit) in [
+        (BudgetScope::Account(checking.id), "0.00"),
+        (BudgetScope::PaymentTarget(" ".to_string()), "1.00"),
+    ] {
+        let invalid = provider
+            .add_budget(&AddBudgetCommand {
+                user_id: user.id,
+                name: "Invalid".to_string(),
+                scope,
+                period: BudgetPeriod::Monthly,
+                limit: rub(limit),
+            })
+            .await;
+        assert!(matches!(invalid, Err(MoneyCalcError::Validation(_))));
+    }
+    assert!(matches!(
+        provider.delete_category(&food).await,
+        Err(MoneyCalcError::Conflict(_))
+    ));
+
+    let food_budget = progress[1].budget.clone();
+    provider
+        .update_budget(&Budget {
+            limit: rub("50.00"),
+            ..food_budget.clone()
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        provider
+            .get_budget_by_id(food_budget.id)
+            .await
+            .unwrap()
+            .limit,
+        rub("50.00")
+    );
+    provider.delete_budget(&food_budget).await.unwrap();
+    assert!(matches!(
+        provider.get_budget_by_id(food_budget.id).await,
+        Err(MoneyCalcError::NotFound(_))
+    ));
+    provider.delete_category(&food).await.unwrap();
+}
+
+pub async fn recurring<P: DataProvider + RecurringProvider>(provider: P) {
+    let (user, checking, savings) = seed(&provider, "1").await;
+    let template = |account: &Account, amount, payment_type, day| AddRecurringCommand {
+        user_id: user.id,
+        account_id: account.id,
+        amount: rub(amount),
+        payment_type,
+        payment_target: "Work".to_string(),
+        description: "Monthly".to_string(),
+        category_id: None,
+        schedule: Schedule::MonthlyOnDay(day),
+        start_date: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+        end_date: None,
+    };
+    provider
+        .add_recurring(&template(&checking, "10.00", PaymentType::Income, 5))
+        .await
+        .unwrap();
+    provider
+        .add_recurring(&template(&savings, "1000.00", PaymentType::Outcome, 1))
+        .await
+        .unwrap();
+    for invalid in [
+        template(&checking, "0.00", PaymentType::Income, 5),
+        template(&checking, "1.00", PaymentType::Transfer, 5),
+        template(&checking, "1.00", PaymentType::Income, 32),
+    ] {
+        assert!(matches!(
+            provider.add_recurring(&invalid).await,
+            Err(MoneyCalcError::Validation(_))
+        ));
+    }
+    let templates = provider.get_recurring(&user).await.unwrap();
+    assert_eq!(templates.len(), 2);
+    let (salary, rent) = (&templates[0], &templates[1]);
+
+    let now = date(10);
+    let report = provider.generate_due(now).await.unwrap();
+    assert_eq!(report.occurrences.len(), 3);
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].recurring_id, rent.id);
+    assert_eq!(balance(&provider, &checking).await, rub("130.00"));
+    assert_eq!(balance(&provider, &savings).await, rub("100.00"));
+
+    let again = provider.generate_due(now).await.unwrap();
+    assert!(again.occurrences.is_empty());
+    assert_eq!(again.failures.len(), 1);
+    let occurrences = provider.get_occurrences(salary).await.unwrap();
+    let days: Vec<NaiveDate> = occurrences
+        .iter()
+        .map(|occurrence| occurrence.date)
+        .collect();
+    assert_eq!(
+        days,
+        [(1, 5), (2, 5), (3, 5)]
+            .map(|(month, day)| NaiveDate::from_ymd_opt(2025, month, day).unwrap())
+    );
+    let generated = provider
+        .get_transaction_by_id(&occurrences[2].transaction_id)
+        .await
+        .unwrap();
+    assert_eq!(generated.create_date, days[2].and_hms_opt(0, 0, 0).unwrap());
+    assert_eq!(generated.amount, rub("10.00"));
+
+    provider.delete_recurring(rent).await.unwrap();
+    assert!(matches!(
+        provider.get_recurring_by_id(rent.id).await,
+        Err(MoneyCalcError::NotFound(_))
+    ));
+    assert!(
+        provider
+            .generate_due(now)
+            .await
+            .unwrap()
+            .failures
+            .is_empty()
+    );
+}
+
+pub async fn audit<P: DataProvider + AuditProvider>(provider: P) {
+    let (user, checking, savings) = seed(&provider, "1").await;
+    let executed = provider
+        .execute_transaction(&outcome(&user, &checking, "10.00", 1))
+        .await
+        .unwrap();
+    provider.delete_account(&savings).await.unwrap();
+
+    let log = provider
+        .get_audit_log(&AuditQuery::default())
+        .await
+        .unwrap();
+    let actions: Vec<&str> = log.iter().map(|entry| entry.action.as_str()).collect();
+    assert_eq!(
+        actions,
+        [
+            "add_user",
+            "add_account",
+            "add_account",
+            "execute_transaction",
+            "delete_account"
+        ]
+    );
+    assert!(log.iter().all(|entry| entry.actor == "system"));
+    assert_eq!(log[0].entity, "user");
+    assert_eq!(log[0].entity_id, user.id.to_string());
+    assert!(log[0].before.is_none() && log[0].after.is_some());
+    assert_eq!(log[3].entity_id, executed.id);
+    assert!(log[4].before.is_some() && log[4].after.is_none());
+
+    let accounts = provider
+        .get_audit_log(&AuditQuery {
+            entity: Some("account".to_string()),
+            entity_id: Some(savings.id.to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(accounts.len(), 2);
+    let page = provider
+        .get_audit_log(&AuditQuery {
+            after_id: Some(log[1].id),
+            limit: 1,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(page, [log[2].clone()]);
+    let zero_limit = provider
+        .get_audit_log(&AuditQuery {
+            limit: 0,
+            ..Default::default()
+        })
+        .await;
+    assert!(matches!(zero_limit, Err(MoneyCalcError::Validation(_))));
+    assert_eq!(provider.verify_audit_log().await.unwrap(), None);
+}
+
+pub async fn reconciliation<P: DataProvider + ReconciliationProvider>(provider: P) {
+    let (user, checking, savings) = seed(&provider, "1").await;
+    let executed = provider
+        .execute_transaction(&outcome(&user, &checking, "25.50", 1))
+        .await
+        .unwrap();
+    provider
+        .reverse_transaction(&ReverseTransactionCommand {
+            transaction_id: executed.id,
+            description: "Refund".to_string(),
+            create_date: date(2),
+        })
+        .await
+        .unwrap();
+    provider
+        .transfer(&TransferCommand {
+            from_account_id: checking.id,
+            to_account_id: savings.id,
+            amount: rub("50.00"),
+            exchange_rate: None,
+            description: "Save".to_string(),
+            create_date: date(3),
+        })
+        .await
+        .unwrap();
+    let report = provider.reconcile_balances(false).await.unwrap();
+    assert_eq!(report.checked_accounts, 2);
+    assert!(report.discrepancies.is_empty());
+
+    provider
+        .change_money(&checking, rub("15.00"))
+        .await
+        .unwrap();
+    let report = provider.reconcile_balances(false).await.unwrap();
+    assert_eq!(
+        report.discrepancies,
+        [BalanceDiscrepancy {
+            account_id: checking.id,
+            stored: rub("65.00"),
+            expected: rub("50.00"),
+            difference: rub("15.00"),
+        }]
+    );
+    assert_eq!(balance(&provider, &checking).await, rub("65.00"));
+
+    assert!(provider.reconcile_balances(true).await.unwrap().repaired);
+    assert_eq!(balance(&provider, &checking).await, rub("50.00"));
+    let report = provider.reconcile_balances(false).await.unwrap();
+    assert!(report.discrepancies.is_empty());
+}
+
+pub async fn balance_history<P: DataProvider + BalanceHistoryProvider>(provider: P) {
+    let (user, checking, _) = seed(&provider, "1").await;
+    for (amount, day) in [("10.00", 2), ("5.00", 4)] {
+        provider
+            .execute_transaction(&outcome(&user, &checking, amount, day))
+            .await
+            .unwrap();
+    }
+    assert_eq!(
+        provider.get_balance_at(&checking, date(1)).await.unwrap(),
+        rub("100.00")
+    );
+    assert_eq!(
+        provider.get_balance_at(&checking, date(3)).await.unwrap(),
+        rub("90.00")
+    );
+    let history = provider
+        .get_daily_balances(&checking, date(1).date(), date(5).date())
+        .await
+        .unwrap();
+    let balances: Vec<Money> = history.iter().map(|day| day.balance).collect();
+    assert_eq!(
+        balances,
+        ["100.00", "90.00", "90.00", "85.00", "85.00"].map(rub)
+    );
+    let reversed = provider
+        .get_daily_balances(&checking, date(5).date(), date(1).date())
+        .await;
+    assert!(matches!(reversed, Err(MoneyCalcError::Validation(_))));
+
+    let snapshot = provider
+        .add_balance_snapshot(&checking, date(3))
+        .await
+        .unwrap();
+    assert_eq!(snapshot.balance, rub("90.00"));
+    provider
+        .execute_transaction(&outcome(&user, &checking, "1.00", 1))
+        .await
+        .unwrap();
+    assert_eq!(
+        provider.get_balance_at(&checking, date(3)).await.unwrap(),
+        rub("89.00")
+    );
+    assert_eq!(
+        provider.get_balance_at(&checking, date(5)).await.unwrap(),
+        rub("84.00")
+    );
+    let missing = provider
+        .get_balance_at(
+            &Account {
+                id: 1000,
+                ..checking.clone()
+            },
+            date(3),
+        )
+        .await;
+    assert!(matches!(missing, Err(MoneyCalcError::NotFound(_))));
+}
+
+/// Tests of every check for provider from `$setup`, it gives provider and value
+/// kept until the end of test, like guard removing its storage.
+macro_rules! conformance {
+    ($module:ident, $setup:expr $(, #[$attribute:meta])*) => {
+        mod $module {
+            use super::*;
+
+            #[tokio::test(flavor = "multi_thread")]
+            $(#[$attribute])*
+            async fn users_and_accounts_test() {
+                let (provider, _storage) = $setup;
+                users_and_accounts(provider).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            $(#[$attribute])*
+            async fn transactions_and_transfers_test() {
+                let (provider, _storage) = $setup;
+                transactions_and_transfers(provider).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            $(#[$attribute])*
+            async fn reversals_and_amendments_test() {
+                let (provider, _storage) = $setup;
+                reversals_and_amendments(provider).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            $(#[$attribute])*
+            async fn external_transactions_test() {
+                let (provider, _storage) = $setup;
+                external_transactions(provider).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            $(#[$attribute])*
+            async fn paging_test() {
+                let (provider, _storage) = $setup;
+                paging(provider).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            $(#[$attribute])*
+            async fn restore_and_delete_test() {
+                let (provider, _storage) = $setup;
+                restore_and_delete(provider).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            $(#[$attribute])*
+            async fn categories_test() {
+                let (provider, _storage) = $setup;
+                categories(provider).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            $(#[$attribute])*
+            async fn tags_test() {
+                let (provider, _storage) = $setup;
+                tags(provider).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            $(#[$attribute])*
+            async fn exchange_rates_test() {
+                let (provider, _storage) = $setup;
+                exchange_rates(provider).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            $(#[$attribute])*
+            async fn budgets_test() {
+                let (provider, _storage) = $setup;
+                budgets(provider).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            $(#[$attribute])*
+            async fn recurring_test() {
+                let (provider, _storage) = $setup;
+                recurring(provider).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            $(#[$attribute])*
+            async fn audit_test() {
+                let (provider, _storage) = $setup;
+                audit(provider).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            $(#[$attribute])*
+            async fn reconciliation_test() {
+                let (provider, _storage) = $setup;
+                reconciliation(provider).await;
+            }
+
+            #[tokio::test(flavor = "multi_thread")]
+            $(#[$attribute])*
+            async fn balance_history_test() {
+                let (provider, _storage) = $setup;
+                balance_history(provider).await;
+            }
+        }
+    };
+}
+
+/// Schema of conformance test, dropped with everything in it when test ends.
+#[cfg(feature = "postgres")]
+struct SchemaGuard {
+    url: String,
+    schema: String,
+}
+
+#[cfg(feature = "postgres")]
+impl Drop for SchemaGuard {
+    fn drop(&mut self) {
+        // Runtime keeps running connections of dropped provider, so their transactions end
+        // and don't hold locks needed by drop.
+        let dropped = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                let (client, connection) =
+                    tokio_postgres::connect(&self.url, tokio_postgres::NoTls).await?;
+                tokio::spawn(connection);
+                client
+                    .batch_execute(&format!("DROP SCHEMA IF EXISTS {} CASCADE", self.schema))
+                    .await
+            })
+        });
+        if let Err(error) = dropped
+            && !std::thread::panicking()
+        {
+            panic!("schema {} was not dropped: {}", self.schema, error);
+        }
+    }
+}
+
+/// Provider on a new schema of database from MONEYCALC_POSTGRES_URL.
+#[cfg(feature = "postgres")]
+fn postgres_provider() -> (PostgresProvider, SchemaGuard) {
+    let url = std::env::var("MONEYCALC_POSTGRES_URL")
+        .expect("MONEYCALC_POSTGRES_URL must be set to run postgres conformance tests");
+    let schema = format!("conformance_{}", uuid::Uuid::new_v4().simple());
+    let provider = PostgresConfiguration::new(&url)
+        .with_schema(&schema)
+        .configure()
+        .unwrap();
+    (provider, SchemaGuard { url, schema })
+}
+
+conformance!(
+    sqlite,
+    (SqliteConfiguration::memory_base().configure().unwrap(), ())
+);
+conformance!(memory, (MemoryProvider::new(), ()));
+#[cfg(feature = "postgres")]
+conformance!(
+    postgres,
+    postgres_provider(),
+    #[ignore = "needs MONEYCALC_POSTGRES_URL, run with --ignored"]
+);
@@ -0,0 +1,121 @@
+use moneycalc::prelude::*;
+
+async fn configure_with_user(number: &str) -> (SqliteProvider, User) {
+    let provider = SqliteConfiguration::memory_base().configure().unwrap();
+    provider
+        .add_user(&AddUserCommand {
+            user_name: "scam".to_string(),
+            user_number: number.to_string(),
+        })
+        .await
+        .unwrap();
+    let user = provider.get_user_by_number(number).await.unwrap();
+    (provider, user)
+}
+
+fn rub(amount: &str) -> Money {
+    Money::parse(amount, Currency::RUB).unwrap()
+}
+
+#[tokio::test]
+async fn users_and_accounts_test() {
+    let (provider, user) = configure_with_user("88005553535").await;
+    for name in ["Checking", "Savings"] {
+        provider
+            .add_account(&AddAccountCommand {
+                user_id: user.id,
+                account_name: name.to_string(),
+                initial_balance: rub("100.00"),
+                overdraft: OverdraftPolicy::default(),
+            })
+            .await
+            .unwrap();
+    }
+
+    let accounts = provider.get_accounts_by_user(&user).await.unwrap();
+    assert_eq!(accounts.len(), 2);
+    let primary = provider.search_account_by_user(&user).await.unwrap();
+    assert_eq!(primary.name, "Checking");
+    assert!(primary.is_primary);
+
+    let result = provider
+        .add_user(&AddUserCommand {
+            user_name: "copy".to_string(),
+            user_number: "88005553535".to_string(),
+        })
+        .await;
+    assert!(matches!(result, Err(MoneyCalcError::Conflict(_))));
+    assert!(matches!(
+        provider.get_user_by_number("missing").await,
+        Err(MoneyCalcError::NotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn transactions_test() {
+    let (provider, user) = configure_with_user("1").await;
+    for name in ["Checking", "Savings"] {
+        provider
+            .add_account(&AddAccountCommand {
+                user_id: user.id,
+                account_name: name.to_string(),
+                initial_balance: rub("100.00"),
+                overdraft: OverdraftPolicy::default(),
+            })
+            .await
+            .unwrap();
+    }
+    let checking = provider
+        .get_account_by_name(&user, "Checking")
+        .await
+        .unwrap();
+    let savings = provider
+        .get_account_by_name(&user, "Savings")
+        .await
+        .unwrap();
+
+    provider
+        .execute_transaction(&MoneyTransaction {
+            id: String::new(),
+            amount: rub("25.50"),
+            description: "Coffee".to_string(),
+            user: user.clone(),
+            account: checking.clone(),
+            payment_type: PaymentType::Outcome,
+            payment_target: "Cafe".to_string(),
+            create_date: chrono::Utc::now().naive_utc(),
+            linked_transaction_id: None,
+            correction_of: None,
+            category_id: None,
+        })
+        .await
+        .unwrap();
+    provider
+        .transfer(&TransferCommand {
+            from_account_id: checking.id,
+            to_account_id: savings.id,
+            amount: rub("50.00"),
+            exchange_rate: None,
+            description: "Save".to_string(),
+            create_date: chrono::Utc::now().naive_utc(),
+        })
+        .await
+        .unwrap();
+
+    let checking = provider.get_account_by_id(checking.id).await.unwrap();
+    let savings = provider.get_account_by_id(savings.id).await.unwrap();
+    assert_eq!(checking.money, rub("24.50"));
+    assert_eq!(savings.money, rub("150.00"));
+
+    let page = provider
+        .get_transactions(&TransactionQuery {
+            account_id: Some(checking.id),
+            order: TransactionOrder::AmountAsc,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(page.transactions.len(), 2);
+    assert_eq!(page.transactions[0].payment_target, "Cafe");
+    assert!(page.next_cursor.is_none());
+}